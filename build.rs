@@ -0,0 +1,14 @@
+//! Build script
+//!
+//! Compiles `proto/ozone.proto` into tonic server stubs when the `grpc`
+//! feature is enabled.
+
+fn main() {
+    println!("cargo:rerun-if-changed=proto/ozone.proto");
+
+    #[cfg(feature = "grpc")]
+    tonic_build::configure()
+        .build_client(false)
+        .compile(&["proto/ozone.proto"], &["proto"])
+        .expect("Failed to compile proto/ozone.proto");
+}
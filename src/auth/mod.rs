@@ -6,6 +6,7 @@
 //! No passwords - cryptographic key pairs only.
 
 use crate::config::AuthConfig;
+use crate::network::reputation::Event;
 use crate::types::{UserID, DeviceID, OzoneError, OzoneResult, PublicKey};
 use crate::types::auth::{User, Session, DeviceRegistration, DeviceType, AuthChallenge};
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
@@ -300,6 +301,23 @@ impl AuthSystem {
         self.users.read().await.get(&user_id).cloned()
     }
     
+    /// Credit a user for a proposal the network accepted or rejected
+    pub async fn record_contribution(&self, user_id: UserID, accepted: bool) -> OzoneResult<()> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(&user_id)
+            .ok_or_else(|| OzoneError::NotFound(format!("User {} not found", user_id)))?;
+        
+        let event = if accepted {
+            user.contribution_count += 1;
+            user.contribution_score += 1.0;
+            Event::ProposalAccepted
+        } else {
+            Event::ProposalRejected
+        };
+        user.reputation = (user.reputation + event.delta() as f64).clamp(0.0, 1.0);
+        Ok(())
+    }
+    
     /// Register a device for a user
     pub async fn register_device(
         &self,
@@ -0,0 +1,188 @@
+//! HTTP and websocket client for a running instance
+
+use futures_util::{SinkExt, StreamExt};
+use ozone_studio::types::{OzoneError, OzoneResult};
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Client for the `/api/v1` REST API and legacy routes
+pub struct ApiClient {
+    base: String,
+    http: reqwest::Client,
+    token: Option<String>,
+}
+
+impl ApiClient {
+    pub fn new(server: &str, token: Option<String>) -> Self {
+        Self {
+            base: server.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            token,
+        }
+    }
+
+    pub async fn get(&self, path: &str) -> OzoneResult<Value> {
+        self.request(Method::GET, path, None::<&Value>).await
+    }
+
+    pub async fn post<B: Serialize>(&self, path: &str, body: &B) -> OzoneResult<Value> {
+        self.request(Method::POST, path, Some(body)).await
+    }
+
+    pub async fn put<B: Serialize>(&self, path: &str, body: &B) -> OzoneResult<Value> {
+        self.request(Method::PUT, path, Some(body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> OzoneResult<Value> {
+        self.request(Method::DELETE, path, None::<&Value>).await
+    }
+
+    async fn request<B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> OzoneResult<Value> {
+        let url = format!("{}{}", self.base, path);
+        let mut request = self.http.request(method, &url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| OzoneError::NetworkError(format!("{}: {}", url, e)))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| OzoneError::NetworkError(e.to_string()))?;
+
+        if status.is_success() {
+            if text.is_empty() {
+                return Ok(Value::Null);
+            }
+            return serde_json::from_str(&text)
+                .map_err(|e| OzoneError::SerializationError(format!("Invalid response: {}", e)));
+        }
+
+        Err(api_error(status, &text))
+    }
+
+    /// Open the `/ws` event stream, authenticate and subscribe
+    pub async fn subscribe(&self, subscribe: &Value) -> OzoneResult<EventStream> {
+        let token = self
+            .token
+            .as_deref()
+            .ok_or_else(|| OzoneError::AuthError("Not logged in".into()))?;
+        let ws_base = self
+            .base
+            .replacen("https://", "wss://", 1)
+            .replacen("http://", "ws://", 1);
+        let url = format!("{}/ws?session_token={}", ws_base, token);
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .map_err(|e| OzoneError::NetworkError(format!("{}/ws: {}", ws_base, e)))?;
+        socket
+            .send(Message::text(subscribe.to_string()))
+            .await
+            .map_err(|e| OzoneError::NetworkError(e.to_string()))?;
+
+        Ok(EventStream { socket })
+    }
+}
+
+/// The error for a failed response
+fn api_error(status: StatusCode, text: &str) -> OzoneError {
+    // `/api/v1` errors are `{"error": {"code", "message"}}`
+    let message = serde_json::from_str::<Value>(text)
+        .ok()
+        .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{} {}", status, text));
+    let variant: fn(String) -> OzoneError = match status {
+        StatusCode::UNAUTHORIZED => OzoneError::AuthError,
+        StatusCode::FORBIDDEN => OzoneError::PermissionDenied,
+        StatusCode::NOT_FOUND => OzoneError::NotFound,
+        StatusCode::CONFLICT => OzoneError::Conflict,
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => OzoneError::ValidationError,
+        _ => OzoneError::ServerError,
+    };
+    // The server message is already the error's Display; don't prefix it twice
+    let prefix = variant(String::new()).to_string();
+    let message = match message.strip_prefix(&prefix) {
+        Some(inner) => inner.to_string(),
+        None => message,
+    };
+    variant(message)
+}
+
+/// Messages received on the `/ws` event stream
+pub struct EventStream {
+    socket: tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+}
+
+impl EventStream {
+    /// Next server message; `None` when the connection closes.
+    /// Server-side errors and expired sessions are returned as errors.
+    pub async fn next(&mut self) -> Option<OzoneResult<Value>> {
+        while let Some(message) = self.socket.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => return None,
+                Ok(_) => continue,
+                Err(e) => return Some(Err(OzoneError::NetworkError(e.to_string()))),
+            };
+            let Ok(value) = serde_json::from_str::<Value>(&text) else {
+                continue;
+            };
+            return Some(match value["action"].as_str() {
+                Some("error") => Err(OzoneError::ServerError(
+                    value["error"]
+                        .as_str()
+                        .unwrap_or("unknown error")
+                        .to_string(),
+                )),
+                Some("session_expired") => Err(OzoneError::AuthError(
+                    "Session expired; run `ozone login`".into(),
+                )),
+                Some("shutting_down") => {
+                    Err(OzoneError::ServerError("Server is shutting down".into()))
+                }
+                _ => Ok(value),
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_mapping() {
+        let body =
+            r#"{"error": {"code": "conflict", "message": "Conflict: Task 3 is already finished"}}"#;
+        match api_error(StatusCode::CONFLICT, body) {
+            OzoneError::Conflict(m) => assert_eq!(m, "Task 3 is already finished"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            api_error(StatusCode::UNPROCESSABLE_ENTITY, "{}"),
+            OzoneError::ValidationError(_)
+        ));
+        match api_error(StatusCode::BAD_GATEWAY, "upstream down") {
+            OzoneError::ServerError(m) => assert_eq!(m, "502 Bad Gateway upstream down"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,787 @@
+//! Subcommand implementations
+
+use crate::client::{ApiClient, EventStream};
+use crate::identity::{sign_challenge, Identity, SavedSession};
+use crate::{Cli, Command, EventsArgs, RunArgs, TaskCommand, ZseiCommand};
+use ozone_studio::grpc::OrchestrateRequest;
+use ozone_studio::orchestrator::AttachedFileSpec;
+use ozone_studio::types::{OzoneError, OzoneResult};
+use serde_json::{json, Value};
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+use std::io::Read;
+use std::path::Path;
+
+/// Interval between polls when following logs
+const FOLLOW_POLL_MS: u64 = 1000;
+
+/// Bytes of an attached text file sent as a preview
+const PREVIEW_BYTES: usize = 512;
+
+/// Task statuses after which nothing more happens
+const TERMINAL_STATUSES: [&str; 3] = ["completed", "failed", "cancelled"];
+
+pub async fn run(cli: Cli) -> OzoneResult<()> {
+    let identity = Identity::new(cli.home, cli.key);
+    let server = cli.server.trim_end_matches('/').to_string();
+    let out = Output { json: cli.json };
+
+    match cli.command {
+        Command::Keygen { force } => {
+            let key = identity.generate_key(force)?;
+            out.show(
+                &json!({
+                    "key_file": identity.key_path.display().to_string(),
+                    "public_key": hex::encode(key.verifying_key().to_bytes()),
+                }),
+                |v| {
+                    println!("Key written to {}", v["key_file"].as_str().unwrap_or(""));
+                    println!("Public key: {}", v["public_key"].as_str().unwrap_or(""));
+                },
+            );
+            Ok(())
+        }
+        Command::Login => login(&identity, &server, &out).await,
+        Command::Logout => {
+            if let Ok(session) = identity.load_session(&server) {
+                let client = ApiClient::new(&server, Some(session.session_token));
+                match client.delete("/api/v1/sessions/current").await {
+                    Ok(_) | Err(OzoneError::AuthError(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            identity.clear_session()?;
+            println!("Logged out");
+            Ok(())
+        }
+        Command::Whoami => {
+            let client = authed_client(&identity, &server)?;
+            out.show(&client.get("/api/v1/sessions/current").await?, |v| {
+                println!("user {}  device {}", v["user_id"], v["device_id"]);
+                println!("session expires {}", format_time(&v["expires_at"]));
+            });
+            Ok(())
+        }
+        Command::Run(args) => run_prompt(&identity, &server, args, &out).await,
+        Command::Answer {
+            orchestration_id,
+            answers,
+        } => {
+            let client = authed_client(&identity, &server)?;
+            let response = client
+                .post(
+                    &format!("/api/v1/orchestrations/{}/clarifications", orchestration_id),
+                    &json!({ "answers": answers }),
+                )
+                .await?;
+            out.show(&response, show_orchestration);
+            Ok(())
+        }
+        Command::Task(command) => {
+            let client = authed_client(&identity, &server)?;
+            task(&client, command, &out).await
+        }
+        Command::Events(args) => {
+            let client = authed_client(&identity, &server)?;
+            events(&client, args, &out).await
+        }
+        Command::Zsei(command) => {
+            let client = authed_client(&identity, &server)?;
+            zsei(&client, command, &out).await
+        }
+    }
+}
+
+fn authed_client(identity: &Identity, server: &str) -> OzoneResult<ApiClient> {
+    let session = identity.load_session(server)?;
+    Ok(ApiClient::new(server, Some(session.session_token)))
+}
+
+// ============================================================================
+// Sessions
+// ============================================================================
+
+async fn login(identity: &Identity, server: &str, out: &Output) -> OzoneResult<()> {
+    let key = identity.load_key()?;
+    let public_key = hex::encode(key.verifying_key().to_bytes());
+    let client = ApiClient::new(server, None);
+
+    let challenge = client
+        .post(
+            "/api/v1/sessions/challenge",
+            &json!({ "public_key": public_key }),
+        )
+        .await?;
+    let signature = sign_challenge(&key, challenge["challenge"].as_str().unwrap_or(""))?;
+    let session = client
+        .post(
+            "/api/v1/sessions",
+            &json!({ "public_key": public_key, "signature": signature }),
+        )
+        .await?;
+
+    let saved = SavedSession {
+        server: server.to_string(),
+        session_token: session["session_token"]
+            .as_str()
+            .ok_or_else(|| OzoneError::AuthError("Server did not return a session token".into()))?
+            .to_string(),
+        user_id: session["user_id"].as_u64().unwrap_or(0),
+        device_id: session["device_id"].as_u64().unwrap_or(0),
+        expires_at: session["expires_at"].as_u64().unwrap_or(0),
+    };
+    identity.save_session(&saved)?;
+
+    out.show(&session, |_| {
+        println!(
+            "Logged in as user {} (device {}), session expires {}",
+            saved.user_id,
+            saved.device_id,
+            format_time(&json!(saved.expires_at))
+        );
+    });
+    Ok(())
+}
+
+// ============================================================================
+// Orchestration
+// ============================================================================
+
+async fn run_prompt(
+    identity: &Identity,
+    server: &str,
+    args: RunArgs,
+    out: &Output,
+) -> OzoneResult<()> {
+    let session = identity.load_session(server)?;
+    let attached_files = args
+        .files
+        .iter()
+        .map(|path| attach_file(path))
+        .collect::<OzoneResult<Vec<_>>>()?;
+
+    let request = OrchestrateRequest {
+        prompt: args.prompt,
+        project_id: args.project,
+        workspace_id: args.workspace,
+        user_id: session.user_id,
+        device_id: session.device_id,
+        consciousness_enabled: args.consciousness,
+        token_budget: args.token_budget,
+        model_config: None,
+        session_token: Some(session.session_token.clone()),
+        attached_files,
+    };
+
+    let client = ApiClient::new(server, Some(session.session_token));
+    let response = client.post("/orchestrate", &request).await?;
+
+    if !response["success"].as_bool().unwrap_or(false) && !out.json {
+        if let Some(error) = response["error"].as_str() {
+            return Err(OzoneError::ServerError(error.to_string()));
+        }
+    }
+
+    out.show(&response, show_orchestration);
+    Ok(())
+}
+
+fn show_orchestration(v: &Value) {
+    if let Some(text) = v["response"].as_str() {
+        println!("{}", text);
+    }
+    if v["needs_clarification"].as_bool().unwrap_or(false) {
+        println!("\nClarification needed:");
+        for point in v["clarification_points"].as_array().into_iter().flatten() {
+            println!("  - {}", point.as_str().unwrap_or_default());
+        }
+        println!(
+            "Answer with: ozone answer {} <answer>...",
+            v["orchestration_id"]
+        );
+    }
+    let mut summary = format!(
+        "\norchestration {}  task {}  blueprint {}  {} stages",
+        v["orchestration_id"],
+        v["task_id"],
+        v["blueprint_id"],
+        v["stages_completed"]
+            .as_array()
+            .map(|s| s.len())
+            .unwrap_or(0)
+    );
+    if let Some(ms) = v["execution_time_ms"].as_u64() {
+        let _ = write!(summary, "  {}ms", ms);
+    }
+    eprintln!("{}", summary);
+}
+
+/// Describe a local file for the orchestrator, with a preview if it is text
+fn attach_file(path: &Path) -> OzoneResult<AttachedFileSpec> {
+    let path = path.canonicalize().map_err(|e| {
+        OzoneError::ValidationError(format!("Cannot attach {}: {}", path.display(), e))
+    })?;
+
+    let mut head = Vec::with_capacity(PREVIEW_BYTES);
+    std::fs::File::open(&path)?
+        .take(PREVIEW_BYTES as u64)
+        .read_to_end(&mut head)?;
+    // A multi-byte character may be cut at the boundary
+    let content_preview = match std::str::from_utf8(&head) {
+        Ok(text) => Some(text.to_string()),
+        Err(e) if e.error_len().is_none() => {
+            Some(String::from_utf8_lossy(&head[..e.valid_up_to()]).into_owned())
+        }
+        Err(_) => None,
+    };
+
+    Ok(AttachedFileSpec {
+        file_path: path.display().to_string(),
+        mime_type: mime_type(&path).map(str::to_string),
+        is_inline: false,
+        content_preview,
+    })
+}
+
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "rs" => "text/x-rust",
+        "py" => "text/x-python",
+        "js" | "mjs" => "text/javascript",
+        "ts" | "tsx" => "text/typescript",
+        "go" => "text/x-go",
+        "json" => "application/json",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "wav" => "audio/wav",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        _ => return None,
+    })
+}
+
+// ============================================================================
+// Tasks
+// ============================================================================
+
+async fn task(client: &ApiClient, command: TaskCommand, out: &Output) -> OzoneResult<()> {
+    match command {
+        TaskCommand::List {
+            status,
+            limit,
+            offset,
+        } => {
+            let mut path = format!("/api/v1/tasks?limit={}&offset={}", limit, offset);
+            if let Some(status) = status {
+                path.push_str(&format!("&status={}", status));
+            }
+            let page = client.get(&path).await?;
+            out.show(&page, |v| {
+                println!("    TASK  STATUS       PROG  CREATED               BLUEPRINT");
+                for task in v["items"].as_array().into_iter().flatten() {
+                    println!(
+                        "{:>8}  {:<10}  {:>4.0}%  {:<20}  {}",
+                        task["task_id"].as_u64().unwrap_or(0),
+                        task["status"].as_str().unwrap_or(""),
+                        task["progress"].as_f64().unwrap_or(0.0) * 100.0,
+                        format_time(&task["created_at"]),
+                        task["blueprint_name"].as_str().unwrap_or(""),
+                    );
+                }
+                println!(
+                    "{} of {} tasks",
+                    v["items"].as_array().map(|i| i.len()).unwrap_or(0),
+                    v["total"]
+                );
+            });
+        }
+        TaskCommand::Get { task_id } => {
+            out.show(
+                &client.get(&format!("/api/v1/tasks/{}", task_id)).await?,
+                |v| print!("{}", format_task(v)),
+            );
+        }
+        TaskCommand::Cancel { task_id } => {
+            let task = client
+                .post(&format!("/api/v1/tasks/{}/cancel", task_id), &json!({}))
+                .await?;
+            out.show(&task, |v| print!("{}", format_task(v)));
+        }
+        TaskCommand::Retry { task_id } => {
+            let task = client
+                .post(&format!("/api/v1/tasks/{}/retry", task_id), &json!({}))
+                .await?;
+            out.show(&task, |v| {
+                println!("Task {} retried as task {}", task_id, v["task_id"]);
+            });
+        }
+        TaskCommand::Logs {
+            task_id,
+            limit,
+            follow,
+        } => follow_logs(client, task_id, limit, follow, out).await?,
+        TaskCommand::Watch { task_id } => {
+            let task = client.get(&format!("/api/v1/tasks/{}", task_id)).await?;
+            if is_terminal(&task["status"]) {
+                out.show(&task, |v| print!("{}", format_task(v)));
+                return Ok(());
+            }
+            let subscribe = json!({"action": "subscribe", "task_ids": [task_id], "since": 0});
+            let mut stream = client.subscribe(&subscribe).await?;
+            watch(&mut stream, out, |event| {
+                event["topic"] == "task" && is_terminal(&event["kind"])
+            })
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Print logs, then poll for new entries until the task finishes
+async fn follow_logs(
+    client: &ApiClient,
+    task_id: u64,
+    limit: usize,
+    follow: bool,
+    out: &Output,
+) -> OzoneResult<()> {
+    let path = format!("/api/v1/tasks/{}/logs?limit={}", task_id, limit);
+    let mut previous: Vec<Value> = Vec::new();
+
+    loop {
+        // Read the status first so the final log entry is not missed
+        let finished = follow
+            && is_terminal(&client.get(&format!("/api/v1/tasks/{}", task_id)).await?["status"]);
+
+        let entries = client
+            .get(&path)
+            .await?
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        for entry in &entries[overlap(&previous, &entries)..] {
+            out.show(entry, |e| {
+                println!(
+                    "{}  {:<5}  {}",
+                    format_time(&e["timestamp"]),
+                    e["level"].as_str().unwrap_or(""),
+                    e["message"].as_str().unwrap_or("")
+                );
+            });
+        }
+        previous = entries;
+
+        if !follow || finished {
+            return Ok(());
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(FOLLOW_POLL_MS)).await;
+    }
+}
+
+/// Number of leading `entries` already printed in the previous poll.
+///
+/// Both polls are the most recent `limit` entries, oldest first, so the
+/// overlap is the longest prefix of `entries` that ends `previous`.
+fn overlap(previous: &[Value], entries: &[Value]) -> usize {
+    (0..=entries.len().min(previous.len()))
+        .rev()
+        .find(|&n| entries[..n] == previous[previous.len() - n..])
+        .unwrap_or(0)
+}
+
+fn format_task(v: &Value) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "task       {}", v["task_id"]);
+    let _ = writeln!(out, "status     {}", v["status"].as_str().unwrap_or(""));
+    let _ = writeln!(
+        out,
+        "progress   {:.0}%",
+        v["progress"].as_f64().unwrap_or(0.0) * 100.0
+    );
+    if let Some(name) = v["blueprint_name"].as_str().filter(|n| !n.is_empty()) {
+        let _ = writeln!(out, "blueprint  {}", name);
+    }
+    if !v["pipeline_id"].is_null() {
+        let _ = writeln!(out, "pipeline   {}", v["pipeline_id"]);
+    }
+    let _ = writeln!(out, "created    {}", format_time(&v["created_at"]));
+    if !v["completed_at"].is_null() {
+        let _ = writeln!(out, "finished   {}", format_time(&v["completed_at"]));
+    }
+    if let Some(error) = v["error"].as_str() {
+        let _ = writeln!(out, "error      {}", error);
+    }
+    out
+}
+
+fn is_terminal(status: &Value) -> bool {
+    status
+        .as_str()
+        .map(|s| TERMINAL_STATUSES.contains(&s))
+        .unwrap_or(false)
+}
+
+// ============================================================================
+// Events
+// ============================================================================
+
+async fn events(client: &ApiClient, args: EventsArgs, out: &Output) -> OzoneResult<()> {
+    let topics = if args.topics.is_empty() && args.task_ids.is_empty() {
+        vec![
+            "task".to_string(),
+            "pipeline".to_string(),
+            "orchestration".to_string(),
+        ]
+    } else {
+        args.topics
+    };
+    let mut subscribe = json!({
+        "action": "subscribe",
+        "topics": topics,
+        "task_ids": args.task_ids,
+    });
+    if let Some(since) = args.since {
+        subscribe["since"] = json!(since);
+    }
+
+    let mut stream = client.subscribe(&subscribe).await?;
+    watch(&mut stream, out, |_| false).await
+}
+
+/// Print events until `done` returns true for one of them
+async fn watch(
+    stream: &mut EventStream,
+    out: &Output,
+    done: impl Fn(&Value) -> bool,
+) -> OzoneResult<()> {
+    while let Some(message) = stream.next().await {
+        let message = message?;
+        match message["action"].as_str() {
+            Some("event") => {
+                out.show(&message, |e| println!("{}", format_event(e)));
+                if done(&message) {
+                    return Ok(());
+                }
+            }
+            Some("resync_required") => eprintln!(
+                "(events before seq {} are no longer retained)",
+                message["oldest_seq"]
+            ),
+            _ => {
+                if out.json {
+                    println!("{}", message);
+                }
+            }
+        }
+    }
+    Err(OzoneError::NetworkError("Event stream closed".into()))
+}
+
+fn format_event(event: &Value) -> String {
+    let data = &event["data"];
+    let detail = match (event["topic"].as_str(), event["kind"].as_str()) {
+        (Some("task"), Some("progress")) => format!(
+            "{:.0}% (step {}/{})",
+            data["progress"].as_f64().unwrap_or(0.0) * 100.0,
+            data["current_step"],
+            data["total_steps"]
+        ),
+        (Some("task"), Some("failed")) => data["error"].as_str().unwrap_or("").to_string(),
+        (Some("pipeline"), _) => format!(
+            "{} [{}] {}%",
+            data["pipeline_name"].as_str().unwrap_or(""),
+            data["execution_id"].as_str().unwrap_or(""),
+            data["progress_percent"]
+        ),
+        (Some("orchestration"), _) => format!(
+            "stage {} {} {}",
+            data["stage"],
+            data["name"].as_str().unwrap_or(""),
+            if data["success"].as_bool().unwrap_or(false) {
+                "ok"
+            } else {
+                "FAILED"
+            }
+        ),
+        _ => data.to_string(),
+    };
+    let task = if event["task_id"].is_null() {
+        String::new()
+    } else {
+        format!(" task {}", event["task_id"])
+    };
+    format!(
+        "#{:<6} {}  {}/{}{}  {}",
+        event["seq"].as_u64().unwrap_or(0),
+        format_time(&event["timestamp"]),
+        event["topic"].as_str().unwrap_or(""),
+        event["kind"].as_str().unwrap_or(""),
+        task,
+        detail
+    )
+}
+
+// ============================================================================
+// ZSEI
+// ============================================================================
+
+/// Format marker written into export files
+const EXPORT_FORMAT: &str = "ozone-containers";
+
+async fn zsei(client: &ApiClient, command: ZseiCommand, out: &Output) -> OzoneResult<()> {
+    match command {
+        ZseiCommand::Get { container_id } => {
+            let container = client
+                .get(&format!("/api/v1/containers/{}", container_id))
+                .await?;
+            out.show(&container, print_json);
+        }
+        ZseiCommand::Query { query } => {
+            let query = read_json_arg(&query)?;
+            out.show(
+                &client.post("/api/v1/containers/query", &query).await?,
+                print_json,
+            );
+        }
+        ZseiCommand::Traverse {
+            container_id,
+            mode,
+            max_depth,
+            max_results,
+            keywords,
+        } => {
+            let mut body = json!({});
+            if let Some(mode) = mode {
+                body["mode"] = json!(mode);
+            }
+            if let Some(max_depth) = max_depth {
+                body["max_depth"] = json!(max_depth);
+            }
+            if let Some(max_results) = max_results {
+                body["max_results"] = json!(max_results);
+            }
+            if !keywords.is_empty() {
+                body["keyword_filter"] = json!(keywords);
+            }
+            let result = client
+                .post(
+                    &format!("/api/v1/containers/{}/traverse", container_id),
+                    &body,
+                )
+                .await?;
+            out.show(&result, print_json);
+        }
+        ZseiCommand::Export {
+            container_id,
+            output,
+            no_children,
+        } => {
+            let containers = export_tree(client, container_id, !no_children).await?;
+            let count = containers.len();
+            let export = json!({
+                "format": EXPORT_FORMAT,
+                "version": 1,
+                "root": container_id,
+                "containers": containers,
+            });
+            let content = serde_json::to_string_pretty(&export)
+                .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content)?;
+                    eprintln!("Exported {} containers to {}", count, path.display());
+                }
+                None => println!("{}", content),
+            }
+        }
+        ZseiCommand::Import { file } => {
+            let content = std::fs::read_to_string(&file)?;
+            let export: Value = serde_json::from_str(&content).map_err(|e| {
+                OzoneError::SerializationError(format!("{}: {}", file.display(), e))
+            })?;
+            if export["format"] != EXPORT_FORMAT {
+                return Err(OzoneError::ValidationError(format!(
+                    "{} is not a container export",
+                    file.display()
+                )));
+            }
+
+            let (mut created, mut updated) = (0, 0);
+            for container in export["containers"].as_array().into_iter().flatten() {
+                let id = container["global_state"]["container_id"]
+                    .as_u64()
+                    .unwrap_or(0);
+                // Existing containers (always including the root, ID 0) are
+                // updated in place; creating with ID 0 would allocate a new one
+                let path = format!("/api/v1/containers/{}", id);
+                match client.get(&path).await {
+                    Ok(_) => {
+                        client.put(&path, container).await?;
+                        updated += 1;
+                    }
+                    Err(OzoneError::NotFound(_)) => {
+                        client.post("/api/v1/containers", container).await?;
+                        created += 1;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            out.show(&json!({"created": created, "updated": updated}), |_| {
+                println!(
+                    "Imported {} containers ({} new, {} updated)",
+                    created + updated,
+                    created,
+                    updated
+                );
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Fetch a container and (optionally) its descendants, parents first
+async fn export_tree(client: &ApiClient, root: u64, recursive: bool) -> OzoneResult<Vec<Value>> {
+    let mut containers = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([root]);
+
+    while let Some(id) = queue.pop_front() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let container = client.get(&format!("/api/v1/containers/{}", id)).await?;
+        if recursive {
+            queue.extend(
+                container["global_state"]["child_ids"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|c| c.as_u64()),
+            );
+        }
+        containers.push(container);
+    }
+    Ok(containers)
+}
+
+/// Parse a JSON argument, or the contents of a file given as `@path`
+fn read_json_arg(arg: &str) -> OzoneResult<Value> {
+    let text = match arg.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => arg.to_string(),
+    };
+    serde_json::from_str(&text)
+        .map_err(|e| OzoneError::ValidationError(format!("Invalid JSON: {}", e)))
+}
+
+// ============================================================================
+// Output
+// ============================================================================
+
+struct Output {
+    json: bool,
+}
+
+impl Output {
+    /// Print `value` as JSON with `--json`, otherwise with `human`
+    fn show(&self, value: &Value, human: impl FnOnce(&Value)) {
+        if self.json {
+            println!("{}", value);
+        } else {
+            human(value);
+        }
+    }
+}
+
+fn print_json(value: &Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    );
+}
+
+fn format_time(secs: &Value) -> String {
+    secs.as_i64()
+        .and_then(|s| chrono::DateTime::<chrono::Utc>::from_timestamp(s, 0))
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_task_and_event() {
+        let task = json!({
+            "task_id": 12,
+            "status": "failed",
+            "progress": 0.5,
+            "blueprint_name": "",
+            "pipeline_id": null,
+            "created_at": 0,
+            "completed_at": 60,
+            "error": "boom",
+        });
+        assert_eq!(
+            format_task(&task),
+            "task       12\n\
+             status     failed\n\
+             progress   50%\n\
+             created    1970-01-01 00:00:00\n\
+             finished   1970-01-01 00:01:00\n\
+             error      boom\n"
+        );
+
+        let event = json!({
+            "seq": 7,
+            "timestamp": 0,
+            "topic": "orchestration",
+            "kind": "stage_completed",
+            "task_id": 12,
+            "data": {"stage": 3, "name": "Blueprint Search", "success": true},
+        });
+        assert_eq!(
+            format_event(&event),
+            "#7      1970-01-01 00:00:00  orchestration/stage_completed task 12  \
+             stage 3 Blueprint Search ok"
+        );
+
+        let event = json!({"seq": 8, "timestamp": "?", "topic": "zsei", "kind": "container_stored",
+                           "task_id": null, "data": {"container_id": 4}});
+        assert_eq!(
+            format_event(&event),
+            "#8      -  zsei/container_stored  {\"container_id\":4}"
+        );
+    }
+
+    #[test]
+    fn test_log_overlap_and_arguments() {
+        let logs: Vec<Value> = (0..5).map(|i| json!({"message": i})).collect();
+        assert_eq!(overlap(&[], &logs[..3]), 0);
+        // Two new entries arrived and the oldest fell out of the window
+        assert_eq!(overlap(&logs[..3], &logs[1..5]), 2);
+        assert_eq!(overlap(&logs[..3], &logs[..3]), 3);
+
+        assert!(is_terminal(&json!("cancelled")));
+        assert!(!is_terminal(&json!("running")));
+        assert_eq!(mime_type(Path::new("notes.MD")), Some("text/markdown"));
+        assert_eq!(mime_type(Path::new("archive.tar")), None);
+
+        assert_eq!(read_json_arg(r#"{"a": 1}"#).unwrap(), json!({"a": 1}));
+        assert!(matches!(
+            read_json_arg("{"),
+            Err(OzoneError::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,187 @@
+//! Local key file and saved session
+
+use ed25519_dalek::{Signer, SigningKey};
+use ozone_studio::types::{OzoneError, OzoneResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Saved login, written by `ozone login`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub server: String,
+    pub session_token: String,
+    pub user_id: u64,
+    pub device_id: u64,
+    pub expires_at: u64,
+}
+
+/// Paths to the CLI's local state
+pub struct Identity {
+    pub key_path: PathBuf,
+    pub session_path: PathBuf,
+}
+
+impl Identity {
+    pub fn new(home: Option<PathBuf>, key: Option<PathBuf>) -> Self {
+        let home = home.unwrap_or_else(|| {
+            std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".ozone")
+        });
+        Self {
+            key_path: key.unwrap_or_else(|| home.join("identity.key")),
+            session_path: home.join("session.json"),
+        }
+    }
+
+    /// Generate and save a new signing key
+    pub fn generate_key(&self, force: bool) -> OzoneResult<SigningKey> {
+        if self.key_path.exists() && !force {
+            return Err(OzoneError::ValidationError(format!(
+                "Key file {} already exists (use --force to replace it)",
+                self.key_path.display()
+            )));
+        }
+        let key = SigningKey::generate(&mut rand::rngs::OsRng);
+        write_private(&self.key_path, &hex::encode(key.to_bytes()))?;
+        Ok(key)
+    }
+
+    /// Load the signing key (hex-encoded 32-byte seed)
+    pub fn load_key(&self) -> OzoneResult<SigningKey> {
+        let content = std::fs::read_to_string(&self.key_path).map_err(|e| {
+            OzoneError::ConfigError(format!(
+                "Cannot read key file {} ({}); run `ozone keygen` first",
+                self.key_path.display(),
+                e
+            ))
+        })?;
+        let bytes: [u8; 32] = hex::decode(content.trim())
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| {
+                OzoneError::ConfigError(format!(
+                    "Key file {} is not a hex-encoded 32-byte key",
+                    self.key_path.display()
+                ))
+            })?;
+        Ok(SigningKey::from_bytes(&bytes))
+    }
+
+    pub fn save_session(&self, session: &SavedSession) -> OzoneResult<()> {
+        let content = serde_json::to_string_pretty(session)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        write_private(&self.session_path, &content)
+    }
+
+    /// Load the saved session for `server`
+    pub fn load_session(&self, server: &str) -> OzoneResult<SavedSession> {
+        let not_logged_in = || OzoneError::AuthError("Not logged in; run `ozone login`".into());
+        let content = std::fs::read_to_string(&self.session_path).map_err(|_| not_logged_in())?;
+        let session: SavedSession = serde_json::from_str(&content).map_err(|_| not_logged_in())?;
+        if session.server != server {
+            return Err(OzoneError::AuthError(format!(
+                "Saved session is for {}; run `ozone login` for {}",
+                session.server, server
+            )));
+        }
+        Ok(session)
+    }
+
+    pub fn clear_session(&self) -> OzoneResult<()> {
+        match std::fs::remove_file(&self.session_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Sign a hex-encoded challenge, returning the hex signature
+pub fn sign_challenge(key: &SigningKey, challenge_hex: &str) -> OzoneResult<String> {
+    let challenge = hex::decode(challenge_hex)
+        .map_err(|_| OzoneError::AuthError("Server sent an invalid challenge".into()))?;
+    Ok(hex::encode(key.sign(&challenge).to_bytes()))
+}
+
+/// Write a file readable only by the current user. Any existing file is
+/// replaced by a new one, so the content never sits under looser permissions
+fn write_private(path: &Path, content: &str) -> OzoneResult<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier};
+
+    #[test]
+    fn test_key_and_session_files() {
+        let home = std::env::temp_dir().join(format!("ozone-cli-{}", uuid::Uuid::new_v4()));
+        let identity = Identity::new(Some(home.clone()), None);
+        assert_eq!(identity.key_path, home.join("identity.key"));
+
+        let key = identity.generate_key(false).unwrap();
+        assert!(identity.generate_key(false).is_err());
+        assert_eq!(identity.load_key().unwrap().to_bytes(), key.to_bytes());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&identity.key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let replaced = identity.generate_key(true).unwrap();
+        assert_eq!(identity.load_key().unwrap().to_bytes(), replaced.to_bytes());
+
+        let signature = sign_challenge(&key, "00ff10").unwrap();
+        let signature = Signature::from_slice(&hex::decode(signature).unwrap()).unwrap();
+        assert!(key
+            .verifying_key()
+            .verify(&[0x00, 0xff, 0x10], &signature)
+            .is_ok());
+        assert!(sign_challenge(&key, "not hex").is_err());
+
+        assert!(matches!(
+            identity.load_session("http://a"),
+            Err(OzoneError::AuthError(_))
+        ));
+        identity
+            .save_session(&SavedSession {
+                server: "http://a".to_string(),
+                session_token: "abcd".to_string(),
+                user_id: 1,
+                device_id: 2,
+                expires_at: 3,
+            })
+            .unwrap();
+        assert_eq!(
+            identity.load_session("http://a").unwrap().session_token,
+            "abcd"
+        );
+        assert!(identity.load_session("http://b").is_err());
+        identity.clear_session().unwrap();
+        identity.clear_session().unwrap();
+        assert!(identity.load_session("http://a").is_err());
+
+        let _ = std::fs::remove_dir_all(&home);
+    }
+}
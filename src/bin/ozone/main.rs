@@ -0,0 +1,251 @@
+//! Ozone Studio CLI client
+//!
+//! Drives a running `ozone-studio` instance over its HTTP API: Ed25519
+//! login with a local key file, orchestration, task management, log and
+//! progress tailing (via the `/ws` event stream), ZSEI queries/traversals
+//! and container export/import.
+
+mod client;
+mod commands;
+mod identity;
+
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    name = "ozone",
+    version,
+    about = "Command-line client for Ozone Studio"
+)]
+pub struct Cli {
+    /// Server base URL
+    #[arg(
+        long,
+        env = "OZONE_SERVER",
+        default_value = "http://127.0.0.1:50051",
+        global = true
+    )]
+    pub server: String,
+
+    /// Directory holding the key file and saved session (default ~/.ozone)
+    #[arg(long, env = "OZONE_HOME", global = true)]
+    pub home: Option<PathBuf>,
+
+    /// Ed25519 key file (default <home>/identity.key)
+    #[arg(long, env = "OZONE_KEY", global = true)]
+    pub key: Option<PathBuf>,
+
+    /// Print raw JSON instead of formatted output
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generate a new Ed25519 key file
+    Keygen {
+        /// Overwrite an existing key file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Log in with the key file and save the session
+    Login,
+    /// End the saved session
+    Logout,
+    /// Show the current session
+    Whoami,
+    /// Run a prompt through the orchestrator
+    Run(RunArgs),
+    /// Answer the questions an orchestration stopped on and continue it
+    Answer {
+        orchestration_id: u64,
+        /// One answer per question, in order
+        #[arg(required = true)]
+        answers: Vec<String>,
+    },
+    /// Manage tasks
+    #[command(subcommand)]
+    Task(TaskCommand),
+    /// Stream events from the server
+    Events(EventsArgs),
+    /// Query and edit ZSEI
+    #[command(subcommand)]
+    Zsei(ZseiCommand),
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Prompt text
+    pub prompt: String,
+    /// Attach a file (repeatable)
+    #[arg(short, long = "file")]
+    pub files: Vec<PathBuf>,
+    #[arg(long)]
+    pub project: Option<u64>,
+    #[arg(long)]
+    pub workspace: Option<u64>,
+    /// Use at most this many tokens of the model's context
+    #[arg(long)]
+    pub token_budget: Option<u32>,
+    /// Enable consciousness for this request
+    #[arg(long)]
+    pub consciousness: bool,
+}
+
+#[derive(Subcommand)]
+pub enum TaskCommand {
+    /// List tasks
+    List {
+        /// Filter by status (queued, running, completed, failed, cancelled)
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+        #[arg(long, default_value_t = 0)]
+        offset: u32,
+    },
+    /// Show a task
+    Get { task_id: u64 },
+    /// Cancel a task
+    Cancel { task_id: u64 },
+    /// Retry a failed or cancelled task
+    Retry { task_id: u64 },
+    /// Print task logs
+    Logs {
+        task_id: u64,
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+        /// Keep printing new entries until the task finishes
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Follow a task's progress and pipeline executions until it finishes
+    Watch { task_id: u64 },
+}
+
+#[derive(Args)]
+pub struct EventsArgs {
+    /// Topic to subscribe to (task, pipeline, orchestration, zsei, consciousness)
+    #[arg(long = "topic")]
+    pub topics: Vec<String>,
+    /// Task ID to subscribe to
+    #[arg(long = "task")]
+    pub task_ids: Vec<u64>,
+    /// Replay retained events after this sequence number
+    #[arg(long)]
+    pub since: Option<u64>,
+}
+
+#[derive(Subcommand)]
+pub enum ZseiCommand {
+    /// Show a container
+    Get { container_id: u64 },
+    /// Run a query (JSON ZSEIQuery, or @file)
+    Query { query: String },
+    /// Traverse from a container
+    Traverse {
+        container_id: u64,
+        /// Traversal mode (e.g. Structural, Semantic, Contextual, Hybrid)
+        #[arg(long)]
+        mode: Option<String>,
+        #[arg(long)]
+        max_depth: Option<u16>,
+        #[arg(long)]
+        max_results: Option<u32>,
+        /// Only keep containers with this keyword (repeatable)
+        #[arg(long = "keyword")]
+        keywords: Vec<String>,
+    },
+    /// Export a container (and its descendants) to a JSON file
+    Export {
+        container_id: u64,
+        /// Output file (default stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only export the container itself
+        #[arg(long)]
+        no_children: bool,
+    },
+    /// Import containers from an export file
+    Import { file: PathBuf },
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = commands::run(cli).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let cli = Cli::try_parse_from([
+            "ozone", "task", "logs", "42", "--follow", "--limit", "20", "--json",
+        ])
+        .unwrap();
+        assert!(cli.json);
+        assert_eq!(cli.server, "http://127.0.0.1:50051");
+        assert!(matches!(
+            cli.command,
+            Command::Task(TaskCommand::Logs {
+                task_id: 42,
+                limit: 20,
+                follow: true
+            })
+        ));
+
+        let cli = Cli::try_parse_from([
+            "ozone",
+            "--server",
+            "http://node:8080",
+            "run",
+            "summarise this",
+            "-f",
+            "a.txt",
+            "--file",
+            "b.rs",
+            "--token-budget",
+            "5000",
+        ])
+        .unwrap();
+        assert_eq!(cli.server, "http://node:8080");
+        let Command::Run(args) = cli.command else {
+            panic!("expected run");
+        };
+        assert_eq!(args.prompt, "summarise this");
+        assert_eq!(args.files, [PathBuf::from("a.txt"), PathBuf::from("b.rs")]);
+        assert_eq!(args.token_budget, Some(5000));
+        assert!(!args.consciousness);
+
+        let cli = Cli::try_parse_from(["ozone", "answer", "9", "Rust", "the api crate"]).unwrap();
+        let Command::Answer {
+            orchestration_id,
+            answers,
+        } = cli.command
+        else {
+            panic!("expected answer");
+        };
+        assert_eq!(orchestration_id, 9);
+        assert_eq!(answers, ["Rust", "the api crate"]);
+        assert!(Cli::try_parse_from(["ozone", "answer", "9"]).is_err());
+
+        assert!(Cli::try_parse_from(["ozone", "task", "get", "not-a-number"]).is_err());
+        assert!(Cli::try_parse_from(["ozone", "zsei"]).is_err());
+    }
+}
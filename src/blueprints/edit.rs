@@ -0,0 +1,623 @@
+//! Blueprint editing
+//!
+//! `apply` makes one `BlueprintModification` to a copy of a blueprint,
+//! renumbers step order, checks that dependencies and
+//! `StepInputSource::PreviousStep` inputs still point at earlier steps, and
+//! bumps the version: major for edits that take a step away, minor for those
+//! that add, move or change what a step does, patch for wording. `diff`
+//! describes the difference between two versions line by line.
+
+use crate::types::blueprint::{
+    Blueprint, BlueprintModification, BlueprintStep, StepInputSource, StepUpdate,
+};
+use crate::types::{OzoneError, OzoneResult, SemVer};
+use std::collections::HashMap;
+
+/// Part of the version an edit bumps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VersionBump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl VersionBump {
+    pub fn of(modification: &BlueprintModification) -> Self {
+        match modification {
+            BlueprintModification::RemoveStep(_) | BlueprintModification::ReplaceStep { .. } => {
+                Self::Major
+            }
+            BlueprintModification::AddStep(_)
+            | BlueprintModification::MoveStep { .. }
+            | BlueprintModification::InsertBefore { .. }
+            | BlueprintModification::InsertAfter { .. } => Self::Minor,
+            BlueprintModification::EditStep { updates, .. } => {
+                let StepUpdate {
+                    action,
+                    inputs,
+                    outputs,
+                    pipeline_suggestion,
+                    conditional,
+                    ..
+                } = updates;
+                if action.is_some()
+                    || inputs.is_some()
+                    || outputs.is_some()
+                    || pipeline_suggestion.is_some()
+                    || conditional.is_some()
+                {
+                    Self::Minor
+                } else {
+                    Self::Patch
+                }
+            }
+        }
+    }
+
+    /// The next version after `version`, or an error once the part being
+    /// bumped is at its largest
+    pub fn apply(self, version: &SemVer) -> OzoneResult<SemVer> {
+        let bump = |part: u16| {
+            part.checked_add(1).ok_or_else(|| {
+                OzoneError::ValidationError(format!("Version {} cannot be bumped further", version))
+            })
+        };
+        Ok(match self {
+            Self::Major => SemVer {
+                major: bump(version.major)?,
+                minor: 0,
+                patch: 0,
+            },
+            Self::Minor => SemVer {
+                minor: bump(version.minor)?,
+                patch: 0,
+                ..version.clone()
+            },
+            Self::Patch => SemVer {
+                patch: bump(version.patch)?,
+                ..version.clone()
+            },
+        })
+    }
+}
+
+/// The blueprint with `modification` made, validated and its version bumped
+pub fn apply(
+    blueprint: &Blueprint,
+    modification: &BlueprintModification,
+) -> OzoneResult<Blueprint> {
+    let mut edited = blueprint.clone();
+    let steps = &mut edited.steps;
+    steps.sort_by_key(|s| s.order);
+
+    match modification.clone() {
+        BlueprintModification::AddStep(step) => steps.push(step),
+        BlueprintModification::RemoveStep(step_id) => {
+            let index = position(steps, step_id)?;
+            steps.remove(index);
+            edited.dependencies.retain(|d| d.step_id != step_id);
+        }
+        BlueprintModification::MoveStep { step_id, new_order } => {
+            let step = steps.remove(position(steps, step_id)?);
+            let index = (new_order as usize).min(steps.len());
+            steps.insert(index, step);
+        }
+        BlueprintModification::EditStep { step_id, updates } => {
+            let index = position(steps, step_id)?;
+            update(&mut steps[index], updates);
+        }
+        BlueprintModification::InsertBefore {
+            reference_step,
+            new_step,
+        } => {
+            let index = position(steps, reference_step)?;
+            steps.insert(index, new_step);
+        }
+        BlueprintModification::InsertAfter {
+            reference_step,
+            new_step,
+        } => {
+            let index = position(steps, reference_step)?;
+            steps.insert(index + 1, new_step);
+        }
+        BlueprintModification::ReplaceStep { step_id, new_step } => {
+            let index = position(steps, step_id)?;
+            steps[index] = new_step;
+        }
+    }
+    for (order, step) in steps.iter_mut().enumerate() {
+        step.order = order as u32;
+    }
+
+    validate(&edited)?;
+    edited.version = VersionBump::of(modification).apply(&blueprint.version)?;
+    Ok(edited)
+}
+
+/// Check that step IDs are unique and that every dependency and
+/// previous-step input names an earlier step (and, for inputs, one of its
+/// outputs)
+pub fn validate(blueprint: &Blueprint) -> OzoneResult<()> {
+    let mut problems = Vec::new();
+    let mut orders: HashMap<u64, u32> = HashMap::new();
+    for step in &blueprint.steps {
+        if orders.insert(step.step_id, step.order).is_some() {
+            problems.push(format!("step {} appears more than once", step.step_id));
+        }
+    }
+
+    for dependency in &blueprint.dependencies {
+        let Some(&order) = orders.get(&dependency.step_id) else {
+            problems.push(format!(
+                "dependencies are listed for missing step {}",
+                dependency.step_id
+            ));
+            continue;
+        };
+        for depends_on in &dependency.depends_on {
+            match orders.get(depends_on) {
+                None => problems.push(format!(
+                    "step {} depends on missing step {}",
+                    dependency.step_id, depends_on
+                )),
+                // Steps run in order, so this also rules out cycles
+                Some(&other) if other >= order => problems.push(format!(
+                    "step {} depends on step {}, which does not run before it",
+                    dependency.step_id, depends_on
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    for step in &blueprint.steps {
+        for input in &step.inputs {
+            let StepInputSource::PreviousStep(source_id, output) = &input.source else {
+                continue;
+            };
+            match blueprint.steps.iter().find(|s| s.step_id == *source_id) {
+                None => problems.push(format!(
+                    "input '{}' of step {} reads missing step {}",
+                    input.name, step.step_id, source_id
+                )),
+                Some(source) if source.order >= step.order => problems.push(format!(
+                    "input '{}' of step {} reads step {}, which does not run before it",
+                    input.name, step.step_id, source_id
+                )),
+                Some(source) if !source.outputs.iter().any(|o| &o.name == output) => {
+                    problems.push(format!(
+                        "input '{}' of step {} reads output '{}', which step {} does not produce",
+                        input.name, step.step_id, output, source_id
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(OzoneError::ValidationError(format!(
+            "Invalid blueprint {}: {}",
+            blueprint.blueprint_id,
+            problems.join("; ")
+        )))
+    }
+}
+
+/// Human-readable changes from `old` to `new`, one per line
+pub fn diff(old: &Blueprint, new: &Blueprint) -> Vec<String> {
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(format!("Renamed from '{}' to '{}'", old.name, new.name));
+    }
+    if old.description != new.description {
+        changes.push("Description changed".to_string());
+    }
+
+    let old_steps = ordered(old);
+    let new_steps = ordered(new);
+    let find = |steps: &[&BlueprintStep], id: u64| steps.iter().position(|s| s.step_id == id);
+
+    for (index, step) in old_steps.iter().enumerate() {
+        if find(&new_steps, step.step_id).is_none() {
+            changes.push(format!(
+                "Removed step {} (position {}): {}",
+                step.step_id,
+                index + 1,
+                step.action
+            ));
+        }
+    }
+    for (index, step) in new_steps.iter().enumerate() {
+        if find(&old_steps, step.step_id).is_none() {
+            changes.push(format!(
+                "Added step {} at position {}: {} - {}",
+                step.step_id,
+                index + 1,
+                step.action,
+                step.description
+            ));
+        }
+    }
+
+    // Steps kept in both, in each version's order; those off the longest
+    // common run are the ones that moved
+    let kept_old: Vec<u64> = old_steps
+        .iter()
+        .map(|s| s.step_id)
+        .filter(|&id| find(&new_steps, id).is_some())
+        .collect();
+    let kept_new: Vec<u64> = new_steps
+        .iter()
+        .map(|s| s.step_id)
+        .filter(|&id| find(&old_steps, id).is_some())
+        .collect();
+    let unmoved = longest_common_subsequence(&kept_old, &kept_new);
+    for &id in &kept_new {
+        let (Some(from), Some(to)) = (find(&old_steps, id), find(&new_steps, id)) else {
+            continue;
+        };
+        if !unmoved.contains(&id) {
+            changes.push(format!(
+                "Moved step {} from position {} to {}",
+                id,
+                from + 1,
+                to + 1
+            ));
+        }
+        changes.extend(step_changes(old_steps[from], new_steps[to]));
+    }
+
+    let depends_on = |blueprint: &Blueprint, id: u64| -> Vec<u64> {
+        let mut ids: Vec<u64> = blueprint
+            .dependencies
+            .iter()
+            .filter(|d| d.step_id == id)
+            .flat_map(|d| d.depends_on.iter().copied())
+            .collect();
+        ids.sort_unstable();
+        ids
+    };
+    for &id in &kept_new {
+        let (before, after) = (depends_on(old, id), depends_on(new, id));
+        if before != after {
+            changes.push(format!(
+                "Step {}: dependencies changed from {:?} to {:?}",
+                id, before, after
+            ));
+        }
+    }
+    changes
+}
+
+fn position(steps: &[BlueprintStep], step_id: u64) -> OzoneResult<usize> {
+    steps
+        .iter()
+        .position(|s| s.step_id == step_id)
+        .ok_or_else(|| OzoneError::NotFound(format!("Blueprint step {} not found", step_id)))
+}
+
+fn update(step: &mut BlueprintStep, updates: StepUpdate) {
+    if let Some(action) = updates.action {
+        step.action = action;
+    }
+    if let Some(description) = updates.description {
+        step.description = description;
+    }
+    if let Some(inputs) = updates.inputs {
+        step.inputs = inputs;
+    }
+    if let Some(outputs) = updates.outputs {
+        step.outputs = outputs;
+    }
+    if let Some(pipeline_suggestion) = updates.pipeline_suggestion {
+        step.pipeline_suggestion = pipeline_suggestion;
+    }
+    if let Some(optional) = updates.optional {
+        step.optional = optional;
+    }
+    if let Some(conditional) = updates.conditional {
+        step.conditional = conditional;
+    }
+}
+
+fn ordered(blueprint: &Blueprint) -> Vec<&BlueprintStep> {
+    let mut steps: Vec<&BlueprintStep> = blueprint.steps.iter().collect();
+    steps.sort_by_key(|s| s.order);
+    steps
+}
+
+/// Changes to one step's fields
+fn step_changes(old: &BlueprintStep, new: &BlueprintStep) -> Vec<String> {
+    let id = new.step_id;
+    let mut changes = Vec::new();
+    if old.action != new.action {
+        changes.push(format!(
+            "Step {}: action changed from '{}' to '{}'",
+            id, old.action, new.action
+        ));
+    }
+    if old.description != new.description {
+        changes.push(format!(
+            "Step {}: description changed from '{}' to '{}'",
+            id, old.description, new.description
+        ));
+    }
+    if old.inputs != new.inputs {
+        let names = |step: &BlueprintStep| -> Vec<String> {
+            step.inputs.iter().map(|i| i.name.clone()).collect()
+        };
+        changes.push(format!(
+            "Step {}: inputs changed from {:?} to {:?}",
+            id,
+            names(old),
+            names(new)
+        ));
+    }
+    if old.outputs != new.outputs {
+        let names = |step: &BlueprintStep| -> Vec<String> {
+            step.outputs.iter().map(|o| o.name.clone()).collect()
+        };
+        changes.push(format!(
+            "Step {}: outputs changed from {:?} to {:?}",
+            id,
+            names(old),
+            names(new)
+        ));
+    }
+    if old.pipeline_suggestion != new.pipeline_suggestion {
+        changes.push(format!(
+            "Step {}: pipeline changed from {:?} to {:?}",
+            id, old.pipeline_suggestion, new.pipeline_suggestion
+        ));
+    }
+    if old.optional != new.optional {
+        changes.push(format!(
+            "Step {}: {}",
+            id,
+            if new.optional {
+                "made optional"
+            } else {
+                "made required"
+            }
+        ));
+    }
+    if old.conditional != new.conditional {
+        let condition = |step: &BlueprintStep| -> String {
+            step.conditional
+                .as_ref()
+                .map(|c| format!("'{}'", c.condition))
+                .unwrap_or_else(|| "none".to_string())
+        };
+        changes.push(format!(
+            "Step {}: condition changed from {} to {}",
+            id,
+            condition(old),
+            condition(new)
+        ));
+    }
+    changes
+}
+
+fn longest_common_subsequence(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut common = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            common.push(a[i]);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    common
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::types::blueprint::{
+        BlueprintDependency, BlueprintDependencyType, StepInput, StepOutput, TaskSignature,
+    };
+    use crate::types::ConsensusStatus;
+
+    pub fn step(step_id: u64, action: &str, reads: Option<(u64, &str)>) -> BlueprintStep {
+        BlueprintStep {
+            step_id,
+            order: 0,
+            action: action.to_string(),
+            description: format!("{} the input", action),
+            inputs: reads
+                .map(|(source, output)| StepInput {
+                    name: output.to_string(),
+                    source: StepInputSource::PreviousStep(source, output.to_string()),
+                    required: true,
+                })
+                .into_iter()
+                .collect(),
+            outputs: vec![StepOutput {
+                name: format!("{}_out", action),
+                description: String::new(),
+                stored: false,
+            }],
+            pipeline_suggestion: None,
+            optional: false,
+            conditional: None,
+        }
+    }
+
+    pub fn blueprint(steps: Vec<BlueprintStep>) -> Blueprint {
+        let mut blueprint = Blueprint {
+            blueprint_id: 9,
+            name: "Parser".to_string(),
+            description: String::new(),
+            task_signature: TaskSignature {
+                input_types: vec![],
+                output_type: "code".to_string(),
+                constraints: vec![],
+                hash: [0; 32],
+            },
+            steps,
+            dependencies: vec![],
+            methodologies_used: vec![],
+            modalities: vec![],
+            categories: vec![],
+            keywords: vec![],
+            topics: vec![],
+            validated: false,
+            validation_runs: 0,
+            success_rate: 0.0,
+            created_at: 0,
+            created_by: vec![],
+            version: SemVer {
+                major: 1,
+                minor: 0,
+                patch: 0,
+            },
+            distributed: false,
+            consensus_status: ConsensusStatus::Open,
+            usage_count: 0,
+        };
+        for (order, step) in blueprint.steps.iter_mut().enumerate() {
+            step.order = order as u32;
+        }
+        blueprint
+    }
+
+    #[test]
+    fn test_apply_validates_references_bumps_version_and_diffs() {
+        let mut original = blueprint(vec![
+            step(1, "tokenize", None),
+            step(2, "parse", Some((1, "tokenize_out"))),
+            step(3, "report", None),
+        ]);
+        original.dependencies.push(BlueprintDependency {
+            step_id: 2,
+            depends_on: vec![1],
+            dependency_type: BlueprintDependencyType::DataFlow,
+        });
+        validate(&original).unwrap();
+
+        // Wording is a patch
+        let reworded = apply(
+            &original,
+            &BlueprintModification::EditStep {
+                step_id: 3,
+                updates: StepUpdate {
+                    description: Some("Summarise errors".to_string()),
+                    ..Default::default()
+                },
+            },
+        )
+        .unwrap();
+        assert_eq!(reworded.version.to_string(), "1.0.1");
+
+        // Inserting is minor; the inserted step takes its position
+        let inserted = apply(
+            &reworded,
+            &BlueprintModification::InsertAfter {
+                reference_step: 1,
+                new_step: step(4, "normalize", Some((1, "tokenize_out"))),
+            },
+        )
+        .unwrap();
+        assert_eq!(inserted.version.to_string(), "1.1.0");
+        let order: Vec<(u64, u32)> = inserted
+            .steps
+            .iter()
+            .map(|s| (s.step_id, s.order))
+            .collect();
+        assert_eq!(order, vec![(1, 0), (4, 1), (2, 2), (3, 3)]);
+
+        // Steps may not move ahead of what they read or depend on
+        for modification in [
+            BlueprintModification::MoveStep {
+                step_id: 2,
+                new_order: 0,
+            },
+            BlueprintModification::RemoveStep(1),
+        ] {
+            let err = apply(&inserted, &modification).unwrap_err();
+            assert!(matches!(err, OzoneError::ValidationError(_)));
+        }
+        let err = apply(
+            &inserted,
+            &BlueprintModification::EditStep {
+                step_id: 2,
+                updates: StepUpdate {
+                    inputs: Some(step(2, "parse", Some((4, "missing"))).inputs),
+                    ..Default::default()
+                },
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("output 'missing'"));
+        assert!(matches!(
+            apply(&inserted, &BlueprintModification::RemoveStep(99)),
+            Err(OzoneError::NotFound(_))
+        ));
+
+        // Removing is major
+        let moved = apply(
+            &inserted,
+            &BlueprintModification::MoveStep {
+                step_id: 3,
+                new_order: 0,
+            },
+        )
+        .unwrap();
+        let removed = apply(&moved, &BlueprintModification::RemoveStep(4)).unwrap();
+        assert_eq!(removed.version.to_string(), "2.0.0");
+
+        assert_eq!(
+            diff(&original, &removed),
+            vec![
+                "Moved step 3 from position 3 to 1".to_string(),
+                "Step 3: description changed from 'report the input' to 'Summarise errors'"
+                    .to_string(),
+            ]
+        );
+        assert_eq!(
+            diff(&reworded, &inserted),
+            vec!["Added step 4 at position 2: normalize - normalize the input".to_string()]
+        );
+
+        // A part at u16::MAX cannot be bumped
+        let mut exhausted = removed;
+        exhausted.version.patch = u16::MAX;
+        let err = apply(
+            &exhausted,
+            &BlueprintModification::EditStep {
+                step_id: 3,
+                updates: StepUpdate {
+                    description: Some("Report errors".to_string()),
+                    ..Default::default()
+                },
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, OzoneError::ValidationError(_)));
+        assert_eq!(
+            VersionBump::Minor
+                .apply(&exhausted.version)
+                .unwrap()
+                .to_string(),
+            "2.1.0"
+        );
+    }
+}
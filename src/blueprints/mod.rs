@@ -1,3 +1,6 @@
 //! Blueprint module — ZSEI-integrated blueprint management
+pub mod edit;
 pub mod store;
-pub use store::BlueprintStore;
+pub mod versions;
+pub use store::{BlueprintStore, ContainerBlueprintSource};
+pub use versions::{BlueprintSource, BlueprintVersion, BlueprintVersionStore};
@@ -1,12 +1,18 @@
 //! Blueprint Store — registers blueprints as ZSEI containers
 
+use super::versions::BlueprintSource;
+use crate::types::blueprint::{
+    Blueprint, BlueprintDependency, BlueprintDependencyType, BlueprintStep, StepInput,
+    StepInputSource, StepOutput, TaskSignature,
+};
 use crate::types::container::{
     blueprint_container_id, Container, ContainerType, Context, GlobalState, IntegrityData,
     LocalState, Metadata, Modality, StoragePointers, TraversalHints, BLUEPRINT_ROOT_ID,
 };
 use crate::types::index::BlueprintIndex;
-use crate::types::{OzoneError, OzoneResult};
+use crate::types::{ConsensusStatus, ContainerID, OzoneError, OzoneResult, SemVer};
 use crate::zsei::ZSEI;
+use serde_json::Value;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -167,9 +173,286 @@ impl BlueprintStore {
     }
 }
 
+/// A blueprint container in ZSEI as the source of the blueprint's edit
+/// history. It blocks on the ZSEI lock, so use it from blocking threads.
+pub struct ContainerBlueprintSource {
+    zsei: Arc<RwLock<ZSEI>>,
+    container: Container,
+}
+
+impl ContainerBlueprintSource {
+    pub fn new(zsei: Arc<RwLock<ZSEI>>, container: Container) -> Self {
+        Self { zsei, container }
+    }
+}
+
+impl BlueprintSource for ContainerBlueprintSource {
+    fn load(&self, _blueprint_id: ContainerID) -> OzoneResult<Blueprint> {
+        let object = self.zsei.blocking_read().load_object(&self.container)?;
+        blueprint_from_container(&self.container, object.as_ref())
+    }
+
+    /// Write the blueprint over the matching fields of the container's
+    /// object, keeping the fields only bootstrap or the orchestrator use
+    fn publish(&self, blueprint: &Blueprint) -> OzoneResult<()> {
+        let zsei = self.zsei.blocking_read();
+        let mut container = self.container.clone();
+        let mut object = match zsei.load_object(&container)? {
+            Some(Value::Object(object)) => object,
+            _ => serde_json::Map::new(),
+        };
+        if let Value::Object(fields) = serde_json::to_value(blueprint)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?
+        {
+            object.extend(fields);
+        }
+        zsei.store_object(&mut container, &Value::Object(object))
+    }
+}
+
+/// The container a blueprint created through the API is stored as, under
+/// the blueprint root with its object at `objects/<id>.json`
+pub fn blueprint_container(blueprint: &Blueprint, owner_id: u64) -> Container {
+    let id = blueprint.blueprint_id;
+    Container {
+        global_state: GlobalState {
+            container_id: id,
+            parent_id: BLUEPRINT_ROOT_ID,
+            child_ids: vec![],
+            child_count: 0,
+            version: 1,
+        },
+        local_state: LocalState {
+            metadata: Metadata {
+                container_type: ContainerType::Blueprint,
+                modality: Modality::Unknown,
+                created_at: blueprint.created_at,
+                updated_at: blueprint.created_at,
+                provenance: "api".to_string(),
+                permissions: 0,
+                owner_id,
+                name: Some(blueprint.name.clone()),
+                materialized_path: Some(format!("/Blueprints/{}", blueprint.name)),
+            },
+            context: Context {
+                keywords: blueprint.keywords.clone(),
+                topics: blueprint.topics.clone(),
+                categories: blueprint.categories.clone(),
+                methodologies: blueprint.methodologies_used.clone(),
+                ..Default::default()
+            },
+            storage: StoragePointers {
+                object_store_path: Some(format!("objects/{}.json", id)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    }
+}
+
+/// The blueprint a container's object holds. Objects written through the
+/// API are serialized `Blueprint`s; bootstrap definitions and blueprints
+/// the orchestrator recorded list their steps in their own shape and are
+/// converted, string inputs reading the latest earlier step that outputs
+/// them and otherwise the task input.
+pub fn blueprint_from_container(
+    container: &Container,
+    object: Option<&Value>,
+) -> OzoneResult<Blueprint> {
+    let id = container.global_state.container_id;
+    let object = object.ok_or_else(|| {
+        OzoneError::ValidationError(format!("Blueprint {} has no stored definition", id))
+    })?;
+    if let Ok(mut blueprint) = serde_json::from_value::<Blueprint>(object.clone()) {
+        blueprint.blueprint_id = id;
+        return Ok(blueprint);
+    }
+
+    let raw_steps = object["steps"]
+        .as_array()
+        .or_else(|| object["storage"]["steps"].as_array())
+        .ok_or_else(|| OzoneError::ValidationError(format!("Blueprint {} lists no steps", id)))?;
+    let mut steps: Vec<BlueprintStep> = Vec::new();
+    let mut dependencies = Vec::new();
+    for raw in raw_steps {
+        let step_id = raw["step_id"]
+            .as_u64()
+            .or_else(|| raw["step_index"].as_u64())
+            .ok_or_else(|| {
+                OzoneError::ValidationError(format!("A step of blueprint {} has no ID", id))
+            })?;
+        let text = |key: &str| raw[key].as_str().unwrap_or_default().to_string();
+        let inputs = raw["inputs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|input| match input.as_str() {
+                Some(name) => Some(StepInput {
+                    name: name.to_string(),
+                    source: steps
+                        .iter()
+                        .rev()
+                        .find(|s| s.outputs.iter().any(|o| o.name == name))
+                        .map(|s| StepInputSource::PreviousStep(s.step_id, name.to_string()))
+                        .unwrap_or_else(|| StepInputSource::TaskInput(name.to_string())),
+                    required: true,
+                }),
+                None => serde_json::from_value(input.clone()).ok(),
+            })
+            .collect();
+        let outputs = raw["outputs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|output| match output.as_str() {
+                Some(name) => Some(StepOutput {
+                    name: name.to_string(),
+                    description: String::new(),
+                    stored: false,
+                }),
+                None => serde_json::from_value(output.clone()).ok(),
+            })
+            .collect();
+        let depends_on: Vec<u64> =
+            serde_json::from_value(raw["depends_on"].clone()).unwrap_or_default();
+        if !depends_on.is_empty() {
+            dependencies.push(BlueprintDependency {
+                step_id,
+                depends_on,
+                dependency_type: BlueprintDependencyType::Sequential,
+            });
+        }
+        steps.push(BlueprintStep {
+            step_id,
+            order: steps.len() as u32,
+            action: Some(text("action"))
+                .filter(|a| !a.is_empty())
+                .unwrap_or_else(|| text("name")),
+            description: text("description"),
+            inputs,
+            outputs,
+            pipeline_suggestion: raw["pipeline_id"].as_u64(),
+            optional: raw["optional"].as_bool().unwrap_or(false),
+            conditional: None,
+        });
+    }
+
+    let context = &container.local_state.context;
+    let task_signature = serde_json::from_value(object["task_signature"].clone())
+        .or_else(|_| serde_json::from_value(object["context"]["task_signature"].clone()))
+        .unwrap_or_else(|_| {
+            let signature = crate::orchestrator::matching::task_signature(
+                &context.topics,
+                crate::orchestrator::matching::infer_output_type(&context.keywords),
+                &[],
+            );
+            TaskSignature {
+                input_types: signature.input_types,
+                output_type: signature.output_type,
+                constraints: signature.constraints,
+                hash: signature.hash,
+            }
+        });
+    let stats = &object["storage"];
+    Ok(Blueprint {
+        blueprint_id: id,
+        name: object["name"]
+            .as_str()
+            .map(str::to_string)
+            .or_else(|| container.local_state.metadata.name.clone())
+            .unwrap_or_default(),
+        description: object["description"]
+            .as_str()
+            .or_else(|| object["metadata"]["description"].as_str())
+            .unwrap_or_default()
+            .to_string(),
+        task_signature,
+        steps,
+        dependencies,
+        methodologies_used: context.methodologies.clone(),
+        modalities: vec![],
+        categories: context.categories.clone(),
+        keywords: context.keywords.clone(),
+        topics: context.topics.clone(),
+        validated: stats["validated"].as_bool().unwrap_or(false),
+        validation_runs: stats["validation_runs"].as_u64().unwrap_or(0) as u32,
+        success_rate: stats["success_rate"].as_f64().unwrap_or(0.0) as f32,
+        created_at: container.local_state.metadata.created_at,
+        created_by: vec![],
+        version: object["version"]
+            .as_str()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(SemVer {
+                major: 1,
+                minor: 0,
+                patch: 0,
+            }),
+        distributed: false,
+        consensus_status: ConsensusStatus::Open,
+        usage_count: stats["usage_count"].as_u64().unwrap_or(0),
+    })
+}
+
 fn now() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_secs()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bootstrap_definition_converts_to_blueprint() {
+        let mut container = Container {
+            global_state: GlobalState {
+                container_id: 7,
+                ..Default::default()
+            },
+            local_state: LocalState::default(),
+        };
+        container.local_state.metadata.name = Some("Code Review".to_string());
+        container.local_state.context.keywords = vec!["review".to_string()];
+        let object = serde_json::json!({
+            "blueprint_id": 2,
+            "name": "Code Review",
+            "version": "2.0.0",
+            "steps": [
+                {"step_id": 1, "name": "receive_code", "pipeline_id": 1,
+                 "action": "receive_task", "outputs": ["code_files"]},
+                {"step_id": 2, "name": "analyze", "description": "Analyze the code",
+                 "pipeline_id": 101, "inputs": ["code_files", "focus"],
+                 "outputs": ["code_analysis"]}
+            ]
+        });
+
+        let blueprint = blueprint_from_container(&container, Some(&object)).unwrap();
+        assert_eq!(blueprint.blueprint_id, 7);
+        assert_eq!(blueprint.version.to_string(), "2.0.0");
+        assert_eq!(blueprint.steps[1].action, "analyze");
+        assert_eq!(blueprint.steps[1].order, 1);
+        assert_eq!(
+            blueprint.steps[1]
+                .inputs
+                .iter()
+                .map(|i| i.source.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                StepInputSource::PreviousStep(1, "code_files".to_string()),
+                StepInputSource::TaskInput("focus".to_string()),
+            ]
+        );
+        super::super::edit::validate(&blueprint).unwrap();
+
+        // A serialized blueprint round-trips as it is
+        let stored = serde_json::to_value(&blueprint).unwrap();
+        let reloaded = blueprint_from_container(&container, Some(&stored)).unwrap();
+        assert_eq!(reloaded.steps, blueprint.steps);
+        assert!(matches!(
+            blueprint_from_container(&container, None),
+            Err(OzoneError::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,491 @@
+//! Blueprint version history
+//!
+//! Every blueprint edited through the API keeps each of its versions: one
+//! JSON file per blueprint holds the snapshots, oldest first, with the
+//! modification that produced each and the changes it made. Modifications
+//! are applied all or none, so a rejected edit leaves no partial version.
+//!
+//! A `BlueprintSource` holds the blueprint itself: a history missing its
+//! file is seeded from the source, and each accepted version is published
+//! to it once the history is saved, which is restored if publishing fails.
+//! A source whose blueprint was edited elsewhere to a later version adds
+//! that version to the history; one behind the history is a conflict.
+
+use super::edit;
+use crate::types::blueprint::{Blueprint, BlueprintModification};
+use crate::types::{ContainerID, OzoneError, OzoneResult, SemVer};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// One version of a blueprint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlueprintVersion {
+    pub version: SemVer,
+    /// None for the version the blueprint was created with
+    pub modification: Option<BlueprintModification>,
+    /// What changed from the previous version
+    pub changes: Vec<String>,
+    pub created_at: u64,
+    pub blueprint: Blueprint,
+}
+
+/// Where a blueprint lives outside its version history
+pub trait BlueprintSource {
+    /// The blueprint as the source holds it now
+    fn load(&self, blueprint_id: ContainerID) -> OzoneResult<Blueprint>;
+
+    /// Store an accepted version as the blueprint's current one
+    fn publish(&self, blueprint: &Blueprint) -> OzoneResult<()>;
+}
+
+/// Version histories, one file per blueprint ID
+pub struct BlueprintVersionStore {
+    dir: PathBuf,
+    /// Held across each read-modify-write of a history
+    write_lock: Mutex<()>,
+}
+
+impl BlueprintVersionStore {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Start the history of a new blueprint and publish it to `source`
+    pub fn create(
+        &self,
+        blueprint: Blueprint,
+        source: &dyn BlueprintSource,
+    ) -> OzoneResult<BlueprintVersion> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if self.path(blueprint.blueprint_id).exists() {
+            return Err(OzoneError::ValidationError(format!(
+                "Blueprint {} already exists",
+                blueprint.blueprint_id
+            )));
+        }
+        let version = first_version(blueprint, "Created")?;
+        let blueprint_id = version.blueprint.blueprint_id;
+        self.save(blueprint_id, std::slice::from_ref(&version))?;
+        if let Err(e) = source.publish(&version.blueprint) {
+            let _ = std::fs::remove_file(self.path(blueprint_id));
+            return Err(e);
+        }
+        Ok(version)
+    }
+
+    /// Every version of a blueprint, oldest first, ending with the version
+    /// `source` holds; a blueprint never edited before starts with it
+    pub fn history(
+        &self,
+        blueprint_id: ContainerID,
+        source: &dyn BlueprintSource,
+    ) -> OzoneResult<Vec<BlueprintVersion>> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.read_synced(blueprint_id, source)
+    }
+
+    /// The latest version of a blueprint
+    pub fn current(
+        &self,
+        blueprint_id: ContainerID,
+        source: &dyn BlueprintSource,
+    ) -> OzoneResult<Blueprint> {
+        self.history(blueprint_id, source)?
+            .pop()
+            .map(|v| v.blueprint)
+            .ok_or_else(|| OzoneError::NotFound(format!("Blueprint {} not found", blueprint_id)))
+    }
+
+    /// A blueprint as it was at `version`
+    pub fn version(
+        &self,
+        blueprint_id: ContainerID,
+        version: &SemVer,
+        source: &dyn BlueprintSource,
+    ) -> OzoneResult<Blueprint> {
+        self.history(blueprint_id, source)?
+            .into_iter()
+            .find(|v| &v.version == version)
+            .map(|v| v.blueprint)
+            .ok_or_else(|| {
+                OzoneError::NotFound(format!(
+                    "Version {} of blueprint {} not found",
+                    version, blueprint_id
+                ))
+            })
+    }
+
+    /// Apply modifications in order, each as a new version, and publish the
+    /// last to `source`; if any is rejected none are kept
+    pub fn modify(
+        &self,
+        blueprint_id: ContainerID,
+        modifications: &[BlueprintModification],
+        source: &dyn BlueprintSource,
+    ) -> OzoneResult<Vec<BlueprintVersion>> {
+        if modifications.is_empty() {
+            return Err(OzoneError::ValidationError(
+                "No modifications given".to_string(),
+            ));
+        }
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut history = self.read_synced(blueprint_id, source)?;
+        let mut current = history
+            .last()
+            .map(|v| v.blueprint.clone())
+            .ok_or_else(|| OzoneError::NotFound(format!("Blueprint {} not found", blueprint_id)))?;
+
+        let mut added = Vec::new();
+        for (index, modification) in modifications.iter().enumerate() {
+            let edited = edit::apply(&current, modification).map_err(|e| match e {
+                OzoneError::ValidationError(msg) => OzoneError::ValidationError(format!(
+                    "Modification {} rejected: {}",
+                    index + 1,
+                    msg
+                )),
+                other => other,
+            })?;
+            added.push(BlueprintVersion {
+                version: edited.version.clone(),
+                modification: Some(modification.clone()),
+                changes: edit::diff(&current, &edited),
+                created_at: now(),
+                blueprint: edited.clone(),
+            });
+            current = edited;
+        }
+        let previous = history.len();
+        history.extend(added.iter().cloned());
+        self.save(blueprint_id, &history)?;
+        if let Err(e) = source.publish(&current) {
+            history.truncate(previous);
+            if let Err(restore) = self.save(blueprint_id, &history) {
+                tracing::warn!(
+                    "Failed to restore the history of blueprint {}: {}",
+                    blueprint_id,
+                    restore
+                );
+            }
+            return Err(e);
+        }
+        Ok(added)
+    }
+
+    /// Changes from one version of a blueprint to another
+    pub fn diff(
+        &self,
+        blueprint_id: ContainerID,
+        from: &SemVer,
+        to: &SemVer,
+        source: &dyn BlueprintSource,
+    ) -> OzoneResult<Vec<String>> {
+        let old = self.version(blueprint_id, from, source)?;
+        let new = self.version(blueprint_id, to, source)?;
+        Ok(edit::diff(&old, &new))
+    }
+
+    /// The saved history, or one seeded from `source`, brought up to the
+    /// version `source` holds; the caller holds the write lock
+    fn read_synced(
+        &self,
+        blueprint_id: ContainerID,
+        source: &dyn BlueprintSource,
+    ) -> OzoneResult<Vec<BlueprintVersion>> {
+        let mut blueprint = source.load(blueprint_id)?;
+        blueprint.blueprint_id = blueprint_id;
+        let mut history = match self.read(blueprint_id)? {
+            Some(history) if !history.is_empty() => history,
+            _ => {
+                let history = vec![first_version(blueprint, "Imported")?];
+                self.save(blueprint_id, &history)?;
+                return Ok(history);
+            }
+        };
+        let head = &history[history.len() - 1].blueprint;
+        if blueprint.version == head.version {
+            return Ok(history);
+        }
+        if blueprint.version < head.version {
+            return Err(OzoneError::Conflict(format!(
+                "Blueprint {} is at version {}, behind its history at {}",
+                blueprint_id, blueprint.version, head.version
+            )));
+        }
+
+        // Edited outside the history since its last version
+        edit::validate(&blueprint)?;
+        let version = BlueprintVersion {
+            version: blueprint.version.clone(),
+            modification: None,
+            changes: edit::diff(head, &blueprint),
+            created_at: now(),
+            blueprint,
+        };
+        history.push(version);
+        self.save(blueprint_id, &history)?;
+        Ok(history)
+    }
+
+    fn read(&self, blueprint_id: ContainerID) -> OzoneResult<Option<Vec<BlueprintVersion>>> {
+        let path = self.path(blueprint_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read(&path)?;
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))
+    }
+
+    fn save(&self, blueprint_id: ContainerID, history: &[BlueprintVersion]) -> OzoneResult<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(blueprint_id);
+        let tmp = path.with_extension("json.tmp");
+        let content = serde_json::to_vec(history)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn path(&self, blueprint_id: ContainerID) -> PathBuf {
+        self.dir.join(format!("{}.json", blueprint_id))
+    }
+}
+
+/// The version a history starts with
+fn first_version(blueprint: Blueprint, how: &str) -> OzoneResult<BlueprintVersion> {
+    edit::validate(&blueprint)?;
+    Ok(BlueprintVersion {
+        version: blueprint.version.clone(),
+        modification: None,
+        changes: vec![format!("{} with {} steps", how, blueprint.steps.len())],
+        created_at: now(),
+        blueprint,
+    })
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blueprints::edit::tests::{blueprint, step};
+    use crate::types::blueprint::StepUpdate;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Holds blueprint 12 before it has a history and whatever is
+    /// published, and records what is published
+    #[derive(Default)]
+    struct MemorySource {
+        blueprints: Mutex<HashMap<ContainerID, Blueprint>>,
+        published: Mutex<Vec<Blueprint>>,
+        fail_publish: AtomicBool,
+    }
+
+    impl BlueprintSource for MemorySource {
+        fn load(&self, blueprint_id: ContainerID) -> OzoneResult<Blueprint> {
+            if let Some(blueprint) = self.blueprints.lock().unwrap().get(&blueprint_id) {
+                return Ok(blueprint.clone());
+            }
+            if blueprint_id != 12 {
+                return Err(OzoneError::NotFound(format!(
+                    "Blueprint {} not found",
+                    blueprint_id
+                )));
+            }
+            Ok(blueprint(vec![step(1, "summarize", None)]))
+        }
+
+        fn publish(&self, blueprint: &Blueprint) -> OzoneResult<()> {
+            if self.fail_publish.load(Ordering::SeqCst) {
+                return Err(OzoneError::StorageError("Disk full".to_string()));
+            }
+            self.published.lock().unwrap().push(blueprint.clone());
+            self.blueprints
+                .lock()
+                .unwrap()
+                .insert(blueprint.blueprint_id, blueprint.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_history_keeps_versions_and_rejects_partial_edits() {
+        let dir =
+            std::env::temp_dir().join(format!("ozone_blueprint_versions_{}", uuid::Uuid::new_v4()));
+        let store = BlueprintVersionStore::new(&dir);
+        let source = MemorySource::default();
+        store
+            .create(
+                blueprint(vec![step(1, "tokenize", None), step(2, "parse", None)]),
+                &source,
+            )
+            .unwrap();
+        assert!(store.create(blueprint(vec![]), &source).is_err());
+
+        let added = store
+            .modify(
+                9,
+                &[
+                    BlueprintModification::AddStep(step(3, "report", Some((2, "parse_out")))),
+                    BlueprintModification::EditStep {
+                        step_id: 1,
+                        updates: StepUpdate {
+                            optional: Some(true),
+                            ..Default::default()
+                        },
+                    },
+                ],
+                &source,
+            )
+            .unwrap();
+        let versions: Vec<String> = added.iter().map(|v| v.version.to_string()).collect();
+        assert_eq!(versions, vec!["1.1.0", "1.1.1"]);
+        assert_eq!(added[1].changes, vec!["Step 1: made optional".to_string()]);
+
+        // The second edit is invalid, so the first is not kept either
+        let err = store
+            .modify(
+                9,
+                &[
+                    BlueprintModification::RemoveStep(1),
+                    BlueprintModification::RemoveStep(2),
+                ],
+                &source,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Modification 2 rejected"));
+        assert_eq!(store.history(9, &source).unwrap().len(), 3);
+
+        // The created and the last accepted version were published
+        let published: Vec<String> = source
+            .published
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|b| b.version.to_string())
+            .collect();
+        assert_eq!(published, vec!["1.0.0", "1.1.1"]);
+
+        let current = store.current(9, &source).unwrap();
+        assert_eq!(current.version.to_string(), "1.1.1");
+        assert_eq!(current.steps.len(), 3);
+        assert_eq!(
+            store
+                .version(9, &"1.0.0".parse().unwrap(), &source)
+                .unwrap()
+                .steps
+                .len(),
+            2
+        );
+        assert_eq!(
+            store
+                .diff(
+                    9,
+                    &"1.0.0".parse().unwrap(),
+                    &"v1.1.1".parse().unwrap(),
+                    &source
+                )
+                .unwrap(),
+            vec![
+                "Added step 3 at position 3: report - report the input".to_string(),
+                "Step 1: made optional".to_string(),
+            ]
+        );
+        assert!(matches!(
+            store.current(10, &source),
+            Err(OzoneError::NotFound(_))
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_history_is_seeded_from_the_source() {
+        let dir =
+            std::env::temp_dir().join(format!("ozone_blueprint_versions_{}", uuid::Uuid::new_v4()));
+        let store = BlueprintVersionStore::new(&dir);
+        let source = MemorySource::default();
+
+        let history = store.history(12, &source).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].blueprint.blueprint_id, 12);
+        assert_eq!(
+            history[0].changes,
+            vec!["Imported with 1 steps".to_string()]
+        );
+
+        let added = store
+            .modify(
+                12,
+                &[BlueprintModification::AddStep(step(
+                    2,
+                    "report",
+                    Some((1, "summarize_out")),
+                ))],
+                &source,
+            )
+            .unwrap();
+        assert_eq!(added[0].version.to_string(), "1.1.0");
+        assert_eq!(store.history(12, &source).unwrap().len(), 2);
+        assert_eq!(source.published.lock().unwrap()[0].steps.len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_history_follows_the_source_and_survives_failed_publishes() {
+        let dir =
+            std::env::temp_dir().join(format!("ozone_blueprint_versions_{}", uuid::Uuid::new_v4()));
+        let store = BlueprintVersionStore::new(&dir);
+        let source = MemorySource::default();
+        store
+            .create(blueprint(vec![step(1, "tokenize", None)]), &source)
+            .unwrap();
+
+        // An edit made elsewhere becomes the next version
+        let mut edited = blueprint(vec![step(1, "tokenize", None), step(2, "parse", None)]);
+        edited.version = "2.0.0".parse().unwrap();
+        source.blueprints.lock().unwrap().insert(9, edited.clone());
+        let history = store.history(9, &source).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].version.to_string(), "2.0.0");
+        assert!(history[1].modification.is_none());
+        assert!(!history[1].changes.is_empty());
+
+        // A source behind the history is not edited over
+        source
+            .blueprints
+            .lock()
+            .unwrap()
+            .insert(9, blueprint(vec![step(1, "tokenize", None)]));
+        let remove = [BlueprintModification::RemoveStep(2)];
+        assert!(matches!(
+            store.modify(9, &remove, &source),
+            Err(OzoneError::Conflict(_))
+        ));
+
+        // A failed publish keeps neither the new version nor the new blueprint
+        source.blueprints.lock().unwrap().insert(9, edited);
+        source.fail_publish.store(true, Ordering::SeqCst);
+        assert!(store.modify(9, &remove, &source).is_err());
+        assert_eq!(store.history(9, &source).unwrap().len(), 2);
+        let mut other = blueprint(vec![step(1, "tokenize", None)]);
+        other.blueprint_id = 20;
+        assert!(store.create(other.clone(), &source).is_err());
+        source.fail_publish.store(false, Ordering::SeqCst);
+        assert!(store.create(other, &source).is_ok());
+        assert_eq!(store.modify(9, &remove, &source).unwrap().len(), 1);
+        assert_eq!(store.history(9, &source).unwrap().len(), 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,7 +1,10 @@
 //! Configuration module for Ozone Studio
 
+use crate::types::auth::ResourceAllocation;
+use crate::types::consensus::{ConsensusMechanism, VerificationSystem, VotingSystem};
 use crate::OzoneError;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Main configuration for Ozone Studio
@@ -106,6 +109,9 @@ pub struct GeneralConfig {
     pub setup_complete: bool,
     #[serde(default)]
     pub user_setup_complete: bool,
+    /// Seconds to wait for running tasks to finish on shutdown
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
 }
 
 impl Default for GeneralConfig {
@@ -115,10 +121,15 @@ impl Default for GeneralConfig {
             log_level: "info".into(),
             setup_complete: false,
             user_setup_complete: false,
+            shutdown_grace_secs: default_shutdown_grace_secs(),
         }
     }
 }
 
+fn default_shutdown_grace_secs() -> u64 {
+    30
+}
+
 /// ZSEI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZSEIConfig {
@@ -132,6 +143,9 @@ pub struct ZSEIConfig {
     pub pipeline_index_path: String,
     pub methodology_index_path: String,
     pub blueprint_index_path: String,
+    /// Directory of blueprint version histories
+    #[serde(default = "default_blueprint_versions_path")]
+    pub blueprint_versions_path: String,
 }
 
 impl Default for ZSEIConfig {
@@ -147,10 +161,15 @@ impl Default for ZSEIConfig {
             pipeline_index_path: "zsei_data/pipelines/index.json".into(),
             methodology_index_path: "zsei_data/methodologies/index.json".into(),
             blueprint_index_path: "zsei_data/blueprints/index.json".into(),
+            blueprint_versions_path: default_blueprint_versions_path(),
         }
     }
 }
 
+fn default_blueprint_versions_path() -> String {
+    "zsei_data/blueprints/versions".into()
+}
+
 /// Pipeline configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineConfig {
@@ -280,6 +299,28 @@ pub struct NetworkConfig {
     pub enable_mdns: bool,
     /// Sync interval in seconds for batch sync
     pub batch_sync_interval_secs: u64,
+    /// Peers dialled on startup (multiaddrs ending in `/p2p/<peer_id>`)
+    #[serde(default)]
+    pub bootstrap_nodes: Vec<String>,
+    /// Directory holding the node keypair, peer book and sync index
+    #[serde(default = "default_network_data_path")]
+    pub data_path: String,
+    /// Containers whose subtrees are synced (e.g. 2 for methodologies, 3 for
+    /// blueprints); empty syncs every shareable container
+    #[serde(default)]
+    pub sync_roots: Vec<u64>,
+    /// Voting on shared proposals
+    #[serde(default)]
+    pub consensus: ConsensusConfig,
+    /// Peer scoring and bans
+    #[serde(default)]
+    pub reputation: ReputationConfig,
+    /// Running pipelines on this account's other devices
+    #[serde(default)]
+    pub devices: DevicesConfig,
+    /// Retrying sync items that could not be sent
+    #[serde(default)]
+    pub outbox: OutboxConfig,
 }
 
 impl Default for NetworkConfig {
@@ -291,6 +332,140 @@ impl Default for NetworkConfig {
             max_peers: 50,
             enable_mdns: true,
             batch_sync_interval_secs: 60,
+            bootstrap_nodes: Vec::new(),
+            data_path: default_network_data_path(),
+            sync_roots: Vec::new(),
+            consensus: ConsensusConfig::default(),
+            reputation: ReputationConfig::default(),
+            devices: DevicesConfig::default(),
+            outbox: OutboxConfig::default(),
+        }
+    }
+}
+
+fn default_network_data_path() -> String {
+    "zsei_data/network".into()
+}
+
+/// Network consensus settings (§20)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsensusConfig {
+    /// Share of the vote weight needed to accept a proposal
+    pub acceptance_threshold: f32,
+    pub voting: VotingSystem,
+    pub verification: VerificationSystem,
+}
+
+impl Default for ConsensusConfig {
+    fn default() -> Self {
+        let mechanism = ConsensusMechanism::default();
+        Self {
+            acceptance_threshold: mechanism.acceptance_threshold,
+            voting: mechanism.voting_system,
+            verification: mechanism.verification_system,
+        }
+    }
+}
+
+/// Peer reputation settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReputationConfig {
+    /// Time for a reputation to move halfway back to neutral (0.5)
+    pub decay_half_life_secs: u64,
+    /// Peers below this are not synced with
+    pub min_sync_reputation: f32,
+    /// Peers falling below this are banned for `ban_secs`
+    pub ban_below: f32,
+    pub ban_secs: u64,
+    /// Messages per peer per minute before the rest are dropped as spam
+    pub max_messages_per_minute: u32,
+    /// Peer IDs that are always refused
+    pub banned_peers: Vec<String>,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            decay_half_life_secs: 7 * 86400,
+            min_sync_reputation: 0.2,
+            ban_below: 0.1,
+            ban_secs: 86400,
+            max_messages_per_minute: 300,
+            banned_peers: Vec::new(),
+        }
+    }
+}
+
+/// Multi-device offloading settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevicesConfig {
+    /// Run pipelines on this account's other devices and accept theirs
+    pub enable_offload: bool,
+    /// Peer IDs of this account's other devices
+    pub account_peers: Vec<String>,
+    pub device_name: String,
+    /// Resources this device offers to the account
+    pub resource_contribution: ResourceAllocation,
+    /// Runtimes and hardware available here (e.g. "python3", "cuda")
+    pub capabilities: Vec<String>,
+    /// Pipelines that may run on another device
+    pub offload_pipelines: Vec<u64>,
+    /// Pipelines accepted from other devices at once
+    pub max_offloaded: u32,
+    /// Offloaded runs not finished by then run locally instead
+    pub offload_timeout_secs: u64,
+}
+
+impl Default for DevicesConfig {
+    fn default() -> Self {
+        Self {
+            enable_offload: false,
+            account_peers: Vec::new(),
+            device_name: String::new(),
+            resource_contribution: ResourceAllocation {
+                cpu_cores: std::thread::available_parallelism()
+                    .map(|n| n.get().min(u8::MAX as usize) as u8)
+                    .unwrap_or(1),
+                ..Default::default()
+            },
+            capabilities: Vec::new(),
+            offload_pipelines: Vec::new(),
+            max_offloaded: 2,
+            offload_timeout_secs: 600,
+        }
+    }
+}
+
+/// Outbound sync queue settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboxConfig {
+    /// Delay before the first retry; doubled after each failed attempt
+    pub retry_base_secs: u64,
+    pub retry_max_secs: u64,
+    /// Failed attempts before a delivery is given up
+    pub max_attempts: u32,
+    /// How long items are kept, by sync priority
+    pub high_ttl_secs: u64,
+    pub normal_ttl_secs: u64,
+    pub low_ttl_secs: u64,
+    /// Items kept at once; the oldest are dropped beyond this
+    pub max_items: usize,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            retry_base_secs: 10,
+            retry_max_secs: 3600,
+            max_attempts: 12,
+            high_ttl_secs: 7 * 86400,
+            normal_ttl_secs: 86400,
+            low_ttl_secs: 3600,
+            max_items: 10_000,
         }
     }
 }
@@ -299,7 +474,11 @@ impl Default for NetworkConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GrpcConfig {
     pub address: String,
+    /// HTTP/WebSocket API port
     pub port: u16,
+    /// Native gRPC (tonic) port, used when built with the `grpc` feature
+    #[serde(default = "default_tonic_port")]
+    pub tonic_port: u16,
 }
 
 impl Default for GrpcConfig {
@@ -307,10 +486,15 @@ impl Default for GrpcConfig {
         Self {
             address: "127.0.0.1".into(),
             port: 50051,
+            tonic_port: default_tonic_port(),
         }
     }
 }
 
+fn default_tonic_port() -> u16 {
+    50052
+}
+
 /// UI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UIConfig {
@@ -373,16 +557,29 @@ pub struct ModelConfig {
     pub api_endpoint: Option<String>,
     pub api_key_env: Option<String>,
     pub api_model: Option<String>,
+    /// Wire format of `api_endpoint`: "anthropic" (default) or "openai"
+    /// for OpenAI-compatible servers
+    #[serde(default)]
+    pub api_format: Option<String>,
 
     /// For local models (GGUF/ONNX)
     pub local_model_type: Option<String>,
     pub local_model_path: Option<String>,
     pub context_length: usize,
     pub gpu_layers: Option<u32>,
+    /// llama.cpp server `/completion` URL serving the local model
+    #[serde(default)]
+    pub local_endpoint: Option<String>,
 
     /// Model selection UI setting
     pub allow_user_selection: bool,
     pub available_models: Vec<AvailableModel>,
+
+    /// Model identifier per orchestration stage ("amt", "blueprint",
+    /// "simulation", "execution", "response"); other stages use the
+    /// default model
+    #[serde(default)]
+    pub stage_models: HashMap<String, String>,
 }
 
 impl Default for ModelConfig {
@@ -392,10 +589,12 @@ impl Default for ModelConfig {
             api_endpoint: Some("https://api.anthropic.com/v1/messages".into()),
             api_key_env: Some("ANTHROPIC_API_KEY".into()),
             api_model: Some("claude-sonnet-4-20250514".into()),
+            api_format: None,
             local_model_type: None,
             local_model_path: None,
             context_length: 8192, // Default, overridden by per-model setting
             gpu_layers: None,
+            local_endpoint: None,
             allow_user_selection: true,
             available_models: vec![
                 AvailableModel {
@@ -403,9 +602,17 @@ impl Default for ModelConfig {
                     model_type: "api".into(),
                     identifier: "claude-sonnet-4-20250514".into(),
                     context_length: 200000,
+                    endpoint: None,
+                    api_key_env: None,
+                    api_format: None,
+                    max_output_tokens: Some(64000),
+                    input_price_per_mtok: Some(3.0),
+                    output_price_per_mtok: Some(15.0),
+                    tokenizer_path: None,
                 },
                 // Local models are added by user via UI or config
             ],
+            stage_models: HashMap::new(),
         }
     }
 }
@@ -419,6 +626,27 @@ pub struct AvailableModel {
     /// Model-specific context length (overrides global setting when this model is active)
     #[serde(default = "default_context_length")]
     pub context_length: usize,
+    /// Where this model is served; by default the `models` endpoint for
+    /// its type
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    #[serde(default)]
+    pub api_format: Option<String>,
+    /// Longest response the model can produce
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+    /// USD per million input tokens, for cost reporting
+    #[serde(default)]
+    pub input_price_per_mtok: Option<f64>,
+    /// USD per million output tokens
+    #[serde(default)]
+    pub output_price_per_mtok: Option<f64>,
+    /// BPE vocabulary for counting tokens: a Hugging Face `tokenizer.json`
+    /// or a tiktoken rank file. Without one, tokens are estimated.
+    #[serde(default)]
+    pub tokenizer_path: Option<String>,
 }
 
 fn default_context_length() -> usize {
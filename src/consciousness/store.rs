@@ -13,6 +13,7 @@ use crate::types::container::{
     CONSCIOUSNESS_CORE_MEMORY_ROOT_ID, CONSCIOUSNESS_EMOTIONAL_ROOT_ID,
     CONSCIOUSNESS_EXPERIENCE_ROOT_ID,
 };
+use crate::events::{EventBus, EventTopic};
 use crate::zsei::ZSEI;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -66,6 +67,9 @@ pub struct ConsciousnessStore {
 
     // ZSEI integration (wired in after initialization via set_zsei)
     zsei: Option<Arc<TokioRwLock<ZSEI>>>,
+
+    // Event bus (wired in after initialization via set_event_bus)
+    events: Option<Arc<EventBus>>,
 }
 
 impl ConsciousnessStore {
@@ -94,6 +98,7 @@ impl ConsciousnessStore {
             next_state_id: 1,
             storage_path,
             zsei: None,
+            events: None,
         };
 
         store.load_from_disk();
@@ -107,6 +112,23 @@ impl ConsciousnessStore {
         tracing::info!("ConsciousnessStore: ZSEI integration enabled");
     }
 
+    /// Wire in the runtime event bus for consciousness state events.
+    pub fn set_event_bus(&mut self, events: Arc<EventBus>) {
+        self.events = Some(events);
+    }
+
+    fn publish(
+        &self,
+        kind: &str,
+        user_id: Option<u64>,
+        task_id: Option<u64>,
+        data: serde_json::Value,
+    ) {
+        if let Some(events) = &self.events {
+            events.publish(EventTopic::Consciousness, kind, user_id, task_id, data);
+        }
+    }
+
     pub fn load_from_disk(&mut self) {
         let path = Path::new(&self.storage_path);
         if !path.exists() {
@@ -224,6 +246,13 @@ impl ConsciousnessStore {
         self.experiences.insert(id, experience.clone());
         self.save_to_disk();
 
+        self.publish(
+            "experience_stored",
+            experience.user_id,
+            experience.task_id,
+            serde_json::to_value(&experience).unwrap_or_default(),
+        );
+
         // --- Async path: persist to ZSEI as a Container ---
         if let Some(zsei_arc) = &self.zsei {
             let container = Self::build_experience_container(id, &experience);
@@ -355,7 +384,8 @@ impl ConsciousnessStore {
 
     // ========== Emotional State ==========
 
-    pub fn update_emotional_state(&mut self, new_state: EmotionalState) {
+    /// Replace the emotional state; `user_id` is whose task caused the change
+    pub fn update_emotional_state(&mut self, new_state: EmotionalState, user_id: Option<u64>) {
         self.emotional_history
             .push(self.current_emotional_state.clone());
         if self.emotional_history.len() > 100 {
@@ -363,13 +393,24 @@ impl ConsciousnessStore {
         }
         self.current_emotional_state = new_state;
         self.save_to_disk();
+
+        self.publish(
+            "emotional_state",
+            user_id,
+            None,
+            serde_json::to_value(&self.current_emotional_state).unwrap_or_default(),
+        );
     }
 
     pub fn get_emotional_state(&self) -> &EmotionalState {
         &self.current_emotional_state
     }
 
-    pub fn process_emotional_trigger(&mut self, trigger: EmotionalTrigger) -> EmotionalState {
+    pub fn process_emotional_trigger(
+        &mut self,
+        trigger: EmotionalTrigger,
+        user_id: Option<u64>,
+    ) -> EmotionalState {
         let mut new_state = self.current_emotional_state.clone();
         new_state.state_id = self.next_state_id;
         self.next_state_id += 1;
@@ -396,13 +437,24 @@ impl ConsciousnessStore {
         }
 
         new_state.triggers.push(trigger);
-        self.update_emotional_state(new_state.clone());
+        self.update_emotional_state(new_state.clone(), user_id);
         new_state
     }
 
     // ========== Reflection (I-Loop) ==========
 
-    pub fn add_reflection(&mut self, reflection: Reflection) {
+    /// Record a reflection; `user_id` is whose interaction prompted it, or
+    /// `None` for the system's own introspection
+    pub fn add_reflection(&mut self, reflection: Reflection, user_id: Option<u64>) {
+        self.publish(
+            "reflection",
+            user_id,
+            None,
+            serde_json::json!({
+                "reflection_id": reflection.reflection_id,
+                "subject": reflection.subject,
+            }),
+        );
         self.reflections.push(reflection);
         self.save_to_disk();
     }
@@ -418,7 +470,18 @@ impl ConsciousnessStore {
 
     // ========== Decision Gate ==========
 
-    pub fn record_gate_decision(&mut self, decision: GateDecisionRecord) {
+    pub fn record_gate_decision(&mut self, decision: GateDecisionRecord, user_id: u64) {
+        // Only the outcome is published, not the reasoning
+        self.publish(
+            "gate_decision",
+            Some(user_id),
+            Some(decision.task_id),
+            serde_json::json!({
+                "gate_id": decision.gate_id,
+                "decision": decision.decision,
+                "confidence": decision.confidence,
+            }),
+        );
         self.gate_decisions.push(decision);
         if self.gate_decisions.len() > 1000 {
             self.gate_decisions.remove(0);
@@ -535,7 +598,7 @@ pub fn pre_task_gate(task_id: u64, task_summary: &str, user_id: u64) -> GateDeci
         ethical_score,
     };
 
-    store.record_gate_decision(record.clone());
+    store.record_gate_decision(record.clone(), user_id);
     record
 }
 
@@ -562,7 +625,7 @@ pub fn post_task_experience(
         source: format!("task:{}", task_id),
         intensity: 0.5,
     };
-    let emotional_after = store.process_emotional_trigger(trigger);
+    let emotional_after = store.process_emotional_trigger(trigger, user_id);
     let emotional_change = (emotional_after.valence - emotional_during.valence).abs();
     let significance = if success { 0.5 } else { 0.7 } + emotional_change;
 
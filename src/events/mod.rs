@@ -0,0 +1,233 @@
+//! Event bus - server-side fan-out of runtime events
+//!
+//! Components publish task lifecycle, pipeline progress, orchestration
+//! stage results, ZSEI container changes and consciousness state here;
+//! websocket clients subscribe through `grpc`. Every event carries a
+//! monotonically increasing sequence number and the most recent events are
+//! kept so a client can resume from the last sequence it saw.
+
+use crate::types::{TaskID, UserID};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// Default number of events kept for resume
+pub const DEFAULT_HISTORY: usize = 4096;
+
+/// Event topics clients can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventTopic {
+    /// Task lifecycle (queued, started, progress, completed, failed, cancelled)
+    Task,
+    /// Pipeline execution progress
+    Pipeline,
+    /// Orchestration stage results
+    Orchestration,
+    /// ZSEI container changes
+    Zsei,
+    /// Consciousness state changes
+    Consciousness,
+}
+
+impl EventTopic {
+    pub const ALL: [EventTopic; 5] = [
+        EventTopic::Task,
+        EventTopic::Pipeline,
+        EventTopic::Orchestration,
+        EventTopic::Zsei,
+        EventTopic::Consciousness,
+    ];
+}
+
+/// A published event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub topic: EventTopic,
+    pub kind: String,
+    /// Owning user; `None` marks a system event that no user session receives
+    pub user_id: Option<UserID>,
+    pub task_id: Option<TaskID>,
+    pub data: serde_json::Value,
+}
+
+impl Event {
+    /// Whether a user may see this event
+    pub fn visible_to(&self, user_id: UserID) -> bool {
+        self.user_id == Some(user_id)
+    }
+}
+
+/// Result of asking for events after a sequence number
+#[derive(Debug)]
+pub enum Replay {
+    /// All events after the requested sequence, oldest first
+    Events(Vec<Event>),
+    /// History no longer reaches back far enough; holds the oldest retained seq
+    Gap(u64),
+}
+
+/// Broadcast bus with bounded history
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+    /// Recent events plus the last assigned sequence (locked together so
+    /// history order always matches sequence order)
+    history: Mutex<(VecDeque<Event>, u64)>,
+    capacity: usize,
+}
+
+impl EventBus {
+    /// Create a bus keeping `capacity` events for resume
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(16));
+        Self {
+            sender,
+            history: Mutex::new((VecDeque::with_capacity(capacity), 0)),
+            capacity,
+        }
+    }
+
+    /// Publish an event and return its sequence number
+    pub fn publish(
+        &self,
+        topic: EventTopic,
+        kind: &str,
+        user_id: Option<UserID>,
+        task_id: Option<TaskID>,
+        data: serde_json::Value,
+    ) -> u64 {
+        let mut guard = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let (history, last_seq) = &mut *guard;
+        *last_seq += 1;
+
+        let event = Event {
+            seq: *last_seq,
+            timestamp: now(),
+            topic,
+            kind: kind.to_string(),
+            user_id,
+            task_id,
+            data,
+        };
+
+        if history.len() >= self.capacity {
+            history.pop_front();
+        }
+        history.push_back(event.clone());
+
+        // No receivers is fine - the event stays in history
+        let _ = self.sender.send(event);
+        *last_seq
+    }
+
+    /// Subscribe to live events
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Sequence number of the most recent event (0 if none)
+    pub fn last_seq(&self) -> u64 {
+        self.history.lock().unwrap_or_else(|e| e.into_inner()).1
+    }
+
+    /// Events published after `seq`
+    pub fn replay_since(&self, seq: u64) -> Replay {
+        let guard = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let (history, last_seq) = &*guard;
+
+        if seq >= *last_seq {
+            return Replay::Events(Vec::new());
+        }
+        match history.front() {
+            Some(oldest) if oldest.seq <= seq + 1 => Replay::Events(
+                history.iter().filter(|e| e.seq > seq).cloned().collect(),
+            ),
+            Some(oldest) => Replay::Gap(oldest.seq),
+            None => Replay::Gap(*last_seq + 1),
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY)
+    }
+}
+
+/// What a client has asked to receive
+#[derive(Debug, Clone, Default)]
+pub struct Subscription {
+    pub topics: HashSet<EventTopic>,
+    pub task_ids: HashSet<TaskID>,
+}
+
+impl Subscription {
+    /// Whether an event matches a subscribed topic or task
+    pub fn matches(&self, event: &Event) -> bool {
+        self.topics.contains(&event.topic)
+            || event
+                .task_id
+                .map(|id| self.task_ids.contains(&id))
+                .unwrap_or(false)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.topics.is_empty() && self.task_ids.is_empty()
+    }
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_since_and_gap() {
+        let bus = EventBus::new(3);
+        for i in 0..5 {
+            bus.publish(EventTopic::Task, "progress", None, Some(i), serde_json::json!({}));
+        }
+        assert_eq!(bus.last_seq(), 5);
+
+        match bus.replay_since(3) {
+            Replay::Events(events) => {
+                assert_eq!(events.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![4, 5])
+            }
+            Replay::Gap(_) => panic!("expected events"),
+        }
+        assert!(matches!(bus.replay_since(1), Replay::Gap(3)));
+        assert!(matches!(bus.replay_since(5), Replay::Events(ref e) if e.is_empty()));
+    }
+
+    #[test]
+    fn test_visibility_and_subscription() {
+        let bus = EventBus::default();
+        let mut rx = bus.subscribe();
+        bus.publish(EventTopic::Task, "queued", Some(7), Some(42), serde_json::json!({}));
+        let event = rx.try_recv().unwrap();
+
+        assert!(event.visible_to(7));
+        assert!(!event.visible_to(8));
+
+        bus.publish(EventTopic::Zsei, "container_stored", None, None, serde_json::json!({}));
+        let system = rx.try_recv().unwrap();
+        assert!(!system.visible_to(7));
+
+        let mut sub = Subscription::default();
+        assert!(!sub.matches(&event));
+        sub.task_ids.insert(42);
+        assert!(sub.matches(&event));
+        sub.task_ids.clear();
+        sub.topics.insert(EventTopic::Task);
+        assert!(sub.matches(&event));
+    }
+}
@@ -2,17 +2,36 @@
 //!
 //! Provides HTTP/WebSocket endpoints for Electron UI.
 //! Uses axum for HTTP and WebSocket support.
+//!
+//! With the `grpc` feature, a tonic server implementing `proto/ozone.proto`
+//! runs alongside on `grpc.tonic_port`. Both front ends go through the
+//! shared operations in `ops`, so auth and behaviour are identical.
+//!
+//! The versioned REST surface lives under `/api/v1` (see `rest`); the
+//! original POST routes are kept for the Electron UI. `/ws` streams events
+//! from the runtime `EventBus` to authenticated subscribers (see `ws`).
+//!
+//! `/health/live` and `/health/ready` report the runtime `Lifecycle`; once
+//! shutdown starts every other route answers 503 `shutting_down`.
+
+mod ops;
+mod rest;
+#[cfg(feature = "grpc")]
+mod service;
+mod ws;
+
+pub use ops::PipelineEvent;
 
+use crate::lifecycle::LifecyclePhase;
+use crate::task::TaskData;
 use crate::types::zsei::ZSEIQuery;
 use crate::types::{OzoneError, OzoneResult};
 use crate::OzoneRuntime;
 use axum::{
-    extract::{
-        ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
-    },
+    extract::{Request, State},
     http::StatusCode,
-    response::IntoResponse,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
@@ -20,6 +39,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
+use utoipa::ToSchema;
 
 /// Shared application state
 pub struct AppState {
@@ -28,6 +48,8 @@ pub struct AppState {
     pub executor_progress: Arc<
         tokio::sync::RwLock<std::collections::HashMap<String, crate::pipeline::PipelineProgress>>,
     >,
+    pub events: Arc<crate::events::EventBus>,
+    pub lifecycle: Arc<crate::lifecycle::Lifecycle>,
 }
 
 // ============================================================================
@@ -39,7 +61,7 @@ pub struct ChallengeRequest {
     pub public_key: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ChallengeResponse {
     pub challenge: String,
     pub expires_at: u64,
@@ -61,6 +83,30 @@ pub struct AuthResponse {
     pub error: Option<String>,
 }
 
+impl AuthResponse {
+    fn failure(error: String) -> Self {
+        Self {
+            success: false,
+            session_token: None,
+            user_id: None,
+            device_id: None,
+            expires_at: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogoutRequest {
+    pub session_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogoutResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PipelineRequest {
     pub pipeline_id: u64,
@@ -76,6 +122,17 @@ pub struct PipelineResponse {
     pub error: Option<String>,
 }
 
+impl PipelineResponse {
+    fn failure(error: String) -> Self {
+        Self {
+            success: false,
+            task_id: Some(0),
+            output: None,
+            error: Some(error),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaskRequest {
     pub task_id: u64,
@@ -90,11 +147,12 @@ pub struct TaskListRequest {
     pub session_token: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct TaskInfo {
     pub task_id: u64,
     pub blueprint_id: Option<u64>,
     pub blueprint_name: String,
+    pub pipeline_id: Option<u64>,
     pub status: String,
     pub progress: f32,
     pub created_at: u64,
@@ -103,6 +161,56 @@ pub struct TaskInfo {
     pub error: Option<String>,
 }
 
+impl From<TaskData> for TaskInfo {
+    fn from(task: TaskData) -> Self {
+        let pipeline_id = task
+            .inputs
+            .as_ref()
+            .and_then(|i| i.get("pipeline_id"))
+            .and_then(|p| p.as_u64());
+        Self {
+            task_id: task.task_id,
+            blueprint_id: task.blueprint_id,
+            blueprint_name: format!("Blueprint #{}", task.blueprint_id.unwrap_or(0)),
+            pipeline_id,
+            status: task.status,
+            progress: task.progress,
+            created_at: task.created_at,
+            started_at: task.started_at,
+            completed_at: task.completed_at,
+            error: task.error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaskResponse {
+    pub success: bool,
+    pub task: Option<TaskInfo>,
+    pub error: Option<String>,
+}
+
+impl TaskResponse {
+    fn failure(error: String) -> Self {
+        Self {
+            success: false,
+            task: None,
+            error: Some(error),
+        }
+    }
+
+    fn from_result(result: OzoneResult<TaskData>) -> Self {
+        match result {
+            Ok(task) => Self {
+                success: true,
+                task: Some(task.into()),
+                error: None,
+            },
+            Err(e) => Self::failure(e.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaskListResponse {
     pub tasks: Vec<TaskInfo>,
@@ -123,11 +231,40 @@ pub struct ZseiResponse {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+pub struct ZseiWriteRequest {
+    /// "create", "update" or "delete"
+    pub operation: String,
+    pub data: serde_json::Value,
+    pub session_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ZseiWriteResponse {
+    pub success: bool,
+    pub container_id: Option<u64>,
+    pub version: Option<u32>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct HealthResponse {
     pub healthy: bool,
     pub version: String,
     pub uptime_secs: u64,
     pub active_tasks: u32,
+    pub connected_peers: u32,
+    pub zsei_status: String,
+    /// Accepting requests (false while starting or shutting down)
+    pub ready: bool,
+    /// Lifecycle phase: starting, ready, draining or stopped
+    pub phase: String,
+}
+
+/// Liveness/readiness probe result
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProbeResponse {
+    pub ready: bool,
+    pub phase: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -199,14 +336,21 @@ pub struct OrchestrateRequest {
     pub user_id: u64,
     pub device_id: u64,
     pub consciousness_enabled: bool,
+    /// Narrows the model's context length; by default the whole context
+    /// is used
     pub token_budget: Option<u32>,
     pub model_config: Option<serde_json::Value>,
     pub session_token: Option<String>,
+    /// Files to attach to the prompt
+    #[serde(default)]
+    pub attached_files: Vec<crate::orchestrator::AttachedFileSpec>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrchestrateResponse {
     pub success: bool,
+    /// Answers or resumes this orchestration later
+    pub orchestration_id: Option<u64>,
     pub response: Option<String>,
     pub task_id: Option<u64>,
     pub blueprint_id: Option<u64>,
@@ -226,7 +370,7 @@ pub struct PipelineRegistryRequest {
     pub session_token: Option<String>, // Optional - registry is semi-public
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PipelineRegistryEntry {
     pub id: u64,
     pub name: String,
@@ -262,15 +406,7 @@ pub struct PipelineUIComponentResponse {
 // ============================================================================
 
 async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
-    let runtime = state.runtime.read().await;
-    let task_mgr = runtime.task_manager.read().await;
-
-    Json(HealthResponse {
-        healthy: true,
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime_secs: state.start_time.elapsed().as_secs(),
-        active_tasks: task_mgr.active_count().await as u32,
-    })
+    Json(state.health().await)
 }
 
 async fn request_challenge(
@@ -284,10 +420,7 @@ async fn request_challenge(
         )
     })?;
 
-    let runtime = state.runtime.read().await;
-    let auth = runtime.auth.read().await;
-
-    match auth.create_challenge(&public_key).await {
+    match state.request_challenge(&public_key).await {
         Ok(challenge) => Ok(Json(ChallengeResponse {
             challenge: hex::encode(&challenge.challenge),
             expires_at: challenge.expires_at,
@@ -302,35 +435,15 @@ async fn authenticate(
 ) -> Json<AuthResponse> {
     let public_key = match hex::decode(&req.public_key) {
         Ok(k) => k,
-        Err(e) => {
-            return Json(AuthResponse {
-                success: false,
-                session_token: None,
-                user_id: None,
-                device_id: None,
-                expires_at: None,
-                error: Some(format!("Invalid public key: {}", e)),
-            })
-        }
+        Err(e) => return Json(AuthResponse::failure(format!("Invalid public key: {}", e))),
     };
 
     let signature = match hex::decode(&req.signature) {
         Ok(s) => s,
-        Err(e) => {
-            return Json(AuthResponse {
-                success: false,
-                session_token: None,
-                user_id: None,
-                device_id: None,
-                expires_at: None,
-                error: Some(format!("Invalid signature: {}", e)),
-            })
-        }
+        Err(e) => return Json(AuthResponse::failure(format!("Invalid signature: {}", e))),
     };
 
-    let mut runtime = state.runtime.write().await;
-
-    match runtime.authenticate(&public_key, &signature).await {
+    match state.authenticate(&public_key, &signature).await {
         Ok(session) => Json(AuthResponse {
             success: true,
             session_token: Some(hex::encode(&session.session_token)),
@@ -339,12 +452,21 @@ async fn authenticate(
             expires_at: Some(session.expires_at),
             error: None,
         }),
-        Err(e) => Json(AuthResponse {
+        Err(e) => Json(AuthResponse::failure(e.to_string())),
+    }
+}
+
+async fn logout(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<LogoutRequest>,
+) -> Json<LogoutResponse> {
+    match state.logout(&req.session_token).await {
+        Ok(()) => Json(LogoutResponse {
+            success: true,
+            error: None,
+        }),
+        Err(e) => Json(LogoutResponse {
             success: false,
-            session_token: None,
-            user_id: None,
-            device_id: None,
-            expires_at: None,
             error: Some(e.to_string()),
         }),
     }
@@ -354,110 +476,100 @@ async fn execute_pipeline(
     State(state): State<Arc<AppState>>,
     Json(req): Json<PipelineRequest>,
 ) -> Json<PipelineResponse> {
-    let runtime = state.runtime.read().await;
-    {
-        let auth = runtime.auth.read().await;
-        let token_bytes = hex::decode(&req.session_token).unwrap_or_default();
-        match auth.validate_session(&token_bytes).await {
-            Ok(_) => {}
-            Err(_) => {
-                return Json(PipelineResponse {
-                    success: false,
-                    task_id: Some(0),
-                    output: None,
-                    error: Some("Invalid session".into()),
-                })
-            }
-        }
-    }
+    let session = match state.authorize(&req.session_token).await {
+        Ok(s) => s,
+        Err(_) => return Json(PipelineResponse::failure("Invalid session".into())),
+    };
 
     let input: crate::types::pipeline::PipelineInput = match serde_json::from_value(req.input) {
         Ok(i) => i,
-        Err(e) => {
-            return Json(PipelineResponse {
-                success: false,
-                task_id: Some(0),
-                output: None,
-                error: Some(format!("Invalid input: {}", e)),
-            })
-        }
+        Err(e) => return Json(PipelineResponse::failure(format!("Invalid input: {}", e))),
     };
 
-    match runtime.execute_pipeline(req.pipeline_id, input).await {
+    match state.execute_pipeline(&session, req.pipeline_id, input).await {
         Ok(output) => Json(PipelineResponse {
             success: output.success,
             task_id: output.task_id,
             output: Some(serde_json::to_value(&output.data).unwrap_or_default()),
             error: output.error,
         }),
-        Err(e) => Json(PipelineResponse {
-            success: false,
-            task_id: Some(0),
-            output: None,
-            error: Some(e.to_string()),
-        }),
+        Err(e) => Json(PipelineResponse::failure(e.to_string())),
     }
 }
 
+async fn create_task(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PipelineRequest>,
+) -> Json<TaskResponse> {
+    let session = match state.authorize(&req.session_token).await {
+        Ok(s) => s,
+        Err(e) => return Json(TaskResponse::failure(e.to_string())),
+    };
+
+    let input: crate::types::pipeline::PipelineInput = match serde_json::from_value(req.input) {
+        Ok(i) => i,
+        Err(e) => return Json(TaskResponse::failure(format!("Invalid input: {}", e))),
+    };
+
+    let result = match state.create_task(&session, req.pipeline_id, input).await {
+        Ok(task_id) => state.get_task(&session, task_id).await,
+        Err(e) => Err(e),
+    };
+    Json(TaskResponse::from_result(result))
+}
+
 async fn get_task(
     State(state): State<Arc<AppState>>,
     Json(req): Json<TaskRequest>,
 ) -> Json<Option<TaskInfo>> {
-    let runtime = state.runtime.read().await;
-    let task_mgr = runtime.task_manager.read().await;
-    let active_tasks = task_mgr.active_count().await as u32;
-
-    match task_mgr.get_task(req.task_id).await {
-        Some(task) => Json(Some(TaskInfo {
-            task_id: task.task_id,
-            blueprint_id: task.blueprint_id,
-            blueprint_name: format!("Blueprint #{}", task.blueprint_id.unwrap_or(0)),
-            status: format!("{:?}", task.status),
-            progress: task.progress,
-            created_at: task.created_at,
-            started_at: task.started_at,
-            completed_at: task.completed_at,
-            error: task.error.map(|e| format!("{:?}", e)),
-        })),
-        None => Json(None),
-    }
+    let Ok(session) = state.authorize(&req.session_token).await else {
+        return Json(None);
+    };
+    Json(
+        state
+            .get_task(&session, req.task_id)
+            .await
+            .ok()
+            .map(TaskInfo::from),
+    )
 }
 
 async fn list_tasks(
     State(state): State<Arc<AppState>>,
     Json(req): Json<TaskListRequest>,
 ) -> Json<TaskListResponse> {
-    let runtime = state.runtime.read().await;
-    let task_mgr = runtime.task_manager.read().await;
-
-    let status_filter = req.status.as_deref();
+    let Ok(session) = state.authorize(&req.session_token).await else {
+        return Json(TaskListResponse {
+            tasks: Vec::new(),
+            total: 0,
+        });
+    };
     let limit = req.limit.unwrap_or(50) as usize;
     let offset = req.offset.unwrap_or(0) as usize;
 
-    let tasks = task_mgr
-        .list_tasks(status_filter, None, limit, offset)
+    let (tasks, total) = state
+        .list_tasks(&session, req.status.as_deref(), limit, offset)
         .await;
-    let total = tasks.len() as u32;
 
     Json(TaskListResponse {
-        tasks: tasks
-            .into_iter()
-            .map(|t| TaskInfo {
-                task_id: t.task_id,
-                blueprint_id: t.blueprint_id,
-                blueprint_name: format!("Blueprint #{}", t.blueprint_id.unwrap_or(0)),
-                status: format!("{:?}", t.status),
-                progress: t.progress,
-                created_at: t.created_at,
-                started_at: t.started_at,
-                completed_at: t.completed_at,
-                error: t.error.map(|e| format!("{:?}", e)),
-            })
-            .collect(),
-        total,
+        tasks: tasks.into_iter().map(TaskInfo::from).collect(),
+        total: total as u32,
     })
 }
 
+async fn cancel_task(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TaskRequest>,
+) -> Json<TaskResponse> {
+    let session = match state.authorize(&req.session_token).await {
+        Ok(s) => s,
+        Err(e) => return Json(TaskResponse::failure(e.to_string())),
+    };
+    Json(TaskResponse::from_result(
+        state.cancel_task(&session, req.task_id).await,
+    ))
+}
+
 async fn query_zsei(
     State(state): State<Arc<AppState>>,
     Json(req): Json<ZseiQueryRequest>,
@@ -473,9 +585,12 @@ async fn query_zsei(
         }
     };
 
-    let runtime = state.runtime.read().await;
+    let result = match state.authorize(&req.session_token).await {
+        Ok(session) => state.query_zsei(&session, query).await,
+        Err(e) => Err(e),
+    };
 
-    match runtime.query_zsei(query).await {
+    match result {
         Ok(result) => Json(ZseiResponse {
             success: true,
             result: Some(serde_json::to_value(&result).unwrap_or_default()),
@@ -489,137 +604,61 @@ async fn query_zsei(
     }
 }
 
+async fn write_zsei(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ZseiWriteRequest>,
+) -> Json<ZseiWriteResponse> {
+    let result = match state.authorize(&req.session_token).await {
+        Ok(session) => state.write_zsei(&session, &req.operation, req.data).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok((container_id, version)) => Json(ZseiWriteResponse {
+            success: true,
+            container_id: Some(container_id),
+            version: Some(version),
+            error: None,
+        }),
+        Err(e) => Json(ZseiWriteResponse {
+            success: false,
+            container_id: None,
+            version: None,
+            error: Some(e.to_string()),
+        }),
+    }
+}
+
 async fn get_config(
     State(state): State<Arc<AppState>>,
     Json(req): Json<ConfigRequest>,
 ) -> Json<ConfigResponse> {
-    let runtime = state.runtime.read().await;
-
-    let config = match req.section.as_deref() {
-        None | Some("") => serde_json::to_value(&runtime.config).ok(),
-        Some("zsei") => serde_json::to_value(&runtime.config.zsei).ok(),
-        Some("pipelines") => serde_json::to_value(&runtime.config.pipelines).ok(),
-        Some("ui") => serde_json::to_value(&runtime.config.ui).ok(),
-        Some("model") | Some("models") => serde_json::to_value(&runtime.config.models).ok(),
-        Some(s) => {
-            return Json(ConfigResponse {
-                success: false,
-                config: None,
-                error: Some(format!("Unknown config section: {}", s)),
-            })
-        }
-    };
-
-    Json(ConfigResponse {
-        success: config.is_some(),
-        config,
-        error: None,
-    })
+    match state.config_section(req.section.as_deref()).await {
+        Ok(config) => Json(ConfigResponse {
+            success: true,
+            config: Some(config),
+            error: None,
+        }),
+        Err(e) => Json(ConfigResponse {
+            success: false,
+            config: None,
+            error: Some(e.to_string()),
+        }),
+    }
 }
 
 async fn set_config(
     State(state): State<Arc<AppState>>,
     Json(req): Json<ConfigSetRequest>,
 ) -> Json<ConfigSetResponse> {
-    // Get config path
-    let config_path = std::env::var("OZONE_CONFIG").unwrap_or_else(|_| "config.toml".to_string());
-
-    // Read current config
-    let mut runtime = state.runtime.write().await;
-
-    // Apply updates from request
-    if let Some(updates) = req.updates.as_object() {
-        // Handle setup_complete flag
-        if let Some(setup_complete) = updates.get("setup_complete") {
-            if let Some(val) = setup_complete.as_bool() {
-                runtime.config.general.setup_complete = val;
-            }
-        }
-
-        // Handle user_setup_complete flag
-        if let Some(user_setup) = updates.get("user_setup_complete") {
-            if let Some(val) = user_setup.as_bool() {
-                runtime.config.general.user_setup_complete = val;
-            }
-        }
-
-        // Handle model updates
-        if let Some(models) = updates.get("models") {
-            let mut model_config = runtime.config.models.clone();
-
-            if let Some(v) = models.get("model_type").and_then(|v| v.as_str()) {
-                model_config.model_type = v.to_string();
-            }
-            if let Some(v) = models.get("api_provider").and_then(|v| v.as_str()) {
-                // map to your actual fields
-                model_config.api_endpoint = match v {
-                    "anthropic" => Some("https://api.anthropic.com/v1/messages".to_string()),
-                    "openai" => Some("https://api.openai.com/v1/chat/completions".to_string()),
-                    "google" => {
-                        Some("https://generativelanguage.googleapis.com/v1beta".to_string())
-                    }
-                    _ => model_config.api_endpoint, // already Option<String>
-                };
-            }
-            if let Some(v) = models.get("api_key").and_then(|v| v.as_str()) {
-                // you probably want to store it in env or a secure place, but for now:
-                model_config.api_key_env = Some(v.to_string()); // or however you store it
-            }
-            if let Some(v) = models.get("local_model_path").and_then(|v| v.as_str()) {
-                model_config.local_model_path = Some(v.to_string());
-            }
-            if let Some(v) = models.get("local_model_type").and_then(|v| v.as_str()) {
-                model_config.local_model_type = Some(v.to_string());
-            }
-
-            runtime.config.models = model_config;
-        }
-
-        // Handle consciousness updates
-        if let Some(consciousness) = updates.get("consciousness") {
-            if let Some(enabled) = consciousness.get("enabled").and_then(|v| v.as_bool()) {
-                runtime.config.consciousness.enabled = enabled;
-            }
-        }
-
-        if let Some(voice) = updates.get("voice") {
-            let mut voice_config = runtime.config.voice.clone();
-
-            if let Some(enabled) = voice.get("enabled").and_then(|v| v.as_bool()) {
-                voice_config.enabled = enabled;
-            }
-            if let Some(path) = voice.get("whisper_model_path").and_then(|v| v.as_str()) {
-                voice_config.whisper_model_path = Some(path.to_string());
-            }
-            // Optional: add more fields if your wizard ever sends them
-            // e.g. backend type, model size preference, etc.
-
-            runtime.config.voice = voice_config;
-        }
-
-        // Handle UI updates
-        if let Some(ui) = updates.get("ui") {
-            if let Ok(ui_config) = serde_json::from_value(ui.clone()) {
-                runtime.config.ui = ui_config;
-            }
-        }
-    }
-
-    // Save config to file
-    match toml::to_string_pretty(&runtime.config) {
-        Ok(config_str) => match std::fs::write(&config_path, &config_str) {
-            Ok(_) => Json(ConfigSetResponse {
-                success: true,
-                error: None,
-            }),
-            Err(e) => Json(ConfigSetResponse {
-                success: false,
-                error: Some(format!("Failed to write config: {}", e)),
-            }),
-        },
+    match state.update_config(&req.updates).await {
+        Ok(()) => Json(ConfigSetResponse {
+            success: true,
+            error: None,
+        }),
         Err(e) => Json(ConfigSetResponse {
             success: false,
-            error: Some(format!("Failed to serialize config: {}", e)),
+            error: Some(e.to_string()),
         }),
     }
 }
@@ -644,16 +683,21 @@ async fn orchestrate(
         "consciousness_enabled".to_string(),
         serde_json::Value::Bool(req.consciousness_enabled),
     );
-    data.insert(
-        "token_budget".to_string(),
-        serde_json::json!(req.token_budget.unwrap_or(100_000)),
-    );
+    if let Some(token_budget) = req.token_budget {
+        data.insert("token_budget".to_string(), serde_json::json!(token_budget));
+    }
     if let Some(proj_id) = req.project_id {
         data.insert("project_id".to_string(), serde_json::json!(proj_id));
     }
     if let Some(ws_id) = req.workspace_id {
         data.insert("workspace_id".to_string(), serde_json::json!(ws_id));
     }
+    if !req.attached_files.is_empty() {
+        data.insert(
+            "attached_files".to_string(),
+            serde_json::to_value(&req.attached_files).unwrap_or_default(),
+        );
+    }
     if let Some(model_cfg) = &req.model_config {
         if let Some(model_id) = model_cfg.get("model_identifier").and_then(|v| v.as_str()) {
             data.insert(
@@ -714,6 +758,7 @@ async fn orchestrate(
             );
             Json(OrchestrateResponse {
                 success: result.success,
+                orchestration_id: Some(result.orchestration_id),
                 response: result.response_text,
                 task_id: result.task_id,
                 blueprint_id: result.blueprint_id,
@@ -732,6 +777,7 @@ async fn orchestrate(
             tracing::error!("Orchestration failed: {}", e);
             Json(OrchestrateResponse {
                 success: false,
+                orchestration_id: None,
                 response: None,
                 task_id: None,
                 blueprint_id: None,
@@ -874,113 +920,6 @@ async fn get_pipeline_ui_component(
     })
 }
 
-async fn websocket_handler(
-    ws: WebSocketUpgrade,
-    State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_websocket(socket, state))
-}
-
-async fn handle_websocket(mut socket: WebSocket, state: Arc<AppState>) {
-    // Start progress broadcast task
-    let progress_map = state.executor_progress.clone();
-    let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(32);
-
-    tokio::spawn(async move {
-        let mut last_snapshot: std::collections::HashMap<String, String> =
-            std::collections::HashMap::new();
-        loop {
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            let map = progress_map.read().await;
-            for (id, progress) in map.iter() {
-                let status = format!("{:?}", progress.status);
-                if last_snapshot.get(id) != Some(&status) {
-                    last_snapshot.insert(id.clone(), status.clone());
-                    let event = serde_json::json!({
-                        "action": "pipeline_progress",
-                        "execution_id": id,
-                        "status": status,
-                        "progress_percent": progress.progress_percent,
-                        "pipeline_id": progress.pipeline_id,
-                        "pipeline_name": progress.pipeline_name,
-                        "task_id": progress.task_id,
-                        "step_index": progress.step_index,
-                        "tokens_used": progress.tokens_used,
-                    });
-                    if tx
-                        .send(serde_json::to_string(&event).unwrap_or_default())
-                        .await
-                        .is_err()
-                    {
-                        break;
-                    }
-                }
-            }
-        }
-    });
-
-    // Main message loop
-    loop {
-        tokio::select! {
-            msg = socket.recv() => {
-                match msg {
-                    Some(Ok(Message::Text(text))) => {
-                        if let Ok(request) = serde_json::from_str::<serde_json::Value>(&text) {
-                            let response = handle_ws_message(request, &state).await;
-                            let _ = socket.send(Message::Text(
-                                serde_json::to_string(&response).unwrap_or_default()
-                            )).await;
-                        }
-                    }
-                    None | Some(Ok(Message::Close(_))) | Some(Err(_)) => break,
-                    _ => {}
-                }
-            }
-            event = rx.recv() => {
-                if let Some(event_str) = event {
-                    let _ = socket.send(Message::Text(event_str)).await;
-                }
-            }
-        }
-    }
-}
-
-async fn handle_ws_message(
-    request: serde_json::Value,
-    _state: &Arc<AppState>,
-) -> serde_json::Value {
-    let action = request.get("action").and_then(|v| v.as_str()).unwrap_or("");
-
-    match action {
-        "ping" => serde_json::json!({"action": "pong"}),
-        "subscribe_tasks" => serde_json::json!({"action": "subscribed", "channel": "tasks"}),
-        "subscribe_pipeline_progress" => {
-            let execution_id = request
-                .get("execution_id")
-                .and_then(|e| e.as_str())
-                .unwrap_or("")
-                .to_string();
-            serde_json::json!({
-                "action": "subscribed",
-                "channel": "pipeline_progress",
-                "execution_id": execution_id
-            })
-        }
-        "cancel_pipeline" => {
-            let execution_id = request
-                .get("execution_id")
-                .and_then(|e| e.as_str())
-                .unwrap_or("")
-                .to_string();
-            serde_json::json!({
-                "action": "cancel_requested",
-                "execution_id": execution_id
-            })
-        }
-        _ => serde_json::json!({"error": "Unknown action"}),
-    }
-}
-
 // ============================================================================
 // Server Startup
 // ============================================================================
@@ -993,15 +932,22 @@ pub async fn start_server(runtime: Arc<RwLock<OzoneRuntime>>) -> OzoneResult<()>
     };
     let addr = format!("{}:{}", config.address, config.port);
 
-    let progress_map = {
+    let (progress_map, events, lifecycle) = {
         let r = runtime.read().await;
-        r.pipeline_registry.read().await.executor().progress_map()
+        let registry = r.pipeline_registry.read().await;
+        (
+            registry.executor().progress_map(),
+            r.events.clone(),
+            r.lifecycle.clone(),
+        )
     };
 
     let state = Arc::new(AppState {
         runtime,
         start_time: std::time::Instant::now(),
         executor_progress: progress_map,
+        events,
+        lifecycle: lifecycle.clone(),
     });
 
     let cors = CorsLayer::new()
@@ -1011,22 +957,44 @@ pub async fn start_server(runtime: Arc<RwLock<OzoneRuntime>>) -> OzoneResult<()>
 
     let app = Router::new()
         .route("/health", get(health))
+        .route("/health/live", get(rest::liveness))
+        .route("/health/ready", get(rest::readiness))
         .route("/auth/challenge", post(request_challenge))
         .route("/auth/authenticate", post(authenticate))
+        .route("/auth/logout", post(logout))
         .route("/pipeline/execute", post(execute_pipeline))
         .route("/pipeline/registry", post(get_pipeline_registry))
         .route("/pipeline/ui-component", post(get_pipeline_ui_component))
         .route("/task/get", post(get_task))
         .route("/task/list", post(list_tasks))
+        .route("/task/create", post(create_task))
+        .route("/task/cancel", post(cancel_task))
         .route("/zsei/query", post(query_zsei))
+        .route("/zsei/write", post(write_zsei))
         .route("/config/get", post(get_config))
         .route("/config/set", post(set_config))
-        .route("/ws", get(websocket_handler))
+        .route("/ws", get(ws::websocket_handler))
         .route("/pipeline/progress", post(get_pipeline_progress))
         .route("/pipeline/cancel", post(cancel_pipeline))
         .route("/orchestrate", post(orchestrate))
+        .nest("/api/v1", rest::router())
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            reject_while_draining,
+        ))
         .layer(cors)
-        .with_state(state);
+        .with_state(state.clone());
+
+    #[cfg(feature = "grpc")]
+    {
+        let grpc_addr = format!("{}:{}", config.address, config.tonic_port);
+        let grpc_state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = service::serve(grpc_state, &grpc_addr).await {
+                tracing::error!("gRPC server failed: {}", e);
+            }
+        });
+    }
 
     tracing::info!("Starting HTTP server on {}", addr);
 
@@ -1034,18 +1002,45 @@ pub async fn start_server(runtime: Arc<RwLock<OzoneRuntime>>) -> OzoneResult<()>
         .await
         .map_err(|e| OzoneError::ServerError(format!("Failed to bind: {}", e)))?;
 
+    lifecycle.advance(LifecyclePhase::Ready);
+
+    // In-flight requests keep running while the runtime drains; the server
+    // itself stops once the runtime has synced its state
     axum::serve(listener, app)
+        .with_graceful_shutdown(async move { lifecycle.reached(LifecyclePhase::Stopped).await })
         .await
         .map_err(|e| OzoneError::ServerError(format!("Server error: {}", e)))?;
 
     Ok(())
 }
 
+/// Reject new requests with 503 once shutdown has started; health probes
+/// stay available so orchestrators can watch the drain
+async fn reject_while_draining(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let is_probe = path.starts_with("/health") || path.starts_with("/api/v1/health");
+    if is_probe || !state.lifecycle.is_shutting_down() {
+        return next.run(request).await;
+    }
+    let body = rest::ErrorBody {
+        error: rest::ErrorDetail {
+            code: "shutting_down".to_string(),
+            message: "Server is shutting down".to_string(),
+        },
+    };
+    (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response()
+}
+
 pub fn to_status(error: OzoneError) -> StatusCode {
     match error {
         OzoneError::AuthError(_) => StatusCode::UNAUTHORIZED,
         OzoneError::NotFound(_) => StatusCode::NOT_FOUND,
         OzoneError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+        OzoneError::Conflict(_) => StatusCode::CONFLICT,
         OzoneError::ValidationError(_) => StatusCode::BAD_REQUEST,
         _ => StatusCode::INTERNAL_SERVER_ERROR,
     }
@@ -1055,13 +1050,17 @@ async fn get_pipeline_progress(
     State(state): State<Arc<AppState>>,
     Json(req): Json<PipelineProgressRequest>,
 ) -> Json<PipelineProgressResponse> {
-    let map = state.executor_progress.read().await;
-    match map.get(&req.execution_id) {
-        Some(progress) => Json(PipelineProgressResponse {
+    let token = req.session_token.as_deref().unwrap_or_default();
+    let result = match state.authorize(token).await {
+        Ok(session) => state.get_execution(&session, &req.execution_id).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(progress) => Json(PipelineProgressResponse {
             success: true,
             execution_id: req.execution_id,
             pipeline_id: Some(progress.pipeline_id),
-            pipeline_name: Some(progress.pipeline_name.clone()),
+            pipeline_name: Some(progress.pipeline_name),
             task_id: progress.task_id,
             step_index: progress.step_index,
             status: format!("{:?}", progress.status),
@@ -1069,9 +1068,9 @@ async fn get_pipeline_progress(
             tokens_used: progress.tokens_used,
             started_at: Some(progress.started_at),
             completed_at: progress.completed_at,
-            error: progress.error.clone(),
+            error: progress.error,
         }),
-        None => Json(PipelineProgressResponse {
+        Err(e) => Json(PipelineProgressResponse {
             success: false,
             execution_id: req.execution_id,
             pipeline_id: None,
@@ -1083,7 +1082,7 @@ async fn get_pipeline_progress(
             tokens_used: None,
             started_at: None,
             completed_at: None,
-            error: Some("Execution not found".to_string()),
+            error: Some(e.to_string()),
         }),
     }
 }
@@ -1092,13 +1091,20 @@ async fn cancel_pipeline(
     State(state): State<Arc<AppState>>,
     Json(req): Json<PipelineCancelRequest>,
 ) -> Json<PipelineCancelResponse> {
-    let runtime = state.runtime.read().await;
-    let registry = runtime.pipeline_registry.read().await;
-    let was_running = registry.executor().cancel(&req.execution_id).await;
-
-    Json(PipelineCancelResponse {
-        success: true,
-        was_running,
-        error: None,
-    })
+    let result = match state.authorize(&req.session_token).await {
+        Ok(session) => state.cancel_execution(&session, &req.execution_id).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(was_running) => Json(PipelineCancelResponse {
+            success: true,
+            was_running,
+            error: None,
+        }),
+        Err(e) => Json(PipelineCancelResponse {
+            success: false,
+            was_running: false,
+            error: Some(e.to_string()),
+        }),
+    }
 }
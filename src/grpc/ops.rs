@@ -0,0 +1,1145 @@
+//! Transport-agnostic operations shared by the HTTP and gRPC front ends.
+//!
+//! Both servers authenticate, execute and query through these methods so
+//! the two APIs stay behaviourally identical.
+
+use super::{AppState, HealthResponse};
+use crate::blueprints::store::blueprint_container;
+use crate::blueprints::{BlueprintVersion, BlueprintVersionStore, ContainerBlueprintSource};
+use crate::network::outbox::OutboxEntry;
+use crate::network::NetworkStatus;
+use crate::orchestrator::checkpoint::{CheckpointStatus, CheckpointSummary};
+use crate::orchestrator::OrchestrationResponse;
+use crate::pipeline::PipelineProgress;
+use crate::task::{TaskData, TaskManager, TaskPriority};
+use crate::types::auth::{AuthChallenge, Session};
+use crate::types::blueprint::{Blueprint, BlueprintModification};
+use crate::types::container::{Container, ContainerType};
+use crate::types::pipeline::{ExecutionContext, PipelineInput, PipelineOutput};
+use crate::types::zsei::{TraversalRequest, TraversalResult, ZSEIQuery, ZSEIQueryResult};
+use crate::types::{ContainerID, LogEntry, OzoneError, OzoneResult, PipelineID, SemVer, TaskID};
+use crate::zsei::ZSEI;
+use crate::OrchestrationOutput;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+/// Poll interval for streamed pipeline events
+const STREAM_POLL_MS: u64 = 250;
+
+/// Event emitted while a streamed pipeline runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineEvent {
+    pub task_id: TaskID,
+    /// "progress", "log", "complete" or "error"
+    pub event_type: String,
+    pub data: serde_json::Value,
+}
+
+impl AppState {
+    // ========================================================================
+    // AUTHENTICATION
+    // ========================================================================
+
+    /// Issue a login challenge for a public key
+    pub async fn request_challenge(&self, public_key: &[u8]) -> OzoneResult<AuthChallenge> {
+        let runtime = self.runtime.read().await;
+        let auth = runtime.auth.read().await;
+        auth.create_challenge(public_key).await
+    }
+
+    /// Verify a signed challenge and open a session
+    pub async fn authenticate(&self, public_key: &[u8], signature: &[u8]) -> OzoneResult<Session> {
+        let runtime = self.runtime.read().await;
+        runtime.authenticate(public_key, signature).await
+    }
+
+    /// Resolve a hex-encoded session token and refresh its activity timestamp
+    pub async fn authorize(&self, session_token: &str) -> OzoneResult<Session> {
+        let token = hex::decode(session_token)
+            .map_err(|_| OzoneError::AuthError("Invalid session token".into()))?;
+
+        let runtime = self.runtime.read().await;
+        let auth = runtime.auth.read().await;
+        let session = auth.validate_session(&token).await?;
+        auth.touch_session(&token).await?;
+        Ok(session)
+    }
+
+    /// Check that a session is still valid without extending it
+    pub async fn check_session(&self, session_token: &str) -> OzoneResult<Session> {
+        let token = hex::decode(session_token)
+            .map_err(|_| OzoneError::AuthError("Invalid session token".into()))?;
+
+        let runtime = self.runtime.read().await;
+        let session = runtime.auth.read().await.validate_session(&token).await?;
+        Ok(session)
+    }
+
+    /// Close a session
+    pub async fn logout(&self, session_token: &str) -> OzoneResult<()> {
+        let token = hex::decode(session_token)
+            .map_err(|_| OzoneError::AuthError("Invalid session token".into()))?;
+
+        let runtime = self.runtime.read().await;
+        runtime.auth.read().await.logout(&token).await?;
+
+        let mut current = runtime.session.write().await;
+        if current.as_ref().map(|s| s.session_token == token).unwrap_or(false) {
+            *current = None;
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // PIPELINES & TASKS
+    // ========================================================================
+
+    /// Execute a pipeline synchronously on behalf of a session
+    pub async fn execute_pipeline(
+        &self,
+        session: &Session,
+        pipeline_id: PipelineID,
+        input: PipelineInput,
+    ) -> OzoneResult<PipelineOutput> {
+        tracing::debug!(
+            "User {} executing pipeline {}",
+            session.user_id,
+            pipeline_id
+        );
+        let runtime = self.runtime.read().await;
+        let registry = runtime.pipeline_registry.read().await;
+        registry.execute(pipeline_id, input, None).await
+    }
+
+    /// Create a task for a pipeline and start executing it in the background
+    pub async fn create_task(
+        &self,
+        session: &Session,
+        pipeline_id: PipelineID,
+        input: PipelineInput,
+    ) -> OzoneResult<TaskID> {
+        let (registry, task_manager) = {
+            let runtime = self.runtime.read().await;
+            (
+                runtime.pipeline_registry.clone(),
+                runtime.task_manager.clone(),
+            )
+        };
+
+        if registry.read().await.get_blueprint(pipeline_id).await.is_none() {
+            return Err(OzoneError::NotFound(format!(
+                "Pipeline {} not found",
+                pipeline_id
+            )));
+        }
+
+        let mut inputs: HashMap<String, serde_json::Value> = input
+            .data
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json::to_value(v).unwrap_or_default()))
+            .collect();
+        inputs.insert("pipeline_id".to_string(), serde_json::json!(pipeline_id));
+
+        let task_id = {
+            let tm = task_manager.read().await;
+            let task_id = tm
+                .enqueue_task(
+                    None,
+                    inputs,
+                    session.user_id,
+                    session.device_id,
+                    input.context.workspace_id,
+                    input.context.project_id,
+                    TaskPriority::Normal,
+                )
+                .await?;
+            tm.mark_running(task_id).await?;
+            task_id
+        };
+
+        tokio::spawn(async move {
+            let result = registry
+                .read()
+                .await
+                .execute(pipeline_id, input, Some(task_id))
+                .await;
+
+            let tm = task_manager.read().await;
+            let cancelled = tm
+                .get_task(task_id)
+                .await
+                .map(|t| t.status == "cancelled")
+                .unwrap_or(true);
+            if cancelled {
+                return;
+            }
+
+            let outcome = match result {
+                Ok(output) if output.success => {
+                    let outputs = serde_json::to_value(&output.data).unwrap_or_default();
+                    tm.complete_task(task_id, Some(outputs), 0).await
+                }
+                Ok(output) => {
+                    let error = output
+                        .error
+                        .unwrap_or_else(|| "Pipeline reported failure".to_string());
+                    tm.fail_task(task_id, error).await
+                }
+                Err(e) => tm.fail_task(task_id, e.to_string()).await,
+            };
+            if let Err(e) = outcome {
+                tracing::warn!("Failed to record result for task {}: {}", task_id, e);
+            }
+        });
+
+        Ok(task_id)
+    }
+
+    /// Get one of the session user's tasks by ID
+    pub async fn get_task(&self, session: &Session, task_id: TaskID) -> OzoneResult<TaskData> {
+        let runtime = self.runtime.read().await;
+        let task_mgr = runtime.task_manager.read().await;
+        owned_task(&task_mgr, session, task_id).await
+    }
+
+    /// List a page of the session user's tasks, newest first, with the
+    /// total number matching
+    pub async fn list_tasks(
+        &self,
+        session: &Session,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> (Vec<TaskData>, usize) {
+        let runtime = self.runtime.read().await;
+        let task_mgr = runtime.task_manager.read().await;
+        let user = Some(session.user_id);
+        let tasks = task_mgr.list_tasks(status, user, limit, offset).await;
+        let total = task_mgr.count_tasks(status, user).await;
+        (tasks, total)
+    }
+
+    /// Get logs of one of the session user's tasks, oldest first
+    pub async fn task_logs(
+        &self,
+        session: &Session,
+        task_id: TaskID,
+        limit: usize,
+    ) -> OzoneResult<Vec<LogEntry>> {
+        let runtime = self.runtime.read().await;
+        let task_mgr = runtime.task_manager.read().await;
+        owned_task(&task_mgr, session, task_id).await?;
+        let mut logs = task_mgr.get_logs(task_id, Some(limit)).await;
+        logs.reverse();
+        Ok(logs)
+    }
+
+    /// Cancel one of the session user's tasks and any pipeline executions
+    /// running on its behalf
+    pub async fn cancel_task(&self, session: &Session, task_id: TaskID) -> OzoneResult<TaskData> {
+        let runtime = self.runtime.read().await;
+
+        {
+            let task_mgr = runtime.task_manager.read().await;
+            owned_task(&task_mgr, session, task_id).await?;
+            task_mgr.cancel_task(task_id).await?;
+        }
+
+        let executions: Vec<String> = self
+            .executor_progress
+            .read()
+            .await
+            .values()
+            .filter(|p| p.task_id == Some(task_id))
+            .map(|p| p.execution_id.clone())
+            .collect();
+        if !executions.is_empty() {
+            let registry = runtime.pipeline_registry.read().await;
+            for execution_id in &executions {
+                registry.executor().cancel(execution_id).await;
+            }
+        }
+
+        let task_mgr = runtime.task_manager.read().await;
+        task_mgr
+            .get_task(task_id)
+            .await
+            .ok_or_else(|| OzoneError::NotFound(format!("Task {} not found", task_id)))
+    }
+
+    /// Progress of a pipeline execution run on behalf of one of the session
+    /// user's tasks
+    pub async fn get_execution(
+        &self,
+        session: &Session,
+        execution_id: &str,
+    ) -> OzoneResult<PipelineProgress> {
+        let progress = self
+            .executor_progress
+            .read()
+            .await
+            .get(execution_id)
+            .cloned()
+            .ok_or_else(|| OzoneError::NotFound(format!("Execution {} not found", execution_id)))?;
+        let task_id = progress.task_id.ok_or_else(|| {
+            OzoneError::PermissionDenied(format!(
+                "Execution {} is not running on behalf of a task",
+                execution_id
+            ))
+        })?;
+        let runtime = self.runtime.read().await;
+        let task_mgr = runtime.task_manager.read().await;
+        owned_task(&task_mgr, session, task_id).await?;
+        Ok(progress)
+    }
+
+    /// Cancel a pipeline execution run on behalf of one of the session
+    /// user's tasks, returning whether it was still running
+    pub async fn cancel_execution(&self, session: &Session, execution_id: &str) -> OzoneResult<bool> {
+        self.get_execution(session, execution_id).await?;
+        let runtime = self.runtime.read().await;
+        let registry = runtime.pipeline_registry.read().await;
+        Ok(registry.executor().cancel(execution_id).await)
+    }
+
+    /// Retry a failed or cancelled task as a new task.
+    ///
+    /// Pipeline tasks are re-executed with their original inputs; other
+    /// tasks (e.g. orchestrations) are re-queued.
+    pub async fn retry_task(&self, session: &Session, task_id: TaskID) -> OzoneResult<TaskID> {
+        let original = self.get_task(session, task_id).await?;
+        if original.status != "failed" && original.status != "cancelled" {
+            return Err(OzoneError::ValidationError(format!(
+                "Task {} is not failed or cancelled",
+                task_id
+            )));
+        }
+
+        let mut inputs: HashMap<String, serde_json::Value> = original
+            .inputs
+            .clone()
+            .and_then(|i| serde_json::from_value(i).ok())
+            .unwrap_or_default();
+
+        match inputs.remove("pipeline_id").and_then(|v| v.as_u64()) {
+            Some(pipeline_id) => {
+                let input = PipelineInput {
+                    data: inputs
+                        .into_iter()
+                        .filter_map(|(k, v)| serde_json::from_value(v).ok().map(|v| (k, v)))
+                        .collect(),
+                    context: ExecutionContext {
+                        user_id: session.user_id,
+                        device_id: session.device_id,
+                        workspace_id: original.workspace_id,
+                        project_id: original.project_id,
+                        task_context_id: Some(task_id),
+                        metadata: HashMap::new(),
+                    },
+                };
+                self.create_task(session, pipeline_id, input).await
+            }
+            None => {
+                let runtime = self.runtime.read().await;
+                let task_mgr = runtime.task_manager.read().await;
+                task_mgr.retry_task(task_id).await
+            }
+        }
+    }
+
+    /// Create a task and stream its progress, logs and final result.
+    ///
+    /// The stream ends after a "complete" or "error" event, or when the
+    /// receiver is dropped.
+    pub async fn stream_pipeline(
+        &self,
+        session: &Session,
+        pipeline_id: PipelineID,
+        input: PipelineInput,
+    ) -> OzoneResult<(TaskID, mpsc::Receiver<PipelineEvent>)> {
+        let task_id = self.create_task(session, pipeline_id, input).await?;
+        let task_manager = self.runtime.read().await.task_manager.clone();
+        let progress_map = self.executor_progress.clone();
+        let (tx, rx) = mpsc::channel(64);
+
+        tokio::spawn(async move {
+            let mut last_progress: HashMap<String, (String, u8)> = HashMap::new();
+            let mut log_offset = 0usize;
+
+            loop {
+                let mut events = Vec::new();
+
+                // Pipeline progress for executions belonging to this task
+                {
+                    let map = progress_map.read().await;
+                    for (id, progress) in map.iter().filter(|(_, p)| p.task_id == Some(task_id)) {
+                        let key = (format!("{:?}", progress.status), progress.progress_percent);
+                        if last_progress.get(id) == Some(&key) {
+                            continue;
+                        }
+                        last_progress.insert(id.clone(), key);
+                        events.push(PipelineEvent {
+                            task_id,
+                            event_type: "progress".to_string(),
+                            data: serde_json::to_value(progress).unwrap_or_default(),
+                        });
+                    }
+                }
+
+                // Task logs written since the last poll
+                let (task, logs) = {
+                    let tm = task_manager.read().await;
+                    let (logs, next_offset) = tm.get_logs_since(task_id, log_offset).await;
+                    log_offset = next_offset;
+                    (tm.get_task(task_id).await, logs)
+                };
+                for entry in logs {
+                    events.push(PipelineEvent {
+                        task_id,
+                        event_type: "log".to_string(),
+                        data: serde_json::to_value(entry).unwrap_or_default(),
+                    });
+                }
+
+                // Terminal state
+                let terminal = match task {
+                    Some(t) if t.status == "completed" => Some(PipelineEvent {
+                        task_id,
+                        event_type: "complete".to_string(),
+                        data: t.outputs.unwrap_or_default(),
+                    }),
+                    Some(t) if t.status == "failed" || t.status == "cancelled" => {
+                        Some(PipelineEvent {
+                            task_id,
+                            event_type: "error".to_string(),
+                            data: serde_json::json!({
+                                "status": t.status,
+                                "error": t.error.unwrap_or_else(|| format!("Task {}", t.status)),
+                            }),
+                        })
+                    }
+                    Some(_) => None,
+                    None => Some(PipelineEvent {
+                        task_id,
+                        event_type: "error".to_string(),
+                        data: serde_json::json!({ "error": "Task no longer exists" }),
+                    }),
+                };
+                let done = terminal.is_some();
+                events.extend(terminal);
+
+                for event in events {
+                    if tx.send(event).await.is_err() {
+                        return;
+                    }
+                }
+                if done {
+                    return;
+                }
+
+                tokio::time::sleep(tokio::time::Duration::from_millis(STREAM_POLL_MS)).await;
+            }
+        });
+
+        Ok((task_id, rx))
+    }
+
+    // ========================================================================
+    // ZSEI
+    // ========================================================================
+
+    /// Whether the session's user may change shared (ownerless) data
+    async fn is_admin(&self, session: &Session) -> bool {
+        let auth = self.runtime.read().await.auth.clone();
+        let user = auth.read().await.get_user(session.user_id).await;
+        user.is_some_and(|u| u.permissions.can_modify_global)
+    }
+
+    /// Get a container by ID, if the session's user may read it
+    pub async fn get_container(&self, session: &Session, id: ContainerID) -> OzoneResult<Container> {
+        let zsei = self.runtime.read().await.zsei.clone();
+        let container = zsei.read().await.get_container(id).await?;
+        let container =
+            container.ok_or_else(|| OzoneError::NotFound(format!("Container {} not found", id)))?;
+        readable(&container, session)?;
+        Ok(container)
+    }
+
+    /// Run a ZSEI query as the session's user. A container the query names
+    /// must be readable by them, or writable for changes; other users'
+    /// containers are left out of listed results
+    pub async fn query_zsei(
+        &self,
+        session: &Session,
+        mut query: ZSEIQuery,
+    ) -> OzoneResult<ZSEIQueryResult> {
+        if let ZSEIQuery::Traverse(request) = query {
+            let result = self.traverse_zsei(session, request).await?;
+            return Ok(ZSEIQueryResult::TraversalResult(result));
+        }
+
+        let admin = self.is_admin(session).await;
+        let zsei = self.runtime.read().await.zsei.clone();
+        let zsei = zsei.read().await;
+        let (target, write) = match &query {
+            ZSEIQuery::GetUserWorkspaces { user_id } => {
+                if *user_id != session.user_id {
+                    return Err(OzoneError::PermissionDenied(format!(
+                        "Workspaces of user {} belong to another user",
+                        user_id
+                    )));
+                }
+                (None, false)
+            }
+            ZSEIQuery::GetProjects { workspace_id }
+            | ZSEIQuery::GetWorkspaceContext { workspace_id } => (Some(*workspace_id), false),
+            ZSEIQuery::GetProjectContext { project_id }
+            | ZSEIQuery::GetFileReferences { project_id }
+            | ZSEIQuery::GetExternalReferences { project_id } => (Some(*project_id), false),
+            ZSEIQuery::CreateContainer { parent_id, .. } => (Some(*parent_id), false),
+            ZSEIQuery::VerifyIntegrity { container_id }
+            | ZSEIQuery::GetVersionHistory { container_id } => (Some(*container_id), false),
+            ZSEIQuery::UpdateContainer { container_id, .. }
+            | ZSEIQuery::DeleteContainer { container_id }
+            | ZSEIQuery::Rollback { container_id, .. } => (Some(*container_id), true),
+            ZSEIQuery::LinkFile { project_id, .. }
+            | ZSEIQuery::LinkURL { project_id, .. }
+            | ZSEIQuery::LinkPackage { project_id, .. }
+            | ZSEIQuery::UnlinkFile { project_id, .. } => (Some(*project_id), true),
+            _ => (None, false),
+        };
+
+        if let Some(id) = target {
+            let container = zsei.get_container(id).await?;
+            match (&container, write) {
+                (Some(container), true) => writable(container, session, admin)?,
+                (Some(container), false) => readable(container, session)?,
+                (None, _) => {}
+            }
+            // Ownership and object paths are set by the server
+            match &mut query {
+                ZSEIQuery::CreateContainer { container, .. } => {
+                    container.local_state.metadata.owner_id = session.user_id;
+                    container.local_state.storage.object_store_path = None;
+                }
+                ZSEIQuery::UpdateContainer { updates, .. } => {
+                    if let Some(existing) = &container {
+                        if let Some(metadata) = updates.metadata.as_mut() {
+                            metadata.owner_id = existing.local_state.metadata.owner_id;
+                        }
+                        if let Some(storage) = updates.storage.as_mut() {
+                            storage.object_store_path =
+                                existing.local_state.storage.object_store_path.clone();
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match zsei.query(query).await? {
+            ZSEIQueryResult::Containers(ids) => {
+                let mut visible = Vec::with_capacity(ids.len());
+                for id in ids {
+                    match zsei.get_container(id).await? {
+                        Some(container) if readable(&container, session).is_err() => {}
+                        _ => visible.push(id),
+                    }
+                }
+                Ok(ZSEIQueryResult::Containers(visible))
+            }
+            ZSEIQueryResult::Container(container) => {
+                readable(&container, session)?;
+                Ok(ZSEIQueryResult::Container(container))
+            }
+            result => Ok(result),
+        }
+    }
+
+    /// Traverse ZSEI from a starting container the session's user may
+    /// read; containers of other users are left out of the result
+    pub async fn traverse_zsei(
+        &self,
+        session: &Session,
+        request: TraversalRequest,
+    ) -> OzoneResult<TraversalResult> {
+        let zsei = self.runtime.read().await.zsei.clone();
+        let zsei = zsei.read().await;
+        let start = zsei
+            .get_container(request.start_container)
+            .await?
+            .ok_or_else(|| {
+                OzoneError::NotFound(format!("Container {} not found", request.start_container))
+            })?;
+        readable(&start, session)?;
+        let mut result = zsei.traverse(request).await?;
+
+        let mut hidden = std::collections::HashSet::new();
+        for &id in &result.containers {
+            let visible = match zsei.get_container(id).await? {
+                Some(container) => readable(&container, session).is_ok(),
+                None => true,
+            };
+            if !visible {
+                hidden.insert(id);
+            }
+        }
+        if !hidden.is_empty() {
+            let distances = std::mem::take(&mut result.distances);
+            result.distances = result
+                .containers
+                .iter()
+                .zip(distances)
+                .filter(|(id, _)| !hidden.contains(id))
+                .map(|(_, distance)| distance)
+                .collect();
+            result.containers.retain(|id| !hidden.contains(id));
+            result
+                .paths
+                .retain(|path| !path.hops.iter().any(|hop| hidden.contains(hop)));
+            result.methodologies.retain(|id| !hidden.contains(id));
+            result.external_refs.retain(|id| !hidden.contains(id));
+        }
+        Ok(result)
+    }
+
+    /// Create, update or delete a container.
+    ///
+    /// `create` and `update` take a serialized `Container`; `delete` takes
+    /// `{"container_id": N}`. Returns the container ID and its version.
+    /// Created containers belong to the session's user; the owner and the
+    /// object path are always set here, never taken from the request.
+    pub async fn write_zsei(
+        &self,
+        session: &Session,
+        operation: &str,
+        data: serde_json::Value,
+    ) -> OzoneResult<(ContainerID, u32)> {
+        let admin = self.is_admin(session).await;
+        let zsei = self.runtime.read().await.zsei.clone();
+        let zsei = zsei.read().await;
+
+        match operation {
+            "create" => {
+                let mut container = parse_container(data)?;
+                let id = if container.global_state.container_id == 0 {
+                    zsei.allocate_id().await
+                } else {
+                    let id = container.global_state.container_id;
+                    if zsei.get_container(id).await?.is_some() {
+                        return Err(OzoneError::ValidationError(format!(
+                            "Container {} already exists",
+                            id
+                        )));
+                    }
+                    id
+                };
+                let parent_id = container.global_state.parent_id;
+                if let Some(parent) = zsei.get_container(parent_id).await? {
+                    readable(&parent, session)?;
+                }
+                container.global_state.container_id = id;
+                container.global_state.version = 1;
+                container.local_state.metadata.owner_id = session.user_id;
+                container.local_state.storage.object_store_path = None;
+                zsei.store_container(container).await?;
+                adopt_child(&zsei, parent_id, id).await?;
+
+                Ok((id, 1))
+            }
+            "update" => {
+                let mut container = parse_container(data)?;
+                let id = container.global_state.container_id;
+                let existing = zsei
+                    .get_container(id)
+                    .await?
+                    .ok_or_else(|| OzoneError::NotFound(format!("Container {} not found", id)))?;
+                writable(&existing, session, admin)?;
+                let version = existing.global_state.version + 1;
+                container.global_state.version = version;
+                container.global_state.parent_id = existing.global_state.parent_id;
+                container.local_state.metadata.owner_id = existing.local_state.metadata.owner_id;
+                container.local_state.storage.object_store_path =
+                    existing.local_state.storage.object_store_path;
+                zsei.store_container(container).await?;
+                Ok((id, version))
+            }
+            "delete" => {
+                let id = data
+                    .get("container_id")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| {
+                        OzoneError::ValidationError("container_id is required".into())
+                    })?;
+                let existing = zsei
+                    .get_container(id)
+                    .await?
+                    .ok_or_else(|| OzoneError::NotFound(format!("Container {} not found", id)))?;
+                writable(&existing, session, admin)?;
+                zsei.delete_container(id).await?;
+
+                let parent_id = existing.global_state.parent_id;
+                if let Some(mut parent) = zsei.get_container(parent_id).await? {
+                    if parent.global_state.child_ids.contains(&id) {
+                        parent.global_state.child_ids.retain(|c| *c != id);
+                        parent.global_state.child_count = parent.global_state.child_ids.len() as u32;
+                        parent.global_state.version += 1;
+                        zsei.store_container(parent).await?;
+                    }
+                }
+
+                Ok((id, existing.global_state.version))
+            }
+            other => Err(OzoneError::ValidationError(format!(
+                "Unknown ZSEI write operation: {}",
+                other
+            ))),
+        }
+    }
+
+    // ========================================================================
+    // BLUEPRINTS
+    // ========================================================================
+
+    /// Store a blueprint as a new container owned by the session's user,
+    /// with a server-assigned ID, and keep it as its first version
+    pub async fn create_blueprint(
+        &self,
+        session: &Session,
+        mut blueprint: Blueprint,
+    ) -> OzoneResult<BlueprintVersion> {
+        let (zsei, versions) = {
+            let runtime = self.runtime.read().await;
+            (runtime.zsei.clone(), runtime.blueprint_versions.clone())
+        };
+        blueprint.blueprint_id = zsei.read().await.allocate_id().await;
+        blueprint.created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let container = blueprint_container(&blueprint, session.user_id);
+        let source = ContainerBlueprintSource::new(zsei.clone(), container.clone());
+        let version = blocking(move || versions.create(blueprint, &source)).await?;
+
+        // The container is registered once its first version is kept
+        let zsei = zsei.read().await;
+        let (id, parent_id) = (
+            container.global_state.container_id,
+            container.global_state.parent_id,
+        );
+        zsei.store_container(container).await?;
+        adopt_child(&zsei, parent_id, id).await?;
+        Ok(version)
+    }
+
+    /// A blueprint at `version`, or its latest version
+    pub async fn get_blueprint(
+        &self,
+        session: &Session,
+        blueprint_id: ContainerID,
+        version: Option<SemVer>,
+    ) -> OzoneResult<Blueprint> {
+        self.with_blueprint(
+            session,
+            blueprint_id,
+            false,
+            move |versions, source| match version {
+                Some(version) => versions.version(blueprint_id, &version, source),
+                None => versions.current(blueprint_id, source),
+            },
+        )
+        .await
+    }
+
+    /// Apply modifications to a blueprint, one new version each, and write
+    /// the last back to its container
+    pub async fn modify_blueprint(
+        &self,
+        session: &Session,
+        blueprint_id: ContainerID,
+        modifications: Vec<BlueprintModification>,
+    ) -> OzoneResult<Vec<BlueprintVersion>> {
+        self.with_blueprint(session, blueprint_id, true, move |versions, source| {
+            versions.modify(blueprint_id, &modifications, source)
+        })
+        .await
+    }
+
+    /// Every version of a blueprint, oldest first
+    pub async fn blueprint_history(
+        &self,
+        session: &Session,
+        blueprint_id: ContainerID,
+    ) -> OzoneResult<Vec<BlueprintVersion>> {
+        self.with_blueprint(session, blueprint_id, false, move |versions, source| {
+            versions.history(blueprint_id, source)
+        })
+        .await
+    }
+
+    /// Changes between two versions of a blueprint
+    pub async fn diff_blueprint(
+        &self,
+        session: &Session,
+        blueprint_id: ContainerID,
+        from: SemVer,
+        to: SemVer,
+    ) -> OzoneResult<Vec<String>> {
+        self.with_blueprint(session, blueprint_id, false, move |versions, source| {
+            versions.diff(blueprint_id, &from, &to, source)
+        })
+        .await
+    }
+
+    /// Run `work` on a blocking thread against the version store and the
+    /// blueprint's container, if the session's user may read the blueprint
+    /// or, for `write`, change it
+    async fn with_blueprint<T: Send + 'static>(
+        &self,
+        session: &Session,
+        blueprint_id: ContainerID,
+        write: bool,
+        work: impl FnOnce(&BlueprintVersionStore, &ContainerBlueprintSource) -> OzoneResult<T>
+            + Send
+            + 'static,
+    ) -> OzoneResult<T> {
+        let (zsei, versions) = {
+            let runtime = self.runtime.read().await;
+            (runtime.zsei.clone(), runtime.blueprint_versions.clone())
+        };
+        let container = zsei
+            .read()
+            .await
+            .get_container(blueprint_id)
+            .await?
+            .filter(|c| c.local_state.metadata.container_type == ContainerType::Blueprint)
+            .ok_or_else(|| OzoneError::NotFound(format!("Blueprint {} not found", blueprint_id)))?;
+        if write {
+            writable(&container, session, self.is_admin(session).await)?;
+        } else {
+            readable(&container, session)?;
+        }
+        let source = ContainerBlueprintSource::new(zsei, container);
+        blocking(move || work(&versions, &source)).await
+    }
+
+    // ========================================================================
+    // ORCHESTRATIONS
+    // ========================================================================
+
+    /// Checkpointed orchestrations of the session's user, most recent first
+    pub async fn orchestrations(&self, session: &Session) -> Vec<CheckpointSummary> {
+        let orchestrator = self.runtime.read().await.orchestrator.clone();
+        orchestrator
+            .checkpoints()
+            .await
+            .into_iter()
+            .filter(|summary| summary.user_id == session.user_id)
+            .collect()
+    }
+
+    /// Continue an orchestration of the session's user after its last
+    /// completed stage and step
+    pub async fn resume_orchestration(
+        &self,
+        session: &Session,
+        orchestration_id: u64,
+    ) -> OzoneResult<OrchestrationOutput> {
+        let orchestrator = self.runtime.read().await.orchestrator.clone();
+        let summary = orchestrator.find_checkpoint(orchestration_id).await;
+        owned_checkpoint(summary, session, &format!("orchestration {}", orchestration_id))?;
+        orchestration_output(orchestrator.resume(orchestration_id).await)
+    }
+
+    /// Answer the questions an orchestration of the session's user stopped
+    /// on and continue it
+    pub async fn answer_clarification(
+        &self,
+        session: &Session,
+        orchestration_id: u64,
+        answers: Vec<String>,
+    ) -> OzoneResult<OrchestrationOutput> {
+        let orchestrator = self.runtime.read().await.orchestrator.clone();
+        let summary = orchestrator.find_checkpoint(orchestration_id).await;
+        let summary = owned_checkpoint(summary, session, &format!("orchestration {}", orchestration_id))?;
+        if summary.status != CheckpointStatus::AwaitingClarification {
+            return Err(OzoneError::Conflict(format!(
+                "Orchestration {} is not awaiting clarification",
+                orchestration_id
+            )));
+        }
+        let response = orchestrator
+            .answer_clarification(orchestration_id, session.user_id, answers)
+            .await
+            .map_err(OzoneError::ValidationError)?;
+        OrchestrationOutput::from_response(response)
+    }
+
+    /// Continue the most recent orchestration of a task of the session's user
+    pub async fn resume_task(
+        &self,
+        session: &Session,
+        task_id: TaskID,
+    ) -> OzoneResult<OrchestrationOutput> {
+        let orchestrator = self.runtime.read().await.orchestrator.clone();
+        let summary = orchestrator
+            .checkpoints()
+            .await
+            .into_iter()
+            .find(|summary| summary.task_id == Some(task_id));
+        let summary = owned_checkpoint(summary, session, &format!("task {}", task_id))?;
+        orchestration_output(orchestrator.resume(summary.orchestration_id).await)
+    }
+
+    // ========================================================================
+    // NETWORK
+    // ========================================================================
+
+    /// P2P status with outbox counts
+    pub async fn network_status(&self) -> NetworkStatus {
+        let runtime = self.runtime.read().await;
+        let network = runtime.network.read().await;
+        network.get_status().await
+    }
+
+    /// Outbox items, oldest first
+    pub async fn outbox(&self) -> Vec<OutboxEntry> {
+        let runtime = self.runtime.read().await;
+        let network = runtime.network.read().await;
+        network.outbox().await
+    }
+
+    // ========================================================================
+    // CONFIG & HEALTH
+    // ========================================================================
+
+    /// Get the whole config, or one section of it
+    pub async fn config_section(&self, section: Option<&str>) -> OzoneResult<serde_json::Value> {
+        let runtime = self.runtime.read().await;
+        let config = &runtime.config;
+
+        let value = match section {
+            None | Some("") => serde_json::to_value(config),
+            Some("zsei") => serde_json::to_value(&config.zsei),
+            Some("pipelines") => serde_json::to_value(&config.pipelines),
+            Some("ui") => serde_json::to_value(&config.ui),
+            Some("model") | Some("models") => serde_json::to_value(&config.models),
+            Some(s) => {
+                return Err(OzoneError::ValidationError(format!(
+                    "Unknown config section: {}",
+                    s
+                )))
+            }
+        };
+
+        value.map_err(|e| OzoneError::SerializationError(e.to_string()))
+    }
+
+    /// Apply config updates and persist them to the config file
+    pub async fn update_config(&self, updates: &serde_json::Value) -> OzoneResult<()> {
+        let config_path =
+            std::env::var("OZONE_CONFIG").unwrap_or_else(|_| "config.toml".to_string());
+
+        let mut runtime = self.runtime.write().await;
+
+        if let Some(updates) = updates.as_object() {
+            // Handle setup_complete flag
+            if let Some(setup_complete) = updates.get("setup_complete") {
+                if let Some(val) = setup_complete.as_bool() {
+                    runtime.config.general.setup_complete = val;
+                }
+            }
+
+            // Handle user_setup_complete flag
+            if let Some(user_setup) = updates.get("user_setup_complete") {
+                if let Some(val) = user_setup.as_bool() {
+                    runtime.config.general.user_setup_complete = val;
+                }
+            }
+
+            // Handle model updates
+            if let Some(models) = updates.get("models") {
+                let mut model_config = runtime.config.models.clone();
+
+                if let Some(v) = models.get("model_type").and_then(|v| v.as_str()) {
+                    model_config.model_type = v.to_string();
+                }
+                if let Some(v) = models.get("api_provider").and_then(|v| v.as_str()) {
+                    // map to your actual fields
+                    model_config.api_endpoint = match v {
+                        "anthropic" => Some("https://api.anthropic.com/v1/messages".to_string()),
+                        "openai" => Some("https://api.openai.com/v1/chat/completions".to_string()),
+                        "google" => {
+                            Some("https://generativelanguage.googleapis.com/v1beta".to_string())
+                        }
+                        _ => model_config.api_endpoint, // already Option<String>
+                    };
+                }
+                if let Some(v) = models.get("api_key").and_then(|v| v.as_str()) {
+                    // you probably want to store it in env or a secure place, but for now:
+                    model_config.api_key_env = Some(v.to_string()); // or however you store it
+                }
+                if let Some(v) = models.get("local_model_path").and_then(|v| v.as_str()) {
+                    model_config.local_model_path = Some(v.to_string());
+                }
+                if let Some(v) = models.get("local_model_type").and_then(|v| v.as_str()) {
+                    model_config.local_model_type = Some(v.to_string());
+                }
+
+                runtime.config.models = model_config;
+            }
+
+            // Handle consciousness updates
+            if let Some(consciousness) = updates.get("consciousness") {
+                if let Some(enabled) = consciousness.get("enabled").and_then(|v| v.as_bool()) {
+                    runtime.config.consciousness.enabled = enabled;
+                }
+            }
+
+            if let Some(voice) = updates.get("voice") {
+                let mut voice_config = runtime.config.voice.clone();
+
+                if let Some(enabled) = voice.get("enabled").and_then(|v| v.as_bool()) {
+                    voice_config.enabled = enabled;
+                }
+                if let Some(path) = voice.get("whisper_model_path").and_then(|v| v.as_str()) {
+                    voice_config.whisper_model_path = Some(path.to_string());
+                }
+                // Optional: add more fields if your wizard ever sends them
+                // e.g. backend type, model size preference, etc.
+
+                runtime.config.voice = voice_config;
+            }
+
+            // Handle UI updates
+            if let Some(ui) = updates.get("ui") {
+                if let Ok(ui_config) = serde_json::from_value(ui.clone()) {
+                    runtime.config.ui = ui_config;
+                }
+            }
+        }
+
+        // Save config to file
+        let config_str = toml::to_string_pretty(&runtime.config)
+            .map_err(|e| OzoneError::ConfigError(format!("Failed to serialize config: {}", e)))?;
+        std::fs::write(&config_path, &config_str)
+            .map_err(|e| OzoneError::ConfigError(format!("Failed to write config: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Current health snapshot
+    pub async fn health(&self) -> HealthResponse {
+        let runtime = self.runtime.read().await;
+        let active_tasks = runtime.task_manager.read().await.active_count().await as u32;
+        let connected_peers =
+            runtime.network.read().await.get_status().await.connected_peers as u32;
+
+        HealthResponse {
+            healthy: true,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_secs: self.start_time.elapsed().as_secs(),
+            active_tasks,
+            connected_peers,
+            zsei_status: "ok".to_string(),
+            ready: self.lifecycle.is_ready(),
+            phase: self.lifecycle.phase().to_string(),
+        }
+    }
+}
+
+/// Check that the session's user may read a container: their own, or a
+/// shared one with no owner, as bootstrap and the orchestrator store
+fn readable(container: &Container, session: &Session) -> OzoneResult<()> {
+    let owner = container.local_state.metadata.owner_id;
+    if owner != 0 && owner != session.user_id {
+        return Err(OzoneError::PermissionDenied(format!(
+            "Container {} belongs to another user",
+            container.global_state.container_id
+        )));
+    }
+    Ok(())
+}
+
+/// Check that the session's user may change a container: their own, or a
+/// shared one if they may modify global data
+fn writable(container: &Container, session: &Session, admin: bool) -> OzoneResult<()> {
+    readable(container, session)?;
+    if container.local_state.metadata.owner_id == 0 && !admin {
+        return Err(OzoneError::PermissionDenied(format!(
+            "Container {} is shared and only an administrator may change it",
+            container.global_state.container_id
+        )));
+    }
+    Ok(())
+}
+
+/// List `id` among its parent's children, if the parent exists
+async fn adopt_child(zsei: &ZSEI, parent_id: ContainerID, id: ContainerID) -> OzoneResult<()> {
+    if let Some(mut parent) = zsei.get_container(parent_id).await? {
+        if parent_id != id && !parent.global_state.child_ids.contains(&id) {
+            parent.global_state.child_ids.push(id);
+            parent.global_state.child_count = parent.global_state.child_ids.len() as u32;
+            parent.global_state.version += 1;
+            zsei.store_container(parent).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Run file work on a blocking thread
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> OzoneResult<T> + Send + 'static,
+) -> OzoneResult<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| OzoneError::StorageError(e.to_string()))?
+}
+
+/// A task, if it belongs to the session's user
+async fn owned_task(
+    task_mgr: &TaskManager,
+    session: &Session,
+    task_id: TaskID,
+) -> OzoneResult<TaskData> {
+    let task = task_mgr
+        .get_task(task_id)
+        .await
+        .ok_or_else(|| OzoneError::NotFound(format!("Task {} not found", task_id)))?;
+    if task.user_id != session.user_id {
+        return Err(OzoneError::PermissionDenied(format!(
+            "Task {} belongs to another user",
+            task_id
+        )));
+    }
+    Ok(task)
+}
+
+/// A checkpoint, if it belongs to the session's user
+fn owned_checkpoint(
+    summary: Option<CheckpointSummary>,
+    session: &Session,
+    what: &str,
+) -> OzoneResult<CheckpointSummary> {
+    let summary =
+        summary.ok_or_else(|| OzoneError::NotFound(format!("No checkpoint for {}", what)))?;
+    if summary.user_id != session.user_id {
+        return Err(OzoneError::PermissionDenied(format!(
+            "Checkpoint of {} belongs to another user",
+            what
+        )));
+    }
+    Ok(summary)
+}
+
+fn orchestration_output(
+    response: Result<OrchestrationResponse, String>,
+) -> OzoneResult<OrchestrationOutput> {
+    OrchestrationOutput::from_response(response.map_err(OzoneError::TaskError)?)
+}
+
+fn parse_container(data: serde_json::Value) -> OzoneResult<Container> {
+    serde_json::from_value(data)
+        .map_err(|e| OzoneError::ValidationError(format!("Invalid container: {}", e)))
+}
@@ -0,0 +1,1830 @@
+//! Versioned REST API (`/api/v1`)
+//!
+//! Resource-style routes over the shared operations in `ops`. Sessions are
+//! passed as `Authorization: Bearer <session_token>`. Failures return the
+//! status from `to_status` with an `ErrorBody`; list endpoints take
+//! `limit`/`offset` and return a `Page`. The OpenAPI document is generated
+//! from the handler annotations and served at `/api/v1/openapi.json`.
+
+use super::{
+    build_pipeline_registry, to_status, AppState, ChallengeResponse, HealthResponse,
+    PipelineRegistryEntry, ProbeResponse, TaskInfo,
+};
+use crate::blueprints::BlueprintVersion;
+use crate::network::outbox::{DeliveryStatus, Destination, OutboxEntry, Payload};
+use crate::orchestrator::checkpoint::CheckpointSummary;
+use crate::types::auth::Session;
+use crate::types::blueprint::{Blueprint, BlueprintModification};
+use crate::types::pipeline::PipelineInput;
+use crate::types::zsei::{TraversalRequest, ZSEIQuery};
+use crate::types::{OzoneError, PipelineID, SemVer};
+use crate::OrchestrationOutput;
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRequest, FromRequestParts, Path, Query, Request, State,
+    },
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    IntoParams, Modify, OpenApi, ToSchema,
+};
+
+/// Page size used when `limit` is omitted
+const DEFAULT_PAGE_LIMIT: u32 = 50;
+
+/// Largest page a client may request
+const MAX_PAGE_LIMIT: u32 = 500;
+
+/// Build the `/api/v1` router (nested by `start_server`)
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/health", get(health))
+        .route("/health/live", get(liveness))
+        .route("/health/ready", get(readiness))
+        .route("/openapi.json", get(openapi_json))
+        .route("/sessions/challenge", post(create_challenge))
+        .route("/sessions", post(create_session))
+        .route("/sessions/current", get(get_session).delete(delete_session))
+        .route("/tasks", get(list_tasks).post(create_task))
+        .route("/tasks/:task_id", get(get_task))
+        .route("/tasks/:task_id/cancel", post(cancel_task))
+        .route("/tasks/:task_id/retry", post(retry_task))
+        .route("/tasks/:task_id/logs", get(task_logs))
+        .route("/tasks/:task_id/resume", post(resume_task))
+        .route("/orchestrations", get(list_orchestrations))
+        .route(
+            "/orchestrations/:orchestration_id/resume",
+            post(resume_orchestration),
+        )
+        .route(
+            "/orchestrations/:orchestration_id/clarifications",
+            post(answer_clarification),
+        )
+        .route("/pipelines", get(list_pipelines))
+        .route("/pipelines/:pipeline_id", get(get_pipeline))
+        .route("/pipelines/:pipeline_id/executions", post(execute_pipeline))
+        .route(
+            "/executions/:execution_id",
+            get(get_execution).delete(cancel_execution),
+        )
+        .route("/blueprints", post(create_blueprint))
+        .route("/blueprints/:blueprint_id", get(get_blueprint))
+        .route(
+            "/blueprints/:blueprint_id/modifications",
+            post(modify_blueprint),
+        )
+        .route("/blueprints/:blueprint_id/versions", get(list_blueprint_versions))
+        .route(
+            "/blueprints/:blueprint_id/versions/:version",
+            get(get_blueprint_version),
+        )
+        .route("/blueprints/:blueprint_id/diff", get(diff_blueprint))
+        .route("/containers", post(create_container))
+        .route("/containers/query", post(query_containers))
+        .route(
+            "/containers/:container_id",
+            get(get_container)
+                .put(update_container)
+                .delete(delete_container),
+        )
+        .route("/containers/:container_id/children", get(container_children))
+        .route("/containers/:container_id/traverse", post(traverse_container))
+        .route("/config", get(get_config).patch(update_config))
+        .route("/config/:section", get(get_config_section))
+        .route("/network", get(network_status))
+        .route("/network/outbox", get(list_outbox))
+}
+
+// ============================================================================
+// OpenAPI
+// ============================================================================
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Ozone Studio API"),
+    paths(
+        health,
+        liveness,
+        readiness,
+        create_challenge,
+        create_session,
+        get_session,
+        delete_session,
+        list_tasks,
+        create_task,
+        get_task,
+        cancel_task,
+        retry_task,
+        task_logs,
+        resume_task,
+        list_orchestrations,
+        resume_orchestration,
+        answer_clarification,
+        list_pipelines,
+        get_pipeline,
+        execute_pipeline,
+        get_execution,
+        cancel_execution,
+        create_blueprint,
+        get_blueprint,
+        modify_blueprint,
+        list_blueprint_versions,
+        get_blueprint_version,
+        diff_blueprint,
+        create_container,
+        query_containers,
+        get_container,
+        update_container,
+        delete_container,
+        container_children,
+        traverse_container,
+        get_config,
+        get_config_section,
+        update_config,
+        network_status,
+        list_outbox,
+    ),
+    components(schemas(
+        ErrorBody,
+        ErrorDetail,
+        HealthResponse,
+        ProbeResponse,
+        ChallengeBody,
+        ChallengeResponse,
+        SessionBody,
+        SessionInfo,
+        TaskInfo,
+        TaskPage,
+        CreateTaskBody,
+        TaskLogEntry,
+        CheckpointInfo,
+        CheckpointPage,
+        OrchestrationResult,
+        ClarificationBody,
+        PipelineRegistryEntry,
+        PipelinePage,
+        ExecuteBody,
+        ExecutionResult,
+        ExecutionInfo,
+        ExecutionCancelled,
+        BlueprintVersionInfo,
+        BlueprintVersionPage,
+        ModifyBlueprintBody,
+        BlueprintModified,
+        BlueprintDiff,
+        ContainerWritten,
+        ContainerIdPage,
+        NetworkStatusInfo,
+        OutboxItem,
+        OutboxDelivery,
+        OutboxPage,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "sessions", description = "Ed25519 challenge/response login"),
+        (name = "tasks", description = "Task lifecycle"),
+        (name = "orchestrations", description = "Checkpointed prompt orchestrations"),
+        (name = "pipelines", description = "Pipeline registry and execution"),
+        (name = "blueprints", description = "Blueprint editing and version history"),
+        (name = "containers", description = "ZSEI containers"),
+        (name = "config", description = "Runtime configuration"),
+        (name = "network", description = "P2P status and outbound sync queue"),
+        (name = "system", description = "Health and API description"),
+    )
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("hex session token")
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Serve the generated OpenAPI document
+async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+// ============================================================================
+// Errors & Extractors
+// ============================================================================
+
+/// Error response body
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorDetail {
+    /// Stable machine-readable code, e.g. `not_found`
+    pub code: String,
+    pub message: String,
+}
+
+/// `OzoneError` rendered as a typed HTTP error
+#[derive(Debug)]
+pub struct ApiError(pub OzoneError);
+
+impl From<OzoneError> for ApiError {
+    fn from(error: OzoneError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: ErrorDetail {
+                code: error_code(&self.0).to_string(),
+                message: self.0.to_string(),
+            },
+        };
+        (to_status(self.0), Json(body)).into_response()
+    }
+}
+
+/// Machine-readable code for each error variant
+pub fn error_code(error: &OzoneError) -> &'static str {
+    match error {
+        OzoneError::AuthError(_) => "auth_error",
+        OzoneError::ZSEIError(_) => "zsei_error",
+        OzoneError::PipelineError(_) => "pipeline_error",
+        OzoneError::TaskError(_) => "task_error",
+        OzoneError::StorageError(_) => "storage_error",
+        OzoneError::IntegrityError(_) => "integrity_error",
+        OzoneError::NetworkError(_) => "network_error",
+        OzoneError::ConfigError(_) => "config_error",
+        OzoneError::ValidationError(_) => "validation_error",
+        OzoneError::NotFound(_) => "not_found",
+        OzoneError::PermissionDenied(_) => "permission_denied",
+        OzoneError::Conflict(_) => "conflict",
+        OzoneError::IoError(_) => "io_error",
+        OzoneError::SerializationError(_) => "serialization_error",
+        OzoneError::DatabaseError(_) => "database_error",
+        OzoneError::ExternalRefError(_) => "external_ref_error",
+        OzoneError::ServerError(_) => "server_error",
+        OzoneError::ConsciousnessError(_) => "consciousness_error",
+        OzoneError::ModelError(_) => "model_error",
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// JSON body whose rejection is reported as a `validation_error`
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Json::<T>::from_request(req, state)
+            .await
+            .map(|Json(value)| ApiJson(value))
+            .map_err(|e: JsonRejection| OzoneError::ValidationError(e.body_text()).into())
+    }
+}
+
+/// Query string whose rejection is reported as a `validation_error`
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Query::<T>::from_request_parts(parts, state)
+            .await
+            .map(|Query(value)| ApiQuery(value))
+            .map_err(|e: QueryRejection| OzoneError::ValidationError(e.body_text()).into())
+    }
+}
+
+/// Path parameters whose rejection is reported as a `validation_error`
+pub struct ApiPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Path::<T>::from_request_parts(parts, state)
+            .await
+            .map(|Path(value)| ApiPath(value))
+            .map_err(|e: PathRejection| OzoneError::ValidationError(e.body_text()).into())
+    }
+}
+
+/// Session resolved from the `Authorization: Bearer` header
+pub struct AuthSession(pub Session);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AuthSession {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| OzoneError::AuthError("Missing bearer token".into()))?;
+
+        Ok(AuthSession(state.authorize(token.trim()).await?))
+    }
+}
+
+// ============================================================================
+// Pagination
+// ============================================================================
+
+/// `limit`/`offset` query parameters
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// Page size (default 50, max 500)
+    pub limit: Option<u32>,
+    /// Number of items to skip
+    pub offset: Option<u32>,
+}
+
+impl PageParams {
+    fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    fn offset(&self) -> u32 {
+        self.offset.unwrap_or(0)
+    }
+}
+
+/// One page of a list endpoint
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[aliases(
+    TaskPage = Page<TaskInfo>,
+    CheckpointPage = Page<CheckpointInfo>,
+    PipelinePage = Page<PipelineRegistryEntry>,
+    ContainerIdPage = Page<u64>,
+    BlueprintVersionPage = Page<BlueprintVersionInfo>,
+    OutboxPage = Page<OutboxItem>
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Total number of items matching the request
+    pub total: u64,
+    pub limit: u32,
+    pub offset: u32,
+    /// Offset of the next page, if any
+    pub next_offset: Option<u32>,
+}
+
+impl<T> Page<T> {
+    fn new(items: Vec<T>, total: usize, params: &PageParams) -> Self {
+        let limit = params.limit();
+        let offset = params.offset();
+        let end = offset as usize + items.len();
+        Self {
+            items,
+            total: total as u64,
+            limit,
+            offset,
+            next_offset: (end < total).then_some(end as u32),
+        }
+    }
+
+    /// Page over an already materialised list
+    fn slice(all: Vec<T>, params: &PageParams) -> Self {
+        let total = all.len();
+        let items = all
+            .into_iter()
+            .skip(params.offset() as usize)
+            .take(params.limit() as usize)
+            .collect();
+        Self::new(items, total, params)
+    }
+}
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChallengeBody {
+    /// Hex-encoded Ed25519 public key
+    pub public_key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SessionBody {
+    /// Hex-encoded Ed25519 public key
+    pub public_key: String,
+    /// Hex-encoded signature over the challenge
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SessionInfo {
+    /// Hex-encoded bearer token (only returned on creation)
+    pub session_token: Option<String>,
+    pub user_id: u64,
+    pub device_id: u64,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+impl SessionInfo {
+    fn from_session(session: &Session, include_token: bool) -> Self {
+        Self {
+            session_token: include_token.then(|| hex::encode(&session.session_token)),
+            user_id: session.user_id,
+            device_id: session.device_id,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateTaskBody {
+    pub pipeline_id: PipelineID,
+    /// Serialized `PipelineInput`
+    #[schema(value_type = Object)]
+    pub input: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskListParams {
+    /// Filter by status ("queued", "running", "completed", "failed", "cancelled")
+    pub status: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogParams {
+    /// Most recent entries to return (default 100)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TaskLogEntry {
+    pub timestamp: u64,
+    pub level: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PipelineListParams {
+    /// Filter by category
+    pub category: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExecuteBody {
+    /// Serialized `PipelineInput`
+    #[schema(value_type = Object)]
+    pub input: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CheckpointInfo {
+    pub orchestration_id: u64,
+    pub task_id: Option<u64>,
+    /// "running", "failed", "awaiting_clarification" or "complete"
+    pub status: String,
+    pub error: Option<String>,
+    /// Last stage that completed
+    pub stage: u8,
+    pub steps_completed: u32,
+    pub total_steps: u32,
+    pub updated_at: u64,
+}
+
+impl From<CheckpointSummary> for CheckpointInfo {
+    fn from(summary: CheckpointSummary) -> Self {
+        Self {
+            orchestration_id: summary.orchestration_id,
+            task_id: summary.task_id,
+            status: label(&summary.status),
+            error: summary.error,
+            stage: summary.stage,
+            steps_completed: summary.steps_completed,
+            total_steps: summary.total_steps,
+            updated_at: summary.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrchestrationResult {
+    pub orchestration_id: u64,
+    pub success: bool,
+    pub response: Option<String>,
+    pub task_id: Option<u64>,
+    pub blueprint_id: Option<u64>,
+    #[schema(value_type = Vec<Object>)]
+    pub stages_completed: Vec<serde_json::Value>,
+    pub needs_clarification: bool,
+    pub clarification_points: Vec<String>,
+}
+
+impl From<OrchestrationOutput> for OrchestrationResult {
+    fn from(output: OrchestrationOutput) -> Self {
+        Self {
+            orchestration_id: output.orchestration_id,
+            success: output.success,
+            response: output.response_text,
+            task_id: output.task_id,
+            blueprint_id: output.blueprint_id,
+            stages_completed: output.stages_completed,
+            needs_clarification: output.needs_clarification,
+            clarification_points: output.clarification_points,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ClarificationBody {
+    /// One answer per open question, in the order of its
+    /// `clarification_points`
+    pub answers: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExecutionResult {
+    pub execution_id: String,
+    pub success: bool,
+    pub task_id: Option<u64>,
+    #[schema(value_type = Object)]
+    pub output: serde_json::Value,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExecutionInfo {
+    pub execution_id: String,
+    pub pipeline_id: u64,
+    pub pipeline_name: String,
+    pub task_id: Option<u64>,
+    pub step_index: Option<u32>,
+    pub status: String,
+    pub progress_percent: u8,
+    pub tokens_used: Option<u32>,
+    pub started_at: u64,
+    pub completed_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExecutionCancelled {
+    pub execution_id: String,
+    pub was_running: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlueprintVersionInfo {
+    pub blueprint_id: u64,
+    /// "major.minor.patch"
+    pub version: String,
+    /// Serialized `BlueprintModification`; null for the first version
+    #[schema(value_type = Object)]
+    pub modification: Option<serde_json::Value>,
+    /// What changed from the previous version
+    pub changes: Vec<String>,
+    pub created_at: u64,
+}
+
+impl BlueprintVersionInfo {
+    fn from_version(version: &BlueprintVersion) -> Self {
+        Self {
+            blueprint_id: version.blueprint.blueprint_id,
+            version: version.version.to_string(),
+            modification: version
+                .modification
+                .as_ref()
+                .and_then(|m| serde_json::to_value(m).ok()),
+            changes: version.changes.clone(),
+            created_at: version.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ModifyBlueprintBody {
+    /// Serialized `BlueprintModification`s, applied in order; if one is
+    /// rejected none are kept
+    #[schema(value_type = Vec<Object>)]
+    pub modifications: Vec<BlueprintModification>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlueprintModified {
+    /// One new version per modification
+    pub versions: Vec<BlueprintVersionInfo>,
+    /// Serialized `Blueprint` at the latest version
+    #[schema(value_type = Object)]
+    pub blueprint: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiffParams {
+    /// Version to compare from
+    pub from: String,
+    /// Version to compare to (default: the latest)
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlueprintDiff {
+    pub from: String,
+    pub to: String,
+    pub changes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ContainerWritten {
+    pub container_id: u64,
+    pub version: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NetworkStatusInfo {
+    pub enabled: bool,
+    pub peer_id: Option<String>,
+    pub connected_peers: usize,
+    pub known_peers: usize,
+    /// Outbox items waiting for delivery, by sync priority
+    pub pending_high: usize,
+    pub pending_normal: usize,
+    pub pending_low: usize,
+    /// Outbox items with a delivery that was refused or ran out of attempts
+    pub failed: usize,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OutboxParams {
+    /// Only items with a delivery in this state ("pending", "delivered", "failed")
+    pub status: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OutboxItem {
+    /// Hash of the content
+    pub id: String,
+    pub container_id: Option<u64>,
+    /// Container kind ("methodology", "blueprint", "container",
+    /// "pipeline_result") or the gossip topic of a message
+    pub kind: String,
+    /// "high", "normal" or "low"
+    pub priority: String,
+    pub queued_at: u64,
+    pub expires_at: u64,
+    pub deliveries: Vec<OutboxDelivery>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OutboxDelivery {
+    /// "broadcast" or a peer ID
+    pub destination: String,
+    /// "pending", "delivered" or "failed"
+    pub status: String,
+    /// Failed attempts so far
+    pub attempts: u32,
+    pub next_attempt: u64,
+    pub last_error: Option<String>,
+}
+
+impl OutboxItem {
+    fn from_entry(entry: OutboxEntry) -> Self {
+        let kind = match &entry.payload {
+            Payload::Item { item_type, .. } => label(item_type),
+            Payload::Message { topic, .. } => topic.clone(),
+        };
+        Self {
+            id: entry.id,
+            container_id: entry.container_id,
+            kind,
+            priority: label(&entry.priority),
+            queued_at: entry.queued_at,
+            expires_at: entry.expires_at,
+            deliveries: entry
+                .deliveries
+                .into_iter()
+                .map(|delivery| OutboxDelivery {
+                    destination: match delivery.destination {
+                        Destination::Broadcast => "broadcast".into(),
+                        Destination::Peer(peer_id) => peer_id,
+                    },
+                    status: label(&delivery.status),
+                    attempts: delivery.attempts,
+                    next_attempt: delivery.next_attempt,
+                    last_error: delivery.last_error,
+                })
+                .collect(),
+        }
+    }
+}
+
+// ============================================================================
+// System
+// ============================================================================
+
+#[utoipa::path(
+    get, path = "/api/v1/health", tag = "system",
+    responses((status = 200, body = HealthResponse))
+)]
+async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
+    Json(state.health().await)
+}
+
+/// Liveness probe: the process is up and serving, even while draining
+#[utoipa::path(
+    get, path = "/api/v1/health/live", tag = "system",
+    responses((status = 200, body = ProbeResponse))
+)]
+pub(super) async fn liveness(State(state): State<Arc<AppState>>) -> Json<ProbeResponse> {
+    Json(probe(&state))
+}
+
+/// Readiness probe: 503 unless the runtime is accepting requests
+#[utoipa::path(
+    get, path = "/api/v1/health/ready", tag = "system",
+    responses(
+        (status = 200, body = ProbeResponse),
+        (status = 503, description = "Starting or shutting down", body = ProbeResponse),
+    )
+)]
+pub(super) async fn readiness(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<ProbeResponse>) {
+    let probe = probe(&state);
+    let status = if probe.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(probe))
+}
+
+fn probe(state: &AppState) -> ProbeResponse {
+    ProbeResponse {
+        ready: state.lifecycle.is_ready(),
+        phase: state.lifecycle.phase().to_string(),
+    }
+}
+
+// ============================================================================
+// Sessions
+// ============================================================================
+
+#[utoipa::path(
+    post, path = "/api/v1/sessions/challenge", tag = "sessions",
+    request_body = ChallengeBody,
+    responses(
+        (status = 201, body = ChallengeResponse),
+        (status = 400, body = ErrorBody),
+    )
+)]
+async fn create_challenge(
+    State(state): State<Arc<AppState>>,
+    ApiJson(body): ApiJson<ChallengeBody>,
+) -> ApiResult<(StatusCode, Json<ChallengeResponse>)> {
+    let public_key = decode_hex("public_key", &body.public_key)?;
+    let challenge = state.request_challenge(&public_key).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(ChallengeResponse {
+            challenge: hex::encode(&challenge.challenge),
+            expires_at: challenge.expires_at,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/sessions", tag = "sessions",
+    request_body = SessionBody,
+    responses(
+        (status = 201, body = SessionInfo),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+    )
+)]
+async fn create_session(
+    State(state): State<Arc<AppState>>,
+    ApiJson(body): ApiJson<SessionBody>,
+) -> ApiResult<(StatusCode, Json<SessionInfo>)> {
+    let public_key = decode_hex("public_key", &body.public_key)?;
+    let signature = decode_hex("signature", &body.signature)?;
+    let session = state.authenticate(&public_key, &signature).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(SessionInfo::from_session(&session, true)),
+    ))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/sessions/current", tag = "sessions",
+    security(("bearer" = [])),
+    responses((status = 200, body = SessionInfo), (status = 401, body = ErrorBody))
+)]
+async fn get_session(AuthSession(session): AuthSession) -> Json<SessionInfo> {
+    Json(SessionInfo::from_session(&session, false))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/sessions/current", tag = "sessions",
+    security(("bearer" = [])),
+    responses((status = 204), (status = 401, body = ErrorBody))
+)]
+async fn delete_session(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+) -> ApiResult<StatusCode> {
+    state.logout(&hex::encode(&session.session_token)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// Tasks
+// ============================================================================
+
+#[utoipa::path(
+    get, path = "/api/v1/tasks", tag = "tasks",
+    security(("bearer" = [])),
+    params(TaskListParams),
+    responses((status = 200, body = TaskPage), (status = 401, body = ErrorBody))
+)]
+async fn list_tasks(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiQuery(params): ApiQuery<TaskListParams>,
+) -> Json<Page<TaskInfo>> {
+    let page = PageParams {
+        limit: params.limit,
+        offset: params.offset,
+    };
+    let (tasks, total) = state
+        .list_tasks(
+            &session,
+            params.status.as_deref(),
+            page.limit() as usize,
+            page.offset() as usize,
+        )
+        .await;
+    Json(Page::new(
+        tasks.into_iter().map(TaskInfo::from).collect(),
+        total,
+        &page,
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/tasks", tag = "tasks",
+    security(("bearer" = [])),
+    request_body = CreateTaskBody,
+    responses(
+        (status = 201, body = TaskInfo),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn create_task(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiJson(body): ApiJson<CreateTaskBody>,
+) -> ApiResult<(StatusCode, Json<TaskInfo>)> {
+    let input = parse_input(body.input)?;
+    let task_id = state.create_task(&session, body.pipeline_id, input).await?;
+    let task = state.get_task(&session, task_id).await?;
+    Ok((StatusCode::CREATED, Json(task.into())))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/tasks/{task_id}", tag = "tasks",
+    security(("bearer" = [])),
+    params(("task_id" = u64, Path, description = "Task ID")),
+    responses(
+        (status = 200, body = TaskInfo),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Task belongs to another user", body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn get_task(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(task_id): ApiPath<u64>,
+) -> ApiResult<Json<TaskInfo>> {
+    Ok(Json(state.get_task(&session, task_id).await?.into()))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/tasks/{task_id}/cancel", tag = "tasks",
+    security(("bearer" = [])),
+    params(("task_id" = u64, Path, description = "Task ID")),
+    responses(
+        (status = 200, body = TaskInfo),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Task belongs to another user", body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "Task already finished", body = ErrorBody),
+    )
+)]
+async fn cancel_task(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(task_id): ApiPath<u64>,
+) -> ApiResult<Json<TaskInfo>> {
+    Ok(Json(state.cancel_task(&session, task_id).await?.into()))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/tasks/{task_id}/retry", tag = "tasks",
+    security(("bearer" = [])),
+    params(("task_id" = u64, Path, description = "Failed or cancelled task to retry")),
+    responses(
+        (status = 201, description = "The new task", body = TaskInfo),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Task belongs to another user", body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn retry_task(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(task_id): ApiPath<u64>,
+) -> ApiResult<(StatusCode, Json<TaskInfo>)> {
+    let new_task_id = state.retry_task(&session, task_id).await?;
+    let task = state.get_task(&session, new_task_id).await?;
+    Ok((StatusCode::CREATED, Json(task.into())))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/tasks/{task_id}/logs", tag = "tasks",
+    security(("bearer" = [])),
+    params(("task_id" = u64, Path, description = "Task ID"), LogParams),
+    responses(
+        (status = 200, body = [TaskLogEntry]),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Task belongs to another user", body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn task_logs(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(task_id): ApiPath<u64>,
+    ApiQuery(params): ApiQuery<LogParams>,
+) -> ApiResult<Json<Vec<TaskLogEntry>>> {
+    let logs = state
+        .task_logs(&session, task_id, params.limit.unwrap_or(100))
+        .await?;
+    Ok(Json(
+        logs.into_iter()
+            .map(|l| TaskLogEntry {
+                timestamp: l.timestamp,
+                level: format!("{:?}", l.level),
+                message: l.message,
+            })
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/tasks/{task_id}/resume", tag = "tasks",
+    security(("bearer" = [])),
+    params(("task_id" = u64, Path, description = "Task whose latest orchestration to continue")),
+    responses(
+        (status = 200, body = OrchestrationResult),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Task belongs to another user", body = ErrorBody),
+        (status = 404, description = "No checkpoint for the task", body = ErrorBody),
+        (status = 500, description = "The orchestration failed again", body = ErrorBody),
+    )
+)]
+async fn resume_task(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(task_id): ApiPath<u64>,
+) -> ApiResult<Json<OrchestrationResult>> {
+    Ok(Json(state.resume_task(&session, task_id).await?.into()))
+}
+
+// ============================================================================
+// Orchestrations
+// ============================================================================
+
+#[utoipa::path(
+    get, path = "/api/v1/orchestrations", tag = "orchestrations",
+    security(("bearer" = [])),
+    params(PageParams),
+    responses((status = 200, body = CheckpointPage), (status = 401, body = ErrorBody))
+)]
+async fn list_orchestrations(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiQuery(page): ApiQuery<PageParams>,
+) -> Json<Page<CheckpointInfo>> {
+    let checkpoints = state.orchestrations(&session).await;
+    Json(Page::slice(
+        checkpoints.into_iter().map(CheckpointInfo::from).collect(),
+        &page,
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/orchestrations/{orchestration_id}/resume", tag = "orchestrations",
+    security(("bearer" = [])),
+    params(("orchestration_id" = u64, Path, description = "Orchestration ID")),
+    responses(
+        (status = 200, body = OrchestrationResult),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Orchestration belongs to another user", body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 500, description = "The orchestration failed again", body = ErrorBody),
+    )
+)]
+async fn resume_orchestration(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(orchestration_id): ApiPath<u64>,
+) -> ApiResult<Json<OrchestrationResult>> {
+    Ok(Json(
+        state
+            .resume_orchestration(&session, orchestration_id)
+            .await?
+            .into(),
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/orchestrations/{orchestration_id}/clarifications", tag = "orchestrations",
+    security(("bearer" = [])),
+    params(("orchestration_id" = u64, Path, description = "Orchestration awaiting clarification")),
+    request_body = ClarificationBody,
+    responses(
+        (status = 200, body = OrchestrationResult),
+        (status = 400, description = "Wrong number of answers", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Orchestration belongs to another user", body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "Orchestration is not awaiting clarification", body = ErrorBody),
+    )
+)]
+async fn answer_clarification(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(orchestration_id): ApiPath<u64>,
+    ApiJson(body): ApiJson<ClarificationBody>,
+) -> ApiResult<Json<OrchestrationResult>> {
+    Ok(Json(
+        state
+            .answer_clarification(&session, orchestration_id, body.answers)
+            .await?
+            .into(),
+    ))
+}
+
+// ============================================================================
+// Pipelines
+// ============================================================================
+
+#[utoipa::path(
+    get, path = "/api/v1/pipelines", tag = "pipelines",
+    security(("bearer" = [])),
+    params(PipelineListParams),
+    responses((status = 200, body = PipelinePage), (status = 401, body = ErrorBody))
+)]
+async fn list_pipelines(
+    _session: AuthSession,
+    ApiQuery(params): ApiQuery<PipelineListParams>,
+) -> Json<Page<PipelineRegistryEntry>> {
+    let mut entries = build_pipeline_registry();
+    if let Some(category) = &params.category {
+        entries.retain(|e| &e.category == category);
+    }
+    entries.sort_by_key(|e| e.id);
+
+    let page = PageParams {
+        limit: params.limit,
+        offset: params.offset,
+    };
+    Json(Page::slice(entries, &page))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/pipelines/{pipeline_id}", tag = "pipelines",
+    security(("bearer" = [])),
+    params(("pipeline_id" = u64, Path, description = "Pipeline ID")),
+    responses(
+        (status = 200, body = PipelineRegistryEntry),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn get_pipeline(
+    _session: AuthSession,
+    ApiPath(pipeline_id): ApiPath<u64>,
+) -> ApiResult<Json<PipelineRegistryEntry>> {
+    build_pipeline_registry()
+        .into_iter()
+        .find(|e| e.id == pipeline_id)
+        .map(Json)
+        .ok_or_else(|| OzoneError::NotFound(format!("Pipeline {} not found", pipeline_id)).into())
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/pipelines/{pipeline_id}/executions", tag = "pipelines",
+    security(("bearer" = [])),
+    params(("pipeline_id" = u64, Path, description = "Pipeline ID")),
+    request_body = ExecuteBody,
+    responses(
+        (status = 200, body = ExecutionResult),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn execute_pipeline(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(pipeline_id): ApiPath<u64>,
+    ApiJson(body): ApiJson<ExecuteBody>,
+) -> ApiResult<Json<ExecutionResult>> {
+    let input = parse_input(body.input)?;
+    let output = state
+        .execute_pipeline(&session, pipeline_id, input)
+        .await?;
+    Ok(Json(ExecutionResult {
+        execution_id: output.execution_id.as_str(),
+        success: output.success,
+        task_id: output.task_id,
+        output: serde_json::to_value(&output.data).unwrap_or_default(),
+        error: output.error,
+    }))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/executions/{execution_id}", tag = "pipelines",
+    security(("bearer" = [])),
+    params(("execution_id" = String, Path, description = "Execution ID")),
+    responses(
+        (status = 200, body = ExecutionInfo),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn get_execution(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(execution_id): ApiPath<String>,
+) -> ApiResult<Json<ExecutionInfo>> {
+    let progress = state.get_execution(&session, &execution_id).await?;
+    Ok(Json(ExecutionInfo {
+        execution_id: progress.execution_id,
+        pipeline_id: progress.pipeline_id,
+        pipeline_name: progress.pipeline_name,
+        task_id: progress.task_id,
+        step_index: progress.step_index,
+        status: format!("{:?}", progress.status),
+        progress_percent: progress.progress_percent,
+        tokens_used: progress.tokens_used,
+        started_at: progress.started_at,
+        completed_at: progress.completed_at,
+        error: progress.error,
+    }))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/executions/{execution_id}", tag = "pipelines",
+    security(("bearer" = [])),
+    params(("execution_id" = String, Path, description = "Execution ID")),
+    responses(
+        (status = 200, body = ExecutionCancelled),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn cancel_execution(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(execution_id): ApiPath<String>,
+) -> ApiResult<Json<ExecutionCancelled>> {
+    let was_running = state.cancel_execution(&session, &execution_id).await?;
+    Ok(Json(ExecutionCancelled {
+        execution_id,
+        was_running,
+    }))
+}
+
+// ============================================================================
+// Blueprints
+// ============================================================================
+
+#[utoipa::path(
+    post, path = "/api/v1/blueprints", tag = "blueprints",
+    security(("bearer" = [])),
+    request_body(content = Object, description = "Serialized Blueprint; kept as its first version under a new blueprint_id"),
+    responses(
+        (status = 201, body = BlueprintVersionInfo),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+    )
+)]
+async fn create_blueprint(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiJson(blueprint): ApiJson<Blueprint>,
+) -> ApiResult<(StatusCode, Json<BlueprintVersionInfo>)> {
+    let version = state.create_blueprint(&session, blueprint).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(BlueprintVersionInfo::from_version(&version)),
+    ))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/blueprints/{blueprint_id}", tag = "blueprints",
+    security(("bearer" = [])),
+    params(("blueprint_id" = u64, Path, description = "Blueprint ID")),
+    responses(
+        (status = 200, description = "Serialized Blueprint at its latest version", body = Object),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The blueprint is behind its version history", body = ErrorBody),
+    )
+)]
+async fn get_blueprint(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(blueprint_id): ApiPath<u64>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let blueprint = state.get_blueprint(&session, blueprint_id, None).await?;
+    Ok(Json(serde_json::to_value(&blueprint).unwrap_or_default()))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/blueprints/{blueprint_id}/modifications", tag = "blueprints",
+    security(("bearer" = [])),
+    params(("blueprint_id" = u64, Path, description = "Blueprint ID")),
+    request_body = ModifyBlueprintBody,
+    responses(
+        (status = 200, body = BlueprintModified),
+        (status = 400, description = "A modification broke a dependency or step input", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The blueprint is behind its version history", body = ErrorBody),
+    )
+)]
+async fn modify_blueprint(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(blueprint_id): ApiPath<u64>,
+    ApiJson(body): ApiJson<ModifyBlueprintBody>,
+) -> ApiResult<Json<BlueprintModified>> {
+    let versions = state
+        .modify_blueprint(&session, blueprint_id, body.modifications)
+        .await?;
+    let blueprint = versions
+        .last()
+        .map(|v| serde_json::to_value(&v.blueprint).unwrap_or_default())
+        .unwrap_or_default();
+    Ok(Json(BlueprintModified {
+        versions: versions
+            .iter()
+            .map(BlueprintVersionInfo::from_version)
+            .collect(),
+        blueprint,
+    }))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/blueprints/{blueprint_id}/versions", tag = "blueprints",
+    security(("bearer" = [])),
+    params(("blueprint_id" = u64, Path, description = "Blueprint ID"), PageParams),
+    responses(
+        (status = 200, description = "Versions, oldest first", body = BlueprintVersionPage),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The blueprint is behind its version history", body = ErrorBody),
+    )
+)]
+async fn list_blueprint_versions(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(blueprint_id): ApiPath<u64>,
+    ApiQuery(page): ApiQuery<PageParams>,
+) -> ApiResult<Json<Page<BlueprintVersionInfo>>> {
+    let history = state.blueprint_history(&session, blueprint_id).await?;
+    let items = history
+        .iter()
+        .map(BlueprintVersionInfo::from_version)
+        .collect();
+    Ok(Json(Page::slice(items, &page)))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/blueprints/{blueprint_id}/versions/{version}", tag = "blueprints",
+    security(("bearer" = [])),
+    params(
+        ("blueprint_id" = u64, Path, description = "Blueprint ID"),
+        ("version" = String, Path, description = "Version, as major.minor.patch"),
+    ),
+    responses(
+        (status = 200, description = "Serialized Blueprint at that version", body = Object),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The blueprint is behind its version history", body = ErrorBody),
+    )
+)]
+async fn get_blueprint_version(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath((blueprint_id, version)): ApiPath<(u64, String)>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let version: SemVer = version.parse()?;
+    let blueprint = state.get_blueprint(&session, blueprint_id, Some(version)).await?;
+    Ok(Json(serde_json::to_value(&blueprint).unwrap_or_default()))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/blueprints/{blueprint_id}/diff", tag = "blueprints",
+    security(("bearer" = [])),
+    params(("blueprint_id" = u64, Path, description = "Blueprint ID"), DiffParams),
+    responses(
+        (status = 200, body = BlueprintDiff),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The blueprint is behind its version history", body = ErrorBody),
+    )
+)]
+async fn diff_blueprint(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(blueprint_id): ApiPath<u64>,
+    ApiQuery(params): ApiQuery<DiffParams>,
+) -> ApiResult<Json<BlueprintDiff>> {
+    let from: SemVer = params.from.parse()?;
+    let to: SemVer = match params.to {
+        Some(to) => to.parse()?,
+        None => state.get_blueprint(&session, blueprint_id, None).await?.version,
+    };
+    let changes = state
+        .diff_blueprint(&session, blueprint_id, from.clone(), to.clone())
+        .await?;
+    Ok(Json(BlueprintDiff {
+        from: from.to_string(),
+        to: to.to_string(),
+        changes,
+    }))
+}
+
+// ============================================================================
+// Containers
+// ============================================================================
+
+#[utoipa::path(
+    post, path = "/api/v1/containers", tag = "containers",
+    security(("bearer" = [])),
+    request_body(content = Object, description = "Serialized Container; container_id 0 allocates a new ID"),
+    responses(
+        (status = 201, body = ContainerWritten),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+    )
+)]
+async fn create_container(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiJson(body): ApiJson<serde_json::Value>,
+) -> ApiResult<(StatusCode, Json<ContainerWritten>)> {
+    let (container_id, version) = state.write_zsei(&session, "create", body).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(ContainerWritten {
+            container_id,
+            version,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/containers/query", tag = "containers",
+    security(("bearer" = [])),
+    request_body(content = Object, description = "Serialized ZSEIQuery"),
+    responses(
+        (status = 200, description = "Serialized ZSEIQueryResult", body = Object),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+    )
+)]
+async fn query_containers(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiJson(query): ApiJson<ZSEIQuery>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let result = state.query_zsei(&session, query).await?;
+    Ok(Json(serde_json::to_value(&result).unwrap_or_default()))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/containers/{container_id}", tag = "containers",
+    security(("bearer" = [])),
+    params(("container_id" = u64, Path, description = "Container ID")),
+    responses(
+        (status = 200, description = "Serialized Container", body = Object),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn get_container(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(container_id): ApiPath<u64>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let container = state.get_container(&session, container_id).await?;
+    Ok(Json(serde_json::to_value(&container).unwrap_or_default()))
+}
+
+#[utoipa::path(
+    put, path = "/api/v1/containers/{container_id}", tag = "containers",
+    security(("bearer" = [])),
+    params(("container_id" = u64, Path, description = "Container ID")),
+    request_body(content = Object, description = "Serialized Container"),
+    responses(
+        (status = 200, body = ContainerWritten),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn update_container(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(container_id): ApiPath<u64>,
+    ApiJson(mut body): ApiJson<serde_json::Value>,
+) -> ApiResult<Json<ContainerWritten>> {
+    // The path is authoritative for which container is written
+    if let Some(global) = body
+        .get_mut("global_state")
+        .and_then(|g| g.as_object_mut())
+    {
+        global.insert("container_id".into(), serde_json::json!(container_id));
+    }
+    let (container_id, version) = state.write_zsei(&session, "update", body).await?;
+    Ok(Json(ContainerWritten {
+        container_id,
+        version,
+    }))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/containers/{container_id}", tag = "containers",
+    security(("bearer" = [])),
+    params(("container_id" = u64, Path, description = "Container ID")),
+    responses(
+        (status = 204),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn delete_container(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(container_id): ApiPath<u64>,
+) -> ApiResult<StatusCode> {
+    state
+        .write_zsei(
+            &session,
+            "delete",
+            serde_json::json!({ "container_id": container_id }),
+        )
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/containers/{container_id}/children", tag = "containers",
+    security(("bearer" = [])),
+    params(("container_id" = u64, Path, description = "Container ID"), PageParams),
+    responses(
+        (status = 200, body = ContainerIdPage),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn container_children(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(container_id): ApiPath<u64>,
+    ApiQuery(page): ApiQuery<PageParams>,
+) -> ApiResult<Json<Page<u64>>> {
+    let container = state.get_container(&session, container_id).await?;
+    Ok(Json(Page::slice(container.global_state.child_ids, &page)))
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/containers/{container_id}/traverse", tag = "containers",
+    security(("bearer" = [])),
+    params(("container_id" = u64, Path, description = "Container to start from")),
+    request_body(content = Object, description = "TraversalRequest fields; omitted fields use defaults"),
+    responses(
+        (status = 200, description = "Serialized TraversalResult", body = Object),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn traverse_container(
+    State(state): State<Arc<AppState>>,
+    AuthSession(session): AuthSession,
+    ApiPath(container_id): ApiPath<u64>,
+    ApiJson(body): ApiJson<serde_json::Value>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let mut request = serde_json::to_value(TraversalRequest::default()).unwrap_or_default();
+    if let (Some(fields), Some(overrides)) = (request.as_object_mut(), body.as_object()) {
+        for (key, value) in overrides {
+            fields.insert(key.clone(), value.clone());
+        }
+    }
+    let mut request: TraversalRequest = serde_json::from_value(request)
+        .map_err(|e| OzoneError::ValidationError(format!("Invalid traversal request: {}", e)))?;
+    request.start_container = container_id;
+
+    let result = state.traverse_zsei(&session, request).await?;
+    Ok(Json(serde_json::to_value(&result).unwrap_or_default()))
+}
+
+// ============================================================================
+// Config
+// ============================================================================
+
+#[utoipa::path(
+    get, path = "/api/v1/config", tag = "config",
+    security(("bearer" = [])),
+    responses((status = 200, body = Object), (status = 401, body = ErrorBody))
+)]
+async fn get_config(
+    State(state): State<Arc<AppState>>,
+    _session: AuthSession,
+) -> ApiResult<Json<serde_json::Value>> {
+    Ok(Json(state.config_section(None).await?))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/config/{section}", tag = "config",
+    security(("bearer" = [])),
+    params(("section" = String, Path, description = "zsei, pipelines, ui or models")),
+    responses(
+        (status = 200, body = Object),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+    )
+)]
+async fn get_config_section(
+    State(state): State<Arc<AppState>>,
+    _session: AuthSession,
+    ApiPath(section): ApiPath<String>,
+) -> ApiResult<Json<serde_json::Value>> {
+    Ok(Json(state.config_section(Some(&section)).await?))
+}
+
+#[utoipa::path(
+    patch, path = "/api/v1/config", tag = "config",
+    security(("bearer" = [])),
+    request_body(content = Object, description = "Partial config updates"),
+    responses(
+        (status = 200, description = "Updated config", body = Object),
+        (status = 401, body = ErrorBody),
+        (status = 500, body = ErrorBody),
+    )
+)]
+async fn update_config(
+    State(state): State<Arc<AppState>>,
+    _session: AuthSession,
+    ApiJson(updates): ApiJson<serde_json::Value>,
+) -> ApiResult<Json<serde_json::Value>> {
+    state.update_config(&updates).await?;
+    Ok(Json(state.config_section(None).await?))
+}
+
+// ============================================================================
+// Network
+// ============================================================================
+
+#[utoipa::path(
+    get, path = "/api/v1/network", tag = "network",
+    security(("bearer" = [])),
+    responses((status = 200, body = NetworkStatusInfo), (status = 401, body = ErrorBody))
+)]
+async fn network_status(
+    State(state): State<Arc<AppState>>,
+    _session: AuthSession,
+) -> Json<NetworkStatusInfo> {
+    let status = state.network_status().await;
+    Json(NetworkStatusInfo {
+        enabled: status.enabled,
+        peer_id: status.peer_id,
+        connected_peers: status.connected_peers,
+        known_peers: status.known_peers,
+        pending_high: status.outbox.pending_high,
+        pending_normal: status.outbox.pending_normal,
+        pending_low: status.outbox.pending_low,
+        failed: status.outbox.failed,
+    })
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/network/outbox", tag = "network",
+    security(("bearer" = [])),
+    params(OutboxParams),
+    responses(
+        (status = 200, body = OutboxPage),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+    )
+)]
+async fn list_outbox(
+    State(state): State<Arc<AppState>>,
+    _session: AuthSession,
+    ApiQuery(params): ApiQuery<OutboxParams>,
+) -> ApiResult<Json<Page<OutboxItem>>> {
+    let status = match params.status.as_deref() {
+        None => None,
+        Some("pending") => Some(DeliveryStatus::Pending),
+        Some("delivered") => Some(DeliveryStatus::Delivered),
+        Some("failed") => Some(DeliveryStatus::Failed),
+        Some(other) => {
+            return Err(
+                OzoneError::ValidationError(format!("Unknown delivery status {}", other)).into(),
+            )
+        }
+    };
+    let mut entries = state.outbox().await;
+    if let Some(status) = status {
+        entries.retain(|entry| entry.has(status));
+    }
+    let items = entries.into_iter().map(OutboxItem::from_entry).collect();
+
+    let page = PageParams {
+        limit: params.limit,
+        offset: params.offset,
+    };
+    Ok(Json(Page::slice(items, &page)))
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Snake-case name of a unit enum variant
+fn label<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, ApiError> {
+    hex::decode(value)
+        .map_err(|e| OzoneError::ValidationError(format!("Invalid {}: {}", field, e)).into())
+}
+
+fn parse_input(input: serde_json::Value) -> Result<PipelineInput, ApiError> {
+    serde_json::from_value(input)
+        .map_err(|e| OzoneError::ValidationError(format!("Invalid input: {}", e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_lists_v1_paths() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let paths = doc["paths"].as_object().unwrap();
+
+        for path in [
+            "/api/v1/tasks",
+            "/api/v1/tasks/{task_id}",
+            "/api/v1/tasks/{task_id}/resume",
+            "/api/v1/orchestrations",
+            "/api/v1/orchestrations/{orchestration_id}/resume",
+            "/api/v1/orchestrations/{orchestration_id}/clarifications",
+            "/api/v1/pipelines",
+            "/api/v1/containers/{container_id}",
+            "/api/v1/blueprints/{blueprint_id}/modifications",
+            "/api/v1/blueprints/{blueprint_id}/versions/{version}",
+            "/api/v1/blueprints/{blueprint_id}/diff",
+            "/api/v1/config",
+            "/api/v1/sessions",
+            "/api/v1/network/outbox",
+        ] {
+            assert!(paths.contains_key(path), "missing {}", path);
+        }
+        assert!(doc["components"]["securitySchemes"]["bearer"].is_object());
+    }
+
+    #[test]
+    fn test_error_status_mapping() {
+        let cases = [
+            (OzoneError::AuthError("x".into()), StatusCode::UNAUTHORIZED, "auth_error"),
+            (OzoneError::NotFound("x".into()), StatusCode::NOT_FOUND, "not_found"),
+            (OzoneError::Conflict("x".into()), StatusCode::CONFLICT, "conflict"),
+            (
+                OzoneError::ValidationError("x".into()),
+                StatusCode::BAD_REQUEST,
+                "validation_error",
+            ),
+            (
+                OzoneError::StorageError("x".into()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "storage_error",
+            ),
+        ];
+
+        for (error, status, code) in cases {
+            assert_eq!(error_code(&error), code);
+            assert_eq!(ApiError(error).into_response().status(), status);
+        }
+    }
+
+    #[test]
+    fn test_page_next_offset() {
+        let params = PageParams {
+            limit: Some(2),
+            offset: Some(1),
+        };
+        let page = Page::slice(vec![1u64, 2, 3, 4], &params);
+        assert_eq!(page.items, vec![2, 3]);
+        assert_eq!(page.total, 4);
+        assert_eq!(page.next_offset, Some(3));
+
+        let last = Page::slice(vec![1u64, 2], &params);
+        assert_eq!(last.next_offset, None);
+    }
+}
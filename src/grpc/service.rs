@@ -0,0 +1,408 @@
+//! Native gRPC service generated from `proto/ozone.proto`
+//!
+//! Thin adapter over the shared operations in `ops`: every RPC authorizes
+//! through the same session check as the HTTP API. An invalid session is
+//! reported as `UNAUTHENTICATED`; operation failures are returned in the
+//! response's `success`/`error` fields, mirroring the HTTP responses.
+
+use super::ops::PipelineEvent;
+use super::AppState;
+use crate::lifecycle::LifecyclePhase;
+use crate::types::pipeline::PipelineInput;
+use crate::types::zsei::ZSEIQuery;
+use crate::types::{OzoneError, OzoneResult};
+use std::sync::Arc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("ozone");
+}
+
+use proto::ozone_service_server::{OzoneService, OzoneServiceServer};
+
+/// Serve the gRPC API until the runtime starts draining or the server fails
+pub async fn serve(state: Arc<AppState>, addr: &str) -> OzoneResult<()> {
+    let addr = addr
+        .parse()
+        .map_err(|e| OzoneError::ServerError(format!("Invalid gRPC address {}: {}", addr, e)))?;
+
+    tracing::info!("Starting gRPC server on {}", addr);
+
+    // Stop accepting calls as soon as the runtime starts draining
+    let lifecycle = state.lifecycle.clone();
+    tonic::transport::Server::builder()
+        .add_service(OzoneServiceServer::new(GrpcService { state }))
+        .serve_with_shutdown(addr, async move {
+            lifecycle.reached(LifecyclePhase::Draining).await
+        })
+        .await
+        .map_err(|e| OzoneError::ServerError(format!("gRPC server error: {}", e)))
+}
+
+/// Map an `OzoneError` to a gRPC status
+pub fn to_grpc_status(error: OzoneError) -> Status {
+    match error {
+        OzoneError::AuthError(m) => Status::unauthenticated(m),
+        OzoneError::NotFound(m) => Status::not_found(m),
+        OzoneError::PermissionDenied(m) => Status::permission_denied(m),
+        OzoneError::Conflict(m) => Status::failed_precondition(m),
+        OzoneError::ValidationError(m) => Status::invalid_argument(m),
+        other => Status::internal(other.to_string()),
+    }
+}
+
+struct GrpcService {
+    state: Arc<AppState>,
+}
+
+impl GrpcService {
+    async fn authorize(&self, session_token: &str) -> Result<crate::types::auth::Session, Status> {
+        self.state
+            .authorize(session_token)
+            .await
+            .map_err(to_grpc_status)
+    }
+}
+
+fn parse_input(input_json: &str) -> Result<PipelineInput, Status> {
+    serde_json::from_str(input_json)
+        .map_err(|e| Status::invalid_argument(format!("Invalid input: {}", e)))
+}
+
+fn task_info(task: crate::task::TaskData) -> proto::TaskInfo {
+    let info = super::TaskInfo::from(task);
+    proto::TaskInfo {
+        task_id: info.task_id,
+        pipeline_id: info.pipeline_id.unwrap_or(0),
+        pipeline_name: info
+            .pipeline_id
+            .and_then(crate::pipeline::get_pipeline_name)
+            .map(str::to_string)
+            .unwrap_or(info.blueprint_name),
+        status: info.status,
+        progress: info.progress,
+        created_at: info.created_at,
+        started_at: info.started_at.unwrap_or(0),
+        completed_at: info.completed_at.unwrap_or(0),
+        error: info.error.unwrap_or_default(),
+    }
+}
+
+fn task_response(result: OzoneResult<crate::task::TaskData>) -> proto::TaskResponse {
+    match result {
+        Ok(task) => proto::TaskResponse {
+            success: true,
+            task: Some(task_info(task)),
+            error: String::new(),
+        },
+        Err(e) => proto::TaskResponse {
+            success: false,
+            task: None,
+            error: e.to_string(),
+        },
+    }
+}
+
+fn to_proto_event(event: PipelineEvent) -> proto::PipelineEvent {
+    proto::PipelineEvent {
+        task_id: event.task_id,
+        event_type: event.event_type,
+        data_json: event.data.to_string(),
+    }
+}
+
+#[tonic::async_trait]
+impl OzoneService for GrpcService {
+    async fn request_challenge(
+        &self,
+        request: Request<proto::ChallengeRequest>,
+    ) -> Result<Response<proto::ChallengeResponse>, Status> {
+        let req = request.into_inner();
+        let challenge = self
+            .state
+            .request_challenge(&req.public_key)
+            .await
+            .map_err(to_grpc_status)?;
+
+        Ok(Response::new(proto::ChallengeResponse {
+            challenge: challenge.challenge,
+            expires_at: challenge.expires_at,
+        }))
+    }
+
+    async fn authenticate(
+        &self,
+        request: Request<proto::AuthRequest>,
+    ) -> Result<Response<proto::AuthResponse>, Status> {
+        let req = request.into_inner();
+        let response = match self.state.authenticate(&req.public_key, &req.signature).await {
+            Ok(session) => proto::AuthResponse {
+                success: true,
+                session_token: hex::encode(&session.session_token),
+                user_id: session.user_id,
+                device_id: session.device_id,
+                expires_at: session.expires_at,
+                error: String::new(),
+            },
+            Err(e) => proto::AuthResponse {
+                success: false,
+                error: e.to_string(),
+                ..Default::default()
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn logout(
+        &self,
+        request: Request<proto::LogoutRequest>,
+    ) -> Result<Response<proto::LogoutResponse>, Status> {
+        let req = request.into_inner();
+        self.state
+            .logout(&req.session_token)
+            .await
+            .map_err(to_grpc_status)?;
+        Ok(Response::new(proto::LogoutResponse { success: true }))
+    }
+
+    async fn execute_pipeline(
+        &self,
+        request: Request<proto::PipelineRequest>,
+    ) -> Result<Response<proto::PipelineResponse>, Status> {
+        let req = request.into_inner();
+        let session = self.authorize(&req.session_token).await?;
+        let input = parse_input(&req.input_json)?;
+
+        let response = match self
+            .state
+            .execute_pipeline(&session, req.pipeline_id, input)
+            .await
+        {
+            Ok(output) => proto::PipelineResponse {
+                success: output.success,
+                task_id: output.task_id.unwrap_or(0),
+                output_json: serde_json::to_string(&output).unwrap_or_default(),
+                error: output.error.unwrap_or_default(),
+            },
+            Err(e) => proto::PipelineResponse {
+                success: false,
+                error: e.to_string(),
+                ..Default::default()
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    type StreamPipelineStream = std::pin::Pin<
+        Box<dyn tokio_stream::Stream<Item = Result<proto::PipelineEvent, Status>> + Send>,
+    >;
+
+    async fn stream_pipeline(
+        &self,
+        request: Request<proto::PipelineRequest>,
+    ) -> Result<Response<Self::StreamPipelineStream>, Status> {
+        let req = request.into_inner();
+        let session = self.authorize(&req.session_token).await?;
+        let input = parse_input(&req.input_json)?;
+
+        let (_, events) = self
+            .state
+            .stream_pipeline(&session, req.pipeline_id, input)
+            .await
+            .map_err(to_grpc_status)?;
+
+        let stream = ReceiverStream::new(events).map(|event| Ok(to_proto_event(event)));
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn create_task(
+        &self,
+        request: Request<proto::CreateTaskRequest>,
+    ) -> Result<Response<proto::TaskResponse>, Status> {
+        let req = request.into_inner();
+        let session = self.authorize(&req.session_token).await?;
+        let input = parse_input(&req.input_json)?;
+
+        let result = match self
+            .state
+            .create_task(&session, req.pipeline_id, input)
+            .await
+        {
+            Ok(task_id) => self.state.get_task(&session, task_id).await,
+            Err(e) => Err(e),
+        };
+        Ok(Response::new(task_response(result)))
+    }
+
+    async fn get_task(
+        &self,
+        request: Request<proto::GetTaskRequest>,
+    ) -> Result<Response<proto::TaskResponse>, Status> {
+        let req = request.into_inner();
+        let session = self.authorize(&req.session_token).await?;
+        Ok(Response::new(task_response(
+            self.state.get_task(&session, req.task_id).await,
+        )))
+    }
+
+    async fn list_tasks(
+        &self,
+        request: Request<proto::ListTasksRequest>,
+    ) -> Result<Response<proto::ListTasksResponse>, Status> {
+        let req = request.into_inner();
+        let session = self.authorize(&req.session_token).await?;
+
+        let status = Some(req.status_filter.as_str()).filter(|s| !s.is_empty());
+        let limit = if req.limit == 0 { 50 } else { req.limit as usize };
+        let (tasks, total) = self
+            .state
+            .list_tasks(&session, status, limit, req.offset as usize)
+            .await;
+
+        Ok(Response::new(proto::ListTasksResponse {
+            total_count: total as u32,
+            tasks: tasks.into_iter().map(task_info).collect(),
+        }))
+    }
+
+    async fn cancel_task(
+        &self,
+        request: Request<proto::CancelTaskRequest>,
+    ) -> Result<Response<proto::TaskResponse>, Status> {
+        let req = request.into_inner();
+        let session = self.authorize(&req.session_token).await?;
+        Ok(Response::new(task_response(
+            self.state.cancel_task(&session, req.task_id).await,
+        )))
+    }
+
+    async fn query_zsei(
+        &self,
+        request: Request<proto::ZseiQueryRequest>,
+    ) -> Result<Response<proto::ZseiQueryResponse>, Status> {
+        let req = request.into_inner();
+        let session = self.authorize(&req.session_token).await?;
+
+        // query_type names the ZSEIQuery variant; query_json holds its fields.
+        // An empty query_type means query_json is already a tagged ZSEIQuery.
+        let body: serde_json::Value = if req.query_json.trim().is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_str(&req.query_json)
+                .map_err(|e| Status::invalid_argument(format!("Invalid query: {}", e)))?
+        };
+        let tagged = match (req.query_type.is_empty(), body) {
+            (true, body) => body,
+            (false, serde_json::Value::Null) => serde_json::Value::String(req.query_type),
+            (false, body) => serde_json::json!({ req.query_type: body }),
+        };
+        let query: ZSEIQuery = serde_json::from_value(tagged)
+            .map_err(|e| Status::invalid_argument(format!("Invalid query: {}", e)))?;
+
+        let response = match self.state.query_zsei(&session, query).await {
+            Ok(result) => proto::ZseiQueryResponse {
+                success: true,
+                result_json: serde_json::to_string(&result).unwrap_or_default(),
+                error: String::new(),
+            },
+            Err(e) => proto::ZseiQueryResponse {
+                success: false,
+                result_json: String::new(),
+                error: e.to_string(),
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn write_zsei(
+        &self,
+        request: Request<proto::ZseiWriteRequest>,
+    ) -> Result<Response<proto::ZseiWriteResponse>, Status> {
+        let req = request.into_inner();
+        let session = self.authorize(&req.session_token).await?;
+
+        let data: serde_json::Value = serde_json::from_str(&req.data_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid data: {}", e)))?;
+
+        let response = match self.state.write_zsei(&session, &req.operation, data).await {
+            Ok((container_id, version)) => proto::ZseiWriteResponse {
+                success: true,
+                container_id,
+                version,
+                error: String::new(),
+            },
+            Err(e) => proto::ZseiWriteResponse {
+                success: false,
+                error: e.to_string(),
+                ..Default::default()
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn get_config(
+        &self,
+        request: Request<proto::GetConfigRequest>,
+    ) -> Result<Response<proto::ConfigResponse>, Status> {
+        let req = request.into_inner();
+        self.authorize(&req.session_token).await?;
+        let result = self.state.config_section(Some(&req.section)).await;
+        Ok(Response::new(config_response(result)))
+    }
+
+    async fn update_config(
+        &self,
+        request: Request<proto::UpdateConfigRequest>,
+    ) -> Result<Response<proto::ConfigResponse>, Status> {
+        let req = request.into_inner();
+        self.authorize(&req.session_token).await?;
+
+        let body: serde_json::Value = serde_json::from_str(&req.config_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid config: {}", e)))?;
+        let updates = if req.section.is_empty() {
+            body
+        } else {
+            serde_json::json!({ req.section.clone(): body })
+        };
+
+        let result = match self.state.update_config(&updates).await {
+            Ok(()) => self.state.config_section(Some(&req.section)).await,
+            Err(e) => Err(e),
+        };
+        Ok(Response::new(config_response(result)))
+    }
+
+    async fn health_check(
+        &self,
+        _request: Request<proto::HealthRequest>,
+    ) -> Result<Response<proto::HealthResponse>, Status> {
+        let health = self.state.health().await;
+        Ok(Response::new(proto::HealthResponse {
+            healthy: health.healthy,
+            version: health.version,
+            uptime_secs: health.uptime_secs,
+            active_tasks: health.active_tasks,
+            connected_peers: health.connected_peers,
+            ready: health.ready,
+            phase: health.phase,
+            zsei_status: health.zsei_status,
+        }))
+    }
+}
+
+fn config_response(result: OzoneResult<serde_json::Value>) -> proto::ConfigResponse {
+    match result {
+        Ok(config) => proto::ConfigResponse {
+            success: true,
+            config_json: config.to_string(),
+            error: String::new(),
+        },
+        Err(e) => proto::ConfigResponse {
+            success: false,
+            config_json: String::new(),
+            error: e.to_string(),
+        },
+    }
+}
@@ -0,0 +1,350 @@
+//! WebSocket event stream
+//!
+//! Clients authenticate with a session token (`/ws?session_token=..` or an
+//! `auth` message), subscribe to topics and/or task IDs, and receive only
+//! the events published on the `EventBus` that belong to them. Passing
+//! `since` on subscribe replays retained events after that sequence number,
+//! so a client can resume after reconnecting.
+//!
+//! Client messages (`action`):
+//! - `auth` `{session_token}`
+//! - `subscribe` `{topics?, task_ids?, since?}`
+//! - `unsubscribe` `{topics?, task_ids?}` (both omitted clears everything)
+//! - `ping`, `cancel_pipeline` `{execution_id}` (executions of the user's tasks)
+//! - `subscribe_tasks` / `subscribe_pipeline_progress` (legacy topic aliases)
+//!
+//! Server messages: `event`, `authenticated`, `subscribed`, `unsubscribed`,
+//! `cancel_requested`, `resync_required`, `session_expired`, `shutting_down`,
+//! `pong`, `error`.
+//! Connections are closed when the runtime starts shutting down.
+
+use super::AppState;
+use crate::events::{Event, EventTopic, Replay, Subscription};
+use crate::lifecycle::LifecyclePhase;
+use crate::types::{TaskID, UserID};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Query, State, WebSocketUpgrade,
+    },
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+/// How often an authenticated connection re-checks its session
+const SESSION_CHECK_SECS: u64 = 30;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct WsParams {
+    pub session_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SubscribeRequest {
+    #[serde(default)]
+    topics: Vec<EventTopic>,
+    #[serde(default)]
+    task_ids: Vec<TaskID>,
+    since: Option<u64>,
+}
+
+/// Per-connection state
+struct Connection {
+    session_token: Option<String>,
+    user_id: Option<UserID>,
+    subscription: Subscription,
+    /// Highest sequence number already considered for delivery
+    cursor: u64,
+}
+
+impl Connection {
+    fn wants(&self, event: &Event) -> bool {
+        match self.user_id {
+            Some(user_id) => event.visible_to(user_id) && self.subscription.matches(event),
+            None => false,
+        }
+    }
+}
+
+pub async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<WsParams>,
+) -> impl IntoResponse {
+    ws.on_upgrade(|socket| handle_websocket(socket, state, params.session_token))
+}
+
+async fn handle_websocket(mut socket: WebSocket, state: Arc<AppState>, token: Option<String>) {
+    // Read the cursor before subscribing so nothing published in between is lost
+    let mut conn = Connection {
+        session_token: None,
+        user_id: None,
+        subscription: Subscription::default(),
+        cursor: state.events.last_seq(),
+    };
+    let mut rx = state.events.subscribe();
+
+    if let Some(token) = token {
+        let reply = authenticate(&mut conn, &state, token).await;
+        if send(&mut socket, &reply).await.is_err() {
+            return;
+        }
+    }
+
+    let mut session_check =
+        tokio::time::interval(tokio::time::Duration::from_secs(SESSION_CHECK_SECS));
+
+    loop {
+        tokio::select! {
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        let replies = match serde_json::from_str::<serde_json::Value>(&text) {
+                            Ok(request) => handle_ws_message(request, &mut conn, &state).await,
+                            Err(e) => vec![error_reply(&format!("Invalid message: {}", e))],
+                        };
+                        for reply in replies {
+                            if send(&mut socket, &reply).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    None | Some(Ok(Message::Close(_))) | Some(Err(_)) => break,
+                    _ => {}
+                }
+            }
+            event = rx.recv() => {
+                let events = match event {
+                    Ok(event) => vec![event],
+                    // Fell behind the broadcast buffer - catch up from history
+                    Err(RecvError::Lagged(_)) => match state.events.replay_since(conn.cursor) {
+                        Replay::Events(events) => events,
+                        Replay::Gap(oldest_seq) => {
+                            conn.cursor = state.events.last_seq();
+                            let reply = resync_reply(oldest_seq, conn.cursor);
+                            if send(&mut socket, &reply).await.is_err() {
+                                return;
+                            }
+                            continue;
+                        }
+                    },
+                    Err(RecvError::Closed) => break,
+                };
+                for event in events {
+                    if event.seq <= conn.cursor {
+                        continue;
+                    }
+                    conn.cursor = event.seq;
+                    if conn.wants(&event) && send(&mut socket, &event_message(&event)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            _ = session_check.tick() => {
+                let Some(token) = conn.session_token.clone() else { continue };
+                if state.check_session(&token).await.is_err() {
+                    conn.session_token = None;
+                    conn.user_id = None;
+                    let reply = serde_json::json!({"action": "session_expired"});
+                    if send(&mut socket, &reply).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            _ = state.lifecycle.reached(LifecyclePhase::Draining) => {
+                let reply = serde_json::json!({
+                    "action": "shutting_down",
+                    "last_seq": conn.cursor,
+                });
+                let _ = send(&mut socket, &reply).await;
+                let _ = socket.send(Message::Close(None)).await;
+                return;
+            }
+        }
+    }
+}
+
+async fn handle_ws_message(
+    request: serde_json::Value,
+    conn: &mut Connection,
+    state: &Arc<AppState>,
+) -> Vec<serde_json::Value> {
+    let action = request.get("action").and_then(|v| v.as_str()).unwrap_or("");
+
+    match action {
+        "ping" => vec![serde_json::json!({"action": "pong"})],
+        "auth" => {
+            let token = request
+                .get("session_token")
+                .and_then(|t| t.as_str())
+                .unwrap_or("")
+                .to_string();
+            vec![authenticate(conn, state, token).await]
+        }
+        _ if conn.user_id.is_none() => vec![error_reply("Not authenticated")],
+        "subscribe" => match serde_json::from_value::<SubscribeRequest>(request) {
+            Ok(sub) => subscribe(conn, state, sub),
+            Err(e) => vec![error_reply(&format!("Invalid subscribe request: {}", e))],
+        },
+        "subscribe_tasks" => subscribe(
+            conn,
+            state,
+            SubscribeRequest {
+                topics: vec![EventTopic::Task],
+                ..Default::default()
+            },
+        ),
+        "subscribe_pipeline_progress" => subscribe(
+            conn,
+            state,
+            SubscribeRequest {
+                topics: vec![EventTopic::Pipeline],
+                ..Default::default()
+            },
+        ),
+        "unsubscribe" => {
+            let sub = serde_json::from_value::<SubscribeRequest>(request).unwrap_or_default();
+            if sub.topics.is_empty() && sub.task_ids.is_empty() {
+                conn.subscription = Subscription::default();
+            } else {
+                for topic in &sub.topics {
+                    conn.subscription.topics.remove(topic);
+                }
+                for task_id in &sub.task_ids {
+                    conn.subscription.task_ids.remove(task_id);
+                }
+            }
+            vec![subscription_reply("unsubscribed", conn)]
+        }
+        "cancel_pipeline" => {
+            let execution_id = request
+                .get("execution_id")
+                .and_then(|e| e.as_str())
+                .unwrap_or("")
+                .to_string();
+            let token = conn.session_token.clone().unwrap_or_default();
+            let result = match state.authorize(&token).await {
+                Ok(session) => state.cancel_execution(&session, &execution_id).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(was_running) => vec![serde_json::json!({
+                    "action": "cancel_requested",
+                    "execution_id": execution_id,
+                    "was_running": was_running,
+                })],
+                Err(e) => vec![error_reply(&e.to_string())],
+            }
+        }
+        _ => vec![error_reply("Unknown action")],
+    }
+}
+
+/// Attach a session to the connection
+async fn authenticate(
+    conn: &mut Connection,
+    state: &Arc<AppState>,
+    token: String,
+) -> serde_json::Value {
+    match state.authorize(&token).await {
+        Ok(session) => {
+            conn.session_token = Some(token);
+            conn.user_id = Some(session.user_id);
+            serde_json::json!({
+                "action": "authenticated",
+                "user_id": session.user_id,
+                "last_seq": state.events.last_seq(),
+            })
+        }
+        Err(e) => error_reply(&e.to_string()),
+    }
+}
+
+/// Extend the subscription, replaying retained events for the new filters
+/// when `since` is given
+fn subscribe(
+    conn: &mut Connection,
+    state: &Arc<AppState>,
+    request: SubscribeRequest,
+) -> Vec<serde_json::Value> {
+    let previous = conn.subscription.clone();
+    let added = Subscription {
+        topics: request.topics.into_iter().collect::<HashSet<_>>(),
+        task_ids: request.task_ids.into_iter().collect::<HashSet<_>>(),
+    };
+    conn.subscription.topics.extend(added.topics.iter().copied());
+    conn.subscription.task_ids.extend(added.task_ids.iter().copied());
+
+    let mut replies = vec![subscription_reply("subscribed", conn)];
+
+    if let Some(since) = request.since {
+        match state.events.replay_since(since) {
+            Replay::Events(events) => {
+                // Later events arrive through the live receiver
+                replies.extend(
+                    events
+                        .iter()
+                        .filter(|e| e.seq <= conn.cursor)
+                        .filter(|e| added.matches(e) && !previous.matches(e))
+                        .filter(|e| conn.wants(e))
+                        .map(event_message),
+                );
+            }
+            Replay::Gap(oldest_seq) => {
+                replies.push(resync_reply(oldest_seq, state.events.last_seq()));
+            }
+        }
+    }
+
+    replies
+}
+
+fn event_message(event: &Event) -> serde_json::Value {
+    serde_json::json!({
+        "action": "event",
+        "seq": event.seq,
+        "timestamp": event.timestamp,
+        "topic": event.topic,
+        "kind": event.kind,
+        "task_id": event.task_id,
+        "data": event.data,
+    })
+}
+
+fn subscription_reply(action: &str, conn: &Connection) -> serde_json::Value {
+    let mut task_ids: Vec<_> = conn.subscription.task_ids.iter().copied().collect();
+    task_ids.sort_unstable();
+    let topics: Vec<_> = EventTopic::ALL
+        .iter()
+        .filter(|t| conn.subscription.topics.contains(t))
+        .collect();
+    serde_json::json!({
+        "action": action,
+        "topics": topics,
+        "task_ids": task_ids,
+        "last_seq": conn.cursor,
+    })
+}
+
+fn resync_reply(oldest_seq: u64, last_seq: u64) -> serde_json::Value {
+    serde_json::json!({
+        "action": "resync_required",
+        "oldest_seq": oldest_seq,
+        "last_seq": last_seq,
+    })
+}
+
+fn error_reply(message: &str) -> serde_json::Value {
+    serde_json::json!({"action": "error", "error": message})
+}
+
+async fn send(socket: &mut WebSocket, message: &serde_json::Value) -> Result<(), axum::Error> {
+    socket
+        .send(Message::Text(
+            serde_json::to_string(message).unwrap_or_default(),
+        ))
+        .await
+}
@@ -30,8 +30,11 @@ pub mod blueprints;
 pub mod bootstrap;
 pub mod config;
 pub mod consciousness;
+pub mod events;
 pub mod grpc;
 pub mod integrity;
+pub mod lifecycle;
+pub mod llm;
 pub mod methodologies;
 pub mod network;
 pub mod orchestrator;
@@ -45,7 +48,9 @@ pub use config::OzoneConfig;
 pub use types::*;
 
 use bootstrap::BootstrapManager;
+use lifecycle::{Lifecycle, LifecyclePhase, ShutdownSummary};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 use task::{RefinementConfig, TaskQueueConfig};
@@ -53,6 +58,8 @@ use task::{RefinementConfig, TaskQueueConfig};
 /// Result of the full AMT orchestration flow
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct OrchestrationOutput {
+    /// Resumes or answers this orchestration later
+    pub orchestration_id: u64,
     pub success: bool,
     pub response_text: Option<String>,
     pub task_id: Option<u64>,
@@ -89,6 +96,18 @@ pub struct OzoneRuntime {
 
     /// Consciousness system (enabled/disabled via config.toml)
     pub consciousness: Option<Arc<RwLock<consciousness::ConsciousnessSystem>>>,
+
+    /// Event bus for websocket subscribers
+    pub events: Arc<events::EventBus>,
+
+    /// Readiness and shutdown phase
+    pub lifecycle: Arc<Lifecycle>,
+
+    /// Version histories of blueprints edited through the API
+    pub blueprint_versions: Arc<blueprints::BlueprintVersionStore>,
+
+    /// Prompt orchestrator, checkpointing under the data directory
+    pub orchestrator: Arc<orchestrator::PromptOrchestrator>,
 }
 
 impl OzoneRuntime {
@@ -112,8 +131,12 @@ impl OzoneRuntime {
             }
         }
 
+        // Event bus shared by every component that publishes events
+        let events = Arc::new(events::EventBus::default());
+
         // Initialize ZSEI
-        let zsei = zsei::ZSEI::new(&config.zsei)?;
+        let mut zsei = zsei::ZSEI::new(&config.zsei)?;
+        zsei.set_event_bus(events.clone());
 
         let zsei_arc = Arc::new(RwLock::new(zsei));
 
@@ -145,15 +168,18 @@ impl OzoneRuntime {
         {
             if let Ok(mut store) = crate::consciousness::CONSCIOUSNESS_STORE.lock() {
                 store.set_zsei(zsei_arc.clone());
+                store.set_event_bus(events.clone());
             }
         }
 
         // Initialize pipeline registry
-        let pipeline_registry = pipeline::PipelineRegistry::new(&config.pipelines)?;
+        let mut pipeline_registry = pipeline::PipelineRegistry::new(&config.pipelines)?;
+        pipeline_registry.set_event_bus(events.clone());
 
         // Initialize task manager
-        let task_manager =
+        let mut task_manager =
             task::TaskManager::new(TaskQueueConfig::default(), RefinementConfig::default())?;
+        task_manager.set_event_bus(events.clone());
 
         // Initialize auth system
         let auth = auth::AuthSystem::new(&config.auth)?;
@@ -162,8 +188,19 @@ impl OzoneRuntime {
         let integrity = integrity::IntegrityMonitor::new(&config.integrity)?;
 
         // Initialize network manager
+        let pipeline_registry = Arc::new(RwLock::new(pipeline_registry));
         let mut network = network::NetworkManager::new(config.network.clone()).await?;
+        network.set_zsei(zsei_arc.clone());
+        network.set_pipelines(pipeline_registry.clone());
         network.initialize().await?;
+        let network = Arc::new(RwLock::new(network));
+        {
+            // Custom pipelines received from peers are installed from the
+            // network, and runs may go to the account's other devices
+            let mut registry = pipeline_registry.write().await;
+            registry.set_code_source(network.clone());
+            registry.set_offloader(network.clone());
+        }
 
         // Initialize consciousness if enabled in config
         let consciousness = if config.consciousness.enabled {
@@ -176,38 +213,234 @@ impl OzoneRuntime {
             None
         };
 
+        let blueprint_versions = Arc::new(blueprints::BlueprintVersionStore::new(
+            &config.zsei.blueprint_versions_path,
+        ));
+
+        // The orchestrator shares the task manager's tasks, calls and
+        // budgets for the configured models per stage and checkpoints every
+        // stage so failed orchestrations can be resumed
+        let orchestrator = orchestrator::PromptOrchestrator::new(
+            pipeline_registry.clone(),
+            zsei_arc.clone(),
+            Arc::new(task_manager.clone()),
+        )
+        .with_event_bus(events.clone())
+        .with_llm(llm::LlmRouter::from_config(&config.models)?)
+        .with_models(llm::ModelCatalog::from_config(&config.models))
+        .with_checkpoints(orchestrator::checkpoint::CheckpointStore::new(
+            std::path::Path::new(&config.general.data_dir).join("checkpoints"),
+        ));
+
         Ok(Self {
             config,
             zsei: zsei_arc.clone(),
-            pipeline_registry: Arc::new(RwLock::new(pipeline_registry)),
+            pipeline_registry,
             task_manager: Arc::new(RwLock::new(task_manager)),
             auth: Arc::new(RwLock::new(auth)),
             integrity: Arc::new(RwLock::new(integrity)),
-            network: Arc::new(RwLock::new(network)),
+            network,
             session: Arc::new(RwLock::new(None)),
             consciousness,
+            events,
+            lifecycle: Arc::new(Lifecycle::new()),
+            blueprint_versions,
+            orchestrator: Arc::new(orchestrator),
         })
     }
 
     /// Start the runtime (gRPC server for UI communication)
-    pub async fn start(self) -> Result<(), OzoneError> {
+    ///
+    /// Runs until Ctrl+C/SIGTERM, then shuts down gracefully and returns
+    /// what happened during shutdown.
+    pub async fn start(self) -> Result<ShutdownSummary, OzoneError> {
         tracing::info!("Starting Ozone Studio runtime");
 
+        let grace = Duration::from_secs(self.config.general.shutdown_grace_secs);
+
         // Wrap self in Arc<RwLock<...>> for sharing with server handlers
         let runtime = Arc::new(RwLock::new(self));
 
-        // Start integrity monitoring
+        // Start integrity monitoring (a read lock, so `stop_monitoring` can get in)
         let integrity = runtime.read().await.integrity.clone();
-        tokio::spawn(async move {
-            if let Err(e) = integrity.write().await.start_monitoring().await {
+        let integrity_handle = tokio::spawn(async move {
+            if let Err(e) = integrity.read().await.start_monitoring().await {
                 tracing::error!("Integrity monitoring failed: {}", e);
             }
         });
 
+        // Periodic batch sync, anti-entropy with connected peers, closing of
+        // expired proposals, reputation updates and device adverts
+        let network = runtime.read().await.network.clone();
+        let auth = runtime.read().await.auth.clone();
+        let pipeline_registry = runtime.read().await.pipeline_registry.clone();
+        let task_manager = runtime.read().await.task_manager.clone();
+        let sync_interval = runtime.read().await.config.network.batch_sync_interval_secs;
+        let sync_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(sync_interval.max(1)));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let network = network.read().await;
+                if let Err(e) = network.process_batch_sync().await {
+                    tracing::warn!("Batch sync failed: {}", e);
+                }
+                if let Err(e) = network.anti_entropy().await {
+                    tracing::warn!("Anti-entropy failed: {}", e);
+                }
+                if let Err(e) = network.close_proposals().await {
+                    tracing::warn!("Closing proposals failed: {}", e);
+                }
+                for settlement in network.update_reputation().await {
+                    let accepted = match settlement.status {
+                        ConsensusStatus::Accepted => true,
+                        ConsensusStatus::Rejected => false,
+                        _ => continue,
+                    };
+                    if let Some(user_id) = settlement.proposed_by {
+                        if let Err(e) = auth.read().await.record_contribution(user_id, accepted).await {
+                            tracing::warn!("Failed to credit user {}: {}", user_id, e);
+                        }
+                    }
+                }
+                let running = pipeline_registry.read().await.executor().running_count();
+                let (queued, _, _) = task_manager.read().await.get_queue_status().await;
+                network
+                    .advertise_device(running as u32, queued as u32)
+                    .await;
+            }
+        });
+
         // Start gRPC server
-        grpc::start_server(runtime).await?;
+        let mut server = tokio::spawn(grpc::start_server(runtime.clone()));
+
+        let server_result = tokio::select! {
+            _ = lifecycle::shutdown_signal() => None,
+            result = &mut server => Some(result),
+        };
+
+        sync_handle.abort();
+        let summary = runtime.read().await.shutdown(grace).await;
+        integrity_handle.abort();
+
+        match server_result {
+            // The server failed on its own (e.g. could not bind)
+            Some(Ok(result)) => result?,
+            Some(Err(e)) => return Err(OzoneError::ServerError(format!("Server task: {}", e))),
+            // Remaining connections get a moment to close before we exit
+            None => {
+                if tokio::time::timeout(Duration::from_secs(5), &mut server)
+                    .await
+                    .is_err()
+                {
+                    tracing::warn!("Closing remaining connections");
+                    server.abort();
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Shut down gracefully: stop accepting requests, give running tasks
+    /// `grace` to finish (failing the rest so they can be retried), stop
+    /// background loops and sync all state to disk
+    pub async fn shutdown(&self, grace: Duration) -> ShutdownSummary {
+        let started = Instant::now();
+        let mut summary = ShutdownSummary::default();
+
+        if !self.lifecycle.advance(LifecyclePhase::Draining) {
+            tracing::warn!("Shutdown already in progress");
+            return summary;
+        }
+        tracing::info!("Shutting down (grace period {}s)", grace.as_secs());
+
+        // Stop background loops that could start new work
+        {
+            let task_manager = self.task_manager.read().await;
+            task_manager.stop_queue_processor().await;
+            task_manager.stop_refinement_daemon().await;
+        }
+        self.integrity.read().await.stop_monitoring().await;
+
+        // Drain running tasks and pipeline executions
+        let executor_running = || async {
+            self.pipeline_registry
+                .read()
+                .await
+                .executor()
+                .running_count()
+        };
+        let unfinished = self.task_manager.read().await.unfinished_tasks().await;
+        summary.active_at_start = unfinished.len() + executor_running().await;
+        if summary.active_at_start > 0 {
+            tracing::info!(
+                "Waiting for {} running task(s)/execution(s)",
+                summary.active_at_start
+            );
+        }
+
+        let deadline = Instant::now() + grace;
+        loop {
+            let tasks = self.task_manager.read().await.unfinished_tasks().await.len();
+            let executions = executor_running().await;
+            if tasks + executions == 0 {
+                summary.drained = true;
+                break;
+            }
+            if Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+
+        if !summary.drained {
+            summary.cancelled_executions = self
+                .pipeline_registry
+                .read()
+                .await
+                .executor()
+                .cancel_all()
+                .await;
+            summary.interrupted_tasks = self
+                .task_manager
+                .read()
+                .await
+                .interrupt_unfinished("Interrupted by shutdown")
+                .await;
+            tracing::warn!(
+                "Grace period expired: {} task(s) interrupted, {} execution(s) cancelled",
+                summary.interrupted_tasks.len(),
+                summary.cancelled_executions
+            );
+        }
 
-        Ok(())
+        // Sync state
+        let mut sync = |name: &str, result: OzoneResult<()>| match result {
+            Ok(()) => summary.synced.push(name.to_string()),
+            Err(e) => summary.errors.push(format!("{}: {}", name, e)),
+        };
+        sync("tasks", self.task_manager.read().await.persist().await);
+        sync("zsei", self.zsei.read().await.sync().await);
+        sync(
+            "consciousness",
+            crate::consciousness::CONSCIOUSNESS_STORE
+                .lock()
+                .map(|store| store.save_to_disk())
+                .map_err(|_| OzoneError::ConsciousnessError("store lock poisoned".into())),
+        );
+        let network = tokio::time::timeout(
+            Duration::from_secs(5),
+            async { self.network.read().await.process_batch_sync().await },
+        )
+        .await
+        .unwrap_or_else(|_| Err(OzoneError::NetworkError("batch sync timed out".into())));
+        sync("network", network);
+        self.network.read().await.stop().await;
+
+        summary.elapsed_ms = started.elapsed().as_millis() as u64;
+        self.lifecycle.advance(LifecyclePhase::Stopped);
+        summary
     }
 
     /// Authenticate a user
@@ -296,17 +529,13 @@ impl OzoneRuntime {
             }
         });
 
-        let token_budget = input
-            .data
-            .get("token_budget")
-            .and_then(|v| {
-                if let crate::types::Value::Int(i) = v {
-                    Some(*i as u32)
-                } else {
-                    None
-                }
-            })
-            .unwrap_or(100_000);
+        let token_budget = input.data.get("token_budget").and_then(|v| {
+            if let crate::types::Value::Int(i) = v {
+                Some(*i as u32)
+            } else {
+                None
+            }
+        });
 
         let consciousness_enabled = input
             .data
@@ -320,75 +549,59 @@ impl OzoneRuntime {
             })
             .unwrap_or(false);
 
-        // Enqueue via task manager — task manager owns queue ordering + consciousness gate
-        let task_manager = self.task_manager.read().await;
-        let mut inputs = std::collections::HashMap::new();
-        inputs.insert(
-            "prompt".to_string(),
-            serde_json::Value::String(prompt.clone()),
-        );
-        if let Some(files_val) = input.data.get("attached_files") {
-            inputs.insert(
-                "attached_files".to_string(),
-                serde_json::to_value(files_val).unwrap_or_default(),
-            );
-        }
-        if let Some(pid) = project_id {
-            inputs.insert("project_id".to_string(), serde_json::json!(pid));
-        }
-        if let Some(wid) = workspace_id {
-            inputs.insert("workspace_id".to_string(), serde_json::json!(wid));
-        }
-
-        let task_id = task_manager
-            .enqueue_task(
-                None, // blueprint selected by orchestrator, not here
-                inputs,
-                user_id,
-                device_id,
-                workspace_id,
-                project_id,
-                crate::task::TaskPriority::Normal,
-            )
-            .await?;
-
-        drop(task_manager);
-
-        // Run the orchestrator — builds the AMT, selects a blueprint, executes steps
-        let mut orchestrator = crate::orchestrator::PromptOrchestrator::new(
-            self.pipeline_registry.clone(),
-            self.zsei.clone(),
-            self.task_manager.clone(),
-        );
+        let attached_files = match input.data.get("attached_files") {
+            Some(files) => serde_json::to_value(files)
+                .and_then(serde_json::from_value)
+                .map_err(|e| OzoneError::ValidationError(format!("Invalid attached_files: {}", e)))?,
+            None => Vec::new(),
+        };
 
-        let orch_result = orchestrator
-            .process(
-                task_id,
-                &prompt,
+        // The orchestrator builds the AMT, selects a blueprint, queues the
+        // task through the task manager and executes its steps
+        let response = self
+            .orchestrator
+            .orchestrate(crate::orchestrator::OrchestrationRequest {
+                prompt,
                 project_id,
                 workspace_id,
-                token_budget,
+                user_id,
+                device_id,
                 consciousness_enabled,
-            )
+                token_budget,
+                model_config: None,
+                attached_files,
+            })
             .await;
+        OrchestrationOutput::from_response(response)
+    }
+}
 
-        match orch_result {
-            Ok(state) => Ok(OrchestrationOutput {
-                success: true,
-                response_text: state.final_response,
-                task_id: Some(task_id),
-                blueprint_id: state.selected_blueprint_id,
-                stages_completed: state.completed_stages,
-                needs_clarification: state.needs_clarification,
-                clarification_points: state.clarification_points.unwrap_or_default(),
-            }),
-            Err(e) => {
-                // Mark task as failed in task manager
-                let task_manager = self.task_manager.read().await;
-                let _ = task_manager.fail_task(task_id, e.to_string()).await;
-                Err(e)
-            }
+impl OrchestrationOutput {
+    /// The output of a finished orchestration; a failed one is an error
+    pub fn from_response(
+        response: crate::orchestrator::OrchestrationResponse,
+    ) -> OzoneResult<Self> {
+        if !response.success {
+            return Err(OzoneError::TaskError(format!(
+                "Orchestration {} failed: {}",
+                response.orchestration_id,
+                response.error.unwrap_or_default()
+            )));
         }
+        Ok(Self {
+            orchestration_id: response.orchestration_id,
+            success: true,
+            response_text: response.response,
+            task_id: response.task_id,
+            blueprint_id: response.blueprint_id,
+            stages_completed: response
+                .stages_completed
+                .into_iter()
+                .map(|s| serde_json::to_value(s).unwrap_or_default())
+                .collect(),
+            needs_clarification: response.needs_clarification,
+            clarification_points: response.clarification_points,
+        })
     }
 }
 
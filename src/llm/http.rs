@@ -0,0 +1,463 @@
+//! HTTP model backends
+//!
+//! One provider type speaks three wire formats: the Anthropic Messages API,
+//! OpenAI-compatible chat completions (OpenAI, vLLM, LM Studio, ...) and the
+//! native `/completion` endpoint of a local llama.cpp server. Streaming reads
+//! server-sent events. A 429 or 5xx response, or a failed connection, is
+//! retried after the `Retry-After` delay or an exponential backoff.
+
+use super::{LlmProvider, LlmRequest, LlmResponse, RetryPolicy};
+use crate::types::{OzoneError, OzoneResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Time allowed to open a connection to the model endpoint
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time allowed for a whole request, streamed body included
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Wire format of a model endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiFormat {
+    Anthropic,
+    OpenAi,
+    /// llama.cpp server `/completion`
+    Local,
+}
+
+impl ApiFormat {
+    pub fn default_endpoint(self) -> &'static str {
+        match self {
+            ApiFormat::Anthropic => "https://api.anthropic.com/v1/messages",
+            ApiFormat::OpenAi => "https://api.openai.com/v1/chat/completions",
+            ApiFormat::Local => "http://127.0.0.1:8080/completion",
+        }
+    }
+}
+
+pub struct HttpProvider {
+    format: ApiFormat,
+    endpoint: String,
+    api_key: Option<String>,
+    retry: RetryPolicy,
+    client: reqwest::Client,
+}
+
+impl HttpProvider {
+    pub fn new(format: ApiFormat, endpoint: impl Into<String>) -> Self {
+        Self {
+            format,
+            endpoint: endpoint.into(),
+            api_key: None,
+            retry: RetryPolicy::default(),
+            client: client(CONNECT_TIMEOUT, REQUEST_TIMEOUT),
+        }
+    }
+
+    /// Give up on connecting after `connect` and on a request after
+    /// `request`; a timed out request is retried like a failed connection
+    pub fn with_timeouts(mut self, connect: Duration, request: Duration) -> Self {
+        self.client = client(connect, request);
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn body(&self, request: &LlmRequest, stream: bool) -> Value {
+        match self.format {
+            ApiFormat::Anthropic => {
+                let mut body = json!({
+                    "model": request.model,
+                    "max_tokens": request.max_tokens,
+                    "temperature": request.temperature,
+                    "messages": [{"role": "user", "content": request.prompt}],
+                    "stream": stream,
+                });
+                if let Some(system) = &request.system {
+                    body["system"] = json!(system);
+                }
+                body
+            }
+            ApiFormat::OpenAi => {
+                let mut messages = Vec::new();
+                if let Some(system) = &request.system {
+                    messages.push(json!({"role": "system", "content": system}));
+                }
+                messages.push(json!({"role": "user", "content": request.prompt}));
+                let mut body = json!({
+                    "model": request.model,
+                    "max_tokens": request.max_tokens,
+                    "temperature": request.temperature,
+                    "messages": messages,
+                    "stream": stream,
+                });
+                if stream {
+                    body["stream_options"] = json!({"include_usage": true});
+                }
+                body
+            }
+            ApiFormat::Local => {
+                let prompt = match &request.system {
+                    Some(system) => format!("{}\n\n{}", system, request.prompt),
+                    None => request.prompt.clone(),
+                };
+                json!({
+                    "prompt": prompt,
+                    "n_predict": request.max_tokens,
+                    "temperature": request.temperature,
+                    "stream": stream,
+                })
+            }
+        }
+    }
+
+    /// POST the request, retrying rate limits and transient failures
+    async fn send(&self, body: &Value) -> OzoneResult<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let mut builder = self.client.post(&self.endpoint).json(body);
+            builder = match (self.format, &self.api_key) {
+                (ApiFormat::Anthropic, Some(key)) => builder.header("x-api-key", key),
+                (_, Some(key)) => builder.bearer_auth(key),
+                (_, None) => builder,
+            };
+            if self.format == ApiFormat::Anthropic {
+                builder = builder.header("anthropic-version", ANTHROPIC_VERSION);
+            }
+
+            let (error, retry_after) = match builder.send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    // Whole seconds only; anything else falls back to the
+                    // backoff, and the wait is capped below either way
+                    let retry_after = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    let text = response.text().await.unwrap_or_default();
+                    let error = OzoneError::ModelError(format!(
+                        "{} returned {}: {}",
+                        self.endpoint,
+                        status,
+                        error_message(&text)
+                    ));
+                    if status.as_u16() != 429 && !status.is_server_error() {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => (
+                    OzoneError::ModelError(format!("{}: {}", self.endpoint, e)),
+                    None,
+                ),
+                Err(e) => return Err(OzoneError::ModelError(format!("{}: {}", self.endpoint, e))),
+            };
+
+            attempt += 1;
+            if attempt > self.retry.max_retries {
+                return Err(error);
+            }
+            let wait = retry_after
+                .unwrap_or_else(|| self.retry.backoff(attempt))
+                .min(self.retry.max_backoff);
+            tracing::warn!(
+                "Model request failed ({}), retry {}/{} in {:?}",
+                error,
+                attempt,
+                self.retry.max_retries,
+                wait
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn parse(&self, value: &Value, request: &LlmRequest) -> LlmResponse {
+        let text = match self.format {
+            ApiFormat::Anthropic => value["content"]
+                .as_array()
+                .map(|blocks| {
+                    blocks
+                        .iter()
+                        .filter_map(|b| b["text"].as_str())
+                        .collect::<String>()
+                })
+                .unwrap_or_default(),
+            ApiFormat::OpenAi => value["choices"][0]["message"]["content"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            ApiFormat::Local => value["content"].as_str().unwrap_or_default().to_string(),
+        };
+        let mut response = LlmResponse {
+            text,
+            model: value["model"]
+                .as_str()
+                .unwrap_or(&request.model)
+                .to_string(),
+            ..Default::default()
+        };
+        self.read_usage(value, &mut response);
+        response
+    }
+
+    /// Token counts, wherever this format reports them
+    fn read_usage(&self, value: &Value, response: &mut LlmResponse) {
+        let count = |v: &Value| v.as_u64().map(|n| n as u32);
+        let (input, output) = match self.format {
+            ApiFormat::Anthropic => (
+                &value["usage"]["input_tokens"],
+                &value["usage"]["output_tokens"],
+            ),
+            ApiFormat::OpenAi => (
+                &value["usage"]["prompt_tokens"],
+                &value["usage"]["completion_tokens"],
+            ),
+            ApiFormat::Local => (&value["tokens_evaluated"], &value["tokens_predicted"]),
+        };
+        if let Some(n) = count(input) {
+            response.input_tokens = n;
+        }
+        if let Some(n) = count(output) {
+            response.output_tokens = n;
+        }
+    }
+
+    /// Apply one streamed event; returns the text it adds
+    fn apply_event(&self, event: &Value, response: &mut LlmResponse) -> OzoneResult<String> {
+        let delta = match self.format {
+            ApiFormat::Anthropic => match event["type"].as_str() {
+                Some("message_start") => {
+                    self.read_usage(&event["message"], response);
+                    if let Some(model) = event["message"]["model"].as_str() {
+                        response.model = model.to_string();
+                    }
+                    None
+                }
+                Some("content_block_delta") => event["delta"]["text"].as_str(),
+                Some("message_delta") => {
+                    if let Some(n) = event["usage"]["output_tokens"].as_u64() {
+                        response.output_tokens = n as u32;
+                    }
+                    None
+                }
+                Some("error") => {
+                    return Err(OzoneError::ModelError(format!(
+                        "{} stream: {}",
+                        self.endpoint,
+                        event["error"]["message"].as_str().unwrap_or("error")
+                    )))
+                }
+                _ => None,
+            },
+            ApiFormat::OpenAi => {
+                self.read_usage(event, response);
+                if let Some(model) = event["model"].as_str() {
+                    response.model = model.to_string();
+                }
+                event["choices"][0]["delta"]["content"].as_str()
+            }
+            ApiFormat::Local => {
+                self.read_usage(event, response);
+                event["content"].as_str()
+            }
+        };
+        let delta = delta.unwrap_or_default().to_string();
+        response.text.push_str(&delta);
+        Ok(delta)
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for HttpProvider {
+    async fn complete(&self, request: &LlmRequest) -> OzoneResult<LlmResponse> {
+        let response = self.send(&self.body(request, false)).await?;
+        let value: Value = response
+            .json()
+            .await
+            .map_err(|e| OzoneError::ModelError(format!("Invalid model response: {}", e)))?;
+        Ok(self.parse(&value, request))
+    }
+
+    async fn stream(
+        &self,
+        request: &LlmRequest,
+        tokens: mpsc::UnboundedSender<String>,
+    ) -> OzoneResult<LlmResponse> {
+        let mut http = self.send(&self.body(request, true)).await?;
+        let mut response = LlmResponse {
+            model: request.model.clone(),
+            ..Default::default()
+        };
+
+        // Server-sent events: `data: <json>` lines, other lines ignored
+        let mut buffer = Vec::new();
+        while let Some(chunk) = http
+            .chunk()
+            .await
+            .map_err(|e| OzoneError::ModelError(format!("{}: {}", self.endpoint, e)))?
+        {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    continue;
+                }
+                let Ok(event) = serde_json::from_str::<Value>(data) else {
+                    continue;
+                };
+                let delta = self.apply_event(&event, &mut response)?;
+                if !delta.is_empty() {
+                    let _ = tokens.send(delta);
+                }
+            }
+        }
+        Ok(response)
+    }
+}
+
+fn client(connect: Duration, request: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(connect)
+        .timeout(request)
+        .build()
+        .unwrap_or_default()
+}
+
+/// The message of an error body in any of the formats, or the body itself
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| {
+            v["error"]["message"]
+                .as_str()
+                .or_else(|| v["error"].as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock_server::MockServer;
+    use crate::llm::MockProvider;
+    use std::sync::Arc;
+
+    fn request() -> LlmRequest {
+        LlmRequest {
+            model: "test-model".to_string(),
+            system: Some("Be brief.".to_string()),
+            prompt: "Say hello".to_string(),
+            max_tokens: 100,
+            temperature: 0.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_formats_complete_and_stream() {
+        let mock = MockProvider::new().reply("Say hello", "Hello there, friend");
+        let server = MockServer::start(Arc::new(mock)).await.unwrap();
+
+        for format in [ApiFormat::Anthropic, ApiFormat::OpenAi, ApiFormat::Local] {
+            let provider = HttpProvider::new(format, server.url(format)).with_api_key("key");
+            let response = provider.complete(&request()).await.unwrap();
+            assert_eq!(response.text, "Hello there, friend", "{:?}", format);
+            assert_eq!(response.output_tokens, 3, "{:?}", format);
+
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let streamed = provider.stream(&request(), tx).await.unwrap();
+            let mut deltas = Vec::new();
+            while let Some(delta) = rx.recv().await {
+                deltas.push(delta);
+            }
+            assert_eq!(deltas.len(), 3, "{:?}", format);
+            assert_eq!(deltas.concat(), "Hello there, friend");
+            assert_eq!(streamed.text, "Hello there, friend");
+            assert_eq!(streamed.tokens_used(), response.tokens_used());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_are_retried() {
+        let server = MockServer::start(Arc::new(MockProvider::new()))
+            .await
+            .unwrap();
+        let retry = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+        };
+        let provider =
+            HttpProvider::new(ApiFormat::OpenAi, server.url(ApiFormat::OpenAi)).with_retry(retry);
+
+        server.fail_next(2, 429);
+        assert!(provider.complete(&request()).await.is_ok());
+        assert_eq!(server.requests(), 3);
+
+        // Malformed delays fall back to the backoff, and long ones are capped
+        for retry_after in ["-1", "NaN", "inf", "1e300", "99999999999"] {
+            server.fail_next_with(1, 429, retry_after);
+            let start = std::time::Instant::now();
+            assert!(provider.complete(&request()).await.is_ok(), "{}", retry_after);
+            assert!(start.elapsed() < Duration::from_secs(5), "{}", retry_after);
+        }
+        assert_eq!(server.requests(), 13);
+
+        server.fail_next(3, 503);
+        let error = provider.complete(&request()).await.unwrap_err();
+        assert!(error.to_string().contains("503"), "{}", error);
+
+        // Client errors are not retried
+        server.fail_next(1, 400);
+        assert!(provider.complete(&request()).await.is_err());
+        assert_eq!(server.requests(), 17);
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_endpoint_times_out() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                open.push(socket);
+            }
+        });
+
+        let retry = RetryPolicy {
+            max_retries: 1,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(10),
+        };
+        let provider = HttpProvider::new(ApiFormat::Local, format!("http://{}/completion", addr))
+            .with_timeouts(Duration::from_secs(1), Duration::from_millis(100))
+            .with_retry(retry);
+        let start = std::time::Instant::now();
+        let error = provider.complete(&request()).await.unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{}", error);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
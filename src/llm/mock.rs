@@ -0,0 +1,102 @@
+//! Deterministic offline provider
+//!
+//! Answers with the reply of the first rule whose text the prompt contains,
+//! or a fixed default. Token counts are word counts and streaming sends one
+//! word at a time, so runs are repeatable without a model or a network.
+
+use super::{LlmProvider, LlmRequest, LlmResponse};
+use crate::types::OzoneResult;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+pub struct MockProvider {
+    rules: Vec<(String, String)>,
+    default_reply: String,
+    requests: Mutex<Vec<LlmRequest>>,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            default_reply: "Mock response".to_string(),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Answer prompts containing `pattern` with `reply`; earlier rules win
+    pub fn reply(mut self, pattern: impl Into<String>, reply: impl Into<String>) -> Self {
+        self.rules.push((pattern.into(), reply.into()));
+        self
+    }
+
+    /// Answer for prompts no rule matches
+    pub fn with_default(mut self, reply: impl Into<String>) -> Self {
+        self.default_reply = reply.into();
+        self
+    }
+
+    /// Every request answered so far, oldest first
+    pub fn requests(&self) -> Vec<LlmRequest> {
+        self.requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default()
+    }
+
+    /// The response to a request, without recording it
+    pub fn answer(&self, request: &LlmRequest) -> LlmResponse {
+        let text = self
+            .rules
+            .iter()
+            .find(|(pattern, _)| request.prompt.contains(pattern.as_str()))
+            .map(|(_, reply)| reply.clone())
+            .unwrap_or_else(|| self.default_reply.clone());
+        let input = request
+            .system
+            .as_deref()
+            .unwrap_or("")
+            .split_whitespace()
+            .count()
+            + request.prompt.split_whitespace().count();
+        LlmResponse {
+            output_tokens: text.split_whitespace().count() as u32,
+            input_tokens: input as u32,
+            model: request.model.clone(),
+            text,
+        }
+    }
+
+    fn record(&self, request: &LlmRequest) {
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.push(request.clone());
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for MockProvider {
+    async fn complete(&self, request: &LlmRequest) -> OzoneResult<LlmResponse> {
+        self.record(request);
+        Ok(self.answer(request))
+    }
+
+    async fn stream(
+        &self,
+        request: &LlmRequest,
+        tokens: mpsc::UnboundedSender<String>,
+    ) -> OzoneResult<LlmResponse> {
+        self.record(request);
+        let response = self.answer(request);
+        for word in response.text.split_inclusive(' ') {
+            let _ = tokens.send(word.to_string());
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,264 @@
+//! Local stand-in for the model HTTP APIs
+//!
+//! Serves `MockProvider` answers on 127.0.0.1 in all three wire formats
+//! (`/v1/messages`, `/v1/chat/completions`, `/completion`), streamed or not,
+//! so `HttpProvider` and the whole orchestration flow can be tested without
+//! a model. Failures can be injected to exercise retries.
+
+use super::{ApiFormat, LlmProvider, LlmRequest, LlmResponse, MockProvider};
+use crate::types::OzoneResult;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+struct ServerState {
+    provider: Arc<MockProvider>,
+    requests: AtomicU32,
+    /// Requests still to fail, with which status and `Retry-After`
+    failures: Mutex<(u32, u16, String)>,
+}
+
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<ServerState>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Serve `provider` on a free local port
+    pub async fn start(provider: Arc<MockProvider>) -> OzoneResult<Self> {
+        let state = Arc::new(ServerState {
+            provider,
+            requests: AtomicU32::new(0),
+            failures: Mutex::new((0, 500, "0".to_string())),
+        });
+        let app = Router::new()
+            .route("/v1/messages", post(anthropic))
+            .route("/v1/chat/completions", post(openai))
+            .route("/completion", post(local))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(Self {
+            address,
+            state,
+            task,
+        })
+    }
+
+    /// Endpoint URL for a wire format
+    pub fn url(&self, format: ApiFormat) -> String {
+        let path = match format {
+            ApiFormat::Anthropic => "/v1/messages",
+            ApiFormat::OpenAi => "/v1/chat/completions",
+            ApiFormat::Local => "/completion",
+        };
+        format!("http://{}{}", self.address, path)
+    }
+
+    /// Answer the next `count` requests with `status` and `Retry-After: 0`
+    pub fn fail_next(&self, count: u32, status: u16) {
+        self.fail_next_with(count, status, "0");
+    }
+
+    /// Answer the next `count` requests with `status` and the given
+    /// `Retry-After`, well-formed or not
+    pub fn fail_next_with(&self, count: u32, status: u16, retry_after: &str) {
+        if let Ok(mut failures) = self.state.failures.lock() {
+            *failures = (count, status, retry_after.to_string());
+        }
+    }
+
+    /// Requests received, including failed ones
+    pub fn requests(&self) -> u32 {
+        self.state.requests.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn anthropic(State(state): State<Arc<ServerState>>, Json(body): Json<Value>) -> Response {
+    handle(&state, ApiFormat::Anthropic, body).await
+}
+
+async fn openai(State(state): State<Arc<ServerState>>, Json(body): Json<Value>) -> Response {
+    handle(&state, ApiFormat::OpenAi, body).await
+}
+
+async fn local(State(state): State<Arc<ServerState>>, Json(body): Json<Value>) -> Response {
+    handle(&state, ApiFormat::Local, body).await
+}
+
+async fn handle(state: &ServerState, format: ApiFormat, body: Value) -> Response {
+    state.requests.fetch_add(1, Ordering::SeqCst);
+    let failure = state.failures.lock().ok().and_then(|mut failures| {
+        (failures.0 > 0).then(|| {
+            failures.0 -= 1;
+            (failures.1, failures.2.clone())
+        })
+    });
+    if let Some((status, retry_after)) = failure {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let error = json!({"error": {"message": "Injected failure"}});
+        return (status, [(header::RETRY_AFTER, retry_after)], Json(error)).into_response();
+    }
+
+    let request = parse_request(format, &body);
+    let response = match state.provider.complete(&request).await {
+        Ok(response) => response,
+        Err(e) => {
+            let error = json!({"error": {"message": e.to_string()}});
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+
+    if body["stream"].as_bool().unwrap_or(false) {
+        let events = stream_events(format, &response);
+        ([(header::CONTENT_TYPE, "text/event-stream")], events).into_response()
+    } else {
+        Json(response_body(format, &response)).into_response()
+    }
+}
+
+fn parse_request(format: ApiFormat, body: &Value) -> LlmRequest {
+    let messages = body["messages"].as_array().cloned().unwrap_or_default();
+    let content = |role: &str| {
+        messages
+            .iter()
+            .rev()
+            .find(|m| m["role"] == role)
+            .and_then(|m| m["content"].as_str())
+            .map(str::to_string)
+    };
+    let (system, prompt) = match format {
+        ApiFormat::Anthropic => (body["system"].as_str().map(str::to_string), content("user")),
+        ApiFormat::OpenAi => (content("system"), content("user")),
+        ApiFormat::Local => (None, body["prompt"].as_str().map(str::to_string)),
+    };
+    LlmRequest {
+        model: body["model"].as_str().unwrap_or("mock").to_string(),
+        system,
+        prompt: prompt.unwrap_or_default(),
+        max_tokens: body["max_tokens"]
+            .as_u64()
+            .or_else(|| body["n_predict"].as_u64())
+            .unwrap_or(0) as u32,
+        temperature: body["temperature"].as_f64().unwrap_or(0.0) as f32,
+    }
+}
+
+fn response_body(format: ApiFormat, response: &LlmResponse) -> Value {
+    match format {
+        ApiFormat::Anthropic => json!({
+            "model": response.model,
+            "content": [{"type": "text", "text": response.text}],
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": response.input_tokens,
+                "output_tokens": response.output_tokens,
+            },
+        }),
+        ApiFormat::OpenAi => json!({
+            "model": response.model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": response.text},
+                "finish_reason": "stop",
+            }],
+            "usage": {
+                "prompt_tokens": response.input_tokens,
+                "completion_tokens": response.output_tokens,
+            },
+        }),
+        ApiFormat::Local => json!({
+            "content": response.text,
+            "stop": true,
+            "tokens_evaluated": response.input_tokens,
+            "tokens_predicted": response.output_tokens,
+        }),
+    }
+}
+
+/// The response as server-sent events, one word per delta
+fn stream_events(format: ApiFormat, response: &LlmResponse) -> String {
+    let words: Vec<&str> = response.text.split_inclusive(' ').collect();
+    let mut events: Vec<Value> = Vec::new();
+    match format {
+        ApiFormat::Anthropic => {
+            events.push(json!({
+                "type": "message_start",
+                "message": {
+                    "model": response.model,
+                    "usage": {"input_tokens": response.input_tokens, "output_tokens": 0},
+                },
+            }));
+            events.extend(words.iter().map(|word| {
+                json!({
+                    "type": "content_block_delta",
+                    "index": 0,
+                    "delta": {"type": "text_delta", "text": word},
+                })
+            }));
+            events.push(json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": "end_turn"},
+                "usage": {"output_tokens": response.output_tokens},
+            }));
+            events.push(json!({"type": "message_stop"}));
+        }
+        ApiFormat::OpenAi => {
+            events.extend(words.iter().map(|word| {
+                json!({
+                    "model": response.model,
+                    "choices": [{"index": 0, "delta": {"content": word}}],
+                })
+            }));
+            events.push(json!({
+                "model": response.model,
+                "choices": [],
+                "usage": {
+                    "prompt_tokens": response.input_tokens,
+                    "completion_tokens": response.output_tokens,
+                },
+            }));
+        }
+        ApiFormat::Local => {
+            events.extend(
+                words
+                    .iter()
+                    .map(|word| json!({"content": word, "stop": false})),
+            );
+            events.push(json!({
+                "content": "",
+                "stop": true,
+                "tokens_evaluated": response.input_tokens,
+                "tokens_predicted": response.output_tokens,
+            }));
+        }
+    }
+
+    let mut body: String = events
+        .iter()
+        .map(|event| match event["type"].as_str() {
+            Some(kind) => format!("event: {}\ndata: {}\n\n", kind, event),
+            None => format!("data: {}\n\n", event),
+        })
+        .collect();
+    if format == ApiFormat::OpenAi {
+        body.push_str("data: [DONE]\n\n");
+    }
+    body
+}
@@ -0,0 +1,128 @@
+//! LLM providers
+//!
+//! Every model call the orchestrator makes goes through an `LlmProvider`.
+//! `http` talks to the Anthropic Messages API, OpenAI-compatible chat
+//! completion endpoints and a local llama.cpp server, retrying rate limits
+//! and transient failures with backoff (`RetryPolicy`). `mock` answers
+//! deterministically without a network, and `mock_server` serves those
+//! answers over each wire format so the HTTP path runs offline too.
+//! `LlmRouter` picks the provider and model for each orchestration stage
+//! from `ModelConfig`, and `ModelCatalog` holds each model's context length,
+//! output limit, prices and tokenizer (`tokenizer`).
+
+pub mod http;
+pub mod mock;
+#[cfg(any(test, feature = "test-harness"))]
+pub mod mock_server;
+pub mod models;
+pub mod router;
+pub mod tokenizer;
+
+pub use http::{ApiFormat, HttpProvider};
+pub use mock::MockProvider;
+pub use models::{ModelCatalog, ModelInfo};
+pub use router::LlmRouter;
+pub use tokenizer::Tokenizer;
+
+use crate::types::OzoneResult;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// One completion request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmRequest {
+    pub model: String,
+    pub system: Option<String>,
+    pub prompt: String,
+    pub max_tokens: u32,
+    pub temperature: f32,
+}
+
+/// A finished completion
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlmResponse {
+    pub text: String,
+    /// Model that answered, as reported by the provider
+    pub model: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl LlmResponse {
+    pub fn tokens_used(&self) -> u32 {
+        self.input_tokens + self.output_tokens
+    }
+
+    pub fn usage(&self) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+        }
+    }
+}
+
+/// Tokens consumed by one or more model calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl TokenUsage {
+    pub fn total(&self) -> u32 {
+        self.input_tokens + self.output_tokens
+    }
+
+    pub fn add(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+#[async_trait::async_trait]
+pub trait LlmProvider: Send + Sync {
+    async fn complete(&self, request: &LlmRequest) -> OzoneResult<LlmResponse>;
+
+    /// Send the text to `tokens` as it is generated, then return the whole
+    /// response. Providers that cannot stream send it in one piece.
+    async fn stream(
+        &self,
+        request: &LlmRequest,
+        tokens: mpsc::UnboundedSender<String>,
+    ) -> OzoneResult<LlmResponse> {
+        let response = self.complete(request).await?;
+        let _ = tokens.send(response.text.clone());
+        Ok(response)
+    }
+}
+
+/// How rate-limited and failed requests are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Wait before the first retry; doubles with each one
+    pub initial_backoff: Duration,
+    /// Longest wait, including one asked for by a rate limit
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `attempt` (from 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
@@ -0,0 +1,170 @@
+//! Model metadata
+//!
+//! `ModelCatalog` holds what the orchestrator needs to budget for a model:
+//! its context length, output limit, prices and tokenizer. It is built from
+//! `ModelConfig`: the default model and every entry of `available_models`.
+
+use super::tokenizer::{self, HeuristicTokenizer, Tokenizer};
+use super::TokenUsage;
+use crate::config::ModelConfig;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct ModelInfo {
+    pub identifier: String,
+    pub context_length: u32,
+    pub max_output_tokens: Option<u32>,
+    /// USD per million input tokens
+    pub input_price_per_mtok: Option<f64>,
+    /// USD per million output tokens
+    pub output_price_per_mtok: Option<f64>,
+    pub tokenizer: Arc<dyn Tokenizer>,
+}
+
+impl ModelInfo {
+    pub fn new(identifier: impl Into<String>, context_length: u32) -> Self {
+        Self {
+            identifier: identifier.into(),
+            context_length,
+            max_output_tokens: None,
+            input_price_per_mtok: None,
+            output_price_per_mtok: None,
+            tokenizer: Arc::new(HeuristicTokenizer),
+        }
+    }
+
+    pub fn with_max_output_tokens(mut self, max_output_tokens: u32) -> Self {
+        self.max_output_tokens = Some(max_output_tokens);
+        self
+    }
+
+    /// Prices in USD per million input and output tokens
+    pub fn with_prices(mut self, input: f64, output: f64) -> Self {
+        self.input_price_per_mtok = Some(input);
+        self.output_price_per_mtok = Some(output);
+        self
+    }
+
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Cost of `usage` in USD, if the model has prices
+    pub fn cost(&self, usage: &TokenUsage) -> Option<f64> {
+        let input = self.input_price_per_mtok? * usage.input_tokens as f64;
+        let output = self.output_price_per_mtok? * usage.output_tokens as f64;
+        Some((input + output) / 1_000_000.0)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ModelCatalog {
+    default_model: Option<String>,
+    models: HashMap<String, ModelInfo>,
+}
+
+impl ModelCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_model(mut self, info: ModelInfo) -> Self {
+        self.models.insert(info.identifier.clone(), info);
+        self
+    }
+
+    /// Model used when a request does not name one
+    pub fn with_default_model(mut self, identifier: impl Into<String>) -> Self {
+        self.default_model = Some(identifier.into());
+        self
+    }
+
+    /// Metadata of the configured models. The default model takes the
+    /// global context length unless it is also listed in
+    /// `available_models`; vocabularies that fail to load fall back to
+    /// estimating.
+    pub fn from_config(config: &ModelConfig) -> Self {
+        let mut catalog = Self::new();
+        let default_model = match config.model_type.as_str() {
+            "api" => config.api_model.clone(),
+            _ => config.local_model_path.clone(),
+        };
+        if let Some(identifier) = default_model {
+            catalog = catalog
+                .with_model(ModelInfo::new(
+                    identifier.clone(),
+                    config.context_length as u32,
+                ))
+                .with_default_model(identifier);
+        }
+
+        for available in &config.available_models {
+            let mut info = ModelInfo::new(
+                available.identifier.clone(),
+                available.context_length as u32,
+            )
+            .with_tokenizer(tokenizer::load(available.tokenizer_path.as_deref()));
+            info.max_output_tokens = available.max_output_tokens;
+            info.input_price_per_mtok = available.input_price_per_mtok;
+            info.output_price_per_mtok = available.output_price_per_mtok;
+            catalog = catalog.with_model(info);
+        }
+        catalog
+    }
+
+    pub fn get(&self, identifier: &str) -> Option<&ModelInfo> {
+        self.models.get(identifier)
+    }
+
+    pub fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
+    /// Tokenizer of a model; unknown models are estimated
+    pub fn tokenizer(&self, identifier: &str) -> Arc<dyn Tokenizer> {
+        self.get(identifier)
+            .map(|info| info.tokenizer.clone())
+            .unwrap_or_else(|| Arc::new(HeuristicTokenizer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AvailableModel;
+
+    #[test]
+    fn test_catalog_from_config() {
+        let mut config = ModelConfig::default();
+        config.available_models.push(AvailableModel {
+            name: "Local".into(),
+            model_type: "gguf".into(),
+            identifier: "qwen.gguf".into(),
+            context_length: 32768,
+            endpoint: None,
+            api_key_env: None,
+            api_format: None,
+            max_output_tokens: Some(4096),
+            input_price_per_mtok: None,
+            output_price_per_mtok: None,
+            tokenizer_path: None,
+        });
+        let catalog = ModelCatalog::from_config(&config);
+
+        assert_eq!(catalog.default_model(), Some("claude-sonnet-4-20250514"));
+        let sonnet = catalog.get("claude-sonnet-4-20250514").unwrap();
+        assert_eq!(sonnet.context_length, 200000);
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+        };
+        assert_eq!(sonnet.cost(&usage), Some(4.5));
+
+        let local = catalog.get("qwen.gguf").unwrap();
+        assert_eq!(local.max_output_tokens, Some(4096));
+        assert_eq!(local.cost(&usage), None);
+        assert!(catalog.get("gpt-4o").is_none());
+    }
+}
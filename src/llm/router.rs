@@ -0,0 +1,214 @@
+//! Per-stage model routing
+//!
+//! The router holds a default provider and model plus one provider per
+//! configured model. A stage listed in `ModelConfig::stage_models` uses its
+//! model; a request that names a model uses that one for every stage.
+
+use super::{ApiFormat, HttpProvider, LlmProvider, MockProvider};
+use crate::config::ModelConfig;
+use crate::types::{OzoneError, OzoneResult};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Stages that can be given their own model
+pub const STAGES: [&str; 5] = ["amt", "blueprint", "simulation", "execution", "response"];
+
+#[derive(Clone)]
+struct Route {
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+}
+
+pub struct LlmRouter {
+    default: Route,
+    /// Providers of non-default models, by identifier
+    models: HashMap<String, Arc<dyn LlmProvider>>,
+    /// Model identifier by stage
+    stages: HashMap<String, String>,
+}
+
+impl LlmRouter {
+    pub fn new(provider: Arc<dyn LlmProvider>, model: impl Into<String>) -> Self {
+        Self {
+            default: Route {
+                provider,
+                model: model.into(),
+            },
+            models: HashMap::new(),
+            stages: HashMap::new(),
+        }
+    }
+
+    /// Serve `identifier` from `provider` instead of the default provider
+    pub fn with_model(
+        mut self,
+        identifier: impl Into<String>,
+        provider: Arc<dyn LlmProvider>,
+    ) -> Self {
+        self.models.insert(identifier.into(), provider);
+        self
+    }
+
+    /// Use model `identifier` for `stage`
+    pub fn with_stage_model(
+        mut self,
+        stage: impl Into<String>,
+        identifier: impl Into<String>,
+    ) -> Self {
+        self.stages.insert(stage.into(), identifier.into());
+        self
+    }
+
+    /// Build the providers the model configuration describes
+    pub fn from_config(config: &ModelConfig) -> OzoneResult<Self> {
+        let (default_endpoint, default_key) = if config.model_type == "api" {
+            (
+                config.api_endpoint.as_deref(),
+                config.api_key_env.as_deref(),
+            )
+        } else {
+            (config.local_endpoint.as_deref(), None)
+        };
+        let provider = build_provider(
+            &config.model_type,
+            config.api_format.as_deref(),
+            default_endpoint,
+            default_key,
+        )?;
+        let model = match config.model_type.as_str() {
+            "api" => config.api_model.clone(),
+            _ => config.local_model_path.clone(),
+        }
+        .unwrap_or_else(|| config.model_type.clone());
+        let mut router = Self::new(provider, model);
+
+        // Models of the default type share its endpoint and key unless they
+        // set their own
+        for available in &config.available_models {
+            let same_type = available.model_type == config.model_type;
+            let provider = build_provider(
+                &available.model_type,
+                available
+                    .api_format
+                    .as_deref()
+                    .or(config.api_format.as_deref().filter(|_| same_type)),
+                available
+                    .endpoint
+                    .as_deref()
+                    .or(default_endpoint.filter(|_| same_type)),
+                available
+                    .api_key_env
+                    .as_deref()
+                    .or(default_key.filter(|_| same_type)),
+            )?;
+            router = router.with_model(available.identifier.clone(), provider);
+        }
+
+        for (stage, identifier) in &config.stage_models {
+            if !STAGES.contains(&stage.as_str()) {
+                return Err(OzoneError::ConfigError(format!(
+                    "Unknown stage '{}' in stage_models; expected one of {}",
+                    stage,
+                    STAGES.join(", ")
+                )));
+            }
+            router = router.with_stage_model(stage.clone(), identifier.clone());
+        }
+        Ok(router)
+    }
+
+    /// Provider and model for `stage`; `model_override` wins over the
+    /// stage's model. Models without a provider of their own use the default
+    /// provider.
+    pub fn route(
+        &self,
+        stage: &str,
+        model_override: Option<&str>,
+    ) -> (Arc<dyn LlmProvider>, String) {
+        let model = model_override
+            .or_else(|| self.stages.get(stage).map(String::as_str))
+            .unwrap_or(&self.default.model);
+        let provider = self
+            .models
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| self.default.provider.clone());
+        (provider, model.to_string())
+    }
+}
+
+/// Provider for a model type: "api" over HTTP in `api_format`, local types
+/// through a llama.cpp server, "mock" offline
+fn build_provider(
+    model_type: &str,
+    api_format: Option<&str>,
+    endpoint: Option<&str>,
+    api_key_env: Option<&str>,
+) -> OzoneResult<Arc<dyn LlmProvider>> {
+    let format = match (model_type, api_format) {
+        ("mock", _) => return Ok(Arc::new(MockProvider::new())),
+        ("api", None | Some("anthropic")) => ApiFormat::Anthropic,
+        ("api", Some("openai")) => ApiFormat::OpenAi,
+        ("api", Some(other)) => {
+            return Err(OzoneError::ConfigError(format!(
+                "Unknown api_format '{}'; expected anthropic or openai",
+                other
+            )))
+        }
+        ("gguf" | "bitnet" | "onnx", _) => ApiFormat::Local,
+        (other, _) => {
+            return Err(OzoneError::ConfigError(format!(
+                "Unknown model_type '{}'",
+                other
+            )))
+        }
+    };
+
+    let mut provider = HttpProvider::new(
+        format,
+        endpoint.unwrap_or_else(|| format.default_endpoint()),
+    );
+    if let Some(key) = api_key_env.and_then(|name| std::env::var(name).ok()) {
+        provider = provider.with_api_key(key);
+    }
+    Ok(Arc::new(provider))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AvailableModel;
+
+    #[test]
+    fn test_stage_routing_from_config() {
+        let mut config = ModelConfig::default();
+        config.available_models.push(AvailableModel {
+            name: "Local".into(),
+            model_type: "gguf".into(),
+            identifier: "qwen.gguf".into(),
+            context_length: 8192,
+            endpoint: None,
+            api_key_env: None,
+            api_format: None,
+            max_output_tokens: None,
+            input_price_per_mtok: None,
+            output_price_per_mtok: None,
+            tokenizer_path: None,
+        });
+        config
+            .stage_models
+            .insert("simulation".into(), "qwen.gguf".into());
+        let router = LlmRouter::from_config(&config).unwrap();
+
+        let (default_provider, model) = router.route("blueprint", None);
+        assert_eq!(model, "claude-sonnet-4-20250514");
+        let (local_provider, model) = router.route("simulation", None);
+        assert_eq!(model, "qwen.gguf");
+        assert!(!Arc::ptr_eq(&default_provider, &local_provider));
+        let (_, model) = router.route("simulation", Some("claude-sonnet-4-20250514"));
+        assert_eq!(model, "claude-sonnet-4-20250514");
+
+        config.stage_models.insert("planning".into(), "x".into());
+        assert!(LlmRouter::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Token counting
+//!
+//! `BpeTokenizer` counts tokens with a model's byte-pair encoding, loaded
+//! from a local vocabulary: a Hugging Face `tokenizer.json` with a byte-level
+//! BPE model, or a tiktoken rank file (`<base64 token> <rank>` per line).
+//! `HeuristicTokenizer` estimates four characters per token and is used for
+//! models without a vocabulary.
+
+use crate::types::{OzoneError, OzoneResult};
+use base64::Engine;
+use fancy_regex::Regex;
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Pre-tokenizer pattern of GPT-2 style byte-level BPE
+const GPT2_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenizer pattern of the cl100k tiktoken vocabulary
+const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+pub trait Tokenizer: Send + Sync {
+    /// Number of tokens `text` encodes to
+    fn count(&self, text: &str) -> u32;
+}
+
+/// Four characters per token, rounded up
+pub struct HeuristicTokenizer;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count(&self, text: &str) -> u32 {
+        text.len().div_ceil(4) as u32
+    }
+}
+
+/// Merge priorities of a BPE vocabulary; lower merges first
+enum Ranks {
+    /// tiktoken: rank of the token a merge produces
+    Tokens(HashMap<Vec<u8>, u32>),
+    /// Hugging Face: rank of the merge of a pair
+    Pairs(HashMap<(Vec<u8>, Vec<u8>), u32>),
+}
+
+pub struct BpeTokenizer {
+    ranks: Ranks,
+    pattern: Regex,
+}
+
+impl BpeTokenizer {
+    /// Load a vocabulary, `tokenizer.json` or a tiktoken rank file
+    pub fn from_file(path: impl AsRef<Path>) -> OzoneResult<Self> {
+        let content = std::fs::read_to_string(path.as_ref())?;
+        if content.trim_start().starts_with('{') {
+            Self::from_hf_json(&content)
+        } else {
+            Self::from_tiktoken(&content)
+        }
+    }
+
+    /// A tiktoken rank file, split with the cl100k pattern
+    pub fn from_tiktoken(content: &str) -> OzoneResult<Self> {
+        let mut ranks = HashMap::new();
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || invalid_vocabulary(format!("bad tiktoken line {}", number + 1));
+            let (token, rank) = line.split_once(' ').ok_or_else(invalid)?;
+            let token = base64::engine::general_purpose::STANDARD
+                .decode(token)
+                .map_err(|_| invalid())?;
+            let rank = rank.trim().parse().map_err(|_| invalid())?;
+            ranks.insert(token, rank);
+        }
+        Ok(Self {
+            ranks: Ranks::Tokens(ranks),
+            pattern: compile(CL100K_PATTERN)?,
+        })
+    }
+
+    /// A Hugging Face `tokenizer.json` with a byte-level BPE model. The
+    /// pre-tokenizer's split pattern is used when it has one.
+    pub fn from_hf_json(content: &str) -> OzoneResult<Self> {
+        let json: Value = serde_json::from_str(content)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        let model = &json["model"];
+        if model["type"] != "BPE" {
+            return Err(invalid_vocabulary(format!(
+                "model type {} is not BPE",
+                model["type"]
+            )));
+        }
+        let pre_tokenizer = json["pre_tokenizer"].to_string();
+        if !pre_tokenizer.contains("ByteLevel") {
+            return Err(invalid_vocabulary(
+                "only byte-level BPE vocabularies are supported".to_string(),
+            ));
+        }
+
+        let bytes_of = byte_level_decoder();
+        let decode = |token: &str| -> OzoneResult<Vec<u8>> {
+            token
+                .chars()
+                .map(|c| bytes_of.get(&c).copied())
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(|| invalid_vocabulary(format!("token {:?} is not byte-level", token)))
+        };
+        let mut ranks = HashMap::new();
+        for (rank, merge) in model["merges"].as_array().into_iter().flatten().enumerate() {
+            // "a b", or ["a", "b"] in newer files
+            let (left, right) = match merge {
+                Value::String(merge) => merge.split_once(' ').unwrap_or((merge, "")),
+                Value::Array(pair) => (
+                    pair.first().and_then(Value::as_str).unwrap_or(""),
+                    pair.get(1).and_then(Value::as_str).unwrap_or(""),
+                ),
+                _ => continue,
+            };
+            ranks
+                .entry((decode(left)?, decode(right)?))
+                .or_insert(rank as u32);
+        }
+
+        let pattern = find_split_pattern(&json["pre_tokenizer"]).unwrap_or(GPT2_PATTERN);
+        Ok(Self {
+            ranks: Ranks::Pairs(ranks),
+            pattern: compile(pattern)?,
+        })
+    }
+
+    fn rank(&self, left: &[u8], right: &[u8]) -> Option<u32> {
+        match &self.ranks {
+            Ranks::Tokens(ranks) => ranks.get(&[left, right].concat()).copied(),
+            Ranks::Pairs(ranks) => ranks.get(&(left.to_vec(), right.to_vec())).copied(),
+        }
+    }
+
+    /// Tokens in one pre-tokenized piece: merge the best ranked adjacent
+    /// pair, leftmost first, until none can be merged. Candidate pairs wait
+    /// in a heap, so a piece of n bytes takes O(n log n) lookups.
+    fn count_piece(&self, piece: &[u8]) -> usize {
+        let n = piece.len();
+        // Parts are linked by start offset: `end[i]` ends the part starting
+        // at `i`, `prev[i]` starts the one before it
+        let mut end: Vec<usize> = (1..=n).collect();
+        let mut prev: Vec<Option<usize>> = (0..n).map(|i| i.checked_sub(1)).collect();
+        let mut alive = vec![true; n];
+        let mut parts = n;
+
+        // (rank, left start, left end, right end) of each adjacent pair
+        let mut heap = BinaryHeap::new();
+        let push = |heap: &mut BinaryHeap<_>, end: &[usize], left: usize| {
+            let right = end[left];
+            if right < n {
+                if let Some(rank) = self.rank(&piece[left..right], &piece[right..end[right]]) {
+                    heap.push(Reverse((rank, left, right, end[right])));
+                }
+            }
+        };
+        for i in 0..n {
+            push(&mut heap, &end, i);
+        }
+
+        while let Some(Reverse((_, left, right, right_end))) = heap.pop() {
+            // Skip pairs changed by an earlier merge
+            if !alive[left] || end[left] != right || !alive[right] || end[right] != right_end {
+                continue;
+            }
+            end[left] = right_end;
+            alive[right] = false;
+            parts -= 1;
+            if right_end < n {
+                prev[right_end] = Some(left);
+            }
+            if let Some(before) = prev[left] {
+                push(&mut heap, &end, before);
+            }
+            push(&mut heap, &end, left);
+        }
+        parts
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn count(&self, text: &str) -> u32 {
+        self.pattern
+            .find_iter(text)
+            .filter_map(Result::ok)
+            .map(|piece| self.count_piece(piece.as_str().as_bytes()))
+            .sum::<usize>() as u32
+    }
+}
+
+/// The vocabulary at `path`, or the heuristic when there is none or it
+/// cannot be read
+pub fn load(path: Option<&str>) -> Arc<dyn Tokenizer> {
+    let Some(path) = path else {
+        return Arc::new(HeuristicTokenizer);
+    };
+    match BpeTokenizer::from_file(path) {
+        Ok(tokenizer) => Arc::new(tokenizer),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to load tokenizer {}, estimating tokens instead: {}",
+                path,
+                e
+            );
+            Arc::new(HeuristicTokenizer)
+        }
+    }
+}
+
+fn invalid_vocabulary(message: String) -> OzoneError {
+    OzoneError::ModelError(format!("Invalid tokenizer vocabulary: {}", message))
+}
+
+fn compile(pattern: &str) -> OzoneResult<Regex> {
+    Regex::new(pattern).map_err(|e| invalid_vocabulary(e.to_string()))
+}
+
+/// The regex of the first `Split` pre-tokenizer, searching sequences
+fn find_split_pattern(pre_tokenizer: &Value) -> Option<&str> {
+    if pre_tokenizer["type"] == "Split" {
+        return pre_tokenizer["pattern"]["Regex"].as_str();
+    }
+    pre_tokenizer["pretokenizers"]
+        .as_array()?
+        .iter()
+        .find_map(find_split_pattern)
+}
+
+/// Byte for each character of the GPT-2 byte-level alphabet, which maps
+/// unprintable bytes to code points from 256 up
+fn byte_level_decoder() -> HashMap<char, u8> {
+    let printable = |b: u8| matches!(b, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF);
+    let mut next = 256u32;
+    (0..=255u8)
+        .map(|b| {
+            let c = if printable(b) {
+                b as u32
+            } else {
+                next += 1;
+                next - 1
+            };
+            (char::from_u32(c).unwrap_or_default(), b)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpe_vocabularies_count_merged_tokens() {
+        let encode = |token: &str| base64::engine::general_purpose::STANDARD.encode(token);
+        let tiktoken: String = ["a", "b", " ", "c", "ab", " ab", "abab"]
+            .iter()
+            .enumerate()
+            .map(|(rank, token)| format!("{} {}\n", encode(token), rank))
+            .collect();
+        let tokenizer = BpeTokenizer::from_tiktoken(&tiktoken).unwrap();
+        assert_eq!(tokenizer.count("abab ab"), 2);
+        assert_eq!(tokenizer.count("abc"), 2);
+
+        let hf = serde_json::json!({
+            "pre_tokenizer": {"type": "ByteLevel", "add_prefix_space": false},
+            "model": {"type": "BPE", "vocab": {}, "merges": ["a b", ["Ġ", "ab"]]}
+        });
+        let tokenizer = BpeTokenizer::from_hf_json(&hf.to_string()).unwrap();
+        assert_eq!(tokenizer.count("ab ab"), 2);
+        assert_eq!(tokenizer.count("abc"), 2);
+        assert_eq!(tokenizer.count(""), 0);
+        assert_eq!(load(Some("/nonexistent/tokenizer.json")).count("test"), 1);
+    }
+
+    #[test]
+    fn test_long_pieces_merge_quickly() {
+        let encode = |token: &str| base64::engine::general_purpose::STANDARD.encode(token);
+        let tiktoken: String = ["a", "aa", "aaaa"]
+            .iter()
+            .enumerate()
+            .map(|(rank, token)| format!("{} {}\n", encode(token), rank))
+            .collect();
+        let tokenizer = BpeTokenizer::from_tiktoken(&tiktoken).unwrap();
+        assert_eq!(tokenizer.count("aaaaaaa"), 3);
+        // One piece of 200k bytes
+        assert_eq!(tokenizer.count(&"a".repeat(200_000)), 50_000);
+    }
+}
@@ -72,12 +72,21 @@ async fn main() -> Result<(), OzoneError> {
         runtime.config.grpc.address,
         runtime.config.grpc.port
     );
+    tracing::info!(
+        "REST API: http://{}:{}/api/v1 (OpenAPI at /api/v1/openapi.json)",
+        runtime.config.grpc.address,
+        runtime.config.grpc.port
+    );
     tracing::info!("Press Ctrl+C to shutdown");
     tracing::info!("────────────────────────────────────────────────────────────────────");
 
     // Start the runtime (this blocks until shutdown)
-    runtime.start().await?;
+    let summary = runtime.start().await?;
 
-    tracing::info!("Ozone Studio shutdown complete");
+    if summary.errors.is_empty() && summary.interrupted_tasks.is_empty() {
+        tracing::info!("Ozone Studio shutdown complete: {}", summary);
+    } else {
+        tracing::warn!("Ozone Studio shutdown complete: {}", summary);
+    }
     Ok(())
 }
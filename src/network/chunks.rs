@@ -0,0 +1,245 @@
+//! Content-addressed storage for pipeline code
+//!
+//! Pipeline artifacts are split into chunks of up to `CHUNK_SIZE`, each
+//! stored under its Blake3 hash in `<data_path>/chunks`. A `CodePointer`
+//! lists an artifact's chunks in order, the hash of the whole artifact and
+//! the peers mirroring it. Peers request chunks by ID over the transfer
+//! protocol; `NetworkManager::fetch_code` pulls missing chunks from the
+//! mirrors in parallel. Every chunk is checked against its ID before it is
+//! stored and the assembled artifact against the pointer's hash.
+//! Pointers come from peers, so their size and chunk count are checked
+//! against `MAX_CODE_SIZE` before anything is fetched or allocated.
+
+use super::transfer::CHUNK_SIZE;
+use super::NetworkManager;
+use crate::pipeline::CodeSource;
+use crate::types::consensus::{ConsensusProposal, ProposalType};
+use crate::types::pipeline::{ChunkID, CodePointer};
+use crate::types::{ConsensusStatus, OzoneError, OzoneResult, Value};
+use std::path::{Path, PathBuf};
+use tokio::sync::RwLock;
+
+/// Chunks requested from mirrors at once
+pub const MAX_PARALLEL_FETCHES: usize = 8;
+
+/// How long to wait for a dialed mirror to connect
+pub const MIRROR_DIAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Largest pipeline artifact this node assembles
+pub const MAX_CODE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Refuse a pointer describing more code than `MAX_CODE_SIZE`
+pub fn check_size(pointer: &CodePointer) -> OzoneResult<()> {
+    let max_chunks = MAX_CODE_SIZE.div_ceil(CHUNK_SIZE as u64);
+    if pointer.size > MAX_CODE_SIZE || pointer.chunks.len() as u64 > max_chunks {
+        return Err(OzoneError::ValidationError(format!(
+            "Code {} of {} bytes in {} chunks exceeds {} bytes",
+            hex::encode(pointer.hash),
+            pointer.size,
+            pointer.chunks.len(),
+            MAX_CODE_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// Chunk files, named by the hex of their hash
+pub struct ChunkStore {
+    dir: PathBuf,
+}
+
+impl ChunkStore {
+    pub fn new(data_path: &Path) -> Self {
+        Self {
+            dir: data_path.join("chunks"),
+        }
+    }
+
+    /// Store an artifact as chunks; the pointer has no mirrors yet
+    pub fn put(&self, data: &[u8]) -> OzoneResult<CodePointer> {
+        let mut chunks = Vec::new();
+        for chunk in data.chunks(CHUNK_SIZE) {
+            let id = *blake3::hash(chunk).as_bytes();
+            if !self.contains(&id) {
+                self.write(&id, chunk)?;
+            }
+            chunks.push(id);
+        }
+        Ok(CodePointer {
+            hash: *blake3::hash(data).as_bytes(),
+            size: data.len() as u64,
+            chunks,
+            mirrors: Vec::new(),
+        })
+    }
+
+    /// Store a chunk received from a peer, refusing data that does not
+    /// hash to `id`
+    pub fn insert(&self, id: &ChunkID, data: &[u8]) -> OzoneResult<()> {
+        if data.len() > CHUNK_SIZE || blake3::hash(data).as_bytes() != id {
+            return Err(OzoneError::IntegrityError(format!(
+                "Chunk does not match its ID {}",
+                hex::encode(id)
+            )));
+        }
+        if self.contains(id) {
+            return Ok(());
+        }
+        self.write(id, data)
+    }
+
+    /// Read a chunk; a corrupted file is removed and reported missing
+    pub fn get(&self, id: &ChunkID) -> OzoneResult<Option<Vec<u8>>> {
+        let path = self.path(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = std::fs::read(&path)?;
+        if blake3::hash(&data).as_bytes() != id {
+            tracing::warn!("Removing corrupted chunk {}", hex::encode(id));
+            std::fs::remove_file(&path)?;
+            return Ok(None);
+        }
+        Ok(Some(data))
+    }
+
+    pub fn contains(&self, id: &ChunkID) -> bool {
+        self.path(id).exists()
+    }
+
+    /// Chunks of an artifact not stored locally
+    pub fn missing(&self, pointer: &CodePointer) -> Vec<ChunkID> {
+        let mut missing: Vec<ChunkID> = pointer
+            .chunks
+            .iter()
+            .filter(|id| !self.contains(id))
+            .copied()
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        missing
+    }
+
+    /// Join an artifact's chunks, checking its size and hash
+    pub fn assemble(&self, pointer: &CodePointer) -> OzoneResult<Vec<u8>> {
+        check_size(pointer)?;
+        let mut chunks = Vec::with_capacity(pointer.chunks.len());
+        for id in &pointer.chunks {
+            let chunk = self.get(id)?.ok_or_else(|| {
+                OzoneError::NotFound(format!("Chunk {} is not stored", hex::encode(id)))
+            })?;
+            chunks.push(chunk);
+        }
+        // Sized by the verified chunks rather than the pointer's claim
+        let size: usize = chunks.iter().map(Vec::len).sum();
+        if size as u64 != pointer.size {
+            return Err(OzoneError::IntegrityError(format!(
+                "Code {} is {} bytes, not {}",
+                hex::encode(pointer.hash),
+                size,
+                pointer.size
+            )));
+        }
+        let data = chunks.concat();
+        if *blake3::hash(&data).as_bytes() != pointer.hash {
+            return Err(OzoneError::IntegrityError(format!(
+                "Code does not match its hash {}",
+                hex::encode(pointer.hash)
+            )));
+        }
+        Ok(data)
+    }
+
+    fn path(&self, id: &ChunkID) -> PathBuf {
+        self.dir.join(hex::encode(id))
+    }
+
+    fn write(&self, id: &ChunkID, data: &[u8]) -> OzoneResult<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(id);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+/// Whether a closed proposal accepts pipeline code: an accepted
+/// `NewPipeline` or `UpdatePipeline` proposal whose content has the hex of
+/// the code's hash under `code_hash`
+pub fn accepts_code(proposal: &ConsensusProposal, pointer: &CodePointer) -> bool {
+    let Value::Map(content) = &proposal.content else {
+        return false;
+    };
+    proposal.status == ConsensusStatus::Accepted
+        && matches!(
+            proposal.proposal_type,
+            ProposalType::NewPipeline | ProposalType::UpdatePipeline
+        )
+        && content.get("code_hash") == Some(&Value::String(hex::encode(pointer.hash)))
+}
+
+#[async_trait::async_trait]
+impl CodeSource for RwLock<NetworkManager> {
+    async fn fetch_code(&self, pointer: &CodePointer) -> OzoneResult<Vec<u8>> {
+        self.read().await.fetch_code(pointer).await
+    }
+
+    async fn code_accepted(&self, pointer: &CodePointer) -> bool {
+        self.read()
+            .await
+            .proposals()
+            .await
+            .iter()
+            .any(|proposal| accepts_code(proposal, pointer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_insert_and_assemble() {
+        let dir = Path::new("/tmp/test_chunk_store");
+        let _ = std::fs::remove_dir_all(dir);
+        let local = ChunkStore::new(&dir.join("local"));
+        let remote = ChunkStore::new(&dir.join("remote"));
+
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 5).map(|i| (i % 251) as u8).collect();
+        let pointer = local.put(&data).unwrap();
+        assert_eq!(pointer.chunks.len(), 3);
+        assert_eq!(pointer.size, data.len() as u64);
+        assert_eq!(local.assemble(&pointer).unwrap(), data);
+
+        // Another node fills in the missing chunks, refusing tampered ones
+        assert_eq!(remote.missing(&pointer).len(), 3);
+        let first = local.get(&pointer.chunks[0]).unwrap().unwrap();
+        assert!(remote.insert(&pointer.chunks[1], &first).is_err());
+        for id in remote.missing(&pointer) {
+            let chunk = local.get(&id).unwrap().unwrap();
+            remote.insert(&id, &chunk).unwrap();
+        }
+        assert!(remote.missing(&pointer).is_empty());
+        assert_eq!(remote.assemble(&pointer).unwrap(), data);
+
+        // A corrupted chunk file is dropped rather than served
+        std::fs::write(remote.path(&pointer.chunks[2]), b"corrupt").unwrap();
+        assert!(remote.get(&pointer.chunks[2]).unwrap().is_none());
+        assert!(remote.assemble(&pointer).is_err());
+        assert_eq!(remote.missing(&pointer), vec![pointer.chunks[2]]);
+
+        // Sizes claimed by a pointer are checked before assembling
+        let mut understated = pointer.clone();
+        understated.size = 1;
+        assert!(local.assemble(&understated).is_err());
+        let mut oversized = pointer.clone();
+        oversized.size = MAX_CODE_SIZE + 1;
+        assert!(matches!(
+            local.assemble(&oversized),
+            Err(OzoneError::ValidationError(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
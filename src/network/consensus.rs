@@ -0,0 +1,991 @@
+//! Network consensus (§20)
+//!
+//! Proposals and votes are signed with the node keypair and gossiped on
+//! `CONSENSUS_TOPIC`. Every node keeps the proposals and votes it has seen
+//! in `consensus.json` and tallies them itself, weighting each vote by the
+//! voter's reputation and contributions in its own peer book. A proposal is
+//! `Open` until it has `min_votes_required` votes, then `Verifying`. When
+//! its voting period ends it is `Expired` without enough votes, `Accepted`
+//! if the accepting share of the weight reaches `acceptance_threshold` and
+//! `Rejected` otherwise. The node closing a proposal gossips it with every
+//! vote it saw, so peers that missed votes catch up and re-tally.
+//!
+//! Votes from keys missing from the peer book, other than this node's own,
+//! carry no weight and do not count towards `min_votes_required`.
+//!
+//! Proposals from peers below `min_reputation_to_propose` or over
+//! `max_proposals_per_day` are refused; the daily limit counts proposals by
+//! when this node received them, and a proposal dated more than
+//! `MAX_CLOCK_SKEW_SECS` before it arrives is refused as stale. Each
+//! proposal's outcome is settled once against its proposer's reputation
+//! (see `reputation`).
+
+use super::protocol::{hex_bytes, MAX_GOSSIP_SIZE};
+use super::reputation::{Offence, Rejection};
+use super::PeerInfo;
+use crate::config::ConsensusConfig;
+use crate::types::consensus::{
+    ConsensusProposal, ConsensusVote, ConsensusVoteType, ProposalType, VerificationResult,
+};
+use crate::types::{Blake3Hash, ConsensusStatus, OzoneError, OzoneResult, UserID, Value};
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+/// File in the network data directory holding proposals and votes
+pub const CONSENSUS_FILE: &str = "consensus.json";
+
+/// Window for `max_proposals_per_day`
+const DAY_SECS: u64 = 86_400;
+
+/// How far a peer's timestamp may be from this node's clock
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+
+/// Reputation of this node and of proposers missing from the peer book,
+/// as for new peers
+const DEFAULT_REPUTATION: f32 = 0.5;
+
+/// A proposal signed by its proposer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedProposal {
+    /// Sent without votes or local state
+    pub proposal: ConsensusProposal,
+    #[serde(with = "hex_bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// A vote signed by its voter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedVote {
+    pub proposal_id: u64,
+    pub vote: ConsensusVote,
+    #[serde(with = "hex_bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Messages gossiped on the consensus topic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ConsensusMessage {
+    Proposal(SignedProposal),
+    Vote(SignedVote),
+    /// A closed proposal with every vote its sender saw
+    Outcome {
+        proposal: SignedProposal,
+        votes: Vec<SignedVote>,
+        status: ConsensusStatus,
+    },
+}
+
+impl ConsensusMessage {
+    pub fn encode(&self) -> OzoneResult<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| OzoneError::SerializationError(e.to_string()))
+    }
+
+    pub fn decode(data: &[u8]) -> OzoneResult<Self> {
+        serde_json::from_slice(data).map_err(|e| {
+            OzoneError::SerializationError(format!("Invalid consensus message: {}", e))
+        })
+    }
+}
+
+impl SignedProposal {
+    /// Check the content hash and signature; returns the proposer
+    pub fn verify(&self) -> Result<PeerId, Rejection> {
+        if content_hash(&self.proposal.content) != self.proposal.hash {
+            return Err(Rejection::new(
+                Offence::HashMismatch,
+                format!(
+                    "Proposal {} does not match its content hash",
+                    self.proposal.proposal_id
+                ),
+            ));
+        }
+        verify_signature(
+            &self.proposal.proposer,
+            &proposal_bytes(&self.proposal),
+            &self.signature,
+        )
+    }
+}
+
+impl SignedVote {
+    /// Check the signature against the proposal voted on; returns the voter
+    pub fn verify(&self, proposal: &ConsensusProposal) -> Result<PeerId, Rejection> {
+        if self.proposal_id != proposal.proposal_id {
+            return Err(format!(
+                "Vote for {} applied to proposal {}",
+                self.proposal_id, proposal.proposal_id
+            )
+            .into());
+        }
+        verify_signature(
+            &self.vote.voter,
+            &vote_bytes(proposal, &self.vote),
+            &self.signature,
+        )
+    }
+}
+
+/// Blake3 hash of proposal content, over its canonical JSON form
+pub fn content_hash(content: &Value) -> Blake3Hash {
+    let canonical = serde_json::to_value(content)
+        .and_then(|value| serde_json::to_vec(&value))
+        .unwrap_or_default();
+    *blake3::hash(&canonical).as_bytes()
+}
+
+fn proposal_bytes(proposal: &ConsensusProposal) -> Vec<u8> {
+    format!(
+        "proposal\n{}\n{}\n{}\n{:?}\n{}",
+        proposal.proposal_id,
+        hex::encode(&proposal.proposer),
+        proposal.timestamp,
+        proposal.proposal_type,
+        hex::encode(proposal.hash)
+    )
+    .into_bytes()
+}
+
+fn vote_bytes(proposal: &ConsensusProposal, vote: &ConsensusVote) -> Vec<u8> {
+    format!(
+        "vote\n{}\n{}\n{}\n{:?}\n{}\n{}",
+        proposal.proposal_id,
+        hex::encode(proposal.hash),
+        hex::encode(&vote.voter),
+        vote.vote,
+        vote.timestamp,
+        serde_json::to_string(&vote.verification_result).unwrap_or_default()
+    )
+    .into_bytes()
+}
+
+fn verify_signature(key: &[u8], bytes: &[u8], signature: &[u8]) -> Result<PeerId, Rejection> {
+    let key = PublicKey::try_decode_protobuf(key).map_err(|e| {
+        Rejection::new(
+            Offence::InvalidSignature,
+            format!("Invalid signer key: {}", e),
+        )
+    })?;
+    if !key.verify(bytes, signature) {
+        return Err(Rejection::new(
+            Offence::InvalidSignature,
+            "Invalid consensus signature",
+        ));
+    }
+    Ok(key.to_peer_id())
+}
+
+/// Reputation and contribution count of a key's peer, if it is known
+fn standing(peers: &HashMap<PeerId, PeerInfo>, key: &[u8]) -> Option<(f32, u64)> {
+    PublicKey::try_decode_protobuf(key)
+        .ok()
+        .and_then(|key| peers.get(&key.to_peer_id()))
+        .map(|peer| (peer.reputation, peer.contribution_count))
+}
+
+/// Weight of a vote from a voter with this reputation and contribution count
+pub fn weight(config: &ConsensusConfig, reputation: f32, contributions: u64) -> f32 {
+    let mut weight = 1.0;
+    if config.voting.reputation_weighting {
+        weight *= reputation.clamp(0.0, 1.0);
+    }
+    if config.voting.contribution_weighting {
+        weight *= 1.0 + (contributions as f32).ln_1p();
+    }
+    weight
+}
+
+/// Weighted votes on a proposal
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tally {
+    pub accept: f32,
+    pub reject: f32,
+    pub needs_review: f32,
+    pub votes: u32,
+}
+
+impl Tally {
+    /// Share of the vote weight that accepted
+    pub fn acceptance(&self) -> f32 {
+        let total = self.accept + self.reject + self.needs_review;
+        if total > 0.0 {
+            self.accept / total
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Final status of a proposal whose voting period ended
+pub fn decide(config: &ConsensusConfig, tally: &Tally) -> ConsensusStatus {
+    if tally.votes < config.voting.min_votes_required {
+        ConsensusStatus::Expired
+    } else if tally.acceptance() >= config.acceptance_threshold {
+        ConsensusStatus::Accepted
+    } else {
+        ConsensusStatus::Rejected
+    }
+}
+
+/// A closed proposal to credit to its proposer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settlement {
+    pub proposer: PeerId,
+    /// Set for this node's own proposals
+    pub proposed_by: Option<UserID>,
+    pub status: ConsensusStatus,
+}
+
+/// A proposal with its signature and signed votes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Record {
+    /// Votes are kept in `votes` with their signatures
+    proposal: ConsensusProposal,
+    #[serde(with = "hex_bytes")]
+    signature: Vec<u8>,
+    votes: Vec<SignedVote>,
+    /// Local user who made the proposal, for this node's own proposals
+    #[serde(default)]
+    proposed_by: Option<UserID>,
+    /// Outcome already credited to the proposer
+    #[serde(default)]
+    settled: bool,
+    /// When this node first saw the proposal, by its own clock
+    #[serde(default)]
+    received: u64,
+}
+
+impl Record {
+    fn signed(&self) -> SignedProposal {
+        SignedProposal {
+            proposal: bare(self.proposal.clone()),
+            signature: self.signature.clone(),
+        }
+    }
+
+    /// The proposal with its votes
+    fn view(&self) -> ConsensusProposal {
+        let mut proposal = self.proposal.clone();
+        proposal.votes = self.votes.iter().map(|v| v.vote.clone()).collect();
+        proposal
+    }
+
+    fn is_closed(&self) -> bool {
+        matches!(
+            self.proposal.status,
+            ConsensusStatus::Accepted | ConsensusStatus::Rejected | ConsensusStatus::Expired
+        )
+    }
+}
+
+/// A proposal as signed, without votes or local state
+fn bare(mut proposal: ConsensusProposal) -> ConsensusProposal {
+    proposal.status = ConsensusStatus::Open;
+    proposal.votes.clear();
+    proposal.local_verification_result = None;
+    proposal.network_verification_count = 0;
+    proposal
+}
+
+#[derive(Default)]
+struct State {
+    records: BTreeMap<u64, Record>,
+    dirty: bool,
+}
+
+/// This node's view of network proposals
+pub struct Consensus {
+    path: PathBuf,
+    config: ConsensusConfig,
+    keypair: Keypair,
+    state: Mutex<State>,
+}
+
+impl Consensus {
+    /// Load saved proposals; a missing or unreadable file starts empty
+    pub fn load(dir: &Path, config: ConsensusConfig, keypair: Keypair) -> Self {
+        let path = dir.join(CONSENSUS_FILE);
+        let records = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring consensus state {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path,
+            config,
+            keypair,
+            state: Mutex::new(State {
+                records,
+                dirty: false,
+            }),
+        }
+    }
+
+    pub async fn save(&self) -> OzoneResult<()> {
+        let mut state = self.state.lock().await;
+        if !state.dirty {
+            return Ok(());
+        }
+        let content = serde_json::to_string(&state.records)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &self.path)?;
+        state.dirty = false;
+        Ok(())
+    }
+
+    /// Known proposals with their votes, newest first
+    pub async fn proposals(&self) -> Vec<ConsensusProposal> {
+        let state = self.state.lock().await;
+        let mut proposals: Vec<_> = state.records.values().map(Record::view).collect();
+        proposals.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+        proposals
+    }
+
+    pub async fn proposal(&self, proposal_id: u64) -> Option<ConsensusProposal> {
+        self.state
+            .lock()
+            .await
+            .records
+            .get(&proposal_id)
+            .map(Record::view)
+    }
+
+    /// Sign and record a proposal made by a local user; returns it with the
+    /// message to gossip
+    pub async fn propose(
+        &self,
+        proposal_type: ProposalType,
+        content: Value,
+        proposed_by: Option<UserID>,
+        now: u64,
+    ) -> OzoneResult<(ConsensusProposal, ConsensusMessage)> {
+        let proposer = self.keypair.public().encode_protobuf();
+        let mut state = self.state.lock().await;
+        let max = self.config.verification.max_proposals_per_day;
+        if proposed_within_day(&state, &proposer, now) >= max as usize {
+            return Err(OzoneError::PermissionDenied(format!(
+                "At most {} proposals per day",
+                max
+            )));
+        }
+
+        let proposal = ConsensusProposal {
+            proposal_id: rand::random(),
+            proposer,
+            timestamp: now,
+            proposal_type,
+            hash: content_hash(&content),
+            content,
+            status: ConsensusStatus::Open,
+            votes: Vec::new(),
+            local_verification_result: None,
+            network_verification_count: 0,
+        };
+        let signature = self
+            .keypair
+            .sign(&proposal_bytes(&proposal))
+            .map_err(|e| OzoneError::NetworkError(format!("Cannot sign proposal: {}", e)))?;
+        let record = Record {
+            proposal: proposal.clone(),
+            signature,
+            votes: Vec::new(),
+            proposed_by,
+            settled: false,
+            received: now,
+        };
+        let message = ConsensusMessage::Proposal(record.signed());
+        if message.encode()?.len() > MAX_GOSSIP_SIZE {
+            return Err(OzoneError::ValidationError(format!(
+                "Proposal content exceeds {} bytes",
+                MAX_GOSSIP_SIZE
+            )));
+        }
+
+        state.records.insert(proposal.proposal_id, record);
+        state.dirty = true;
+        Ok((proposal, message))
+    }
+
+    /// Sign and record this node's vote; returns the message to gossip
+    pub async fn vote(
+        &self,
+        proposal_id: u64,
+        vote: ConsensusVoteType,
+        verification: VerificationResult,
+        peers: &HashMap<PeerId, PeerInfo>,
+        now: u64,
+    ) -> OzoneResult<ConsensusMessage> {
+        let voter = self.keypair.public().encode_protobuf();
+        let mut state = self.state.lock().await;
+        let record = state
+            .records
+            .get_mut(&proposal_id)
+            .ok_or_else(|| OzoneError::NotFound(format!("Proposal {}", proposal_id)))?;
+        if record.is_closed() || now >= self.closes_at(&record.proposal) {
+            return Err(OzoneError::ValidationError(format!(
+                "Voting on proposal {} has closed",
+                proposal_id
+            )));
+        }
+        if record.votes.iter().any(|v| v.vote.voter == voter) {
+            return Err(OzoneError::ValidationError(format!(
+                "Already voted on proposal {}",
+                proposal_id
+            )));
+        }
+
+        let vote = ConsensusVote {
+            voter,
+            vote,
+            timestamp: now,
+            verification_result: verification.clone(),
+        };
+        let signature = self
+            .keypair
+            .sign(&vote_bytes(&record.proposal, &vote))
+            .map_err(|e| OzoneError::NetworkError(format!("Cannot sign vote: {}", e)))?;
+        let signed = SignedVote {
+            proposal_id,
+            vote,
+            signature,
+        };
+        record.proposal.local_verification_result = Some(verification);
+        record.votes.push(signed.clone());
+        self.refresh(record, peers, now);
+        state.dirty = true;
+        Ok(ConsensusMessage::Vote(signed))
+    }
+
+    /// Apply a message gossiped by `author`; returns whether anything
+    /// changed, or why the message is invalid
+    pub async fn handle(
+        &self,
+        author: &PeerId,
+        data: &[u8],
+        peers: &HashMap<PeerId, PeerInfo>,
+        now: u64,
+    ) -> Result<bool, Rejection> {
+        let message = ConsensusMessage::decode(data).map_err(|e| e.to_string())?;
+        let mut state = self.state.lock().await;
+        let changed = match message {
+            ConsensusMessage::Proposal(signed) => {
+                self.admit(&mut state, signed, Some(author), peers, now)?
+            }
+            ConsensusMessage::Vote(signed) => {
+                // Votes can arrive before their proposal; it is caught up
+                // with the outcome
+                let Some(record) = state.records.get_mut(&signed.proposal_id) else {
+                    return Ok(false);
+                };
+                let added = self.add_vote(record, signed, Some(author), now)?;
+                if added {
+                    self.refresh(record, peers, now);
+                }
+                added
+            }
+            ConsensusMessage::Outcome {
+                proposal, votes, ..
+            } => {
+                let proposal_id = proposal.proposal.proposal_id;
+                let mut changed = self.admit(&mut state, proposal, None, peers, now)?;
+                if let Some(record) = state.records.get_mut(&proposal_id) {
+                    // One bad relayed vote does not discard the others
+                    for vote in votes {
+                        match self.add_vote(record, vote, None, now) {
+                            Ok(added) => changed |= added,
+                            Err(rejection) => tracing::debug!(
+                                "Skipped a vote relayed with proposal {}: {}",
+                                proposal_id,
+                                rejection.reason
+                            ),
+                        }
+                    }
+                    if changed {
+                        self.refresh(record, peers, now);
+                    }
+                }
+                changed
+            }
+        };
+        state.dirty |= changed;
+        Ok(changed)
+    }
+
+    /// Close proposals whose voting period ended; returns the outcomes to
+    /// gossip
+    pub async fn close_expired(
+        &self,
+        peers: &HashMap<PeerId, PeerInfo>,
+        now: u64,
+    ) -> Vec<ConsensusMessage> {
+        let mut state = self.state.lock().await;
+        let mut outcomes = Vec::new();
+        for record in state.records.values_mut() {
+            if record.is_closed() || now < self.closes_at(&record.proposal) {
+                continue;
+            }
+            self.refresh(record, peers, now);
+            tracing::info!(
+                "Proposal {} closed as {:?} with {} votes",
+                record.proposal.proposal_id,
+                record.proposal.status,
+                record.votes.len()
+            );
+            outcomes.push(ConsensusMessage::Outcome {
+                proposal: record.signed(),
+                votes: record.votes.clone(),
+                status: record.proposal.status,
+            });
+        }
+        state.dirty |= !outcomes.is_empty();
+        outcomes
+    }
+
+    /// Closed proposals whose outcome has not been credited yet; each is
+    /// returned once
+    pub async fn take_settled(&self) -> Vec<Settlement> {
+        let mut state = self.state.lock().await;
+        let mut settled = Vec::new();
+        for record in state.records.values_mut() {
+            if !record.is_closed() || record.settled {
+                continue;
+            }
+            record.settled = true;
+            if let Ok(key) = PublicKey::try_decode_protobuf(&record.proposal.proposer) {
+                settled.push(Settlement {
+                    proposer: key.to_peer_id(),
+                    proposed_by: record.proposed_by,
+                    status: record.proposal.status,
+                });
+            }
+        }
+        state.dirty |= !settled.is_empty();
+        settled
+    }
+
+    /// Weighted votes on a proposal
+    pub fn tally(&self, votes: &[ConsensusVote], peers: &HashMap<PeerId, PeerInfo>) -> Tally {
+        let own = self.keypair.public().encode_protobuf();
+        let mut tally = Tally::default();
+        for vote in votes {
+            // Keys missing from the peer book could be minted freely
+            let standing = if vote.voter == own {
+                Some((DEFAULT_REPUTATION, 0))
+            } else {
+                standing(peers, &vote.voter)
+            };
+            let Some((reputation, contributions)) = standing else {
+                continue;
+            };
+            let weight = weight(&self.config, reputation, contributions);
+            match vote.vote {
+                ConsensusVoteType::Accept => tally.accept += weight,
+                ConsensusVoteType::Reject => tally.reject += weight,
+                ConsensusVoteType::NeedsReview => tally.needs_review += weight,
+            }
+            tally.votes += 1;
+        }
+        tally
+    }
+
+    fn closes_at(&self, proposal: &ConsensusProposal) -> u64 {
+        proposal
+            .timestamp
+            .saturating_add(self.config.voting.voting_duration_secs)
+    }
+
+    /// Recompute a proposal's status from its votes; an outcome already
+    /// credited to the proposer is final
+    fn refresh(&self, record: &mut Record, peers: &HashMap<PeerId, PeerInfo>, now: u64) {
+        let votes: Vec<ConsensusVote> = record.votes.iter().map(|v| v.vote.clone()).collect();
+        let tally = self.tally(&votes, peers);
+        record.proposal.network_verification_count = votes
+            .iter()
+            .filter(|v| v.verification_result.verified)
+            .count() as u32;
+        if record.settled {
+            return;
+        }
+        record.proposal.status = if now >= self.closes_at(&record.proposal) {
+            decide(&self.config, &tally)
+        } else if tally.votes >= self.config.voting.min_votes_required {
+            ConsensusStatus::Verifying
+        } else {
+            ConsensusStatus::Open
+        };
+    }
+
+    /// Record a peer's proposal unless its proposer is over the limits;
+    /// `author` must be the proposer when it sent the proposal itself
+    fn admit(
+        &self,
+        state: &mut State,
+        signed: SignedProposal,
+        author: Option<&PeerId>,
+        peers: &HashMap<PeerId, PeerInfo>,
+        now: u64,
+    ) -> Result<bool, Rejection> {
+        let proposer = signed.verify()?;
+        if author.is_some_and(|author| *author != proposer) {
+            return Err(Rejection::new(
+                Offence::InvalidSignature,
+                format!("Proposal by {} relayed as its own", proposer),
+            ));
+        }
+        let proposal = bare(signed.proposal);
+        let proposal_id = proposal.proposal_id;
+        if let Some(known) = state.records.get(&proposal_id) {
+            if known.proposal.hash == proposal.hash && known.proposal.proposer == proposal.proposer
+            {
+                return Ok(false);
+            }
+            return Err(format!("Proposal {} conflicts with a known one", proposal_id).into());
+        }
+        if proposal.timestamp > now + MAX_CLOCK_SKEW_SECS {
+            return Err(format!("Proposal {} is dated in the future", proposal_id).into());
+        }
+        // The proposer gossips a proposal as it makes it; outcomes relay it
+        // when its voting period ends
+        let fresh_until = if author.is_some() {
+            proposal.timestamp
+        } else {
+            self.closes_at(&proposal)
+        };
+        if fresh_until.saturating_add(MAX_CLOCK_SKEW_SECS) < now {
+            return Err(format!("Proposal {} is stale", proposal_id).into());
+        }
+
+        let verification = &self.config.verification;
+        let (reputation, _) =
+            standing(peers, &proposal.proposer).unwrap_or((DEFAULT_REPUTATION, 0));
+        if reputation < verification.min_reputation_to_propose {
+            return Err(format!(
+                "Proposer {} has reputation {:.2}, below {:.2}",
+                proposer, reputation, verification.min_reputation_to_propose
+            )
+            .into());
+        }
+        if proposed_within_day(state, &proposal.proposer, now)
+            >= verification.max_proposals_per_day as usize
+        {
+            return Err(Rejection::new(
+                Offence::Spam,
+                format!(
+                    "Proposer {} is over {} proposals per day",
+                    proposer, verification.max_proposals_per_day
+                ),
+            ));
+        }
+
+        let mut record = Record {
+            proposal,
+            signature: signed.signature,
+            votes: Vec::new(),
+            proposed_by: None,
+            settled: false,
+            received: now,
+        };
+        self.refresh(&mut record, peers, now);
+        tracing::info!(
+            "Proposal {} ({:?}) from {}",
+            proposal_id,
+            record.proposal.proposal_type,
+            proposer
+        );
+        state.records.insert(proposal_id, record);
+        Ok(true)
+    }
+
+    /// Add a vote cast during the voting period; false if the voter already
+    /// voted. `author` must be the voter when it sent the vote itself
+    fn add_vote(
+        &self,
+        record: &mut Record,
+        signed: SignedVote,
+        author: Option<&PeerId>,
+        now: u64,
+    ) -> Result<bool, Rejection> {
+        let voter = signed.verify(&record.proposal)?;
+        if author.is_some_and(|author| *author != voter) {
+            return Err(Rejection::new(
+                Offence::InvalidSignature,
+                format!("Vote by {} relayed as its own", voter),
+            ));
+        }
+        let cast = signed.vote.timestamp;
+        if cast < record.proposal.timestamp
+            || cast > self.closes_at(&record.proposal)
+            || cast > now + MAX_CLOCK_SKEW_SECS
+        {
+            return Err(format!(
+                "Vote by {} cast outside the voting period of {}",
+                voter, record.proposal.proposal_id
+            )
+            .into());
+        }
+        if record
+            .votes
+            .iter()
+            .any(|v| v.vote.voter == signed.vote.voter)
+        {
+            return Ok(false);
+        }
+        record.votes.push(signed);
+        Ok(true)
+    }
+}
+
+/// Proposals by `proposer` received in the day up to `at`
+fn proposed_within_day(state: &State, proposer: &[u8], at: u64) -> usize {
+    let since = at.saturating_sub(DAY_SECS);
+    state
+        .records
+        .values()
+        .filter(|r| r.proposal.proposer == proposer)
+        .filter(|r| r.received > since && r.received <= at)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(dir: &str, keypair: &Keypair) -> Consensus {
+        let mut config = ConsensusConfig::default();
+        config.voting.min_votes_required = 2;
+        config.voting.voting_duration_secs = 100;
+        config.verification.max_proposals_per_day = 2;
+        let dir = Path::new("/tmp").join(dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        Consensus::load(&dir, config, keypair.clone())
+    }
+
+    fn reload(dir: &str, keypair: &Keypair) -> Consensus {
+        let dir = Path::new("/tmp").join(dir);
+        Consensus::load(&dir, ConsensusConfig::default(), keypair.clone())
+    }
+
+    fn peer_info(peer_id: &PeerId, reputation: f32, contribution_count: u64) -> PeerInfo {
+        PeerInfo {
+            peer_id: peer_id.to_string(),
+            address: String::new(),
+            addresses: Vec::new(),
+            last_seen: 0,
+            latency_ms: 0,
+            reputation,
+            contribution_count,
+            capabilities: Vec::new(),
+            connected: true,
+            agent_version: None,
+            reputation_updated: 0,
+            banned_until: 0,
+        }
+    }
+
+    fn verified() -> VerificationResult {
+        VerificationResult {
+            verified: true,
+            zero_shot_passed: true,
+            semantic_valid: true,
+            signature_valid: true,
+            concerns: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_votes_are_weighted_and_proposals_close() {
+        let (a, b, c) = (
+            Keypair::generate_ed25519(),
+            Keypair::generate_ed25519(),
+            Keypair::generate_ed25519(),
+        );
+        let d = Keypair::generate_ed25519();
+        let (node_a, node_b, node_c, node_d) = (
+            engine("ozone_consensus_a", &a),
+            engine("ozone_consensus_b", &b),
+            engine("ozone_consensus_c", &c),
+            engine("ozone_consensus_d", &d),
+        );
+        let peer = |keypair: &Keypair| keypair.public().to_peer_id();
+        let mut peers = HashMap::new();
+        for (keypair, reputation, contributions) in [(&b, 0.9, 20), (&c, 0.2, 0)] {
+            let peer_id = peer(keypair);
+            peers.insert(peer_id, peer_info(&peer_id, reputation, contributions));
+        }
+
+        let content = Value::String("new methodology".into());
+        let (proposal, message) = node_a
+            .propose(ProposalType::NewMethodology, content, Some(1), 1000)
+            .await
+            .unwrap();
+        let id = proposal.proposal_id;
+        let data = message.encode().unwrap();
+
+        // Only the proposer may announce its proposal
+        assert!(node_b.handle(&peer(&c), &data, &peers, 1001).await.is_err());
+        assert!(node_b.handle(&peer(&a), &data, &peers, 1001).await.unwrap());
+        assert!(!node_b.handle(&peer(&a), &data, &peers, 1001).await.unwrap());
+        assert!(node_c.handle(&peer(&a), &data, &peers, 1001).await.unwrap());
+
+        let accept = node_b
+            .vote(id, ConsensusVoteType::Accept, verified(), &peers, 1010)
+            .await
+            .unwrap();
+        let reject = node_c
+            .vote(id, ConsensusVoteType::Reject, verified(), &peers, 1020)
+            .await
+            .unwrap();
+        assert!(node_c
+            .vote(id, ConsensusVoteType::Accept, verified(), &peers, 1030)
+            .await
+            .is_err());
+        for (voter, message) in [(&b, &accept), (&c, &reject)] {
+            let data = message.encode().unwrap();
+            assert!(node_a
+                .handle(&peer(voter), &data, &peers, 1040)
+                .await
+                .unwrap());
+        }
+        assert_eq!(
+            node_a.proposal(id).await.unwrap().status,
+            ConsensusStatus::Verifying
+        );
+
+        // b has the higher reputation and contributions, so acceptance wins
+        let votes = node_a.proposal(id).await.unwrap().votes;
+        let tally = node_a.tally(&votes, &peers);
+        assert!(tally.acceptance() > 0.67);
+        // Votes from keys outside the peer book are not counted
+        assert_eq!(node_a.tally(&votes, &HashMap::new()), Tally::default());
+        assert!(node_a.close_expired(&peers, 1050).await.is_empty());
+        let outcomes = node_a.close_expired(&peers, 1100).await;
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(
+            node_a.proposal(id).await.unwrap().status,
+            ConsensusStatus::Accepted
+        );
+
+        // b missed c's vote; the outcome catches it up, skipping a forged
+        // vote relayed with it
+        let ConsensusMessage::Outcome {
+            proposal,
+            mut votes,
+            status,
+        } = outcomes[0].clone()
+        else {
+            panic!("not an outcome");
+        };
+        let mut forged = votes[1].clone();
+        forged.vote.vote = ConsensusVoteType::Accept;
+        votes.insert(0, forged);
+        let relayed = ConsensusMessage::Outcome {
+            proposal,
+            votes,
+            status,
+        };
+        let data = relayed.encode().unwrap();
+        assert!(node_b.handle(&peer(&a), &data, &peers, 1101).await.unwrap());
+        let seen = node_b.proposal(id).await.unwrap();
+        assert_eq!(seen.votes.len(), 2);
+        assert_eq!(seen.status, ConsensusStatus::Accepted);
+
+        // The outcome is credited once
+        let settled = node_a.take_settled().await;
+        assert_eq!(settled.len(), 1);
+        assert_eq!(settled[0].proposer, peer(&a));
+        assert_eq!(settled[0].proposed_by, Some(1));
+        assert!(node_a.take_settled().await.is_empty());
+        assert_eq!(node_b.take_settled().await[0].proposed_by, None);
+
+        // A late vote is kept but does not change a credited outcome
+        let data = message.encode().unwrap();
+        assert!(node_d.handle(&peer(&a), &data, &peers, 1001).await.unwrap());
+        let late = node_d
+            .vote(id, ConsensusVoteType::Reject, verified(), &peers, 1090)
+            .await
+            .unwrap();
+        peers.insert(peer(&d), peer_info(&peer(&d), 1.0, 1000));
+        let data = late.encode().unwrap();
+        assert!(node_b.handle(&peer(&d), &data, &peers, 1102).await.unwrap());
+        let seen = node_b.proposal(id).await.unwrap();
+        assert_eq!(seen.votes.len(), 3);
+        assert_eq!(seen.status, ConsensusStatus::Accepted);
+        assert!(node_b.take_settled().await.is_empty());
+
+        node_a.save().await.unwrap();
+        let reloaded = reload("ozone_consensus_a", &a);
+        assert_eq!(reloaded.proposals().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_proposal_limits() {
+        let (a, b) = (Keypair::generate_ed25519(), Keypair::generate_ed25519());
+        let (node_a, node_b) = (
+            engine("ozone_consensus_limit_a", &a),
+            engine("ozone_consensus_limit_b", &b),
+        );
+        let author = a.public().to_peer_id();
+        let mut messages = Vec::new();
+        for i in 0..2 {
+            let (_, message) = node_a
+                .propose(ProposalType::NewCategory, Value::Int(i), None, 1000)
+                .await
+                .unwrap();
+            messages.push(message.encode().unwrap());
+        }
+        assert!(node_a
+            .propose(ProposalType::NewCategory, Value::Int(2), None, 2000)
+            .await
+            .is_err());
+        assert!(node_a
+            .propose(
+                ProposalType::NewCategory,
+                Value::Int(2),
+                None,
+                1000 + DAY_SECS
+            )
+            .await
+            .is_ok());
+
+        // Low-reputation proposers are refused
+        let mut peers = HashMap::new();
+        peers.insert(author, peer_info(&author, 0.1, 0));
+        assert!(node_b
+            .handle(&author, &messages[0], &peers, 1001)
+            .await
+            .is_err());
+
+        // A proposal dated well before it arrives is refused
+        peers.clear();
+        assert!(node_b
+            .handle(&author, &messages[0], &peers, 1001 + MAX_CLOCK_SKEW_SECS)
+            .await
+            .is_err());
+
+        // A peer over the daily limit is refused too
+        let mut limited = engine("ozone_consensus_limit_b", &b);
+        limited.config.verification.max_proposals_per_day = 1;
+        assert!(limited
+            .handle(&author, &messages[0], &peers, 1001)
+            .await
+            .unwrap());
+        assert_eq!(
+            limited
+                .handle(&author, &messages[1], &peers, 1001)
+                .await
+                .unwrap_err()
+                .offence,
+            Offence::Spam
+        );
+    }
+}
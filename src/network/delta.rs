@@ -0,0 +1,882 @@
+//! Delta sync
+//!
+//! Container IDs are local to a node, so a shared container is known on the
+//! network by its `SyncId`: the node that created it and its ID there. The
+//! structural roots every node creates at bootstrap keep their reserved IDs.
+//! Inbound containers are stored under a local ID mapped from their
+//! `SyncId`, placed under the local copy of their parent, and lose the
+//! sender's owner and object path; the object a container points to (a
+//! blueprint's definition, say) travels with it.
+//!
+//! Each shared container carries a version vector (one counter per node
+//! that changed it), kept in `<data_path>/sync_index.json` with its `SyncId`
+//! and the hash of the content last seen. A local edit shows up as a new
+//! hash and bumps this node's counter.
+//!
+//! Anti-entropy: a node pages through a peer's digests (id, vector, hash),
+//! pushes the containers it has newer and pulls the ones the peer has
+//! newer, so only changed containers cross the wire. Selective sync limits
+//! digests and accepted containers to the subtrees under `sync_roots`;
+//! user data (user-owned containers, and user, project and task subtrees)
+//! is never included whatever the scope. Concurrent changes are resolved the
+//! same way on every node (latest `updated_at`, then highest content hash)
+//! and recorded as `ChangeType::Merge`.
+
+use super::inbound::{is_shareable, InboundOutcome};
+use super::protocol::Envelope;
+use super::SyncItemType;
+use crate::types::container::{ChangeType, VersionRecord, ROOT_CONTAINER_ID};
+use crate::types::{Container, ContainerID, ContainerType, OzoneError, OzoneResult};
+use crate::zsei::ZSEI;
+use libp2p::identity::Keypair;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+/// Sync index file
+pub const SYNC_INDEX_FILE: &str = "sync_index.json";
+
+/// Digests returned per page
+pub const DIGEST_PAGE_SIZE: usize = 2000;
+
+/// Containers visited when collecting digests for the sync scope
+const MAX_SCOPE_CONTAINERS: usize = 100_000;
+
+/// IDs below this are the structural roots every node creates at bootstrap
+/// (see `types::container`), the same everywhere
+pub const RESERVED_IDS: ContainerID = 100;
+
+/// Network-wide identity of a shared container
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SyncId {
+    /// Peer that created the container; empty for reserved IDs
+    pub origin: String,
+    /// Its ID on that peer
+    pub id: ContainerID,
+}
+
+impl SyncId {
+    /// ID of a container created on `origin`
+    pub fn new(origin: &str, id: ContainerID) -> Self {
+        let origin = if id < RESERVED_IDS {
+            String::new()
+        } else {
+            origin.to_string()
+        };
+        Self { origin, id }
+    }
+
+    /// Whether this names a reserved container
+    pub fn is_reserved(&self) -> bool {
+        self.id < RESERVED_IDS
+    }
+}
+
+impl std::fmt::Display for SyncId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_reserved() {
+            write!(f, "{}", self.id)
+        } else {
+            write!(f, "{}/{}", self.origin, self.id)
+        }
+    }
+}
+
+/// What an envelope carries: a container as stored by the sender, the
+/// `SyncId`s of it and its parent, and the object it points to, if any
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncedContainer {
+    pub id: SyncId,
+    pub parent: SyncId,
+    pub container: Container,
+    #[serde(default)]
+    pub object: Option<serde_json::Value>,
+}
+
+impl SyncedContainer {
+    /// A container named by its IDs on `origin`, without its object; for
+    /// nodes with no replica to map IDs
+    pub fn unmapped(origin: &str, container: Container) -> Self {
+        Self {
+            id: SyncId::new(origin, container.global_state.container_id),
+            parent: SyncId::new(origin, container.global_state.parent_id),
+            container,
+            object: None,
+        }
+    }
+}
+
+/// How the order of two version vectors relates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Causality {
+    Equal,
+    /// Every counter is at most the other's
+    Before,
+    /// Every counter is at least the other's
+    After,
+    /// Each has changes the other has not seen
+    Concurrent,
+}
+
+/// Per-node change counters for one container
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionVector(pub BTreeMap<String, u64>);
+
+impl VersionVector {
+    pub fn increment(&mut self, node: &str) {
+        *self.0.entry(node.to_string()).or_insert(0) += 1;
+    }
+
+    /// Take the higher counter for every node
+    pub fn merge(&mut self, other: &VersionVector) {
+        for (node, &count) in &other.0 {
+            let entry = self.0.entry(node.clone()).or_insert(0);
+            *entry = (*entry).max(count);
+        }
+    }
+
+    pub fn compare(&self, other: &VersionVector) -> Causality {
+        let nodes: HashSet<&String> = self.0.keys().chain(other.0.keys()).collect();
+        let (mut behind, mut ahead) = (false, false);
+        for node in nodes {
+            let mine = self.0.get(node).copied().unwrap_or(0);
+            let theirs = other.0.get(node).copied().unwrap_or(0);
+            behind |= mine < theirs;
+            ahead |= mine > theirs;
+        }
+        match (behind, ahead) {
+            (false, false) => Causality::Equal,
+            (true, false) => Causality::Before,
+            (false, true) => Causality::After,
+            (true, true) => Causality::Concurrent,
+        }
+    }
+}
+
+/// What a node holds for one container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Digest {
+    pub id: SyncId,
+    pub version: VersionVector,
+    /// `container_hash` of the content
+    pub hash: String,
+}
+
+/// Containers to exchange with a peer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncPlan {
+    /// Newer here, or missing on the peer
+    pub push: Vec<SyncId>,
+    /// Newer on the peer, or missing here
+    pub pull: Vec<SyncId>,
+}
+
+/// Compare local and remote digests; concurrent changes go both ways so
+/// each side resolves the conflict
+pub fn plan(local: &[Digest], remote: &[Digest]) -> SyncPlan {
+    let local: HashMap<&SyncId, &Digest> = local.iter().map(|d| (&d.id, d)).collect();
+    let remote: HashMap<&SyncId, &Digest> = remote.iter().map(|d| (&d.id, d)).collect();
+
+    let mut plan = SyncPlan::default();
+    for (&id, mine) in &local {
+        match remote.get(id) {
+            None => plan.push.push(id.clone()),
+            Some(theirs) => match mine.version.compare(&theirs.version) {
+                Causality::After => plan.push.push(id.clone()),
+                Causality::Before => plan.pull.push(id.clone()),
+                Causality::Concurrent if mine.hash != theirs.hash => {
+                    plan.push.push(id.clone());
+                    plan.pull.push(id.clone());
+                }
+                _ => {}
+            },
+        }
+    }
+    plan.pull.extend(
+        remote
+            .keys()
+            .filter(|id| !local.contains_key(*id))
+            .map(|&id| id.clone()),
+    );
+    plan.push.sort_unstable();
+    plan.pull.sort_unstable();
+    plan
+}
+
+/// Hash of the synced content of a container and its object. IDs, owner,
+/// object path, provenance, traversal hints and integrity data are local and
+/// left out.
+pub fn container_hash(container: &Container, object: Option<&serde_json::Value>) -> String {
+    let mut content = container.clone();
+    content.global_state = Default::default();
+    content.local_state.metadata.owner_id = 0;
+    content.local_state.metadata.provenance.clear();
+    content.local_state.storage.object_store_path = None;
+    content.local_state.hints = Default::default();
+    content.local_state.integrity = Default::default();
+    // Through `Value` so map keys are sorted
+    let value = serde_json::json!({ "container": content, "object": object });
+    blake3::hash(value.to_string().as_bytes())
+        .to_hex()
+        .to_string()
+}
+
+/// Pick the winner of a conflict; the same on every node
+pub fn resolve<'a>(a: &'a Container, b: &'a Container) -> &'a Container {
+    let key = |c: &Container| (c.local_state.metadata.updated_at, container_hash(c, None));
+    if key(b) > key(a) {
+        b
+    } else {
+        a
+    }
+}
+
+/// Item type a container is sent as
+pub fn kind_for(container_type: ContainerType) -> SyncItemType {
+    match container_type {
+        ContainerType::Methodology => SyncItemType::Methodology,
+        ContainerType::Blueprint => SyncItemType::Blueprint,
+        _ => SyncItemType::Container,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncEntry {
+    /// Absent for containers indexed before `SyncId`s were kept
+    #[serde(default)]
+    id: Option<SyncId>,
+    version: VersionVector,
+    hash: String,
+}
+
+/// `SyncId`s and version vectors by local container ID, saved between runs
+#[derive(Debug, Default)]
+pub struct SyncIndex {
+    path: PathBuf,
+    entries: HashMap<ContainerID, SyncEntry>,
+    /// Local ID of each `SyncId` in `entries`
+    local: HashMap<SyncId, ContainerID>,
+    dirty: bool,
+}
+
+impl SyncIndex {
+    /// Load the index; a missing or unreadable file starts empty
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(SYNC_INDEX_FILE);
+        let entries: HashMap<ContainerID, SyncEntry> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring sync index {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        let local = entries
+            .iter()
+            .filter_map(|(&id, entry)| Some((entry.id.clone()?, id)))
+            .collect();
+        Self {
+            path,
+            entries,
+            local,
+            dirty: false,
+        }
+    }
+
+    pub fn save(&mut self) -> OzoneResult<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let content = serde_json::to_string(&self.entries)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn set(&mut self, id: ContainerID, sync_id: SyncId, version: VersionVector, hash: String) {
+        if let Some(SyncEntry { id: Some(old), .. }) = self.entries.get(&id) {
+            if *old != sync_id {
+                self.local.remove(old);
+            }
+        }
+        self.local.insert(sync_id.clone(), id);
+        let entry = SyncEntry {
+            id: Some(sync_id),
+            version,
+            hash,
+        };
+        self.entries.insert(id, entry);
+        self.dirty = true;
+    }
+
+    /// `SyncId` of a local container; one created here is named after
+    /// `local_peer`
+    fn sync_id(&self, id: ContainerID, local_peer: &str) -> SyncId {
+        self.entries
+            .get(&id)
+            .and_then(|entry| entry.id.clone())
+            .unwrap_or_else(|| SyncId::new(local_peer, id))
+    }
+
+    /// Local ID of a container known on the network as `sync_id`
+    fn local_id(&self, sync_id: &SyncId, local_peer: &str) -> Option<ContainerID> {
+        if sync_id.is_reserved() {
+            return Some(sync_id.id);
+        }
+        match self.local.get(sync_id) {
+            Some(&id) => Some(id),
+            // Created here and not indexed under its `SyncId` yet
+            None if sync_id.origin == local_peer => match self.entries.get(&sync_id.id) {
+                Some(SyncEntry { id: Some(_), .. }) => None,
+                _ => Some(sync_id.id),
+            },
+            None => None,
+        }
+    }
+}
+
+/// Whether a container may leave this node: a shareable type no user owns
+pub fn is_shared(container: &Container) -> bool {
+    is_shareable(container.local_state.metadata.container_type)
+        && container.local_state.metadata.owner_id == 0
+}
+
+/// This node's replica of the shared containers in ZSEI
+pub struct Replica {
+    zsei: Arc<RwLock<ZSEI>>,
+    index: Mutex<SyncIndex>,
+    /// Subtrees to sync; empty syncs every shareable container
+    roots: Vec<ContainerID>,
+    keypair: Keypair,
+    local_peer: String,
+    /// Envelopes being fetched by peers in chunks
+    serving: Mutex<HashMap<(PeerId, SyncId), Vec<u8>>>,
+}
+
+impl Replica {
+    pub fn new(
+        zsei: Arc<RwLock<ZSEI>>,
+        index: SyncIndex,
+        roots: Vec<ContainerID>,
+        keypair: Keypair,
+    ) -> Self {
+        let local_peer = keypair.public().to_peer_id().to_string();
+        Self {
+            zsei,
+            index: Mutex::new(index),
+            roots,
+            keypair,
+            local_peer,
+            serving: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Digest of a container, bumping this node's counter if its content
+    /// or object changed since it was last seen
+    pub async fn observe(&self, container: &Container) -> Digest {
+        let object = self.object(container).await;
+        self.digest(container, object.as_ref()).await
+    }
+
+    async fn digest(&self, container: &Container, object: Option<&serde_json::Value>) -> Digest {
+        let id = container.global_state.container_id;
+        let hash = container_hash(container, object);
+        let mut index = self.index.lock().await;
+        let sync_id = index.sync_id(id, &self.local_peer);
+        let entry = index.entries.get(&id).cloned();
+        if let Some(entry) = entry.as_ref().filter(|e| e.hash == hash && e.id.is_some()) {
+            return Digest {
+                id: sync_id,
+                version: entry.version.clone(),
+                hash,
+            };
+        }
+        let mut version = entry.as_ref().map(|e| e.version.clone()).unwrap_or_default();
+        if entry.is_none_or(|e| e.hash != hash) {
+            version.increment(&self.local_peer);
+        }
+        index.set(id, sync_id.clone(), version.clone(), hash.clone());
+        Digest {
+            id: sync_id,
+            version,
+            hash,
+        }
+    }
+
+    /// The object a container points to, if it has one that can be read
+    async fn object(&self, container: &Container) -> Option<serde_json::Value> {
+        container.local_state.storage.object_store_path.as_ref()?;
+        match self.zsei.read().await.load_object(container) {
+            Ok(object) => object,
+            Err(e) => {
+                tracing::warn!(
+                    "Cannot read object of container {}: {}",
+                    container.global_state.container_id,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Digests of every container in the sync scope, ordered by `SyncId`
+    pub async fn digests(&self) -> Vec<Digest> {
+        let mut shared = Vec::new();
+        {
+            let zsei = self.zsei.read().await;
+            let starts = if self.roots.is_empty() {
+                vec![ROOT_CONTAINER_ID]
+            } else {
+                self.roots.clone()
+            };
+            let mut queue: VecDeque<ContainerID> = starts.into();
+            let mut visited = HashSet::new();
+
+            while let Some(id) = queue.pop_front() {
+                if visited.len() >= MAX_SCOPE_CONTAINERS || !visited.insert(id) {
+                    continue;
+                }
+                let Ok(Some(container)) = zsei.get_container(id).await else {
+                    continue;
+                };
+                let container_type = container.local_state.metadata.container_type;
+                let owned = container.local_state.metadata.owner_id != 0;
+                if owned || (!is_shareable(container_type) && container_type != ContainerType::Root)
+                {
+                    // User data and everything under it stays local
+                    continue;
+                }
+                queue.extend(container.global_state.child_ids.iter().copied());
+                if is_shareable(container_type) {
+                    shared.push(container);
+                }
+            }
+        }
+
+        let mut digests = Vec::with_capacity(shared.len());
+        for container in &shared {
+            digests.push(self.observe(container).await);
+        }
+        digests.sort_by(|a, b| a.id.cmp(&b.id));
+        digests
+    }
+
+    /// One page of digests after `after`; returns whether more follow
+    pub async fn digest_page(&self, after: Option<&SyncId>) -> (Vec<Digest>, bool) {
+        let mut digests: Vec<Digest> = self
+            .digests()
+            .await
+            .into_iter()
+            .filter(|d| after.is_none_or(|after| d.id > *after))
+            .collect();
+        let more = digests.len() > DIGEST_PAGE_SIZE;
+        digests.truncate(DIGEST_PAGE_SIZE);
+        (digests, more)
+    }
+
+    /// Signed envelope for a container in the sync scope
+    pub async fn envelope(&self, sync_id: &SyncId) -> OzoneResult<Envelope> {
+        let not_found = || OzoneError::NotFound(format!("Container {}", sync_id));
+        let id = self
+            .index
+            .lock()
+            .await
+            .local_id(sync_id, &self.local_peer)
+            .ok_or_else(not_found)?;
+        let container = self
+            .zsei
+            .read()
+            .await
+            .get_container(id)
+            .await?
+            .ok_or_else(not_found)?;
+        if !is_shared(&container) || !self.in_scope(&container).await {
+            return Err(OzoneError::PermissionDenied(format!(
+                "Container {} is not shared",
+                sync_id
+            )));
+        }
+        self.seal(&container).await
+    }
+
+    /// Sign a container with its current version vector
+    pub async fn seal(&self, container: &Container) -> OzoneResult<Envelope> {
+        let kind = kind_for(container.local_state.metadata.container_type);
+        self.seal_item(kind, container, super::now()).await
+    }
+
+    /// Sign a container as `kind`, with its `SyncId`s, object and current
+    /// version vector
+    pub async fn seal_item(
+        &self,
+        kind: SyncItemType,
+        container: &Container,
+        timestamp: u64,
+    ) -> OzoneResult<Envelope> {
+        let object = self.object(container).await;
+        let digest = self.digest(container, object.as_ref()).await;
+        let parent = self
+            .index
+            .lock()
+            .await
+            .sync_id(container.global_state.parent_id, &self.local_peer);
+        let item = SyncedContainer {
+            id: digest.id,
+            parent,
+            container: container.clone(),
+            object,
+        };
+        let payload = serde_json::to_vec(&item)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        Envelope::seal(kind, payload, digest.version, timestamp, &self.keypair)
+    }
+
+    /// Encoded envelope for a peer fetching `sync_id` in chunks; built on
+    /// the first chunk and dropped after the last
+    pub async fn serve(
+        &self,
+        peer: PeerId,
+        sync_id: SyncId,
+        index: u32,
+    ) -> OzoneResult<super::transfer::Chunk> {
+        let mut serving = self.serving.lock().await;
+        let key = (peer, sync_id);
+        if index == 0 || !serving.contains_key(&key) {
+            let data = self.envelope(&key.1).await?.encode()?;
+            serving.insert(key.clone(), data);
+        }
+        let data = &serving[&key];
+        let chunk = super::transfer::chunk(key.1.id, data, index)
+            .ok_or_else(|| OzoneError::NotFound(format!("Chunk {} of {}", index, key.1)))?;
+        if chunk.index + 1 == chunk.total {
+            serving.remove(&key);
+        }
+        Ok(chunk)
+    }
+
+    /// Whether a container falls under the sync roots
+    async fn in_scope(&self, container: &Container) -> bool {
+        if self.roots.is_empty() || self.roots.contains(&container.global_state.container_id) {
+            return true;
+        }
+        let zsei = self.zsei.read().await;
+        let mut current = container.global_state.parent_id;
+        for _ in 0..64 {
+            if self.roots.contains(&current) {
+                return true;
+            }
+            if current == ROOT_CONTAINER_ID {
+                return false;
+            }
+            let Ok(Some(parent)) = zsei.get_container(current).await else {
+                return false;
+            };
+            let parent_type = parent.local_state.metadata.container_type;
+            if !is_shareable(parent_type) && parent_type != ContainerType::Root {
+                return false;
+            }
+            current = parent.global_state.parent_id;
+        }
+        false
+    }
+
+    /// Apply a container received from `author` with its version vector
+    pub async fn apply(
+        &self,
+        author: &PeerId,
+        item: SyncedContainer,
+        version: VersionVector,
+    ) -> InboundOutcome {
+        let SyncedContainer {
+            id: sync_id,
+            parent,
+            container: mut incoming,
+            object,
+        } = item;
+        let (local_id, parent_id) = {
+            let index = self.index.lock().await;
+            (
+                index.local_id(&sync_id, &self.local_peer),
+                index.local_id(&parent, &self.local_peer),
+            )
+        };
+        let Some(parent_id) = parent_id else {
+            return InboundOutcome::Ignored(format!(
+                "Parent {} of container {} is not synced yet",
+                parent, sync_id
+            ));
+        };
+        let existing = match local_id {
+            Some(id) => match self.zsei.read().await.get_container(id).await {
+                Ok(existing) => existing,
+                Err(e) => return InboundOutcome::Ignored(format!("Lookup failed: {}", e)),
+            },
+            None => None,
+        };
+
+        // Give the container its local identity: the ID mapped from its
+        // `SyncId` (0 until one is allocated), the local parent and
+        // children, no owner and only a local object path
+        let children = existing
+            .as_ref()
+            .map(|e| e.global_state.child_ids.clone())
+            .unwrap_or_default();
+        incoming.global_state.container_id = match &existing {
+            Some(existing) => existing.global_state.container_id,
+            None if sync_id.is_reserved() => sync_id.id,
+            None => 0,
+        };
+        incoming.global_state.parent_id = parent_id;
+        incoming.global_state.child_count = children.len() as u32;
+        incoming.global_state.child_ids = children;
+        incoming.local_state.metadata.owner_id = 0;
+        incoming.local_state.metadata.provenance = format!("peer:{}", author);
+        incoming.local_state.storage.object_store_path = existing
+            .as_ref()
+            .and_then(|e| e.local_state.storage.object_store_path.clone());
+        if !self.in_scope(&incoming).await {
+            return InboundOutcome::Ignored(format!(
+                "Container {} is outside the sync scope",
+                sync_id
+            ));
+        }
+        let Some(existing) = existing else {
+            return self.store(sync_id, incoming, object, version).await;
+        };
+
+        if !is_shared(&existing) {
+            return InboundOutcome::Rejected(format!(
+                "Container {} is not shared here",
+                sync_id
+            ));
+        }
+        let local_type = existing.local_state.metadata.container_type;
+        if local_type != incoming.local_state.metadata.container_type {
+            return InboundOutcome::Rejected(format!(
+                "Container {} is a local {:?}",
+                sync_id, local_type
+            ));
+        }
+
+        let local_object = self.object(&existing).await;
+        let local = self.digest(&existing, local_object.as_ref()).await;
+        match local.version.compare(&version) {
+            Causality::Before => self.store(sync_id, incoming, object, version).await,
+            Causality::Equal | Causality::After => InboundOutcome::Ignored(format!(
+                "Container {} is not newer than the local copy",
+                sync_id
+            )),
+            Causality::Concurrent => {
+                let mut merged = local.version;
+                merged.merge(&version);
+                if local.hash == container_hash(&incoming, object.as_ref()) {
+                    let id = existing.global_state.container_id;
+                    let mut index = self.index.lock().await;
+                    index.set(id, sync_id.clone(), merged, local.hash);
+                    return InboundOutcome::Ignored(format!(
+                        "Container {} already matches",
+                        sync_id
+                    ));
+                }
+
+                let winner = resolve(&existing, &incoming);
+                let from_peer = std::ptr::eq(winner, &incoming);
+                let mut winner = winner.clone();
+                let winner_object = if from_peer { object } else { local_object };
+                let content_hash =
+                    blake3::Hash::from_hex(container_hash(&winner, winner_object.as_ref()))
+                        .map(|hash| *hash.as_bytes())
+                        .unwrap_or_default();
+                winner
+                    .local_state
+                    .integrity
+                    .version_history
+                    .push(VersionRecord {
+                        version: winner.global_state.version as u64,
+                        timestamp: super::now(),
+                        content_hash,
+                        change_type: ChangeType::Merge,
+                        rollback_available: false,
+                    });
+                tracing::info!(
+                    "Merged concurrent changes to container {} (kept the {} copy)",
+                    sync_id,
+                    if from_peer { "peer's" } else { "local" }
+                );
+                self.store(sync_id, winner, winner_object, merged).await
+            }
+        }
+    }
+
+    /// Store a container with its object under its local ID, allocating
+    /// one if it has none, and list it among its parent's children
+    async fn store(
+        &self,
+        sync_id: SyncId,
+        mut container: Container,
+        object: Option<serde_json::Value>,
+        version: VersionVector,
+    ) -> InboundOutcome {
+        let zsei = self.zsei.read().await;
+        if container.global_state.container_id == 0 {
+            let mut id = zsei.allocate_id().await;
+            while id < RESERVED_IDS {
+                id = zsei.allocate_id().await;
+            }
+            container.global_state.container_id = id;
+        }
+        match &object {
+            Some(object) => {
+                if let Err(e) = zsei.store_object(&mut container, object) {
+                    return InboundOutcome::Ignored(format!(
+                        "Store of {} failed: {}",
+                        sync_id, e
+                    ));
+                }
+            }
+            None => container.local_state.storage.object_store_path = None,
+        }
+        let hash = container_hash(&container, object.as_ref());
+        let parent_id = container.global_state.parent_id;
+        let id = match zsei.store_container(container).await {
+            Ok(id) => id,
+            Err(e) => {
+                return InboundOutcome::Ignored(format!("Store of {} failed: {}", sync_id, e))
+            }
+        };
+        if let Ok(Some(mut parent)) = zsei.get_container(parent_id).await {
+            if parent_id != id && !parent.global_state.child_ids.contains(&id) {
+                parent.global_state.child_ids.push(id);
+                parent.global_state.child_count = parent.global_state.child_ids.len() as u32;
+                if let Err(e) = zsei.store_container(parent).await {
+                    tracing::warn!("Cannot list {} under {}: {}", id, parent_id, e);
+                }
+            }
+        }
+        self.index.lock().await.set(id, sync_id, version, hash);
+        InboundOutcome::Stored(id)
+    }
+
+    /// Save the version index
+    pub async fn save(&self) -> OzoneResult<()> {
+        self.index.lock().await.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{GlobalState, LocalState};
+
+    fn vector(counts: &[(&str, u64)]) -> VersionVector {
+        VersionVector(counts.iter().map(|(n, c)| (n.to_string(), *c)).collect())
+    }
+
+    fn sync_id(id: ContainerID) -> SyncId {
+        SyncId::new("peer", id)
+    }
+
+    fn digest(id: ContainerID, counts: &[(&str, u64)], hash: &str) -> Digest {
+        Digest {
+            id: sync_id(id),
+            version: vector(counts),
+            hash: hash.into(),
+        }
+    }
+
+    #[test]
+    fn test_version_vector_order() {
+        let a = vector(&[("a", 2), ("b", 1)]);
+        assert_eq!(a.compare(&a.clone()), Causality::Equal);
+        assert_eq!(vector(&[("a", 1)]).compare(&a), Causality::Before);
+        assert_eq!(a.compare(&vector(&[("a", 1)])), Causality::After);
+
+        let b = vector(&[("a", 1), ("b", 2)]);
+        assert_eq!(a.compare(&b), Causality::Concurrent);
+        let mut merged = a.clone();
+        merged.merge(&b);
+        assert_eq!(merged, vector(&[("a", 2), ("b", 2)]));
+        assert_eq!(merged.compare(&a), Causality::After);
+    }
+
+    #[test]
+    fn test_plan_exchanges_only_changes() {
+        let local = vec![
+            digest(101, &[("a", 1)], "x"),
+            digest(102, &[("a", 2)], "y"),
+            digest(103, &[("a", 1)], "z"),
+            digest(104, &[("a", 1)], "p"),
+            digest(105, &[("a", 2), ("b", 1)], "q"),
+        ];
+        let remote = vec![
+            digest(101, &[("a", 1)], "x"),
+            digest(102, &[("a", 1)], "w"),
+            digest(103, &[("a", 1), ("b", 1)], "v"),
+            digest(105, &[("a", 1), ("b", 2)], "r"),
+            digest(106, &[("b", 1)], "s"),
+        ];
+        let plan = plan(&local, &remote);
+        assert_eq!(plan.push, [102, 104, 105].map(sync_id));
+        assert_eq!(plan.pull, [103, 105, 106].map(sync_id));
+    }
+
+    #[test]
+    fn test_conflicts_resolve_the_same_way_everywhere() {
+        let mut a = Container {
+            global_state: GlobalState::default(),
+            local_state: LocalState::default(),
+        };
+        a.local_state.metadata.updated_at = 10;
+        let mut b = a.clone();
+        b.local_state.metadata.name = Some("edited".into());
+
+        let winner = container_hash(resolve(&a, &b), None);
+        assert_eq!(winner, container_hash(resolve(&b, &a), None));
+
+        b.local_state.metadata.updated_at = 11;
+        assert_eq!(resolve(&a, &b).local_state.metadata.updated_at, 11);
+
+        // Local-only fields do not count as changes, the object does
+        let mut c = a.clone();
+        c.local_state.metadata.provenance = "peer:x".into();
+        c.local_state.hints.access_frequency = 9;
+        c.global_state.container_id = 100_001;
+        c.global_state.parent_id = 3;
+        c.local_state.metadata.owner_id = 7;
+        c.local_state.storage.object_store_path = Some("objects/100001.json".into());
+        assert_eq!(container_hash(&a, None), container_hash(&c, None));
+        let object = serde_json::json!({ "steps": [] });
+        assert_ne!(container_hash(&a, None), container_hash(&a, Some(&object)));
+    }
+
+    #[test]
+    fn test_index_maps_sync_ids_to_local_ids() {
+        let mut index = SyncIndex::default();
+        // Reserved IDs are the same everywhere
+        assert_eq!(index.sync_id(10, "me"), SyncId::new("other", 10));
+        assert_eq!(index.local_id(&SyncId::new("other", 10), "me"), Some(10));
+
+        // A container created here is named after this node
+        assert_eq!(index.sync_id(40001, "me"), SyncId::new("me", 40001));
+        assert_eq!(index.local_id(&SyncId::new("me", 40001), "me"), Some(40001));
+
+        // The same ID from another node is a different container
+        let theirs = SyncId::new("other", 40001);
+        assert_eq!(index.local_id(&theirs, "me"), None);
+        index.set(100_005, theirs.clone(), VersionVector::default(), "h".into());
+        assert_eq!(index.local_id(&theirs, "me"), Some(100_005));
+        assert_eq!(index.sync_id(100_005, "me"), theirs);
+
+        // A local ID taken by another node's container is not ours
+        index.set(40002, SyncId::new("other", 9000), VersionVector::default(), "h".into());
+        assert_eq!(index.local_id(&SyncId::new("me", 40002), "me"), None);
+    }
+}
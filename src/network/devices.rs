@@ -0,0 +1,720 @@
+//! Multi-device offloading
+//!
+//! Devices of one account list each other's peer IDs in `account_peers`.
+//! Each advertises its `DeviceRegistration` (the resources it contributes
+//! and its capabilities) with its current load to the others over the
+//! transfer protocol. When a pipeline in `offload_pipelines` runs, the
+//! account devices that meet its `Requirements` and are less loaded than
+//! this one are tried in order of load; the first to accept runs it,
+//! streams its progress back and returns the output. The pipeline registry
+//! runs it locally when no device accepts, the device fails or it does not
+//! finish within `offload_timeout_secs`. Peers outside the account can
+//! neither advertise nor offload.
+
+use super::protocol::hex_bytes;
+use super::swarm::SwarmCommand;
+use super::transfer::{TransferRequest, TransferResponse};
+use super::NetworkManager;
+use crate::config::DevicesConfig;
+use crate::pipeline::{Offloader, PipelineProgress, PipelineRegistry};
+use crate::types::auth::{DeviceRegistration, DeviceStatus, ResourceAllocation};
+use crate::types::pipeline::{ExecutionID, PipelineBlueprint, PipelineInput, PipelineOutput};
+use crate::types::{OzoneError, OzoneResult, TaskID};
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+
+/// Adverts older than this are ignored
+const ADVERT_TTL_SECS: u64 = 300;
+
+/// How often a device running an offloaded pipeline reports progress
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// A device's capacity and load
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceAdvert {
+    pub device: DeviceRegistration,
+    /// Pipelines running on the device
+    pub running: u32,
+    /// Tasks waiting in its queue
+    pub queued: u32,
+    /// Offloaded runs it accepts at once
+    pub max_offloaded: u32,
+    /// Offloaded runs it is running
+    pub offloaded: u32,
+    pub timestamp: u64,
+}
+
+impl DeviceAdvert {
+    /// Work per contributed core; lower is better
+    pub fn load(&self) -> f32 {
+        (self.running + self.queued) as f32
+            / self.device.resource_contribution.cpu_cores.max(1) as f32
+    }
+}
+
+/// What a pipeline run needs from the device it runs on
+#[derive(Debug, Clone, Default)]
+pub struct Requirements {
+    pub resources: ResourceAllocation,
+    pub capabilities: Vec<String>,
+}
+
+impl Requirements {
+    /// The non-optional runtime requirements of the executable
+    /// implementation as capabilities, and minimum resources from the
+    /// `cpu_cores`, `memory_gb` and `gpu` entries of the context metadata
+    pub fn of(blueprint: &PipelineBlueprint, input: &PipelineInput) -> Self {
+        let capabilities = blueprint
+            .implementations
+            .iter()
+            .filter(|implementation| implementation.executable)
+            .flat_map(|implementation| &implementation.runtime_requirements)
+            .filter(|dependency| !dependency.optional)
+            .map(|dependency| dependency.name.clone())
+            .collect();
+        let metadata = &input.context.metadata;
+        let number = |key: &str| metadata.get(key).and_then(|value| value.parse().ok());
+        Self {
+            resources: ResourceAllocation {
+                cpu_cores: number("cpu_cores").unwrap_or(0.0) as u8,
+                memory_gb: number("memory_gb").unwrap_or(0.0),
+                gpu_available: metadata.get("gpu").is_some_and(|value| value == "true"),
+                ..Default::default()
+            },
+            capabilities,
+        }
+    }
+
+    pub fn met_by(&self, device: &DeviceRegistration) -> bool {
+        let offered = &device.resource_contribution;
+        offered.cpu_cores >= self.resources.cpu_cores
+            && offered.memory_gb >= self.resources.memory_gb
+            && (offered.gpu_available || !self.resources.gpu_available)
+            && self
+                .capabilities
+                .iter()
+                .all(|capability| device.capabilities.contains(capability))
+    }
+}
+
+/// A pipeline run sent to another device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OffloadRequest {
+    pub offload_id: u64,
+    pub task_id: Option<TaskID>,
+    /// JSON-encoded `PipelineBlueprint`, registered by devices that do not
+    /// know the pipeline
+    #[serde(with = "hex_bytes")]
+    pub blueprint: Vec<u8>,
+    /// JSON-encoded `PipelineInput`
+    #[serde(with = "hex_bytes")]
+    pub input: Vec<u8>,
+}
+
+/// A run this device offloaded, awaiting its result
+struct Pending {
+    peer: PeerId,
+    progress: mpsc::UnboundedSender<PipelineProgress>,
+    result: oneshot::Sender<OzoneResult<PipelineOutput>>,
+}
+
+/// This device's advert, the adverts of the account's other devices and
+/// the runs offloaded to them
+pub struct Devices {
+    config: DevicesConfig,
+    account: Vec<PeerId>,
+    local: Mutex<DeviceAdvert>,
+    adverts: Mutex<HashMap<PeerId, DeviceAdvert>>,
+    pending: Mutex<HashMap<u64, Pending>>,
+}
+
+impl Devices {
+    pub fn new(config: DevicesConfig, keypair: &Keypair, now: u64) -> Self {
+        let peer_id = keypair.public().to_peer_id();
+        let account = config
+            .account_peers
+            .iter()
+            .filter_map(|peer| match peer.parse() {
+                Ok(peer_id) => Some(peer_id),
+                Err(e) => {
+                    tracing::warn!("Ignoring account peer {}: {}", peer, e);
+                    None
+                }
+            })
+            .collect();
+        let device_name = if config.device_name.is_empty() {
+            peer_id.to_string()
+        } else {
+            config.device_name.clone()
+        };
+        let device = DeviceRegistration {
+            device_id: device_id(&peer_id),
+            device_name,
+            public_key: keypair.public().encode_protobuf(),
+            registered_at: now,
+            last_seen: now,
+            status: DeviceStatus::Online,
+            resource_contribution: config.resource_contribution.clone(),
+            capabilities: config.capabilities.clone(),
+            ..Default::default()
+        };
+        let local = DeviceAdvert {
+            device,
+            running: 0,
+            queued: 0,
+            max_offloaded: config.max_offloaded,
+            offloaded: 0,
+            timestamp: now,
+        };
+        Self {
+            config,
+            account,
+            local: Mutex::new(local),
+            adverts: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &DevicesConfig {
+        &self.config
+    }
+
+    /// The account's other devices
+    pub fn account(&self) -> &[PeerId] {
+        &self.account
+    }
+
+    /// This device's advert with its current load
+    pub async fn update_load(&self, running: u32, queued: u32, now: u64) -> DeviceAdvert {
+        let mut local = self.local.lock().await;
+        local.running = running;
+        local.queued = queued;
+        local.timestamp = now;
+        local.device.last_seen = now;
+        local.clone()
+    }
+
+    pub async fn local(&self) -> DeviceAdvert {
+        self.local.lock().await.clone()
+    }
+
+    /// This device first, then the account's other devices
+    pub async fn devices(&self) -> Vec<DeviceAdvert> {
+        let mut devices = vec![self.local().await];
+        devices.extend(self.adverts.lock().await.values().cloned());
+        devices
+    }
+
+    /// Store the advert of an account device
+    pub async fn observe(
+        &self,
+        peer: &PeerId,
+        advert: DeviceAdvert,
+        now: u64,
+    ) -> Result<(), String> {
+        if !self.account.contains(peer) {
+            return Err("Not a device of this account".into());
+        }
+        let key = PublicKey::try_decode_protobuf(&advert.device.public_key)
+            .map_err(|e| format!("Invalid device key: {}", e))?;
+        if key.to_peer_id() != *peer {
+            return Err("Device key does not match the sender".into());
+        }
+        let mut advert = advert;
+        advert.device.last_seen = now;
+        advert.timestamp = now;
+        self.adverts.lock().await.insert(*peer, advert);
+        Ok(())
+    }
+
+    /// Account devices to offload a run to, least loaded first; empty when
+    /// this device suits the run at least as well as any of them
+    pub async fn candidates(&self, requirements: &Requirements, now: u64) -> Vec<PeerId> {
+        let local = self.local().await;
+        let local_load = if requirements.met_by(&local.device) {
+            local.load()
+        } else {
+            f32::INFINITY
+        };
+        let adverts = self.adverts.lock().await;
+        let mut candidates: Vec<(&PeerId, f32)> = adverts
+            .iter()
+            .filter(|(_, advert)| {
+                now.saturating_sub(advert.timestamp) <= ADVERT_TTL_SECS
+                    && advert.device.status == DeviceStatus::Online
+                    && advert.offloaded < advert.max_offloaded
+                    && requirements.met_by(&advert.device)
+            })
+            .map(|(peer, advert)| (peer, advert.load()))
+            .filter(|(_, load)| *load < local_load)
+            .collect();
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+        candidates.into_iter().map(|(peer, _)| *peer).collect()
+    }
+
+    /// Count a run sent to a device until its next advert
+    pub async fn assigned(&self, peer: &PeerId) {
+        if let Some(advert) = self.adverts.lock().await.get_mut(peer) {
+            advert.running += 1;
+            advert.offloaded += 1;
+        }
+    }
+
+    /// Accept a run from an account device if there is room
+    pub async fn admit(&self, peer: &PeerId) -> Result<(), String> {
+        if !self.config.enable_offload || !self.account.contains(peer) {
+            return Err("This device does not run pipelines for the sender".into());
+        }
+        let mut local = self.local.lock().await;
+        if local.offloaded >= local.max_offloaded {
+            return Err(format!(
+                "Already running {} offloaded pipelines",
+                local.offloaded
+            ));
+        }
+        local.offloaded += 1;
+        Ok(())
+    }
+
+    /// An accepted run finished
+    pub async fn release(&self) {
+        let mut local = self.local.lock().await;
+        local.offloaded = local.offloaded.saturating_sub(1);
+    }
+
+    /// Wait for the result of a run offloaded to `peer`
+    pub async fn expect(
+        &self,
+        offload_id: u64,
+        peer: PeerId,
+        progress: mpsc::UnboundedSender<PipelineProgress>,
+    ) -> oneshot::Receiver<OzoneResult<PipelineOutput>> {
+        let (result, receiver) = oneshot::channel();
+        self.pending.lock().await.insert(
+            offload_id,
+            Pending {
+                peer,
+                progress,
+                result,
+            },
+        );
+        receiver
+    }
+
+    pub async fn forget(&self, offload_id: u64) {
+        self.pending.lock().await.remove(&offload_id);
+    }
+
+    /// Pass on progress reported by the device running an offloaded run
+    pub async fn progress(
+        &self,
+        peer: &PeerId,
+        offload_id: u64,
+        progress: PipelineProgress,
+    ) -> Result<(), String> {
+        match self.pending.lock().await.get(&offload_id) {
+            Some(pending) if pending.peer == *peer => {
+                let _ = pending.progress.send(progress);
+                Ok(())
+            }
+            _ => Err(format!("No run {} offloaded to the sender", offload_id)),
+        }
+    }
+
+    /// Hand over the result of an offloaded run
+    pub async fn complete(
+        &self,
+        peer: &PeerId,
+        offload_id: u64,
+        result: OzoneResult<PipelineOutput>,
+    ) -> Result<(), String> {
+        let mut pending = self.pending.lock().await;
+        match pending.get(&offload_id) {
+            Some(run) if run.peer == *peer => {}
+            _ => return Err(format!("No run {} offloaded to the sender", offload_id)),
+        }
+        if let Some(run) = pending.remove(&offload_id) {
+            let _ = run.result.send(result);
+        }
+        Ok(())
+    }
+
+    /// Fail the runs offloaded to a device that disconnected
+    pub async fn peer_lost(&self, peer: &PeerId) {
+        self.adverts.lock().await.remove(peer);
+        let mut pending = self.pending.lock().await;
+        let lost: Vec<u64> = pending
+            .iter()
+            .filter(|(_, run)| run.peer == *peer)
+            .map(|(offload_id, _)| *offload_id)
+            .collect();
+        for offload_id in lost {
+            if let Some(run) = pending.remove(&offload_id) {
+                let _ = run.result.send(Err(OzoneError::NetworkError(format!(
+                    "Device {} disconnected",
+                    peer
+                ))));
+            }
+        }
+    }
+}
+
+/// Stable device ID derived from the node's peer ID
+fn device_id(peer_id: &PeerId) -> u64 {
+    let hash = blake3::hash(&peer_id.to_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.as_bytes()[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Run pipelines offloaded to this device, each in its own task
+pub(super) async fn serve(
+    mut runs: mpsc::Receiver<(PeerId, OffloadRequest)>,
+    pipelines: Arc<RwLock<PipelineRegistry>>,
+    commands: mpsc::Sender<SwarmCommand>,
+    devices: Arc<Devices>,
+) {
+    while let Some((origin, request)) = runs.recv().await {
+        let pipelines = pipelines.clone();
+        let commands = commands.clone();
+        let devices = devices.clone();
+        tokio::spawn(async move {
+            let offload_id = request.offload_id;
+            let result = run(&origin, request, &pipelines, &commands).await;
+            devices.release().await;
+            let (output, error) = match result.and_then(|output| {
+                serde_json::to_vec(&output)
+                    .map_err(|e| OzoneError::SerializationError(e.to_string()))
+            }) {
+                Ok(output) => (output, None),
+                Err(e) => (Vec::new(), Some(e.to_string())),
+            };
+            let report = TransferRequest::OffloadResult {
+                offload_id,
+                output,
+                error,
+            };
+            if let Err(e) = send(&commands, origin, report).await {
+                tracing::warn!(
+                    "Result of run {} not returned to {}: {}",
+                    offload_id,
+                    origin,
+                    e
+                );
+            }
+        });
+    }
+}
+
+/// Run one offloaded pipeline, reporting its progress to `origin`
+async fn run(
+    origin: &PeerId,
+    request: OffloadRequest,
+    pipelines: &RwLock<PipelineRegistry>,
+    commands: &mpsc::Sender<SwarmCommand>,
+) -> OzoneResult<PipelineOutput> {
+    let decode = |e: serde_json::Error| OzoneError::SerializationError(e.to_string());
+    let blueprint: PipelineBlueprint =
+        serde_json::from_slice(&request.blueprint).map_err(decode)?;
+    let input: PipelineInput = serde_json::from_slice(&request.input).map_err(decode)?;
+    tracing::info!("Running pipeline {} for {}", blueprint.name, origin);
+
+    let execution_id = ExecutionID::new();
+    let registry = pipelines.read().await;
+    let execution = registry.execute_offloaded(execution_id, blueprint, input, request.task_id);
+    tokio::pin!(execution);
+    let mut tick = tokio::time::interval(PROGRESS_INTERVAL);
+    let mut reported = None;
+    let result = loop {
+        tokio::select! {
+            result = &mut execution => break result,
+            _ = tick.tick() => {
+                reported = report(origin, request.offload_id, &registry, execution_id, reported, commands).await;
+            }
+        }
+    };
+    report(
+        origin,
+        request.offload_id,
+        &registry,
+        execution_id,
+        reported,
+        commands,
+    )
+    .await;
+    result
+}
+
+/// Send the run's progress to `origin` if it changed since `reported`;
+/// returns what was last reported
+async fn report(
+    origin: &PeerId,
+    offload_id: u64,
+    registry: &PipelineRegistry,
+    execution_id: ExecutionID,
+    reported: Option<String>,
+    commands: &mpsc::Sender<SwarmCommand>,
+) -> Option<String> {
+    let progress = registry
+        .executor()
+        .get_progress(&execution_id.as_str())
+        .await?;
+    let state = format!("{:?} {}", progress.status, progress.progress_percent);
+    if reported.as_ref() == Some(&state) {
+        return reported;
+    }
+    let request = TransferRequest::OffloadProgress {
+        offload_id,
+        progress,
+    };
+    match send(commands, *origin, request).await {
+        Ok(_) => Some(state),
+        Err(e) => {
+            tracing::debug!("Progress of run {} not sent: {}", offload_id, e);
+            reported
+        }
+    }
+}
+
+/// Send a transfer request through the swarm driver
+async fn send(
+    commands: &mpsc::Sender<SwarmCommand>,
+    peer_id: PeerId,
+    request: TransferRequest,
+) -> OzoneResult<TransferResponse> {
+    let not_running = || OzoneError::NetworkError("P2P networking is not running".into());
+    let (reply, response) = oneshot::channel();
+    commands
+        .send(SwarmCommand::Send {
+            peer_id,
+            request,
+            reply,
+        })
+        .await
+        .map_err(|_| not_running())?;
+    response.await.map_err(|_| not_running())?
+}
+
+#[async_trait::async_trait]
+impl Offloader for RwLock<NetworkManager> {
+    async fn offload(
+        &self,
+        blueprint: &PipelineBlueprint,
+        input: &PipelineInput,
+        task_id: Option<TaskID>,
+        progress: mpsc::UnboundedSender<PipelineProgress>,
+    ) -> Option<OzoneResult<PipelineOutput>> {
+        self.read()
+            .await
+            .offload(blueprint, input, task_id, progress)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::pipeline::{
+        BlueprintSpec, ConsensusStatus, Dependency, ExecutionContext, ExecutionFlow,
+        Implementation, Language, Schema,
+    };
+
+    fn advert(keypair: &Keypair, cores: u8, running: u32, gpu: bool) -> DeviceAdvert {
+        let config = DevicesConfig {
+            resource_contribution: ResourceAllocation {
+                cpu_cores: cores,
+                gpu_available: gpu,
+                ..Default::default()
+            },
+            capabilities: vec!["python3".into()],
+            max_offloaded: 1,
+            ..Default::default()
+        };
+        let mut advert = Devices::new(config, keypair, 1000).local.into_inner();
+        advert.running = running;
+        advert
+    }
+
+    fn blueprint(requirement: &str) -> PipelineBlueprint {
+        PipelineBlueprint {
+            pipeline_id: 100,
+            name: "render".into(),
+            version: Default::default(),
+            author: Vec::new(),
+            description: String::new(),
+            specification: BlueprintSpec {
+                input_schema: Schema::default(),
+                output_schema: Schema::default(),
+                dependencies: Vec::new(),
+                sub_pipelines: Vec::new(),
+                execution_flow: ExecutionFlow::Sequential(Vec::new()),
+            },
+            implementations: vec![Implementation {
+                language: Language::Python,
+                runtime_requirements: vec![Dependency {
+                    name: requirement.into(),
+                    version: String::new(),
+                    optional: false,
+                }],
+                code_location: crate::types::pipeline::CodePointer {
+                    hash: [0; 32],
+                    size: 0,
+                    chunks: Vec::new(),
+                    mirrors: Vec::new(),
+                },
+                executable: true,
+            }],
+            content_hash: [0; 32],
+            peers: Vec::new(),
+            consensus_status: ConsensusStatus::Accepted,
+            verified_by: 0,
+        }
+    }
+
+    fn input(gpu: bool) -> PipelineInput {
+        let mut metadata = HashMap::new();
+        if gpu {
+            metadata.insert("gpu".to_string(), "true".to_string());
+        }
+        PipelineInput {
+            data: HashMap::new(),
+            context: ExecutionContext {
+                user_id: 1,
+                device_id: 1,
+                workspace_id: None,
+                project_id: None,
+                task_context_id: None,
+                metadata,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_candidates_follow_requirements_and_load() {
+        let (idle, busy, gpu, stranger) = (
+            Keypair::generate_ed25519(),
+            Keypair::generate_ed25519(),
+            Keypair::generate_ed25519(),
+            Keypair::generate_ed25519(),
+        );
+        let (idle_id, busy_id, gpu_id) = (
+            idle.public().to_peer_id(),
+            busy.public().to_peer_id(),
+            gpu.public().to_peer_id(),
+        );
+        let config = DevicesConfig {
+            enable_offload: true,
+            account_peers: vec![idle_id.to_string(), busy_id.to_string(), gpu_id.to_string()],
+            resource_contribution: ResourceAllocation {
+                cpu_cores: 4,
+                ..Default::default()
+            },
+            capabilities: vec!["python3".into()],
+            max_offloaded: 1,
+            ..Default::default()
+        };
+        let devices = Devices::new(config, &Keypair::generate_ed25519(), 1000);
+        devices.update_load(4, 0, 1000).await;
+
+        devices
+            .observe(&idle_id, advert(&idle, 8, 0, false), 1000)
+            .await
+            .unwrap();
+        devices
+            .observe(&busy_id, advert(&busy, 2, 4, false), 1000)
+            .await
+            .unwrap();
+        devices
+            .observe(&gpu_id, advert(&gpu, 8, 2, true), 1000)
+            .await
+            .unwrap();
+        // Only account devices advertising their own key are kept
+        let stranger_id = stranger.public().to_peer_id();
+        assert!(devices
+            .observe(&stranger_id, advert(&stranger, 64, 0, true), 1000)
+            .await
+            .is_err());
+        assert!(devices
+            .observe(&idle_id, advert(&gpu, 8, 0, true), 1000)
+            .await
+            .is_err());
+
+        // Less loaded devices first; the busier one is no better than here
+        let requirements = Requirements::of(&blueprint("python3"), &input(false));
+        assert_eq!(
+            devices.candidates(&requirements, 1000).await,
+            vec![idle_id, gpu_id]
+        );
+
+        // Only the GPU device can run a GPU pipeline, though this device is idle
+        devices.update_load(0, 0, 1000).await;
+        let requirements = Requirements::of(&blueprint("python3"), &input(true));
+        assert_eq!(devices.candidates(&requirements, 1000).await, vec![gpu_id]);
+
+        // Missing capabilities, full devices and stale adverts are skipped
+        let requirements = Requirements::of(&blueprint("cuda"), &input(false));
+        assert!(devices.candidates(&requirements, 1000).await.is_empty());
+        devices.update_load(4, 0, 1000).await;
+        devices.assigned(&idle_id).await;
+        let requirements = Requirements::of(&blueprint("python3"), &input(false));
+        assert_eq!(devices.candidates(&requirements, 1000).await, vec![gpu_id]);
+        assert!(devices
+            .candidates(&requirements, 1001 + ADVERT_TTL_SECS)
+            .await
+            .is_empty());
+
+        // Runs from the account are admitted up to `max_offloaded`
+        assert!(devices.admit(&stranger_id).await.is_err());
+        devices.admit(&idle_id).await.unwrap();
+        assert!(devices.admit(&busy_id).await.is_err());
+        devices.release().await;
+        devices.admit(&busy_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_results_only_accepted_from_the_device_running_them() {
+        let peer = PeerId::random();
+        let devices = Devices::new(DevicesConfig::default(), &Keypair::generate_ed25519(), 0);
+        let (progress, mut updates) = mpsc::unbounded_channel();
+        let result = devices.expect(7, peer, progress).await;
+
+        let output = PipelineOutput {
+            data: HashMap::new(),
+            execution_id: ExecutionID::new(),
+            task_id: None,
+            success: true,
+            error: None,
+        };
+        assert!(devices
+            .complete(&PeerId::random(), 7, Ok(output.clone()))
+            .await
+            .is_err());
+        let progress = PipelineProgress {
+            execution_id: "remote".into(),
+            pipeline_id: 100,
+            pipeline_name: "render".into(),
+            task_id: None,
+            step_index: None,
+            status: crate::pipeline::ProgressStatus::Running,
+            progress_percent: 0,
+            started_at: 0,
+            completed_at: None,
+            tokens_used: None,
+            error: None,
+        };
+        devices.progress(&peer, 7, progress).await.unwrap();
+        assert_eq!(updates.recv().await.unwrap().execution_id, "remote");
+        devices.complete(&peer, 7, Ok(output)).await.unwrap();
+        assert!(result.await.unwrap().unwrap().success);
+
+        // A disconnect fails what is still running there
+        let (progress, _updates) = mpsc::unbounded_channel();
+        let result = devices.expect(8, peer, progress).await;
+        devices.peer_lost(&peer).await;
+        assert!(result.await.unwrap().is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Fault injection for testing the P2P layer
+//!
+//! Every `NetworkManager` shares a `Faults` with its swarm driver, which
+//! consults it for each inbound gossipsub message and transfer request.
+//! Messages from a blocked peer or lost to the configured loss rate are
+//! dropped: gossip is not forwarded and transfer requests get no response,
+//! so the sender sees a failed request. The rest are delayed by the
+//! configured latency before they are handled, on a timer so the swarm
+//! driver keeps handling other traffic meanwhile. Connections from blocked
+//! peers are closed, which partitions the node from them. Nothing is
+//! injected unless a test sets it (see `harness`).
+
+use libp2p::PeerId;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+#[derive(Debug, Default)]
+struct FaultState {
+    latency: Duration,
+    /// Share of inbound messages dropped, 0.0 to 1.0
+    loss: f64,
+    blocked: HashSet<PeerId>,
+}
+
+/// Faults injected into one node's inbound traffic
+#[derive(Debug, Default)]
+pub struct Faults {
+    state: RwLock<FaultState>,
+}
+
+impl Faults {
+    /// Delay every inbound message by `latency`
+    pub async fn set_latency(&self, latency: Duration) {
+        self.state.write().await.latency = latency;
+    }
+
+    /// Drop this share of inbound messages
+    pub async fn set_loss(&self, loss: f64) {
+        self.state.write().await.loss = loss.clamp(0.0, 1.0);
+    }
+
+    /// Refuse connections and messages from a peer
+    pub async fn block(&self, peer_id: PeerId) {
+        self.state.write().await.blocked.insert(peer_id);
+    }
+
+    pub async fn unblock(&self, peer_id: &PeerId) {
+        self.state.write().await.blocked.remove(peer_id);
+    }
+
+    pub async fn is_blocked(&self, peer_id: &PeerId) -> bool {
+        self.state.read().await.blocked.contains(peer_id)
+    }
+
+    /// Remove every injected fault
+    pub async fn clear(&self) {
+        *self.state.write().await = FaultState::default();
+    }
+
+    /// How long to hold a message from `peer_id` before handling it;
+    /// `None` drops it
+    pub(super) async fn admit(&self, peer_id: &PeerId) -> Option<Duration> {
+        let state = self.state.read().await;
+        if state.blocked.contains(peer_id) {
+            return None;
+        }
+        if state.loss > 0.0 && rand::random::<f64>() < state.loss {
+            return None;
+        }
+        Some(state.latency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_block_loss_and_latency() {
+        let faults = Faults::default();
+        let peer = PeerId::random();
+        let other = PeerId::random();
+        assert_eq!(faults.admit(&peer).await, Some(Duration::ZERO));
+
+        faults.set_latency(Duration::from_millis(20)).await;
+        faults.block(peer).await;
+        assert_eq!(faults.admit(&peer).await, None);
+        assert_eq!(faults.admit(&other).await, Some(Duration::from_millis(20)));
+
+        faults.set_loss(2.0).await;
+        assert_eq!(faults.admit(&other).await, None);
+
+        faults.clear().await;
+        assert!(!faults.is_blocked(&peer).await);
+        assert_eq!(faults.admit(&peer).await, Some(Duration::ZERO));
+    }
+}
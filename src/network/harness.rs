@@ -0,0 +1,503 @@
+//! In-process multi-node harness for testing sync and consensus
+//!
+//! `Cluster::start` runs N `NetworkManager`s on loopback, each with its own
+//! data directory and ZSEI, and `connect` links every pair of nodes. Tests
+//! inject latency, message loss and partitions through each node's
+//! `Faults`, then wait for the nodes' shared ZSEI state to converge under
+//! anti-entropy or for all of them to settle a proposal the same way.
+//! Compiled for unit tests and with the `test-harness` feature.
+
+use super::delta::Digest;
+use super::swarm::SwarmCommand;
+use super::NetworkManager;
+use crate::config::{NetworkConfig, ZSEIConfig};
+use crate::types::{ConsensusStatus, OzoneError, OzoneResult};
+use crate::zsei::ZSEI;
+use libp2p::multiaddr::Protocol;
+use libp2p::PeerId;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// How often waits re-check the cluster
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait for a node to listen or for peers to connect
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time for gossipsub to exchange subscriptions after connecting
+const GOSSIP_SETTLE: Duration = Duration::from_secs(1);
+
+/// One node of a cluster
+pub struct Node {
+    pub network: NetworkManager,
+    pub zsei: Arc<RwLock<ZSEI>>,
+    pub peer_id: PeerId,
+    /// Loopback address with the peer ID, for dialing the node
+    pub address: String,
+}
+
+/// Nodes running in this process
+pub struct Cluster {
+    pub nodes: Vec<Node>,
+    dir: PathBuf,
+}
+
+impl Cluster {
+    /// Start `size` nodes under `<temp>/ozone_cluster_<name>`, removing
+    /// what an earlier run left there; `configure` adjusts each node's
+    /// network config before it starts
+    pub async fn start(
+        name: &str,
+        size: usize,
+        configure: impl Fn(usize, &mut NetworkConfig),
+    ) -> OzoneResult<Self> {
+        let dir = std::env::temp_dir().join(format!("ozone_cluster_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut nodes = Vec::with_capacity(size);
+        for index in 0..size {
+            let node_dir = dir.join(format!("node{}", index));
+            std::fs::create_dir_all(&node_dir)?;
+            let zsei = Arc::new(RwLock::new(ZSEI::new(&zsei_config(&node_dir))?));
+
+            let mut config = NetworkConfig {
+                enable_p2p: true,
+                p2p_port: 0,
+                enable_mdns: false,
+                data_path: node_dir.join("network").to_string_lossy().into_owned(),
+                ..Default::default()
+            };
+            configure(index, &mut config);
+
+            let mut network = NetworkManager::new(config).await?;
+            network.set_zsei(zsei.clone());
+            network.initialize().await?;
+            let peer_id = network.local_peer_id;
+            let address = loopback_address(&network).await?;
+            nodes.push(Node {
+                network,
+                zsei,
+                peer_id,
+                address,
+            });
+        }
+
+        Ok(Self { nodes, dir })
+    }
+
+    /// Where the nodes keep their data
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Indices of every node
+    pub fn all(&self) -> Vec<usize> {
+        (0..self.nodes.len()).collect()
+    }
+
+    /// Dial every pair of nodes not partitioned from each other and wait
+    /// until they are connected
+    pub async fn connect(&mut self) -> OzoneResult<()> {
+        for i in 0..self.nodes.len() {
+            for j in i + 1..self.nodes.len() {
+                if self.is_linked(i, j).await && !self.is_connected(i, j).await {
+                    let address = self.nodes[j].address.clone();
+                    self.nodes[i].network.connect_peer(&address).await?;
+                }
+            }
+        }
+
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        loop {
+            let mut missing = None;
+            'pairs: for i in 0..self.nodes.len() {
+                for j in i + 1..self.nodes.len() {
+                    if self.is_linked(i, j).await
+                        && !(self.is_connected(i, j).await && self.is_connected(j, i).await)
+                    {
+                        missing = Some((i, j));
+                        break 'pairs;
+                    }
+                }
+            }
+            match missing {
+                None => break,
+                Some((i, j)) if Instant::now() >= deadline => {
+                    return Err(OzoneError::NetworkError(format!(
+                        "Nodes {} and {} did not connect",
+                        i, j
+                    )));
+                }
+                Some(_) => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        }
+        tokio::time::sleep(GOSSIP_SETTLE).await;
+        Ok(())
+    }
+
+    /// Split the cluster: nodes in different groups block and disconnect
+    /// each other, and nodes in no group are cut off from all others
+    pub async fn partition(&self, groups: &[&[usize]]) {
+        let group_of = |node: usize| {
+            groups
+                .iter()
+                .position(|group| group.contains(&node))
+                .unwrap_or(groups.len() + node)
+        };
+        for i in 0..self.nodes.len() {
+            for j in 0..self.nodes.len() {
+                if i == j || group_of(i) == group_of(j) {
+                    continue;
+                }
+                let peer_id = self.nodes[j].peer_id;
+                self.nodes[i].network.faults.block(peer_id).await;
+                let _ = self.nodes[i]
+                    .network
+                    .command(|reply| SwarmCommand::Disconnect { peer_id, reply })
+                    .await;
+            }
+        }
+    }
+
+    /// Lift all partitions and reconnect the nodes
+    pub async fn heal(&mut self) -> OzoneResult<()> {
+        for node in &self.nodes {
+            for peer in &self.nodes {
+                node.network.faults.unblock(&peer.peer_id).await;
+            }
+        }
+        self.connect().await
+    }
+
+    /// Delay every message each node receives
+    pub async fn set_latency(&self, latency: Duration) {
+        for node in &self.nodes {
+            node.network.faults.set_latency(latency).await;
+        }
+    }
+
+    /// Drop this share of the messages each node receives
+    pub async fn set_loss(&self, loss: f64) {
+        for node in &self.nodes {
+            node.network.faults.set_loss(loss).await;
+        }
+    }
+
+    /// Run one anti-entropy round on every node
+    pub async fn sync_round(&self) -> OzoneResult<()> {
+        for node in &self.nodes {
+            node.network.anti_entropy().await?;
+        }
+        Ok(())
+    }
+
+    /// Digests of a node's shared containers
+    pub async fn state(&self, node: usize) -> Vec<Digest> {
+        match &self.nodes[node].network.replica {
+            Some(replica) => replica.digests().await,
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether the given nodes hold the same shared containers, at the
+    /// same versions
+    pub async fn converged(&self, among: &[usize]) -> bool {
+        let Some((&first, rest)) = among.split_first() else {
+            return true;
+        };
+        let expected = self.state(first).await;
+        for &node in rest {
+            if self.state(node).await != expected {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Run anti-entropy rounds until the given nodes converge; returns
+    /// their shared state
+    pub async fn await_converged(
+        &self,
+        among: &[usize],
+        timeout: Duration,
+    ) -> OzoneResult<Vec<Digest>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.sync_round().await?;
+            if self.converged(among).await {
+                return Ok(match among.first() {
+                    Some(&node) => self.state(node).await,
+                    None => Vec::new(),
+                });
+            }
+            if Instant::now() >= deadline {
+                return Err(OzoneError::NetworkError(format!(
+                    "Nodes {:?} did not converge within {:?}",
+                    among, timeout
+                )));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Wait until every node knows a proposal
+    pub async fn await_proposal(&self, proposal_id: u64, timeout: Duration) -> OzoneResult<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut missing = None;
+            for (index, node) in self.nodes.iter().enumerate() {
+                if node.network.proposal(proposal_id).await.is_none() {
+                    missing = Some(index);
+                    break;
+                }
+            }
+            match missing {
+                None => return Ok(()),
+                Some(index) if Instant::now() >= deadline => {
+                    return Err(OzoneError::NetworkError(format!(
+                        "Node {} did not receive proposal {}",
+                        index, proposal_id
+                    )));
+                }
+                Some(_) => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        }
+    }
+
+    /// Close expired proposals on every node until all of them settle a
+    /// proposal the same way; returns the outcome
+    pub async fn await_outcome(
+        &self,
+        proposal_id: u64,
+        timeout: Duration,
+    ) -> OzoneResult<ConsensusStatus> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut statuses = Vec::with_capacity(self.nodes.len());
+            for node in &self.nodes {
+                node.network.close_proposals().await?;
+                statuses.push(
+                    node.network
+                        .proposal(proposal_id)
+                        .await
+                        .map(|proposal| proposal.status),
+                );
+            }
+            if let Some(Some(status)) = statuses.first() {
+                let closed = !matches!(status, ConsensusStatus::Open | ConsensusStatus::Verifying);
+                if closed && statuses.iter().all(|s| s.as_ref() == Some(status)) {
+                    return Ok(*status);
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(OzoneError::NetworkError(format!(
+                    "Proposal {} did not settle within {:?}: {:?}",
+                    proposal_id, timeout, statuses
+                )));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Stop every node
+    pub async fn stop(&self) {
+        for node in &self.nodes {
+            node.network.stop().await;
+        }
+    }
+
+    /// Faults are one-sided; a link is up only if neither node blocks the
+    /// other
+    async fn is_linked(&self, i: usize, j: usize) -> bool {
+        !self.nodes[i]
+            .network
+            .faults
+            .is_blocked(&self.nodes[j].peer_id)
+            .await
+            && !self.nodes[j]
+                .network
+                .faults
+                .is_blocked(&self.nodes[i].peer_id)
+                .await
+    }
+
+    async fn is_connected(&self, i: usize, j: usize) -> bool {
+        self.nodes[i]
+            .network
+            .known_peers
+            .read()
+            .await
+            .get(&self.nodes[j].peer_id)
+            .is_some_and(|peer| peer.connected)
+    }
+}
+
+fn zsei_config(dir: &Path) -> ZSEIConfig {
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    ZSEIConfig {
+        global_path: path("global.mmap"),
+        local_path: path("local"),
+        cache_path: path("cache"),
+        ml_path: path("ml"),
+        pipeline_index_path: path("pipelines/index.json"),
+        methodology_index_path: path("methodologies/index.json"),
+        blueprint_index_path: path("blueprints/index.json"),
+        blueprint_versions_path: path("blueprints/versions"),
+        ..Default::default()
+    }
+}
+
+/// The loopback address a node listens on, once it is listening
+async fn loopback_address(network: &NetworkManager) -> OzoneResult<String> {
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    loop {
+        let addresses = network
+            .command(|reply| SwarmCommand::ListenAddresses { reply })
+            .await?;
+        let loopback = addresses.into_iter().find(|addr| {
+            addr.iter()
+                .any(|p| matches!(p, Protocol::Ip4(ip) if ip.is_loopback()))
+        });
+        if let Some(addr) = loopback {
+            return Ok(format!("{}/p2p/{}", addr, network.local_peer_id));
+        }
+        if Instant::now() >= deadline {
+            return Err(OzoneError::NetworkError(
+                "Node is not listening on loopback".into(),
+            ));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::consensus::{ConsensusVoteType, ProposalType, VerificationResult};
+    use crate::types::{Container, ContainerType, GlobalState, LocalState, Value};
+
+    fn container(
+        id: u64,
+        parent: u64,
+        container_type: ContainerType,
+        children: &[u64],
+    ) -> Container {
+        let mut container = Container {
+            global_state: GlobalState::default(),
+            local_state: LocalState::default(),
+        };
+        container.global_state.container_id = id;
+        container.global_state.parent_id = parent;
+        container.global_state.child_ids = children.to_vec();
+        container.local_state.metadata.container_type = container_type;
+        container
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sync_converges_after_partition() {
+        let methodologies = [30001, 30002, 30003];
+        let mut cluster = Cluster::start("sync", 3, |_, _| {}).await.unwrap();
+        for (index, node) in cluster.nodes.iter().enumerate() {
+            let zsei = node.zsei.read().await;
+            zsei.store_container(container(0, 0, ContainerType::Root, &[2]))
+                .await
+                .unwrap();
+            zsei.store_container(container(2, 0, ContainerType::Root, &methodologies))
+                .await
+                .unwrap();
+            zsei.store_container(container(
+                methodologies[index],
+                2,
+                ContainerType::Methodology,
+                &[],
+            ))
+            .await
+            .unwrap();
+        }
+
+        cluster.connect().await.unwrap();
+        cluster.partition(&[&[0, 1]]).await;
+        cluster.set_latency(Duration::from_millis(10)).await;
+        cluster.set_loss(0.2).await;
+
+        // The majority side converges without node 2
+        let state = cluster
+            .await_converged(&[0, 1], Duration::from_secs(20))
+            .await
+            .unwrap();
+        assert_eq!(state.len(), 2);
+        assert_eq!(cluster.state(2).await.len(), 1);
+        assert!(!cluster.converged(&cluster.all()).await);
+
+        cluster.heal().await.unwrap();
+        let state = cluster
+            .await_converged(&cluster.all(), Duration::from_secs(20))
+            .await
+            .unwrap();
+        let mut ids: Vec<u64> = state.iter().map(|d| d.id.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, methodologies);
+        cluster.stop().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_nodes_agree_on_outcome() {
+        let mut cluster = Cluster::start("consensus", 3, |_, config| {
+            config.consensus.voting.min_votes_required = 2;
+            config.consensus.voting.voting_duration_secs = 2;
+        })
+        .await
+        .unwrap();
+        cluster.connect().await.unwrap();
+        cluster.set_latency(Duration::from_millis(10)).await;
+
+        let proposal = cluster.nodes[0]
+            .network
+            .propose(
+                ProposalType::NewMethodology,
+                Value::String("m".into()),
+                None,
+            )
+            .await
+            .unwrap();
+        cluster
+            .await_proposal(proposal.proposal_id, Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        let verification = VerificationResult {
+            verified: true,
+            zero_shot_passed: true,
+            semantic_valid: true,
+            signature_valid: true,
+            concerns: Vec::new(),
+        };
+        for node in &cluster.nodes[1..] {
+            node.network
+                .vote(
+                    proposal.proposal_id,
+                    ConsensusVoteType::Accept,
+                    verification.clone(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let outcome = cluster
+            .await_outcome(proposal.proposal_id, Duration::from_secs(15))
+            .await
+            .unwrap();
+        assert_eq!(outcome, ConsensusStatus::Accepted);
+        cluster.stop().await;
+    }
+}
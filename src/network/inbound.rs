@@ -0,0 +1,235 @@
+//! Inbound sync messages
+//!
+//! Envelopes received from peers are verified (schema version, content
+//! hash, signature), checked against the topic they arrived on and the peer
+//! that authored them, and their containers applied to the local replica
+//! (see `delta`). Only shareable knowledge is accepted: user, task and
+//! consciousness containers never leave a node, so a peer sending them is
+//! rejected. The sender's owner and object path are never kept. Rejections carry the `Offence` charged to the peer's
+//! reputation.
+
+use super::delta::{Replica, SyncedContainer};
+use super::protocol::{
+    self, Envelope, BLUEPRINT_TOPIC, METHODOLOGY_TOPIC, MIN_SCHEMA_VERSION, SCHEMA_VERSION,
+};
+use super::reputation::{Offence, Rejection};
+use super::SyncItemType;
+use crate::types::{ContainerID, ContainerType};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Result of handling an inbound message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InboundOutcome {
+    /// Stored in ZSEI (new, newer or merged)
+    Stored(ContainerID),
+    /// Valid but not applied (stale version, no ZSEI attached, local error)
+    Ignored(String),
+    /// Invalid; the sender should be penalized and the message not forwarded
+    Rejected(String),
+}
+
+/// Container types that may be synced between peers
+pub fn is_shareable(container_type: ContainerType) -> bool {
+    matches!(
+        container_type,
+        ContainerType::Modality
+            | ContainerType::Category
+            | ContainerType::SubCategory
+            | ContainerType::Methodology
+            | ContainerType::Blueprint
+            | ContainerType::Pipeline
+            | ContainerType::URLReference
+            | ContainerType::PackageReference
+            | ContainerType::Derived
+    )
+}
+
+/// Decode and check an envelope from `author`, received on `topic` or,
+/// without a topic, through a direct transfer
+pub fn validate(
+    topic: Option<&str>,
+    author: &PeerId,
+    data: &[u8],
+) -> Result<(Envelope, SyncedContainer), Rejection> {
+    let envelope = Envelope::decode(data).map_err(|e| e.to_string())?;
+    if protocol::content_hash(&envelope.payload) != envelope.content_hash {
+        return Err(Rejection::new(
+            Offence::HashMismatch,
+            "Payload does not match its content hash",
+        ));
+    }
+    let sender = envelope.verify().map_err(|e| {
+        let supported = (MIN_SCHEMA_VERSION..=SCHEMA_VERSION).contains(&envelope.schema_version);
+        let offence = if supported {
+            Offence::InvalidSignature
+        } else {
+            Offence::Invalid
+        };
+        Rejection::new(offence, e.to_string())
+    })?;
+    if sender != *author {
+        return Err(Rejection::new(
+            Offence::InvalidSignature,
+            format!("Envelope signed by {} sent by {}", sender, author),
+        ));
+    }
+
+    let expected_kind = match topic {
+        Some(METHODOLOGY_TOPIC) => Some(SyncItemType::Methodology),
+        Some(BLUEPRINT_TOPIC) => Some(SyncItemType::Blueprint),
+        _ => None,
+    };
+    if let Some(expected) = expected_kind {
+        if envelope.kind != expected {
+            return Err(format!(
+                "{:?} message on topic {}",
+                envelope.kind,
+                topic.unwrap_or_default()
+            )
+            .into());
+        }
+    }
+
+    let item = envelope.item().map_err(|e| e.to_string())?;
+    let container_type = item.container.local_state.metadata.container_type;
+    let type_matches = match envelope.kind {
+        SyncItemType::Methodology => container_type == ContainerType::Methodology,
+        SyncItemType::Blueprint => container_type == ContainerType::Blueprint,
+        SyncItemType::Container | SyncItemType::PipelineResult => is_shareable(container_type),
+    };
+    if !type_matches {
+        return Err(format!(
+            "{:?} message carries a {:?} container",
+            envelope.kind, container_type
+        )
+        .into());
+    }
+
+    if item.id.id == 0 {
+        return Err(Rejection::new(Offence::Invalid, "Container has no ID"));
+    }
+    for id in [&item.id, &item.parent] {
+        if id.is_reserved() != id.origin.is_empty() {
+            return Err(Rejection::new(
+                Offence::Invalid,
+                format!("Invalid sync ID {}", id),
+            ));
+        }
+    }
+
+    Ok((envelope, item))
+}
+
+/// Applies validated messages to the local replica
+#[derive(Clone, Default)]
+pub struct InboundHandler {
+    replica: Option<Arc<Replica>>,
+}
+
+impl InboundHandler {
+    pub fn new(replica: Option<Arc<Replica>>) -> Self {
+        Self { replica }
+    }
+
+    /// Validate and apply an envelope from `author`
+    pub async fn handle(
+        &self,
+        author: &PeerId,
+        topic: Option<&str>,
+        data: &[u8],
+    ) -> Result<InboundOutcome, Rejection> {
+        let (envelope, item) = validate(topic, author, data)?;
+        let Some(replica) = &self.replica else {
+            return Ok(InboundOutcome::Ignored("ZSEI not attached".to_string()));
+        };
+        Ok(replica.apply(author, item, envelope.version).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::protocol::CONTAINER_TOPIC;
+    use crate::types::{Container, GlobalState, LocalState};
+    use libp2p::identity::Keypair;
+
+    fn message(
+        keypair: &Keypair,
+        kind: SyncItemType,
+        container_type: ContainerType,
+        id: ContainerID,
+    ) -> Vec<u8> {
+        let mut container = Container {
+            global_state: GlobalState::default(),
+            local_state: LocalState::default(),
+        };
+        container.global_state.container_id = id;
+        container.local_state.metadata.container_type = container_type;
+        let origin = keypair.public().to_peer_id().to_string();
+        let item = SyncedContainer::unmapped(&origin, container);
+        let payload = serde_json::to_vec(&item).unwrap();
+        Envelope::seal(kind, payload, Default::default(), 0, keypair)
+            .unwrap()
+            .encode()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_validate_topic_and_type() {
+        let keypair = Keypair::generate_ed25519();
+        let author = keypair.public().to_peer_id();
+        let methodology = message(
+            &keypair,
+            SyncItemType::Methodology,
+            ContainerType::Methodology,
+            7,
+        );
+        assert!(validate(Some(METHODOLOGY_TOPIC), &author, &methodology).is_ok());
+        assert!(validate(None, &author, &methodology).is_ok());
+        assert!(validate(Some(BLUEPRINT_TOPIC), &author, &methodology).is_err());
+        assert!(validate(Some(METHODOLOGY_TOPIC), &PeerId::random(), &methodology).is_err());
+
+        let mislabelled = message(
+            &keypair,
+            SyncItemType::Methodology,
+            ContainerType::Blueprint,
+            7,
+        );
+        assert!(validate(Some(METHODOLOGY_TOPIC), &author, &mislabelled).is_err());
+
+        let category = message(
+            &keypair,
+            SyncItemType::Container,
+            ContainerType::Category,
+            8,
+        );
+        assert!(validate(Some(CONTAINER_TOPIC), &author, &category).is_ok());
+
+        let no_id = message(
+            &keypair,
+            SyncItemType::Container,
+            ContainerType::Category,
+            0,
+        );
+        assert!(validate(Some(CONTAINER_TOPIC), &author, &no_id).is_err());
+
+        assert!(validate(Some(CONTAINER_TOPIC), &author, b"not json").is_err());
+    }
+
+    #[test]
+    fn test_user_data_is_never_accepted() {
+        let keypair = Keypair::generate_ed25519();
+        let author = keypair.public().to_peer_id();
+        for container_type in [
+            ContainerType::User,
+            ContainerType::Project,
+            ContainerType::Task,
+            ContainerType::ExperienceMemory,
+        ] {
+            let data = message(&keypair, SyncItemType::Container, container_type, 9);
+            assert!(validate(Some(CONTAINER_TOPIC), &author, &data).is_err());
+        }
+    }
+}
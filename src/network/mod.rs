@@ -6,6 +6,26 @@
 //! - P2P peer discovery and connection
 //! - Hook-based sync triggers for methodologies, blueprints, and findings
 //! - Network status monitoring
+//! - A background swarm driver (`swarm`) that keeps peer state current and
+//!   stores validated inbound methodologies, blueprints and containers in
+//!   ZSEI (`inbound`)
+//! - Signed, versioned envelopes (`protocol`), gossiped to all peers or sent
+//!   to one peer, in chunks when large, over the transfer protocol
+//!   (`transfer`)
+//! - Delta sync (`delta`): version vectors per shared container and
+//!   periodic anti-entropy with each peer, limited to `sync_roots`
+//! - Consensus (`consensus`): signed proposals and weighted votes on shared
+//!   knowledge, closed when their voting period ends
+//! - Peer reputation (`reputation`) from verification and consensus
+//!   outcomes, deciding vote weights and which peers are synced with
+//! - Content-addressed pipeline code (`chunks`), served to peers and
+//!   fetched from the mirrors in a `CodePointer`
+//! - Multi-device offloading (`devices`): pipeline runs sent to the
+//!   account's other devices by their resources and load
+//! - A persistent outbox (`outbox`): sync items are retried with backoff
+//!   until delivered or expired, across restarts
+//! - Fault injection (`faults`) and an in-process multi-node harness
+//!   (`harness`) for testing sync and consensus without real machines
 //!
 //! SYNC BEHAVIOR:
 //! - Methodologies: ALWAYS sync (no significance check)
@@ -14,18 +34,59 @@
 //! - Pipeline Results: ON-DEMAND only (explicit share request)
 //! - User Data: NEVER sync (local only)
 
+pub mod chunks;
+pub mod consensus;
+pub mod delta;
+pub mod devices;
+pub mod faults;
+#[cfg(any(test, feature = "test-harness"))]
+pub mod harness;
+mod inbound;
+pub mod outbox;
+pub mod peer_book;
+pub mod protocol;
+pub mod reputation;
+mod swarm;
+mod transfer;
+
+pub use inbound::{is_shareable, InboundOutcome};
+pub use protocol::Envelope;
+
 use crate::config::NetworkConfig;
-use crate::types::{ContainerID, OzoneError, OzoneResult};
-use libp2p::{gossipsub, identify, mdns, ping, PeerId};
-use std::collections::HashMap;
+use crate::pipeline::{PipelineProgress, PipelineRegistry};
+use crate::types::consensus::{
+    ConsensusProposal, ConsensusVoteType, ProposalType, VerificationResult,
+};
+use crate::types::pipeline::{
+    ChunkID, CodePointer, PeerNode, PipelineBlueprint, PipelineInput, PipelineOutput,
+};
+use crate::types::{
+    ConsensusStatus, Container, ContainerID, OzoneError, OzoneResult, TaskID, UserID, Value,
+};
+use crate::zsei::ZSEI;
+use chunks::ChunkStore;
+use consensus::{Consensus, ConsensusMessage, Settlement};
+use delta::{Replica, SyncId, SyncIndex, SyncedContainer, VersionVector};
+use devices::{DeviceAdvert, Devices, OffloadRequest, Requirements};
+use faults::Faults;
+use libp2p::identity::Keypair;
+use libp2p::swarm::behaviour::toggle::Toggle;
+use libp2p::{gossipsub, identify, mdns, ping, request_response, PeerId};
+use outbox::{Destination, Outbox, OutboxEntry, OutboxSummary, Outcome, Payload};
+use reputation::{Event, Offence};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex, RwLock};
+use swarm::{SwarmCommand, SwarmDriver};
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use transfer::{TransferRequest, TransferResponse};
 
 #[allow(unused_imports)]
 use tracing;
 
 /// Sync priority levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SyncPriority {
     /// Immediate broadcast (methodologies, blueprints)
     High,
@@ -48,7 +109,8 @@ pub struct SyncItem {
 }
 
 /// Type of item being synced
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SyncItemType {
     Methodology,
     Blueprint,
@@ -61,21 +123,38 @@ pub type HookFn = Arc<dyn Fn(SyncItem) -> OzoneResult<()> + Send + Sync>;
 
 /// Network manager for P2P operations
 pub struct NetworkManager {
-    /// Sync queue for high priority items (immediate)
-    high_priority_queue: Arc<RwLock<Vec<SyncItem>>>,
-
-    /// Sync queue for normal priority items (batched)
-    normal_queue: Arc<RwLock<Vec<SyncItem>>>,
+    /// Sync items waiting to reach peers (persistent)
+    outbox: Outbox,
 
     /// Registered hooks
     hooks: Arc<RwLock<HashMap<String, Vec<HookFn>>>>,
 
-    /// Channel for sync notifications
-    sync_sender: mpsc::Sender<SyncItem>,
-    sync_receiver: Arc<RwLock<mpsc::Receiver<SyncItem>>>,
+    /// libp2p swarm, until `initialize` hands it to the driver
+    swarm: Mutex<Option<libp2p::Swarm<OzoneBehaviour>>>,
+
+    /// Commands to the running swarm driver
+    commands: Option<mpsc::Sender<SwarmCommand>>,
+
+    /// Where validated inbound containers are stored
+    zsei: Option<Arc<RwLock<ZSEI>>>,
+
+    /// Shared containers and their version vectors, once ZSEI is attached
+    replica: Option<Arc<Replica>>,
+
+    /// Proposals and votes seen by this node
+    consensus: Arc<Consensus>,
+
+    /// Pipeline code chunks stored on this node
+    chunks: Arc<ChunkStore>,
+
+    /// This device and the account's other devices
+    devices: Arc<Devices>,
 
-    /// libp2p swarm
-    swarm: Arc<Mutex<libp2p::Swarm<OzoneBehaviour>>>,
+    /// Runs pipelines offloaded by the account's other devices
+    pipelines: Option<Arc<RwLock<PipelineRegistry>>>,
+
+    /// Latency, loss and partitions injected by tests
+    faults: Arc<Faults>,
 
     /// Network configuration
     config: NetworkConfig,
@@ -91,19 +170,36 @@ pub struct NetworkManager {
 
     /// Local libp2p peer ID
     local_peer_id: PeerId,
+
+    /// Node keypair, used to sign envelopes
+    local_key: Keypair,
 }
 
-/// Peer information
-#[derive(Debug, Clone)]
+/// Peer information (saved in the peer book)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerInfo {
     pub peer_id: String,
     pub address: String,
     pub addresses: Vec<String>,
     pub last_seen: u64,
+    #[serde(default)]
     pub latency_ms: u32,
     pub reputation: f32,
     pub contribution_count: u64,
+    #[serde(default)]
     pub capabilities: Vec<String>,
+    /// Currently connected
+    #[serde(skip)]
+    pub connected: bool,
+    /// Agent reported by identify
+    #[serde(default)]
+    pub agent_version: Option<String>,
+    /// When `reputation` last changed or decayed
+    #[serde(default)]
+    pub reputation_updated: u64,
+    /// Refused until this time (see `reputation`)
+    #[serde(default)]
+    pub banned_until: u64,
 }
 
 /// Network statistics
@@ -113,6 +209,8 @@ pub struct NetworkStats {
     pub messages_received: u64,
     pub bytes_sent: u64,
     pub bytes_received: u64,
+    /// Inbound messages that failed validation
+    pub messages_rejected: u64,
 }
 
 /// Get current unix timestamp
@@ -127,27 +225,36 @@ fn now() -> u64 {
 #[derive(libp2p::swarm::NetworkBehaviour)]
 struct OzoneBehaviour {
     gossipsub: gossipsub::Behaviour,
-    mdns: mdns::tokio::Behaviour,
+    mdns: Toggle<mdns::tokio::Behaviour>,
     ping: ping::Behaviour,
     identify: identify::Behaviour,
+    transfer: request_response::cbor::Behaviour<TransferRequest, TransferResponse>,
 }
 
 impl NetworkManager {
     pub async fn new(config: NetworkConfig) -> OzoneResult<Self> {
         use libp2p::{
-            gossipsub, identify, identity, mdns, noise, ping, SwarmBuilder, tcp, yamux,
+            gossipsub, identify, mdns, noise, ping, request_response, SwarmBuilder, tcp, yamux,
         };
 
-        // Generate or load keypair
-        let local_key = identity::Keypair::generate_ed25519();
+        // Load the node keypair (stable PeerId) and saved peers
+        let data_path = std::path::Path::new(&config.data_path);
+        let local_key = peer_book::load_or_create_keypair(data_path)?;
         let local_peer_id = PeerId::from(local_key.public());
+        let known_peers = peer_book::PeerBook::new(data_path).load();
+        let consensus = Consensus::load(data_path, config.consensus.clone(), local_key.clone());
+        let chunks = ChunkStore::new(data_path);
+        let devices = Devices::new(config.devices.clone(), &local_key, now());
+        let outbox = Outbox::load(data_path, config.outbox.clone());
 
         log::info!("Local peer ID: {}", local_peer_id);
 
-        // Configure gossipsub
+        // Configure gossipsub; messages are forwarded only after the
+        // inbound handler accepts them
         let gossipsub_config = gossipsub::ConfigBuilder::default()
             .heartbeat_interval(std::time::Duration::from_secs(1))
             .validation_mode(gossipsub::ValidationMode::Strict)
+            .validate_messages()
             .build()
             .map_err(|e| OzoneError::NetworkError(format!("Gossipsub config error: {}", e)))?;
 
@@ -158,11 +265,17 @@ impl NetworkManager {
         .map_err(|e| OzoneError::NetworkError(format!("Gossipsub init error: {}", e)))?;
 
         // Configure mDNS for local peer discovery
-        let mdns = mdns::tokio::Behaviour::new(mdns::Config::default(), local_peer_id)
-            .map_err(|e| OzoneError::NetworkError(format!("mDNS init error: {}", e)))?;
+        let mdns = if config.enable_p2p && config.enable_mdns {
+            Some(
+                mdns::tokio::Behaviour::new(mdns::Config::default(), local_peer_id)
+                    .map_err(|e| OzoneError::NetworkError(format!("mDNS init error: {}", e)))?,
+            )
+        } else {
+            None
+        };
 
         // Build the swarm
-        let swarm = SwarmBuilder::with_existing_identity(local_key)
+        let swarm = SwarmBuilder::with_existing_identity(local_key.clone())
             .with_tokio()
             .with_tcp(
                 tcp::Config::default(),
@@ -173,34 +286,63 @@ impl NetworkManager {
             .with_behaviour(|_key| {
                 Ok(OzoneBehaviour {
                     gossipsub,
-                    mdns,
+                    mdns: Toggle::from(mdns),
                     ping: ping::Behaviour::default(),
-                    identify: identify::Behaviour::new(identify::Config::new(
-                        "/ozone/1.0.0".into(),
-                        _key.public(),
-                    )),
+                    identify: identify::Behaviour::new(
+                        identify::Config::new(protocol::PROTOCOL_VERSION.into(), _key.public())
+                            .with_agent_version(format!(
+                                "ozone-studio/{}",
+                                env!("CARGO_PKG_VERSION")
+                            )),
+                    ),
+                    transfer: request_response::cbor::Behaviour::new(
+                        [(
+                            transfer::TRANSFER_PROTOCOL,
+                            request_response::ProtocolSupport::Full,
+                        )],
+                        request_response::Config::default()
+                            .with_request_timeout(std::time::Duration::from_secs(30)),
+                    ),
                 })
             })
             .map_err(|e| OzoneError::NetworkError(format!("Behaviour error: {}", e)))?
+            .with_swarm_config(|c| {
+                c.with_idle_connection_timeout(std::time::Duration::from_secs(60))
+            })
             .build();
 
-        let (sync_sender, sync_receiver) = mpsc::channel(1000);
-
         Ok(Self {
-            high_priority_queue: Arc::new(RwLock::new(Vec::new())),
-            normal_queue: Arc::new(RwLock::new(Vec::new())),
+            outbox,
             hooks: Arc::new(RwLock::new(HashMap::new())),
-            sync_sender,
-            sync_receiver: Arc::new(RwLock::new(sync_receiver)),
-            swarm: Arc::new(Mutex::new(swarm)),
+            swarm: Mutex::new(Some(swarm)),
+            commands: None,
+            zsei: None,
+            replica: None,
+            consensus: Arc::new(consensus),
+            chunks: Arc::new(chunks),
+            devices: Arc::new(devices),
+            pipelines: None,
+            faults: Arc::new(Faults::default()),
             config,
-            known_peers: Arc::new(RwLock::new(HashMap::new())),
+            known_peers: Arc::new(RwLock::new(known_peers)),
             connection_attempts: HashMap::new(),
             stats: Arc::new(Mutex::new(NetworkStats::default())),
             local_peer_id,
+            local_key,
         })
     }
 
+    /// Store validated inbound containers in ZSEI (call before `initialize`)
+    pub fn set_zsei(&mut self, zsei: Arc<RwLock<ZSEI>>) {
+        self.zsei = Some(zsei);
+    }
+
+    /// Run pipelines offloaded by the account's other devices (call before
+    /// `initialize`; only when `enable_offload` is set)
+    pub fn set_pipelines(&mut self, pipelines: Arc<RwLock<PipelineRegistry>>) {
+        self.pipelines = Some(pipelines);
+    }
+
     /// Initialize P2P network
     pub async fn initialize(&mut self) -> OzoneResult<()> {
         if !self.config.enable_p2p {
@@ -213,10 +355,30 @@ impl NetworkManager {
         // Register default hooks
         self.register_default_hooks().await;
 
-        // Reconnect to known peers from persistence
-        let peers = self.known_peers.read().await;
-        let addrs: Vec<String> = peers.values().map(|p| p.address.clone()).collect();
-        drop(peers);
+        self.start_swarm()?;
+
+        // Bootstrap nodes first, then the best known peers from the peer book
+        let mut addrs = self.config.bootstrap_nodes.clone();
+        {
+            let peers = self.known_peers.read().await;
+            let mut known: Vec<&PeerInfo> = peers.values().collect();
+            known.sort_by(|a, b| {
+                peer_book::rank(b)
+                    .partial_cmp(&peer_book::rank(a))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            let now = now();
+            addrs.extend(
+                known
+                    .into_iter()
+                    .filter(|peer| peer.banned_until <= now)
+                    .filter_map(peer_book::dial_address),
+            );
+        }
+        // Keep the first (best ranked) occurrence of each address
+        let mut seen = HashSet::new();
+        addrs.retain(|addr| seen.insert(addr.clone()));
+        addrs.truncate(self.config.max_peers as usize);
 
         for addr in addrs {
             if let Err(e) = self.connect_peer(&addr).await {
@@ -227,6 +389,115 @@ impl NetworkManager {
         Ok(())
     }
 
+    /// Listen, subscribe to the sync topics and hand the swarm to the driver
+    fn start_swarm(&mut self) -> OzoneResult<()> {
+        let Some(mut swarm) = self.swarm.get_mut().take() else {
+            return Ok(());
+        };
+
+        let listen_addr: libp2p::Multiaddr = format!("/ip4/0.0.0.0/tcp/{}", self.config.p2p_port)
+            .parse()
+            .map_err(|e| OzoneError::NetworkError(format!("Invalid listen address: {}", e)))?;
+        // Outbound connections still work if the port is taken
+        if let Err(e) = swarm.listen_on(listen_addr) {
+            tracing::warn!("P2P cannot listen on port {}: {}", self.config.p2p_port, e);
+        }
+
+        for topic in protocol::SYNC_TOPICS
+            .into_iter()
+            .chain([protocol::CONSENSUS_TOPIC])
+        {
+            swarm
+                .behaviour_mut()
+                .gossipsub
+                .subscribe(&gossipsub::IdentTopic::new(topic))
+                .map_err(|e| OzoneError::NetworkError(format!("Subscribe {}: {:?}", topic, e)))?;
+        }
+
+        let data_path = std::path::Path::new(&self.config.data_path);
+        self.replica = self.zsei.clone().map(|zsei| {
+            Arc::new(Replica::new(
+                zsei,
+                SyncIndex::load(data_path),
+                self.config.sync_roots.clone(),
+                self.local_key.clone(),
+            ))
+        });
+
+        let (commands, receiver) = mpsc::channel(256);
+        let (delayed_sender, delayed) = mpsc::unbounded_channel();
+        let offloads = match &self.pipelines {
+            Some(pipelines) if self.config.devices.enable_offload => {
+                let (offloads, runs) =
+                    mpsc::channel(self.config.devices.max_offloaded.max(1) as usize);
+                tokio::spawn(devices::serve(
+                    runs,
+                    pipelines.clone(),
+                    commands.clone(),
+                    self.devices.clone(),
+                ));
+                Some(offloads)
+            }
+            _ => None,
+        };
+        let driver = SwarmDriver {
+            swarm,
+            commands: receiver,
+            peer_book: peer_book::PeerBook::new(data_path),
+            known_peers: self.known_peers.clone(),
+            stats: self.stats.clone(),
+            inbound: inbound::InboundHandler::new(self.replica.clone()),
+            replica: self.replica.clone(),
+            consensus: self.consensus.clone(),
+            chunks: self.chunks.clone(),
+            devices: self.devices.clone(),
+            offloads,
+            faults: self.faults.clone(),
+            delayed,
+            delayed_sender,
+            reassembler: transfer::Reassembler::default(),
+            pending: HashMap::new(),
+            max_peers: self.config.max_peers as usize,
+            reputation: self.config.reputation.clone(),
+            rate_limit: reputation::RateLimit::default(),
+            peers_dirty: false,
+        };
+        tokio::spawn(driver.run());
+        self.commands = Some(commands);
+
+        Ok(())
+    }
+
+    /// Stop the swarm driver, closing all peer connections and saving the
+    /// peer book, sync index, consensus state and outbox
+    pub async fn stop(&self) {
+        let _ = self.command(|reply| SwarmCommand::Shutdown { reply }).await;
+        self.save_outbox().await;
+        if let Some(replica) = &self.replica {
+            if let Err(e) = replica.save().await {
+                tracing::warn!("Failed to save sync index: {}", e);
+            }
+        }
+        if let Err(e) = self.consensus.save().await {
+            tracing::warn!("Failed to save consensus state: {}", e);
+        }
+    }
+
+    /// Send a command to the swarm driver and wait for its reply
+    async fn command<T>(
+        &self,
+        build: impl FnOnce(oneshot::Sender<T>) -> SwarmCommand,
+    ) -> OzoneResult<T> {
+        let not_running = || OzoneError::NetworkError("P2P networking is not running".into());
+        let commands = self.commands.as_ref().ok_or_else(not_running)?;
+        let (reply, response) = oneshot::channel();
+        commands
+            .send(build(reply))
+            .await
+            .map_err(|_| not_running())?;
+        response.await.map_err(|_| not_running())
+    }
+
     /// Register default hooks for automatic sync
     async fn register_default_hooks(&self) {
         // Methodology hook - ALWAYS sync
@@ -301,28 +572,36 @@ impl NetworkManager {
             }
         }
 
-        // Also queue for sync based on priority
-        match item.priority {
-            SyncPriority::High => {
-                self.high_priority_queue.write().await.push(item.clone());
-                // Immediately trigger broadcast
-                self.broadcast_high_priority().await?;
-            }
-            SyncPriority::Normal => {
-                self.normal_queue.write().await.push(item);
-            }
-            SyncPriority::Low => {
-                // Only queue if explicitly requested
-            }
-            SyncPriority::Never => {
-                // Never sync
+        // Also queue for sync; high priority items are sent at once, the
+        // rest with the next batch sync
+        let payload = Payload::Item {
+            item_type: item.item_type,
+            data: item.data,
+            timestamp: item.timestamp,
+        };
+        let queued = self
+            .outbox
+            .enqueue(
+                Some(item.container_id),
+                item.priority,
+                payload,
+                Destination::Broadcast,
+                now(),
+            )
+            .await;
+        if let Some(id) = queued {
+            if item.priority == SyncPriority::High {
+                self.flush_outbox(Some(&id)).await;
             }
+            self.save_outbox().await;
         }
 
         Ok(())
     }
 
     /// Notify of new methodology (ALWAYS syncs)
+    ///
+    /// `data` is the JSON-serialized container.
     pub async fn on_methodology_created(
         &self,
         container_id: ContainerID,
@@ -340,6 +619,8 @@ impl NetworkManager {
     }
 
     /// Notify of new blueprint (ALWAYS syncs)
+    ///
+    /// `data` is the JSON-serialized container.
     pub async fn on_blueprint_created(
         &self,
         container_id: ContainerID,
@@ -357,6 +638,8 @@ impl NetworkManager {
     }
 
     /// Notify of new container finding (ALWAYS syncs, batched)
+    ///
+    /// `data` is the JSON-serialized container.
     pub async fn on_container_created(
         &self,
         container_id: ContainerID,
@@ -374,6 +657,8 @@ impl NetworkManager {
     }
 
     /// Notify of pipeline completion (ON-DEMAND only)
+    ///
+    /// `data` is the JSON-serialized container.
     pub async fn on_pipeline_completed(
         &self,
         container_id: ContainerID,
@@ -395,55 +680,721 @@ impl NetworkManager {
         self.trigger_hooks("on_pipeline_completed", item).await
     }
 
-    /// Broadcast high priority items immediately
-    async fn broadcast_high_priority(&self) -> OzoneResult<()> {
-        let items = {
-            let mut queue = self.high_priority_queue.write().await;
-            std::mem::take(&mut *queue)
-        };
+    /// Process batch sync (called periodically)
+    ///
+    /// Drops expired outbox items and attempts the deliveries that are due.
+    pub async fn process_batch_sync(&self) -> OzoneResult<()> {
+        let expired = self.outbox.expire(now()).await;
+        if expired > 0 {
+            tracing::warn!("{} sync items expired undelivered", expired);
+        }
+        self.flush_outbox(None).await;
+        self.outbox.save().await
+    }
+
+    /// Attempt the outbox deliveries that are due, or only those of item
+    /// `only`; kept waiting while P2P networking is not running
+    async fn flush_outbox(&self, only: Option<&str>) {
+        if self.commands.is_none() {
+            return;
+        }
+        let due = self.outbox.due(only, now()).await;
+        if only.is_none() && !due.is_empty() {
+            tracing::info!("Processing batch sync of {} deliveries", due.len());
+        }
+        for attempt in due {
+            let outcome = self.deliver(&attempt).await;
+            if let Outcome::Retry(reason) | Outcome::Refused(reason) = &outcome {
+                tracing::warn!(
+                    "Failed to sync {} to {}: {}",
+                    attempt.id,
+                    attempt.destination,
+                    reason
+                );
+            }
+            self.outbox
+                .record(&attempt.id, &attempt.destination, outcome, now())
+                .await;
+        }
+    }
+
+    /// Attempt one delivery. Lower priority containers already in ZSEI
+    /// reach peers through anti-entropy, which only sends what changed.
+    async fn deliver(&self, attempt: &outbox::Attempt) -> Outcome {
+        match (&attempt.payload, &attempt.destination) {
+            (
+                Payload::Item {
+                    item_type,
+                    data,
+                    timestamp,
+                },
+                Destination::Broadcast,
+            ) => {
+                let in_zsei = match attempt.container_id {
+                    Some(container_id) => {
+                        self.replica.is_some() && self.in_zsei(container_id).await
+                    }
+                    None => false,
+                };
+                if attempt.priority != SyncPriority::High && in_zsei {
+                    return Outcome::Delivered;
+                }
+                reached(self.publish_item(item_type, data, *timestamp).await)
+            }
+            (Payload::Message { topic, data }, Destination::Broadcast) => {
+                reached(self.publish(topic, data).await)
+            }
+            (
+                Payload::Item {
+                    item_type,
+                    data,
+                    timestamp,
+                },
+                Destination::Peer(peer),
+            ) => {
+                let Ok(peer_id) = peer.parse::<PeerId>() else {
+                    return Outcome::Refused(format!("Invalid peer ID {}", peer));
+                };
+                match self
+                    .send_item(&peer_id, item_type.clone(), data, *timestamp)
+                    .await
+                {
+                    Ok(InboundOutcome::Rejected(reason)) => Outcome::Refused(reason),
+                    Ok(_) => Outcome::Delivered,
+                    Err(e @ OzoneError::NetworkError(_)) => Outcome::Retry(e.to_string()),
+                    Err(e) => Outcome::Refused(e.to_string()),
+                }
+            }
+            (Payload::Message { .. }, Destination::Peer(_)) => {
+                Outcome::Refused("Gossip messages are only broadcast".into())
+            }
+        }
+    }
+
+    /// Queue a payload that could not be sent for retry
+    async fn defer(
+        &self,
+        container_id: Option<ContainerID>,
+        priority: SyncPriority,
+        payload: Payload,
+        destination: Destination,
+        reason: String,
+    ) {
+        let now = now();
+        if let Some(id) = self
+            .outbox
+            .enqueue(container_id, priority, payload, destination.clone(), now)
+            .await
+        {
+            tracing::info!("Queued {} for {} to retry: {}", id, destination, reason);
+            self.outbox
+                .record(&id, &destination, Outcome::Retry(reason), now)
+                .await;
+            self.save_outbox().await;
+        }
+    }
 
-        if items.is_empty() {
+    async fn save_outbox(&self) {
+        if let Err(e) = self.outbox.save().await {
+            tracing::warn!("Failed to save outbox: {}", e);
+        }
+    }
+
+    /// Items waiting to reach peers, and those that did or failed, oldest
+    /// first
+    pub async fn outbox(&self) -> Vec<OutboxEntry> {
+        self.outbox.entries().await
+    }
+
+    async fn in_zsei(&self, container_id: ContainerID) -> bool {
+        match &self.zsei {
+            Some(zsei) => matches!(
+                zsei.read().await.get_container(container_id).await,
+                Ok(Some(_))
+            ),
+            None => false,
+        }
+    }
+
+    /// Exchange changed containers with every connected peer
+    pub async fn anti_entropy(&self) -> OzoneResult<()> {
+        let Some(replica) = &self.replica else {
             return Ok(());
+        };
+        let now = now();
+        let connected: Vec<PeerId> = self
+            .known_peers
+            .read()
+            .await
+            .iter()
+            .filter(|(_, peer)| peer.connected)
+            .filter(|(peer_id, peer)| {
+                reputation::may_sync(peer_id, Some(peer), &self.config.reputation, now)
+            })
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+
+        for peer_id in connected {
+            match self.sync_with(replica, &peer_id).await {
+                Ok(plan) if plan.push.is_empty() && plan.pull.is_empty() => {}
+                Ok(plan) => tracing::info!(
+                    "Synced with {}: {} pushed, {} pulled",
+                    peer_id,
+                    plan.push.len(),
+                    plan.pull.len()
+                ),
+                Err(e) => tracing::warn!("Anti-entropy with {} failed: {}", peer_id, e),
+            }
         }
+        replica.save().await
+    }
 
-        let peers = self.known_peers.read().await;
+    /// Compare digests with a peer, push what it lacks and pull what it has
+    /// newer
+    async fn sync_with(&self, replica: &Replica, peer_id: &PeerId) -> OzoneResult<delta::SyncPlan> {
+        let local = replica.digests().await;
+        let mut remote = Vec::new();
+        let mut after = None;
+        loop {
+            match self
+                .request(peer_id, TransferRequest::Digests { after })
+                .await?
+            {
+                TransferResponse::Digests { digests, more } => {
+                    after = digests.last().map(|d| d.id.clone());
+                    remote.extend(digests);
+                    if !more || after.is_none() {
+                        break;
+                    }
+                }
+                other => return Err(unexpected(peer_id, &other)),
+            }
+        }
 
-        for item in &items {
-            for (peer_id, _peer) in peers.iter() {
-                if let Err(e) = self.send_to_peer(peer_id, &item.data).await {
-                    tracing::warn!("Failed to send to peer {}: {}", peer_id, e);
+        let plan = delta::plan(&local, &remote);
+        for id in &plan.push {
+            let result = match replica.envelope(id).await {
+                Ok(envelope) => self.send_envelope(peer_id, &envelope.encode()?).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::debug!("Failed to push {} to {}: {}", id, peer_id, e);
+            }
+        }
+        for id in &plan.pull {
+            match self.fetch(peer_id, id).await {
+                Ok(outcome) => tracing::debug!("Pulled {} from {}: {:?}", id, peer_id, outcome),
+                Err(e) => tracing::debug!("Failed to pull {} from {}: {}", id, peer_id, e),
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Fetch a container from a peer in chunks and apply it
+    async fn fetch(&self, peer_id: &PeerId, id: &SyncId) -> OzoneResult<InboundOutcome> {
+        let mut reassembler = transfer::Reassembler::default();
+        let mut index = 0;
+        loop {
+            let request = TransferRequest::Fetch {
+                id: id.clone(),
+                index,
+            };
+            let chunk = match self.request(peer_id, request).await? {
+                TransferResponse::Chunk(chunk) => chunk,
+                TransferResponse::Refused(reason) => {
+                    return Err(OzoneError::NetworkError(format!(
+                        "Peer {} refused {}: {}",
+                        peer_id, id, reason
+                    )))
+                }
+                other => return Err(unexpected(peer_id, &other)),
+            };
+            match reassembler.accept(*peer_id, chunk) {
+                Ok(Some(data)) => {
+                    let author = *peer_id;
+                    return self
+                        .command(|reply| SwarmCommand::Apply {
+                            author,
+                            data,
+                            reply,
+                        })
+                        .await;
                 }
+                Ok(None) => index += 1,
+                Err(reason) => return Err(OzoneError::NetworkError(reason)),
             }
         }
+    }
 
-        Ok(())
+    /// Store pipeline code as chunks served to peers; the pointer names
+    /// this node as its mirror
+    pub async fn store_code(&self, data: &[u8]) -> OzoneResult<CodePointer> {
+        let mut pointer = self.chunks.put(data)?;
+        let addresses = self
+            .command(|reply| SwarmCommand::ListenAddresses { reply })
+            .await
+            .unwrap_or_default();
+        // Prefer an address other peers can reach
+        let address = addresses
+            .iter()
+            .find(|addr| {
+                !addr
+                    .iter()
+                    .any(|p| matches!(p, libp2p::multiaddr::Protocol::Ip4(ip) if ip.is_loopback()))
+            })
+            .or(addresses.first())
+            .map(|addr| format!("{}/p2p/{}", addr, self.local_peer_id))
+            .unwrap_or_default();
+        pointer.mirrors.push(PeerNode {
+            peer_id: self.local_key.public().encode_protobuf(),
+            address,
+            last_seen: now(),
+        });
+        Ok(pointer)
     }
 
-    /// Process batch sync (called periodically)
-    pub async fn process_batch_sync(&self) -> OzoneResult<()> {
-        let items = {
-            let mut queue = self.normal_queue.write().await;
-            std::mem::take(&mut *queue)
+    /// Assemble pipeline code, fetching missing chunks from its mirrors in
+    /// parallel. Mirrors serving chunks that fail verification lose
+    /// reputation and the chunk is tried on the next mirror.
+    pub async fn fetch_code(&self, pointer: &CodePointer) -> OzoneResult<Vec<u8>> {
+        use futures_util::StreamExt;
+
+        chunks::check_size(pointer)?;
+        let missing = self.chunks.missing(pointer);
+        if !missing.is_empty() {
+            let mirrors = self.mirrors(pointer).await;
+            if mirrors.is_empty() {
+                return Err(OzoneError::NotFound(format!(
+                    "No mirror of code {} is reachable",
+                    hex::encode(pointer.hash)
+                )));
+            }
+            let fetched: Vec<OzoneResult<()>> =
+                futures_util::stream::iter(missing.into_iter().enumerate())
+                    .map(|(i, chunk_id)| self.fetch_chunk(&mirrors, i, chunk_id))
+                    .buffer_unordered(chunks::MAX_PARALLEL_FETCHES)
+                    .collect()
+                    .await;
+            fetched.into_iter().collect::<OzoneResult<()>>()?;
+        }
+        self.chunks.assemble(pointer)
+    }
+
+    /// Mirrors of a pointer that are connected, dialing those that are not
+    async fn mirrors(&self, pointer: &CodePointer) -> Vec<PeerId> {
+        let mut mirrors = Vec::new();
+        for node in &pointer.mirrors {
+            let Ok(key) = libp2p::identity::PublicKey::try_decode_protobuf(&node.peer_id) else {
+                continue;
+            };
+            let peer_id = key.to_peer_id();
+            if peer_id == self.local_peer_id || mirrors.contains(&peer_id) {
+                continue;
+            }
+            let address = {
+                let peers = self.known_peers.read().await;
+                let peer = peers.get(&peer_id);
+                if reputation::is_banned(&peer_id, peer, &self.config.reputation, now()) {
+                    continue;
+                }
+                peer.and_then(peer_book::dial_address)
+                    .unwrap_or_else(|| node.address.clone())
+            };
+            if self.ensure_connected(&peer_id, &address).await {
+                mirrors.push(peer_id);
+            }
+        }
+        mirrors
+    }
+
+    /// Dial a peer unless connected; true once it is connected
+    async fn ensure_connected(&self, peer_id: &PeerId, address: &str) -> bool {
+        let peer_id = *peer_id;
+        let is_connected = || async {
+            self.command(|reply| SwarmCommand::IsConnected { peer_id, reply })
+                .await
+                .unwrap_or(false)
+        };
+        if is_connected().await {
+            return true;
+        }
+        let Ok(addr) = address.parse::<libp2p::Multiaddr>() else {
+            return false;
         };
+        if !matches!(
+            self.command(|reply| SwarmCommand::Dial { addr, reply })
+                .await,
+            Ok(Ok(()))
+        ) {
+            return false;
+        }
+        let deadline = tokio::time::Instant::now() + chunks::MIRROR_DIAL_TIMEOUT;
+        while tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            if is_connected().await {
+                return true;
+            }
+        }
+        false
+    }
 
-        if items.is_empty() {
-            return Ok(());
+    /// Fetch one chunk, starting with mirror `offset` so parallel fetches
+    /// spread over the mirrors
+    async fn fetch_chunk(
+        &self,
+        mirrors: &[PeerId],
+        offset: usize,
+        chunk_id: ChunkID,
+    ) -> OzoneResult<()> {
+        for i in 0..mirrors.len() {
+            let peer_id = &mirrors[(offset + i) % mirrors.len()];
+            let request = TransferRequest::CodeChunk { chunk_id };
+            match self.request(peer_id, request).await {
+                Ok(TransferResponse::CodeChunk(data)) => {
+                    match self.chunks.insert(&chunk_id, &data) {
+                        Ok(()) => return Ok(()),
+                        Err(e) => {
+                            tracing::warn!("Refused chunk from {}: {}", peer_id, e);
+                            self.penalize(peer_id, Offence::HashMismatch).await;
+                        }
+                    }
+                }
+                Ok(other) => tracing::debug!("{}", unexpected(peer_id, &other)),
+                Err(e) => tracing::debug!("Chunk request to {} failed: {}", peer_id, e),
+            }
         }
+        Err(OzoneError::NotFound(format!(
+            "No mirror served chunk {}",
+            hex::encode(chunk_id)
+        )))
+    }
 
-        tracing::info!("Processing batch sync of {} items", items.len());
+    /// Report this device's load to the account's connected devices,
+    /// storing the adverts they answer with
+    pub async fn advertise_device(&self, running: u32, queued: u32) {
+        if self.devices.account().is_empty() {
+            return;
+        }
+        let advert = self.devices.update_load(running, queued, now()).await;
+        for peer_id in self.devices.account() {
+            let connected = self
+                .known_peers
+                .read()
+                .await
+                .get(peer_id)
+                .is_some_and(|peer| peer.connected);
+            if !connected {
+                continue;
+            }
+            match self
+                .request(peer_id, TransferRequest::Device(advert.clone()))
+                .await
+            {
+                Ok(TransferResponse::Device(theirs)) => {
+                    if let Err(reason) = self.devices.observe(peer_id, theirs, now()).await {
+                        tracing::warn!("Ignored device advert from {}: {}", peer_id, reason);
+                    }
+                }
+                Ok(other) => tracing::debug!("{}", unexpected(peer_id, &other)),
+                Err(e) => tracing::debug!("Device advert to {} failed: {}", peer_id, e),
+            }
+        }
+    }
 
-        let peers = self.known_peers.read().await;
+    /// This device, then the account's other devices as last advertised
+    pub async fn devices(&self) -> Vec<DeviceAdvert> {
+        self.devices.devices().await
+    }
 
-        // Group items and send in batches
-        for item in &items {
-            for (peer_id, _peer) in peers.iter() {
-                if let Err(e) = self.send_to_peer(peer_id, &item.data).await {
-                    tracing::warn!("Failed to batch send to peer {}: {}", peer_id, e);
+    /// Run a pipeline on the least loaded account device that suits it and
+    /// accepts the run. `None` when the pipeline is not in
+    /// `offload_pipelines` or no device is better placed or accepts it;
+    /// an error when the device fails it or does not finish in time.
+    pub async fn offload(
+        &self,
+        blueprint: &PipelineBlueprint,
+        input: &PipelineInput,
+        task_id: Option<TaskID>,
+        progress: mpsc::UnboundedSender<PipelineProgress>,
+    ) -> Option<OzoneResult<PipelineOutput>> {
+        let config = self.devices.config();
+        if !config.enable_offload || !config.offload_pipelines.contains(&blueprint.pipeline_id) {
+            return None;
+        }
+        let candidates = self
+            .devices
+            .candidates(&Requirements::of(blueprint, input), now())
+            .await;
+        if candidates.is_empty() {
+            return None;
+        }
+        let (Ok(blueprint_json), Ok(input_json)) =
+            (serde_json::to_vec(blueprint), serde_json::to_vec(input))
+        else {
+            return None;
+        };
+        let timeout = std::time::Duration::from_secs(config.offload_timeout_secs);
+
+        for peer_id in candidates {
+            let offload_id = rand::random();
+            let result = self
+                .devices
+                .expect(offload_id, peer_id, progress.clone())
+                .await;
+            let request = TransferRequest::Offload(OffloadRequest {
+                offload_id,
+                task_id,
+                blueprint: blueprint_json.clone(),
+                input: input_json.clone(),
+            });
+            match self.request(&peer_id, request).await {
+                Ok(TransferResponse::Acknowledged) => {
+                    self.devices.assigned(&peer_id).await;
+                    tracing::info!("Offloaded pipeline {} to {}", blueprint.name, peer_id);
+                    let outcome = tokio::time::timeout(timeout, result).await;
+                    self.devices.forget(offload_id).await;
+                    return Some(match outcome {
+                        Ok(Ok(result)) => result,
+                        Ok(Err(_)) => Err(OzoneError::NetworkError(format!(
+                            "Offloaded run on {} was dropped",
+                            peer_id
+                        ))),
+                        Err(_) => Err(OzoneError::NetworkError(format!(
+                            "Offloaded run on {} did not finish in {}s",
+                            peer_id, config.offload_timeout_secs
+                        ))),
+                    });
+                }
+                Ok(TransferResponse::Refused(reason)) => {
+                    tracing::debug!(
+                        "{} declined pipeline {}: {}",
+                        peer_id,
+                        blueprint.name,
+                        reason
+                    )
                 }
+                Ok(other) => tracing::debug!("{}", unexpected(&peer_id, &other)),
+                Err(e) => tracing::debug!("Offload to {} failed: {}", peer_id, e),
             }
+            self.devices.forget(offload_id).await;
         }
+        None
+    }
 
-        Ok(())
+    /// Record an offence by a peer, disconnecting it if it gets banned
+    async fn penalize(&self, peer_id: &PeerId, offence: Offence) {
+        let now = now();
+        let config = &self.config.reputation;
+        let banned = match self.known_peers.write().await.get_mut(peer_id) {
+            Some(peer) => {
+                reputation::record(peer, Event::Offence(offence), config, now);
+                reputation::is_banned(peer_id, Some(peer), config, now)
+            }
+            None => false,
+        };
+        if banned {
+            let peer_id = *peer_id;
+            let _ = self
+                .command(|reply| SwarmCommand::Disconnect { peer_id, reply })
+                .await;
+        }
+    }
+
+    /// Put a proposal by a local user to the network
+    pub async fn propose(
+        &self,
+        proposal_type: ProposalType,
+        content: Value,
+        proposed_by: Option<UserID>,
+    ) -> OzoneResult<ConsensusProposal> {
+        let (proposal, message) = self
+            .consensus
+            .propose(proposal_type, content, proposed_by, now())
+            .await?;
+        // Peers that miss it receive it with the outcome
+        if let Err(e) = self.gossip_consensus(&message).await {
+            tracing::warn!("Proposal {} not gossiped: {}", proposal.proposal_id, e);
+        }
+        self.consensus.save().await?;
+        Ok(proposal)
+    }
+
+    /// Vote on an open proposal
+    pub async fn vote(
+        &self,
+        proposal_id: u64,
+        vote: ConsensusVoteType,
+        verification: VerificationResult,
+    ) -> OzoneResult<()> {
+        let message = {
+            let peers = self.known_peers.read().await;
+            self.consensus
+                .vote(proposal_id, vote, verification, &peers, now())
+                .await?
+        };
+        if let Err(e) = self.gossip_consensus(&message).await {
+            tracing::warn!("Vote on {} not gossiped: {}", proposal_id, e);
+        }
+        self.consensus.save().await
+    }
+
+    /// Known proposals with their votes, newest first
+    pub async fn proposals(&self) -> Vec<ConsensusProposal> {
+        self.consensus.proposals().await
+    }
+
+    pub async fn proposal(&self, proposal_id: u64) -> Option<ConsensusProposal> {
+        self.consensus.proposal(proposal_id).await
+    }
+
+    /// Close proposals whose voting period ended and gossip the outcomes
+    pub async fn close_proposals(&self) -> OzoneResult<()> {
+        let outcomes = {
+            let peers = self.known_peers.read().await;
+            self.consensus.close_expired(&peers, now()).await
+        };
+        for message in &outcomes {
+            if let Err(e) = self.gossip_consensus(message).await {
+                tracing::debug!("Outcome not gossiped: {}", e);
+            }
+        }
+        self.consensus.save().await
+    }
+
+    /// Decay peer reputations and credit closed proposals to peers that
+    /// made them; returns this node's own closed proposals
+    pub async fn update_reputation(&self) -> Vec<Settlement> {
+        let now = now();
+        let config = &self.config.reputation;
+        let mut own = Vec::new();
+        let mut peers = self.known_peers.write().await;
+        for peer in peers.values_mut() {
+            reputation::decay(peer, config, now);
+        }
+        for settlement in self.consensus.take_settled().await {
+            if settlement.proposer == self.local_peer_id {
+                own.push(settlement);
+                continue;
+            }
+            let event = match settlement.status {
+                ConsensusStatus::Accepted => Event::ProposalAccepted,
+                ConsensusStatus::Rejected => Event::ProposalRejected,
+                _ => continue,
+            };
+            if let Some(peer) = peers.get_mut(&settlement.proposer) {
+                reputation::record(peer, event, config, now);
+            }
+        }
+        own
+    }
+
+    /// Refuse a peer until it is unbanned, closing its connections
+    pub async fn ban_peer(&self, peer_id: &PeerId) {
+        {
+            let mut peers = self.known_peers.write().await;
+            let peer = peers.entry(*peer_id).or_insert_with(|| PeerInfo {
+                peer_id: peer_id.to_string(),
+                address: String::new(),
+                addresses: Vec::new(),
+                last_seen: now(),
+                latency_ms: 0,
+                reputation: reputation::NEUTRAL,
+                contribution_count: 0,
+                capabilities: Vec::new(),
+                connected: false,
+                agent_version: None,
+                reputation_updated: 0,
+                banned_until: 0,
+            });
+            peer.banned_until = reputation::BANNED_FOREVER;
+        }
+        let peer_id = *peer_id;
+        let _ = self
+            .command(|reply| SwarmCommand::Disconnect { peer_id, reply })
+            .await;
+        tracing::info!("Banned peer {}", peer_id);
+    }
+
+    /// Lift a ban, restoring a neutral reputation
+    pub async fn unban_peer(&self, peer_id: &PeerId) {
+        if let Some(peer) = self.known_peers.write().await.get_mut(peer_id) {
+            peer.banned_until = 0;
+            peer.reputation = reputation::NEUTRAL;
+            peer.reputation_updated = now();
+        }
+    }
+
+    async fn gossip_consensus(&self, message: &ConsensusMessage) -> OzoneResult<usize> {
+        let data = message.encode()?;
+        if data.len() > protocol::MAX_GOSSIP_SIZE {
+            return Err(OzoneError::NetworkError(format!(
+                "Consensus message of {} bytes is too large to gossip",
+                data.len()
+            )));
+        }
+        self.broadcast(protocol::CONSENSUS_TOPIC, &data).await
+    }
+
+    /// Publish a sync item on the topic for its type; envelopes too large
+    /// to gossip are sent to each connected peer instead
+    async fn publish_item(
+        &self,
+        item_type: &SyncItemType,
+        data: &[u8],
+        timestamp: u64,
+    ) -> OzoneResult<usize> {
+        let data = self
+            .seal(item_type.clone(), data, timestamp)
+            .await?
+            .encode()?;
+        if data.len() <= protocol::MAX_GOSSIP_SIZE {
+            return self.publish(protocol::topic_for(item_type), &data).await;
+        }
+
+        let connected: Vec<PeerId> = self
+            .known_peers
+            .read()
+            .await
+            .iter()
+            .filter(|(_, peer)| peer.connected)
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+        let mut sent = 0;
+        for peer_id in connected {
+            match self.send_envelope(&peer_id, &data).await {
+                Ok(_) => sent += 1,
+                Err(e) => tracing::warn!("Failed to send {:?} to {}: {}", item_type, peer_id, e),
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Sign a JSON-serialized container with its sync IDs and version
+    /// vector, refusing user data, which never leaves this node
+    async fn seal(&self, kind: SyncItemType, data: &[u8], timestamp: u64) -> OzoneResult<Envelope> {
+        let container: Container = serde_json::from_slice(data).map_err(|e| {
+            OzoneError::SerializationError(format!("Sync item is not a container: {}", e))
+        })?;
+        let container_type = container.local_state.metadata.container_type;
+        if !is_shareable(container_type) {
+            return Err(OzoneError::PermissionDenied(format!(
+                "{:?} containers are never synced",
+                container_type
+            )));
+        }
+        if container.local_state.metadata.owner_id != 0 {
+            return Err(OzoneError::PermissionDenied(format!(
+                "Container {} belongs to a user and is never synced",
+                container.global_state.container_id
+            )));
+        }
+        if let Some(replica) = &self.replica {
+            return replica.seal_item(kind, &container, timestamp).await;
+        }
+        let item = SyncedContainer::unmapped(&self.local_peer_id.to_string(), container);
+        let payload = serde_json::to_vec(&item)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        Envelope::seal(kind, payload, VersionVector::default(), timestamp, &self.local_key)
     }
 
     /// Connect to a peer using libp2p
@@ -464,31 +1415,38 @@ impl NetworkManager {
             }
         });
 
-        // Get the swarm
-        let mut swarm = self.swarm.lock().await;
-
         // Dial the peer
-        match swarm.dial(multiaddr.clone()) {
-            Ok(_) => {
+        let addr = multiaddr.clone();
+        match self
+            .command(|reply| SwarmCommand::Dial { addr, reply })
+            .await
+            .and_then(|result| result)
+        {
+            Ok(()) => {
                 // Track the connection attempt
                 self.connection_attempts
                     .insert(multiaddr.to_string(), std::time::Instant::now());
 
                 // If we have peer_id, add to known peers
                 if let Some(pid) = peer_id {
-                    self.known_peers.write().await.insert(
-                        pid,
-                        PeerInfo {
+                    self.known_peers
+                        .write()
+                        .await
+                        .entry(pid)
+                        .or_insert_with(|| PeerInfo {
                             peer_id: pid.to_string(),
                             address: multiaddr.to_string(),
                             addresses: vec![multiaddr.to_string()],
                             last_seen: now(),
                             latency_ms: 0,
-                            reputation: 0.5,
+                            reputation: reputation::NEUTRAL,
                             contribution_count: 0,
                             capabilities: Vec::new(),
-                        },
-                    );
+                            connected: false,
+                            agent_version: None,
+                            reputation_updated: 0,
+                            banned_until: 0,
+                        });
                 }
 
                 tracing::info!("Initiated connection to peer: {}", peer_addr);
@@ -496,96 +1454,210 @@ impl NetworkManager {
             }
             Err(e) => {
                 tracing::warn!("Failed to dial peer {}: {}", peer_addr, e);
-                Err(OzoneError::NetworkError(format!("Failed to connect: {}", e)))
+                Err(e)
             }
         }
     }
 
-    /// Send data to a specific peer using libp2p gossipsub
-    pub async fn send_to_peer(&self, peer_id: &PeerId, data: &[u8]) -> OzoneResult<()> {
-        // Get the swarm
-        let mut swarm = self.swarm.lock().await;
+    /// Send a JSON-serialized container to a specific peer over the
+    /// transfer protocol; returns how the peer handled it. A container that
+    /// cannot be sent now stays in the outbox and is retried.
+    pub async fn send_to_peer(
+        &self,
+        peer_id: &PeerId,
+        kind: SyncItemType,
+        data: &[u8],
+    ) -> OzoneResult<InboundOutcome> {
+        let timestamp = now();
+        let result = self.send_item(peer_id, kind.clone(), data, timestamp).await;
+        if let Err(e @ OzoneError::NetworkError(_)) = &result {
+            let container_id = serde_json::from_slice::<Container>(data)
+                .ok()
+                .map(|container| container.global_state.container_id);
+            let payload = Payload::Item {
+                item_type: kind,
+                data: data.to_vec(),
+                timestamp,
+            };
+            let destination = Destination::Peer(peer_id.to_string());
+            self.defer(
+                container_id,
+                SyncPriority::Normal,
+                payload,
+                destination,
+                e.to_string(),
+            )
+            .await;
+        }
+        result
+    }
 
+    /// Seal a container and send it to a connected peer
+    async fn send_item(
+        &self,
+        peer_id: &PeerId,
+        kind: SyncItemType,
+        data: &[u8],
+        timestamp: u64,
+    ) -> OzoneResult<InboundOutcome> {
         // Check if peer is connected
-        if !swarm.is_connected(peer_id) {
+        let peer = *peer_id;
+        if !self
+            .command(|reply| SwarmCommand::IsConnected {
+                peer_id: peer,
+                reply,
+            })
+            .await?
+        {
             return Err(OzoneError::NetworkError(format!(
                 "Peer {} is not connected",
                 peer_id
             )));
         }
 
-        // Create a direct topic for this peer (for unicast)
-        let topic_str = format!("direct/{}", peer_id);
-        let topic = libp2p::gossipsub::IdentTopic::new(&topic_str);
-
-        // Publish the message
-        match swarm
-            .behaviour_mut()
-            .gossipsub
-            .publish(topic, data.to_vec())
-        {
-            Ok(message_id) => {
-                tracing::debug!("Sent message {} to peer {}", message_id, peer_id);
-
-                // Update stats
-                let mut stats = self.stats.lock().await;
-                stats.messages_sent += 1;
-                stats.bytes_sent += data.len() as u64;
+        let envelope = self.seal(kind, data, timestamp).await?;
+        let outcome = self.send_envelope(peer_id, &envelope.encode()?).await?;
+        tracing::debug!("Sent message to peer {}: {:?}", peer_id, outcome);
+        Ok(outcome)
+    }
 
-                Ok(())
-            }
-            Err(e) => {
-                tracing::warn!("Failed to send to peer {}: {:?}", peer_id, e);
-                Err(OzoneError::NetworkError(format!("Send failed: {:?}", e)))
+    /// Send an encoded envelope to a peer, in chunks if it is large
+    async fn send_envelope(&self, peer_id: &PeerId, data: &[u8]) -> OzoneResult<InboundOutcome> {
+        for request in transfer::split(rand::random(), data) {
+            match self.request(peer_id, request).await? {
+                TransferResponse::ChunkReceived => {}
+                TransferResponse::Done(outcome) => {
+                    let mut stats = self.stats.lock().await;
+                    stats.messages_sent += 1;
+                    stats.bytes_sent += data.len() as u64;
+                    return Ok(outcome);
+                }
+                other => return Err(unexpected(peer_id, &other)),
             }
         }
+        Err(OzoneError::NetworkError(format!(
+            "Peer {} did not acknowledge the transfer",
+            peer_id
+        )))
     }
 
-    /// Broadcast data to all connected peers
+    /// Send a transfer request and wait for the peer's response
+    async fn request(
+        &self,
+        peer_id: &PeerId,
+        request: TransferRequest,
+    ) -> OzoneResult<TransferResponse> {
+        let peer_id = *peer_id;
+        self.command(|reply| SwarmCommand::Send {
+            peer_id,
+            request,
+            reply,
+        })
+        .await?
+    }
+
+    /// Broadcast data to all connected peers; data that reaches no peer
+    /// stays in the outbox and is retried
     pub async fn broadcast(&self, topic: &str, data: &[u8]) -> OzoneResult<usize> {
-        use libp2p::gossipsub::IdentTopic;
+        let result = self.publish(topic, data).await;
+        let reason = match &result {
+            Ok(0) => Some("No peer reached".to_string()),
+            Err(e @ OzoneError::NetworkError(_)) => Some(e.to_string()),
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            let payload = Payload::Message {
+                topic: topic.to_string(),
+                data: data.to_vec(),
+            };
+            self.defer(
+                None,
+                SyncPriority::High,
+                payload,
+                Destination::Broadcast,
+                reason,
+            )
+            .await;
+        }
+        result
+    }
 
-        let topic = IdentTopic::new(topic);
-        let mut swarm = self.swarm.lock().await;
+    /// Publish on a gossip topic; returns the number of connected peers
+    async fn publish(&self, topic: &str, data: &[u8]) -> OzoneResult<usize> {
+        let topic = topic.to_string();
+        let payload = data.to_vec();
+        let peer_count = self
+            .command(|reply| SwarmCommand::Publish {
+                topic,
+                data: payload,
+                reply,
+            })
+            .await??;
 
-        match swarm
-            .behaviour_mut()
-            .gossipsub
-            .publish(topic, data.to_vec())
-        {
-            Ok(_) => {
-                let peer_count = swarm.connected_peers().count();
-                let mut stats = self.stats.lock().await;
-                stats.messages_sent += peer_count as u64;
-                stats.bytes_sent += (data.len() * peer_count) as u64;
-                Ok(peer_count)
-            }
-            Err(e) => Err(OzoneError::NetworkError(format!("Broadcast failed: {:?}", e))),
-        }
+        let mut stats = self.stats.lock().await;
+        stats.messages_sent += peer_count as u64;
+        stats.bytes_sent += (data.len() * peer_count) as u64;
+        Ok(peer_count)
+    }
+
+    /// Faults injected into this node's inbound traffic
+    pub fn faults(&self) -> Arc<Faults> {
+        self.faults.clone()
+    }
+
+    /// Known peers and their current state
+    pub async fn peers(&self) -> Vec<PeerInfo> {
+        self.known_peers.read().await.values().cloned().collect()
+    }
+
+    /// Message counters
+    pub async fn stats(&self) -> NetworkStats {
+        self.stats.lock().await.clone()
     }
 
     /// Get network status
     pub async fn get_status(&self) -> NetworkStatus {
         let peers = self.known_peers.read().await;
-        let high_queue = self.high_priority_queue.read().await;
-        let normal_queue = self.normal_queue.read().await;
+        let outbox = self.outbox.summary().await;
 
         NetworkStatus {
             enabled: self.config.enable_p2p,
             peer_id: Some(self.local_peer_id.to_string()),
-            connected_peers: peers.len(),
-            pending_high_priority: high_queue.len(),
-            pending_normal: normal_queue.len(),
+            connected_peers: peers.values().filter(|p| p.connected).count(),
+            known_peers: peers.len(),
+            pending_high_priority: outbox.pending_high,
+            pending_normal: outbox.pending_normal,
+            outbox,
         }
     }
 }
 
+fn unexpected(peer_id: &PeerId, response: &TransferResponse) -> OzoneError {
+    OzoneError::NetworkError(format!(
+        "Unexpected response from {}: {:?}",
+        peer_id, response
+    ))
+}
+
 /// Network status summary
 #[derive(Debug, Clone)]
 pub struct NetworkStatus {
     pub enabled: bool,
     pub peer_id: Option<String>,
     pub connected_peers: usize,
+    pub known_peers: usize,
     pub pending_high_priority: usize,
     pub pending_normal: usize,
+    /// Outbox items by state
+    pub outbox: OutboxSummary,
+}
+
+/// Outcome of publishing or sending to every connected peer
+fn reached(result: OzoneResult<usize>) -> Outcome {
+    match result {
+        Ok(0) => Outcome::Retry("No peer reached".into()),
+        Ok(_) => Outcome::Delivered,
+        Err(e @ OzoneError::NetworkError(_)) => Outcome::Retry(e.to_string()),
+        Err(e) => Outcome::Refused(e.to_string()),
+    }
 }
@@ -0,0 +1,511 @@
+//! Persistent outbound sync queue
+//!
+//! Payloads waiting to reach peers are kept in `<data_path>/outbox.json`
+//! until delivered, so a failed send or a restart loses nothing. Each item
+//! is identified by the Blake3 hash of its payload; queueing the same
+//! payload again adds destinations to the existing item instead of a copy.
+//! An item is broadcast to every peer and/or sent to specific peers, with
+//! delivery tracked per destination. A failed attempt is retried after
+//! `retry_base_secs`, doubling up to `retry_max_secs`, and given up after
+//! `max_attempts` or when the peer refuses it. Items expire after the TTL
+//! for their `SyncPriority`, delivered or not.
+
+use super::protocol::hex_bytes;
+use super::{SyncItemType, SyncPriority};
+use crate::config::OutboxConfig;
+use crate::types::{ContainerID, OzoneError, OzoneResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+const OUTBOX_FILE: &str = "outbox.json";
+
+/// What an outbox item sends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Payload {
+    /// A JSON-serialized container, sealed in an envelope when sent
+    Item {
+        item_type: SyncItemType,
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+        timestamp: u64,
+    },
+    /// An encoded message for a gossip topic
+    Message {
+        topic: String,
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+    },
+}
+
+impl Payload {
+    /// Hex of the Blake3 hash of the content, ignoring the timestamp
+    pub fn id(&self) -> String {
+        let mut hasher = blake3::Hasher::new();
+        match self {
+            Payload::Item {
+                item_type, data, ..
+            } => {
+                hasher.update(format!("{:?}", item_type).as_bytes());
+                hasher.update(data);
+            }
+            Payload::Message { topic, data } => {
+                hasher.update(topic.as_bytes());
+                hasher.update(data);
+            }
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Destination {
+    /// Every connected peer
+    Broadcast,
+    /// One peer, by peer ID
+    Peer(String),
+}
+
+impl std::fmt::Display for Destination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Destination::Broadcast => write!(f, "all peers"),
+            Destination::Peer(peer_id) => write!(f, "{}", peer_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// Refused by the peer or out of attempts
+    Failed,
+}
+
+/// Delivery of an item to one destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    pub destination: Destination,
+    pub status: DeliveryStatus,
+    /// Failed attempts so far
+    pub attempts: u32,
+    /// Not attempted before this time
+    pub next_attempt: u64,
+    pub last_error: Option<String>,
+    pub updated_at: u64,
+}
+
+impl Delivery {
+    fn new(destination: Destination, now: u64) -> Self {
+        Self {
+            destination,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt: now,
+            last_error: None,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    /// See `Payload::id`
+    pub id: String,
+    pub container_id: Option<ContainerID>,
+    pub priority: SyncPriority,
+    pub payload: Payload,
+    pub queued_at: u64,
+    pub expires_at: u64,
+    pub deliveries: Vec<Delivery>,
+}
+
+impl OutboxEntry {
+    pub fn is_pending(&self) -> bool {
+        self.has(DeliveryStatus::Pending)
+    }
+
+    pub fn has(&self, status: DeliveryStatus) -> bool {
+        self.deliveries.iter().any(|d| d.status == status)
+    }
+}
+
+/// A delivery due for an attempt
+#[derive(Debug, Clone)]
+pub struct Attempt {
+    pub id: String,
+    pub container_id: Option<ContainerID>,
+    pub priority: SyncPriority,
+    pub payload: Payload,
+    pub destination: Destination,
+}
+
+/// How an attempt went
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Delivered,
+    /// Failed for now; retried after a backoff
+    Retry(String),
+    /// Refused for good
+    Refused(String),
+}
+
+/// Items with pending deliveries by priority, and items with failed ones
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OutboxSummary {
+    pub pending_high: usize,
+    pub pending_normal: usize,
+    pub pending_low: usize,
+    pub failed: usize,
+}
+
+struct State {
+    entries: BTreeMap<String, OutboxEntry>,
+    dirty: bool,
+}
+
+pub struct Outbox {
+    path: PathBuf,
+    config: OutboxConfig,
+    state: Mutex<State>,
+}
+
+impl Outbox {
+    /// Load saved items; a missing or unreadable file starts empty
+    pub fn load(dir: &Path, config: OutboxConfig) -> Self {
+        let path = dir.join(OUTBOX_FILE);
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring outbox {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path,
+            config,
+            state: Mutex::new(State {
+                entries,
+                dirty: false,
+            }),
+        }
+    }
+
+    pub async fn save(&self) -> OzoneResult<()> {
+        let mut state = self.state.lock().await;
+        if !state.dirty {
+            return Ok(());
+        }
+        let content = serde_json::to_string(&state.entries)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &self.path)?;
+        state.dirty = false;
+        Ok(())
+    }
+
+    /// Queue a payload for a destination; returns the item ID, or `None`
+    /// for priorities that never sync. A destination the item already has
+    /// is left alone unless its delivery failed, which starts it over.
+    pub async fn enqueue(
+        &self,
+        container_id: Option<ContainerID>,
+        priority: SyncPriority,
+        payload: Payload,
+        destination: Destination,
+        now: u64,
+    ) -> Option<String> {
+        let ttl = match priority {
+            SyncPriority::High => self.config.high_ttl_secs,
+            SyncPriority::Normal => self.config.normal_ttl_secs,
+            SyncPriority::Low => self.config.low_ttl_secs,
+            SyncPriority::Never => return None,
+        };
+        let id = payload.id();
+        let mut state = self.state.lock().await;
+        let entry = state
+            .entries
+            .entry(id.clone())
+            .or_insert_with(|| OutboxEntry {
+                id: id.clone(),
+                container_id,
+                priority,
+                payload,
+                queued_at: now,
+                expires_at: now + ttl,
+                deliveries: Vec::new(),
+            });
+        match entry
+            .deliveries
+            .iter_mut()
+            .find(|d| d.destination == destination)
+        {
+            Some(delivery) if delivery.status == DeliveryStatus::Failed => {
+                *delivery = Delivery::new(destination, now);
+                entry.expires_at = entry.expires_at.max(now + ttl);
+            }
+            Some(_) => {}
+            None => {
+                entry.deliveries.push(Delivery::new(destination, now));
+                entry.expires_at = entry.expires_at.max(now + ttl);
+            }
+        }
+        state.dirty = true;
+
+        // Over the limit, drop finished items first, then the oldest
+        while state.entries.len() > self.config.max_items.max(1) {
+            let Some(oldest) = state
+                .entries
+                .values()
+                .filter(|entry| entry.id != id)
+                .min_by_key(|entry| (entry.is_pending(), entry.queued_at))
+                .map(|entry| entry.id.clone())
+            else {
+                break;
+            };
+            if let Some(dropped) = state.entries.remove(&oldest) {
+                if dropped.is_pending() {
+                    tracing::warn!("Outbox full, dropped undelivered item {}", dropped.id);
+                }
+            }
+        }
+        Some(id)
+    }
+
+    /// Pending deliveries whose next attempt is due, of item `only` if set,
+    /// high priority first
+    pub async fn due(&self, only: Option<&str>, now: u64) -> Vec<Attempt> {
+        let state = self.state.lock().await;
+        let mut due: Vec<(u64, Attempt)> = Vec::new();
+        for entry in state.entries.values() {
+            if only.is_some_and(|id| id != entry.id) || entry.expires_at <= now {
+                continue;
+            }
+            for delivery in &entry.deliveries {
+                if delivery.status == DeliveryStatus::Pending && delivery.next_attempt <= now {
+                    due.push((
+                        entry.queued_at,
+                        Attempt {
+                            id: entry.id.clone(),
+                            container_id: entry.container_id,
+                            priority: entry.priority,
+                            payload: entry.payload.clone(),
+                            destination: delivery.destination.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+        due.sort_by_key(|(queued_at, attempt)| (rank(attempt.priority), *queued_at));
+        due.into_iter().map(|(_, attempt)| attempt).collect()
+    }
+
+    /// Record how an attempt went
+    pub async fn record(&self, id: &str, destination: &Destination, outcome: Outcome, now: u64) {
+        let mut state = self.state.lock().await;
+        let Some(delivery) = state.entries.get_mut(id).and_then(|entry| {
+            entry
+                .deliveries
+                .iter_mut()
+                .find(|d| d.destination == *destination)
+        }) else {
+            return;
+        };
+        delivery.updated_at = now;
+        match outcome {
+            Outcome::Delivered => {
+                delivery.status = DeliveryStatus::Delivered;
+                delivery.last_error = None;
+            }
+            Outcome::Retry(error) => {
+                delivery.attempts += 1;
+                delivery.last_error = Some(error);
+                if delivery.attempts >= self.config.max_attempts {
+                    delivery.status = DeliveryStatus::Failed;
+                } else {
+                    delivery.next_attempt = now + self.backoff(delivery.attempts);
+                }
+            }
+            Outcome::Refused(error) => {
+                delivery.attempts += 1;
+                delivery.last_error = Some(error);
+                delivery.status = DeliveryStatus::Failed;
+            }
+        }
+        state.dirty = true;
+    }
+
+    /// Drop expired items; returns how many were still undelivered
+    pub async fn expire(&self, now: u64) -> usize {
+        let mut state = self.state.lock().await;
+        let before = state.entries.len();
+        let mut undelivered = 0;
+        state.entries.retain(|_, entry| {
+            let keep = entry.expires_at > now;
+            if !keep && entry.is_pending() {
+                undelivered += 1;
+            }
+            keep
+        });
+        if state.entries.len() != before {
+            state.dirty = true;
+        }
+        undelivered
+    }
+
+    pub async fn summary(&self) -> OutboxSummary {
+        let state = self.state.lock().await;
+        let mut summary = OutboxSummary::default();
+        for entry in state.entries.values() {
+            if entry.is_pending() {
+                match entry.priority {
+                    SyncPriority::High => summary.pending_high += 1,
+                    SyncPriority::Normal => summary.pending_normal += 1,
+                    SyncPriority::Low | SyncPriority::Never => summary.pending_low += 1,
+                }
+            }
+            if entry.has(DeliveryStatus::Failed) {
+                summary.failed += 1;
+            }
+        }
+        summary
+    }
+
+    /// All items, oldest first
+    pub async fn entries(&self) -> Vec<OutboxEntry> {
+        let mut entries: Vec<OutboxEntry> =
+            self.state.lock().await.entries.values().cloned().collect();
+        entries.sort_by_key(|entry| entry.queued_at);
+        entries
+    }
+
+    /// Delay before the attempt after `attempts` failed ones
+    fn backoff(&self, attempts: u32) -> u64 {
+        let doubling = 1u64 << attempts.saturating_sub(1).min(32);
+        self.config
+            .retry_base_secs
+            .saturating_mul(doubling)
+            .min(self.config.retry_max_secs)
+    }
+}
+
+fn rank(priority: SyncPriority) -> u8 {
+    match priority {
+        SyncPriority::High => 0,
+        SyncPriority::Normal => 1,
+        SyncPriority::Low => 2,
+        SyncPriority::Never => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(data: &[u8], timestamp: u64) -> Payload {
+        Payload::Item {
+            item_type: SyncItemType::Container,
+            data: data.to_vec(),
+            timestamp,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup_backoff_and_persistence() {
+        let dir = Path::new("/tmp/test_outbox");
+        let _ = std::fs::remove_dir_all(dir);
+        let config = OutboxConfig {
+            retry_base_secs: 10,
+            retry_max_secs: 25,
+            max_attempts: 3,
+            ..Default::default()
+        };
+        let outbox = Outbox::load(dir, config.clone());
+        let peer = Destination::Peer("peer-a".into());
+
+        // The same content queued twice is one item with both destinations
+        let id = outbox
+            .enqueue(
+                Some(7),
+                SyncPriority::Normal,
+                item(b"x", 100),
+                Destination::Broadcast,
+                100,
+            )
+            .await
+            .unwrap();
+        let again = outbox
+            .enqueue(
+                Some(7),
+                SyncPriority::Normal,
+                item(b"x", 150),
+                peer.clone(),
+                150,
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, again);
+        assert!(outbox
+            .enqueue(
+                None,
+                SyncPriority::Never,
+                item(b"y", 100),
+                Destination::Broadcast,
+                100
+            )
+            .await
+            .is_none());
+        assert_eq!(outbox.due(None, 150).await.len(), 2);
+
+        // Failures back off exponentially up to the cap, then give up
+        outbox
+            .record(&id, &Destination::Broadcast, Outcome::Delivered, 150)
+            .await;
+        outbox
+            .record(&id, &peer, Outcome::Retry("offline".into()), 150)
+            .await;
+        assert!(outbox.due(None, 159).await.is_empty());
+        assert_eq!(outbox.due(None, 160).await.len(), 1);
+        outbox
+            .record(&id, &peer, Outcome::Retry("offline".into()), 160)
+            .await;
+        assert!(outbox.due(None, 179).await.is_empty());
+        assert_eq!(outbox.summary().await.pending_normal, 1);
+
+        // Pending state survives a restart
+        outbox.save().await.unwrap();
+        let outbox = Outbox::load(dir, config);
+        let entry = &outbox.entries().await[0];
+        assert_eq!(entry.deliveries[1].attempts, 2);
+        assert_eq!(entry.deliveries[1].next_attempt, 180);
+        outbox
+            .record(&id, &peer, Outcome::Retry("offline".into()), 180)
+            .await;
+        let summary = outbox.summary().await;
+        assert_eq!((summary.pending_normal, summary.failed), (0, 1));
+
+        // Queueing a failed delivery again starts it over; items expire
+        outbox
+            .enqueue(
+                Some(7),
+                SyncPriority::Normal,
+                item(b"x", 200),
+                peer.clone(),
+                200,
+            )
+            .await;
+        assert_eq!(outbox.due(None, 200).await.len(), 1);
+        assert_eq!(outbox.expire(200 + 86400).await, 1);
+        assert!(outbox.entries().await.is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
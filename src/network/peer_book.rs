@@ -0,0 +1,179 @@
+//! Persistent node identity and peer book
+//!
+//! The node keypair is kept in `<data_path>/node.key` so the PeerId is
+//! stable across restarts. Known peers are saved to `<data_path>/peers.json`
+//! with their reputation and bans, and used to reconnect on the next start.
+
+use super::reputation::BANNED_FOREVER;
+use super::PeerInfo;
+use crate::types::{OzoneError, OzoneResult};
+use libp2p::{identity::Keypair, PeerId};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Node keypair file (protobuf-encoded)
+pub const KEY_FILE: &str = "node.key";
+
+/// Peer book file
+pub const PEER_BOOK_FILE: &str = "peers.json";
+
+/// Peers kept in the book; the least useful are dropped first
+const MAX_ENTRIES: usize = 1000;
+
+/// Load the node keypair, generating and saving one on first start
+pub fn load_or_create_keypair(dir: &Path) -> OzoneResult<Keypair> {
+    let path = dir.join(KEY_FILE);
+    if path.exists() {
+        let bytes = std::fs::read(&path)?;
+        return Keypair::from_protobuf_encoding(&bytes).map_err(|e| {
+            OzoneError::NetworkError(format!("Invalid node key {}: {}", path.display(), e))
+        });
+    }
+
+    let keypair = Keypair::generate_ed25519();
+    let bytes = keypair
+        .to_protobuf_encoding()
+        .map_err(|e| OzoneError::NetworkError(format!("Cannot encode node key: {}", e)))?;
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, bytes)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    tracing::info!("Generated new node key at {}", path.display());
+    Ok(keypair)
+}
+
+/// Known peers saved between runs
+pub struct PeerBook {
+    path: PathBuf,
+}
+
+impl PeerBook {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(PEER_BOOK_FILE),
+        }
+    }
+
+    /// Load saved peers; a missing or unreadable book starts empty
+    pub fn load(&self) -> HashMap<PeerId, PeerInfo> {
+        let Ok(content) = std::fs::read_to_string(&self.path) else {
+            return HashMap::new();
+        };
+        let entries: Vec<PeerInfo> = match serde_json::from_str(&content) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Ignoring peer book {}: {}", self.path.display(), e);
+                return HashMap::new();
+            }
+        };
+        entries
+            .into_iter()
+            .filter_map(|peer| Some((peer.peer_id.parse().ok()?, peer)))
+            .collect()
+    }
+
+    /// Save peers that have an address to dial, and every peer banned for
+    /// good
+    pub fn save(&self, peers: &HashMap<PeerId, PeerInfo>) -> OzoneResult<()> {
+        let (mut entries, banned): (Vec<&PeerInfo>, Vec<&PeerInfo>) = peers
+            .values()
+            .partition(|p| p.banned_until != BANNED_FOREVER);
+        entries.retain(|p| !p.addresses.is_empty());
+        entries.sort_by(|a, b| {
+            rank(b)
+                .partial_cmp(&rank(a))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        entries.truncate(MAX_ENTRIES);
+        entries.extend(banned);
+
+        let content = serde_json::to_string_pretty(&entries)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Order peers by reputation, then recency
+pub fn rank(peer: &PeerInfo) -> (f32, u64) {
+    (peer.reputation, peer.last_seen)
+}
+
+/// Address to dial a known peer at, with its `/p2p/` suffix so the
+/// connection is checked against the expected PeerId
+pub fn dial_address(peer: &PeerInfo) -> Option<String> {
+    let address = if peer.address.is_empty() {
+        peer.addresses.first()?
+    } else {
+        &peer.address
+    };
+    if address.contains("/p2p/") {
+        Some(address.clone())
+    } else {
+        Some(format!("{}/p2p/{}", address, peer.peer_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypair_and_peer_book_persist() {
+        let dir = Path::new("/tmp/test_peer_book");
+        let _ = std::fs::remove_dir_all(dir);
+
+        let first = load_or_create_keypair(dir).unwrap();
+        let second = load_or_create_keypair(dir).unwrap();
+        assert_eq!(first.public().to_peer_id(), second.public().to_peer_id());
+
+        let peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let mut peers = HashMap::new();
+        peers.insert(
+            peer_id,
+            PeerInfo {
+                peer_id: peer_id.to_string(),
+                address: "/ip4/127.0.0.1/tcp/9090".into(),
+                addresses: vec!["/ip4/127.0.0.1/tcp/9090".into()],
+                last_seen: 42,
+                latency_ms: 5,
+                reputation: 0.8,
+                contribution_count: 3,
+                capabilities: Vec::new(),
+                connected: true,
+                agent_version: None,
+                reputation_updated: 42,
+                banned_until: 0,
+            },
+        );
+        let banned = Keypair::generate_ed25519().public().to_peer_id();
+        let mut banned_info = peers[&peer_id].clone();
+        banned_info.peer_id = banned.to_string();
+        banned_info.addresses.clear();
+        banned_info.banned_until = BANNED_FOREVER;
+        peers.insert(banned, banned_info);
+
+        let book = PeerBook::new(dir);
+        book.save(&peers).unwrap();
+        let loaded = book.load();
+        let peer = &loaded[&peer_id];
+        assert_eq!(peer.contribution_count, 3);
+        assert_eq!(peer.reputation, 0.8);
+        assert!(!peer.connected);
+        assert_eq!(loaded[&banned].banned_until, BANNED_FOREVER);
+        assert_eq!(
+            dial_address(peer).unwrap(),
+            format!("/ip4/127.0.0.1/tcp/9090/p2p/{}", peer_id)
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,216 @@
+//! Wire format for P2P sync
+//!
+//! Every item is wrapped in a signed `Envelope`: item type, schema version,
+//! the sender's public key, the container's version vector (see `delta`), a
+//! Blake3 hash of the payload and the payload itself (a JSON-serialized
+//! `SyncedContainer`: the container, its and its parent's network-wide IDs
+//! and the object it points to). Envelopes are published on the
+//! gossipsub topic for their type, or sent to one peer over the transfer
+//! protocol (see `transfer`) when they are addressed to a peer or too large
+//! to gossip.
+
+use super::delta::{SyncedContainer, VersionVector};
+use super::SyncItemType;
+use crate::types::{OzoneError, OzoneResult};
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+/// Identify protocol version advertised by Ozone nodes
+pub const PROTOCOL_VERSION: &str = "/ozone/1.0.0";
+
+/// Envelope schema version written by this node
+pub const SCHEMA_VERSION: u16 = 3;
+
+/// Oldest envelope schema version accepted (1 had no version vector, 2
+/// carried a bare container under the sender's local IDs)
+pub const MIN_SCHEMA_VERSION: u16 = 3;
+
+/// Largest encoded envelope published on gossipsub, under its default
+/// 64 KiB transmit limit; larger ones are sent to each peer over the
+/// transfer protocol
+pub const MAX_GOSSIP_SIZE: usize = 60 * 1024;
+
+/// Topic for methodology announcements
+pub const METHODOLOGY_TOPIC: &str = "ozone/methodologies/1.0.0";
+
+/// Topic for blueprint announcements
+pub const BLUEPRINT_TOPIC: &str = "ozone/blueprints/1.0.0";
+
+/// Topic for container findings and shared pipeline results
+pub const CONTAINER_TOPIC: &str = "ozone/containers/1.0.0";
+
+/// Topic for consensus proposals, votes and outcomes (see `consensus`)
+pub const CONSENSUS_TOPIC: &str = "ozone/consensus/1.0.0";
+
+/// Topics every node subscribes to for container sync
+pub const SYNC_TOPICS: [&str; 3] = [METHODOLOGY_TOPIC, BLUEPRINT_TOPIC, CONTAINER_TOPIC];
+
+/// Broadcast topic for an item type
+pub fn topic_for(item_type: &SyncItemType) -> &'static str {
+    match item_type {
+        SyncItemType::Methodology => METHODOLOGY_TOPIC,
+        SyncItemType::Blueprint => BLUEPRINT_TOPIC,
+        SyncItemType::Container | SyncItemType::PipelineResult => CONTAINER_TOPIC,
+    }
+}
+
+/// Blake3 hash of a payload, hex-encoded
+pub fn content_hash(payload: &[u8]) -> String {
+    blake3::hash(payload).to_hex().to_string()
+}
+
+/// A signed sync item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub kind: SyncItemType,
+    pub schema_version: u16,
+    /// Sender's protobuf-encoded public key
+    #[serde(with = "hex_bytes")]
+    pub sender: Vec<u8>,
+    /// Version vector of the container in `payload`
+    #[serde(default)]
+    pub version: VersionVector,
+    /// Blake3 hash of `payload`
+    pub content_hash: String,
+    pub timestamp: u64,
+    #[serde(with = "hex_bytes")]
+    pub payload: Vec<u8>,
+    /// Sender's signature over the header fields, which include the hash
+    #[serde(with = "hex_bytes")]
+    pub signature: Vec<u8>,
+}
+
+impl Envelope {
+    /// Wrap and sign a payload
+    pub fn seal(
+        kind: SyncItemType,
+        payload: Vec<u8>,
+        version: VersionVector,
+        timestamp: u64,
+        keypair: &Keypair,
+    ) -> OzoneResult<Self> {
+        let mut envelope = Self {
+            kind,
+            schema_version: SCHEMA_VERSION,
+            sender: keypair.public().encode_protobuf(),
+            version,
+            content_hash: content_hash(&payload),
+            timestamp,
+            payload,
+            signature: Vec::new(),
+        };
+        envelope.signature = keypair
+            .sign(&envelope.signed_bytes())
+            .map_err(|e| OzoneError::NetworkError(format!("Cannot sign envelope: {}", e)))?;
+        Ok(envelope)
+    }
+
+    /// Check the schema version, hash and signature; returns the sender
+    pub fn verify(&self) -> OzoneResult<PeerId> {
+        if !(MIN_SCHEMA_VERSION..=SCHEMA_VERSION).contains(&self.schema_version) {
+            return Err(OzoneError::NetworkError(format!(
+                "Unsupported schema version {}",
+                self.schema_version
+            )));
+        }
+        if content_hash(&self.payload) != self.content_hash {
+            return Err(OzoneError::NetworkError(
+                "Payload does not match its content hash".into(),
+            ));
+        }
+        let key = PublicKey::try_decode_protobuf(&self.sender)
+            .map_err(|e| OzoneError::NetworkError(format!("Invalid sender key: {}", e)))?;
+        if !key.verify(&self.signed_bytes(), &self.signature) {
+            return Err(OzoneError::NetworkError(
+                "Invalid envelope signature".into(),
+            ));
+        }
+        Ok(key.to_peer_id())
+    }
+
+    /// Decode the payload as a container with its sync IDs
+    pub fn item(&self) -> OzoneResult<SyncedContainer> {
+        serde_json::from_slice(&self.payload)
+            .map_err(|e| OzoneError::SerializationError(format!("Invalid container: {}", e)))
+    }
+
+    pub fn encode(&self) -> OzoneResult<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| OzoneError::SerializationError(e.to_string()))
+    }
+
+    pub fn decode(data: &[u8]) -> OzoneResult<Self> {
+        serde_json::from_slice(data)
+            .map_err(|e| OzoneError::SerializationError(format!("Invalid envelope: {}", e)))
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        format!(
+            "{}\n{:?}\n{}\n{}\n{}\n{}",
+            self.schema_version,
+            self.kind,
+            hex::encode(&self.sender),
+            serde_json::to_string(&self.version).unwrap_or_default(),
+            self.content_hash,
+            self.timestamp
+        )
+        .into_bytes()
+    }
+}
+
+/// Serialize bytes as a hex string
+pub(super) mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hex::decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_signature_and_hash() {
+        let keypair = Keypair::generate_ed25519();
+        let mut version = VersionVector::default();
+        version.increment("a");
+        let envelope = Envelope::seal(
+            SyncItemType::Methodology,
+            b"{}".to_vec(),
+            version,
+            1,
+            &keypair,
+        )
+        .unwrap();
+
+        let decoded = Envelope::decode(&envelope.encode().unwrap()).unwrap();
+        assert_eq!(decoded.verify().unwrap(), keypair.public().to_peer_id());
+
+        let mut tampered = decoded.clone();
+        tampered.payload = b"[]".to_vec();
+        assert!(tampered.verify().is_err());
+
+        let mut rehashed = tampered.clone();
+        rehashed.content_hash = content_hash(&rehashed.payload);
+        assert!(rehashed.verify().is_err());
+
+        let mut relabelled = decoded.clone();
+        relabelled.kind = SyncItemType::Blueprint;
+        assert!(relabelled.verify().is_err());
+
+        let mut bumped = decoded.clone();
+        bumped.version.increment("b");
+        assert!(bumped.verify().is_err());
+
+        let mut future = decoded;
+        future.schema_version = SCHEMA_VERSION + 1;
+        assert!(future.verify().is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Peer reputation
+//!
+//! `PeerInfo.reputation` starts at `NEUTRAL` and moves with what the peer
+//! sends: containers that pass local verification and proposals the
+//! network accepts raise it; invalid signatures, hash mismatches, message
+//! floods, otherwise invalid messages and rejected proposals lower it. It
+//! decays back toward neutral with `decay_half_life_secs`.
+//!
+//! Reputation weights the peer's consensus votes (see `consensus`). Peers
+//! below `min_sync_reputation` are neither synced with nor accepted from;
+//! peers falling below `ban_below` are banned for `ban_secs`. Peers in
+//! `banned_peers` or banned through `NetworkManager::ban_peer` are refused
+//! for good.
+
+use super::PeerInfo;
+use crate::config::ReputationConfig;
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Reputation of a peer we know nothing about
+pub const NEUTRAL: f32 = 0.5;
+
+/// `banned_until` of a peer banned until it is unbanned
+pub const BANNED_FOREVER: u64 = u64::MAX;
+
+/// Misbehaviour found in a peer's message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offence {
+    InvalidSignature,
+    HashMismatch,
+    /// More messages than `max_messages_per_minute` or proposals than
+    /// `max_proposals_per_day`
+    Spam,
+    /// Any other invalid message
+    Invalid,
+}
+
+/// Why a peer's message was refused
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub offence: Offence,
+    pub reason: String,
+}
+
+impl Rejection {
+    pub fn new(offence: Offence, reason: impl Into<String>) -> Self {
+        Self {
+            offence,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<String> for Rejection {
+    fn from(reason: String) -> Self {
+        Self::new(Offence::Invalid, reason)
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+/// Something a peer did that changes its reputation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A container it sent passed verification and was stored
+    Contribution,
+    ProposalAccepted,
+    ProposalRejected,
+    Offence(Offence),
+}
+
+impl Event {
+    /// Change in reputation
+    pub fn delta(self) -> f32 {
+        match self {
+            Event::Contribution => 0.01,
+            Event::ProposalAccepted => 0.05,
+            Event::ProposalRejected => -0.05,
+            Event::Offence(Offence::InvalidSignature | Offence::HashMismatch) => -0.2,
+            Event::Offence(Offence::Spam) => -0.1,
+            Event::Offence(Offence::Invalid) => -0.05,
+        }
+    }
+}
+
+/// Move a peer's reputation toward neutral for the time since it last
+/// changed
+pub fn decay(peer: &mut PeerInfo, config: &ReputationConfig, now: u64) {
+    let elapsed = now.saturating_sub(peer.reputation_updated);
+    if peer.reputation_updated == 0 || config.decay_half_life_secs == 0 {
+        peer.reputation_updated = now;
+        return;
+    }
+    if elapsed == 0 {
+        return;
+    }
+    let factor = 0.5f32.powf(elapsed as f32 / config.decay_half_life_secs as f32);
+    peer.reputation = NEUTRAL + (peer.reputation - NEUTRAL) * factor;
+    peer.reputation_updated = now;
+}
+
+/// Apply an event to a peer, banning it if its reputation falls below
+/// `ban_below`
+pub fn record(peer: &mut PeerInfo, event: Event, config: &ReputationConfig, now: u64) {
+    decay(peer, config, now);
+    peer.reputation = (peer.reputation + event.delta()).clamp(0.0, 1.0);
+    if peer.reputation < config.ban_below && peer.banned_until < now {
+        peer.banned_until = now.saturating_add(config.ban_secs);
+        tracing::warn!(
+            "Banned peer {} for {}s (reputation {:.2})",
+            peer.peer_id,
+            config.ban_secs,
+            peer.reputation
+        );
+    }
+}
+
+/// Whether a peer is refused, by the config or a ban still in force
+pub fn is_banned(
+    peer_id: &PeerId,
+    peer: Option<&PeerInfo>,
+    config: &ReputationConfig,
+    now: u64,
+) -> bool {
+    config
+        .banned_peers
+        .iter()
+        .any(|banned| *banned == peer_id.to_string())
+        || peer.is_some_and(|peer| peer.banned_until > now)
+}
+
+/// Whether sync data is exchanged with a peer
+pub fn may_sync(
+    peer_id: &PeerId,
+    peer: Option<&PeerInfo>,
+    config: &ReputationConfig,
+    now: u64,
+) -> bool {
+    !is_banned(peer_id, peer, config, now)
+        && peer.map_or(NEUTRAL, |peer| peer.reputation) >= config.min_sync_reputation
+}
+
+/// Counts each peer's messages per minute
+#[derive(Default)]
+pub struct RateLimit {
+    windows: HashMap<PeerId, (u64, u32)>,
+}
+
+impl RateLimit {
+    /// Count a message; returns how many the peer sent this minute
+    pub fn count(&mut self, peer: PeerId, now: u64) -> u32 {
+        let minute = now / 60;
+        self.windows.retain(|_, (window, _)| *window == minute);
+        let (_, count) = self.windows.entry(peer).or_insert((minute, 0));
+        *count += 1;
+        *count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer() -> PeerInfo {
+        PeerInfo {
+            peer_id: PeerId::random().to_string(),
+            address: String::new(),
+            addresses: Vec::new(),
+            last_seen: 0,
+            latency_ms: 0,
+            reputation: NEUTRAL,
+            contribution_count: 0,
+            capabilities: Vec::new(),
+            connected: true,
+            agent_version: None,
+            reputation_updated: 0,
+            banned_until: 0,
+        }
+    }
+
+    #[test]
+    fn test_events_decay_and_bans() {
+        let config = ReputationConfig::default();
+        let mut good = peer();
+        for _ in 0..10 {
+            record(&mut good, Event::Contribution, &config, 1000);
+        }
+        record(&mut good, Event::ProposalAccepted, &config, 1000);
+        assert!((good.reputation - 0.65).abs() < 1e-4);
+
+        // Half the distance to neutral is gone after one half-life
+        decay(&mut good, &config, 1000 + config.decay_half_life_secs);
+        assert!((good.reputation - 0.575).abs() < 1e-4);
+
+        let config = ReputationConfig {
+            min_sync_reputation: 0.25,
+            ban_below: 0.05,
+            ..Default::default()
+        };
+        let mut bad = peer();
+        let peer_id: PeerId = bad.peer_id.parse().unwrap();
+        record(
+            &mut bad,
+            Event::Offence(Offence::InvalidSignature),
+            &config,
+            1000,
+        );
+        assert!(may_sync(&peer_id, Some(&bad), &config, 1000));
+        record(
+            &mut bad,
+            Event::Offence(Offence::HashMismatch),
+            &config,
+            1000,
+        );
+        assert!(!may_sync(&peer_id, Some(&bad), &config, 1000));
+        assert!(!is_banned(&peer_id, Some(&bad), &config, 1000));
+        record(&mut bad, Event::Offence(Offence::Spam), &config, 1000);
+        assert!(is_banned(&peer_id, Some(&bad), &config, 1000));
+        assert!(!is_banned(
+            &peer_id,
+            Some(&bad),
+            &config,
+            1001 + config.ban_secs
+        ));
+
+        let listed = ReputationConfig {
+            banned_peers: vec![good.peer_id.clone()],
+            ..Default::default()
+        };
+        let good_id: PeerId = good.peer_id.parse().unwrap();
+        assert!(is_banned(&good_id, Some(&good), &listed, 1000));
+    }
+
+    #[test]
+    fn test_rate_limit_resets_each_minute() {
+        let mut limit = RateLimit::default();
+        let peer = PeerId::random();
+        assert_eq!(limit.count(peer, 60), 1);
+        assert_eq!(limit.count(peer, 119), 2);
+        assert_eq!(limit.count(PeerId::random(), 119), 1);
+        assert_eq!(limit.count(peer, 120), 1);
+    }
+}
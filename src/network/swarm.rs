@@ -0,0 +1,779 @@
+//! Swarm driver
+//!
+//! Owns the libp2p swarm once the network is initialized and polls it in a
+//! background task. `NetworkManager` talks to it through `SwarmCommand`s.
+//! The driver keeps `known_peers` current from connection, mDNS, identify
+//! and ping events and passes gossipsub messages and direct transfers to
+//! the `InboundHandler`, reporting the outcome back to gossipsub so invalid
+//! messages are not forwarded. Digest and fetch requests from peers doing
+//! anti-entropy are served from the `Replica`, code chunk requests from the
+//! `ChunkStore`, and consensus messages go to `Consensus`. Device adverts
+//! and offloaded pipeline runs are exchanged only with the account's other
+//! devices (see `devices`). Each outcome feeds the author's reputation;
+//! banned peers are disconnected and their messages, like floods and
+//! messages from peers below the sync reputation, are dropped (see
+//! `reputation`). The peer book is saved periodically when peers change and
+//! when the driver stops. `max_peers` caps both dials and accepted
+//! connections. Faults injected by tests (see `faults`) are applied before
+//! anything else; delayed messages wait on a timer task and come back
+//! through `delayed`, so the driver keeps polling meanwhile.
+
+use super::chunks::ChunkStore;
+use super::consensus::Consensus;
+use super::delta::Replica;
+use super::devices::{Devices, OffloadRequest};
+use super::faults::Faults;
+use super::inbound::{InboundHandler, InboundOutcome};
+use super::peer_book::PeerBook;
+use super::reputation::{self, Event, Offence, RateLimit};
+use super::transfer::{Reassembler, TransferRequest, TransferResponse};
+use super::{now, NetworkStats, OzoneBehaviour, OzoneBehaviourEvent, PeerInfo};
+use crate::config::ReputationConfig;
+use crate::types::{OzoneError, OzoneResult};
+use futures_util::StreamExt;
+use libp2p::gossipsub::{self, IdentTopic, MessageAcceptance, MessageId};
+use libp2p::request_response::{self, OutboundRequestId, ResponseChannel};
+use libp2p::swarm::SwarmEvent;
+use libp2p::{identify, mdns, ping, Multiaddr, PeerId, Swarm};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+
+/// How often changed peer state is written to the peer book
+const PEER_BOOK_SAVE_SECS: u64 = 30;
+
+/// Requests from `NetworkManager` to the running swarm
+pub(super) enum SwarmCommand {
+    Dial {
+        addr: Multiaddr,
+        reply: oneshot::Sender<OzoneResult<()>>,
+    },
+    /// Publish on a topic; replies with the number of connected peers
+    Publish {
+        topic: String,
+        data: Vec<u8>,
+        reply: oneshot::Sender<OzoneResult<usize>>,
+    },
+    IsConnected {
+        peer_id: PeerId,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Send a transfer request; replies with the peer's response
+    Send {
+        peer_id: PeerId,
+        request: TransferRequest,
+        reply: oneshot::Sender<OzoneResult<TransferResponse>>,
+    },
+    /// Apply an envelope fetched from `author`
+    Apply {
+        author: PeerId,
+        data: Vec<u8>,
+        reply: oneshot::Sender<InboundOutcome>,
+    },
+    /// Addresses peers can dial this node on
+    ListenAddresses {
+        reply: oneshot::Sender<Vec<Multiaddr>>,
+    },
+    /// Close connections to a peer that was just banned
+    Disconnect {
+        peer_id: PeerId,
+        reply: oneshot::Sender<()>,
+    },
+    /// Stop the driver; replies once the peer book is saved
+    Shutdown { reply: oneshot::Sender<()> },
+}
+
+/// An inbound message, as held back by injected latency
+pub(super) enum Inbound {
+    Gossip {
+        propagation_source: PeerId,
+        message_id: MessageId,
+        message: gossipsub::Message,
+    },
+    Request {
+        peer: PeerId,
+        request: TransferRequest,
+        channel: ResponseChannel<TransferResponse>,
+    },
+}
+
+pub(super) struct SwarmDriver {
+    pub swarm: Swarm<OzoneBehaviour>,
+    pub commands: mpsc::Receiver<SwarmCommand>,
+    pub peer_book: PeerBook,
+    pub known_peers: Arc<RwLock<HashMap<PeerId, PeerInfo>>>,
+    pub stats: Arc<Mutex<NetworkStats>>,
+    pub inbound: InboundHandler,
+    /// Serves digests and fetches to peers
+    pub replica: Option<Arc<Replica>>,
+    /// Receives proposals and votes
+    pub consensus: Arc<Consensus>,
+    /// Serves pipeline code chunks to peers
+    pub chunks: Arc<ChunkStore>,
+    /// Adverts and offloaded runs of the account's devices
+    pub devices: Arc<Devices>,
+    /// Runs pipelines other devices offload here; `None` when this device
+    /// does not accept them
+    pub offloads: Option<mpsc::Sender<(PeerId, OffloadRequest)>>,
+    /// Drops and delays inbound messages in tests
+    pub faults: Arc<Faults>,
+    /// Messages whose injected latency has passed
+    pub delayed: mpsc::UnboundedReceiver<Inbound>,
+    pub delayed_sender: mpsc::UnboundedSender<Inbound>,
+    /// Chunked transfers being received
+    pub reassembler: Reassembler,
+    /// Outbound transfer requests awaiting a response
+    pub pending: HashMap<OutboundRequestId, oneshot::Sender<OzoneResult<TransferResponse>>>,
+    pub max_peers: usize,
+    pub reputation: ReputationConfig,
+    /// Inbound messages per peer this minute
+    pub rate_limit: RateLimit,
+    /// Peer state changed since the last peer book save
+    pub peers_dirty: bool,
+}
+
+impl SwarmDriver {
+    /// Run until shut down or the manager is dropped
+    pub async fn run(mut self) {
+        let mut save_tick =
+            tokio::time::interval(std::time::Duration::from_secs(PEER_BOOK_SAVE_SECS));
+        let shutdown_reply = loop {
+            tokio::select! {
+                command = self.commands.recv() => match command {
+                    Some(SwarmCommand::Shutdown { reply }) => break Some(reply),
+                    None => break None,
+                    Some(command) => self.handle_command(command).await,
+                },
+                event = self.swarm.select_next_some() => self.handle_event(event).await,
+                Some(inbound) = self.delayed.recv() => self.deliver(inbound).await,
+                _ = save_tick.tick() => {
+                    if self.peers_dirty {
+                        self.save_peer_book().await;
+                    }
+                }
+            }
+        };
+
+        self.save_peer_book().await;
+        tracing::info!("P2P swarm stopped");
+        if let Some(reply) = shutdown_reply {
+            let _ = reply.send(());
+        }
+    }
+
+    async fn save_peer_book(&mut self) {
+        let peers = self.known_peers.read().await;
+        match self.peer_book.save(&peers) {
+            Ok(()) => self.peers_dirty = false,
+            Err(e) => tracing::warn!("Failed to save peer book: {}", e),
+        }
+    }
+
+    fn at_peer_limit(&self) -> bool {
+        self.swarm.connected_peers().count() >= self.max_peers
+    }
+
+    async fn handle_command(&mut self, command: SwarmCommand) {
+        match command {
+            SwarmCommand::Dial { addr, reply } => {
+                let result = if self.at_peer_limit() {
+                    Err(OzoneError::NetworkError(format!(
+                        "Peer limit ({}) reached",
+                        self.max_peers
+                    )))
+                } else {
+                    self.swarm
+                        .dial(addr)
+                        .map_err(|e| OzoneError::NetworkError(format!("Failed to connect: {}", e)))
+                };
+                let _ = reply.send(result);
+            }
+            SwarmCommand::Publish { topic, data, reply } => {
+                let result = self
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .publish(IdentTopic::new(topic), data)
+                    .map(|_| self.swarm.connected_peers().count())
+                    .map_err(|e| OzoneError::NetworkError(format!("Publish failed: {:?}", e)));
+                let _ = reply.send(result);
+            }
+            SwarmCommand::IsConnected { peer_id, reply } => {
+                let _ = reply.send(self.swarm.is_connected(&peer_id));
+            }
+            SwarmCommand::Send {
+                peer_id,
+                request,
+                reply,
+            } => {
+                let request_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .transfer
+                    .send_request(&peer_id, request);
+                self.pending.insert(request_id, reply);
+            }
+            SwarmCommand::Apply {
+                author,
+                data,
+                reply,
+            } => {
+                let _ = reply.send(self.apply(&author, None, &data).await);
+            }
+            SwarmCommand::ListenAddresses { reply } => {
+                let addresses = self
+                    .swarm
+                    .external_addresses()
+                    .chain(self.swarm.listeners())
+                    .cloned()
+                    .collect();
+                let _ = reply.send(addresses);
+            }
+            SwarmCommand::Disconnect { peer_id, reply } => {
+                let _ = self.swarm.disconnect_peer_id(peer_id);
+                self.peers_dirty = true;
+                let _ = reply.send(());
+            }
+            SwarmCommand::Shutdown { .. } => {}
+        }
+    }
+
+    async fn handle_event(&mut self, event: SwarmEvent<OzoneBehaviourEvent>) {
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                tracing::info!("P2P listening on {}", address);
+            }
+            SwarmEvent::ConnectionEstablished {
+                peer_id,
+                endpoint,
+                num_established,
+                ..
+            } => {
+                if self.is_banned(&peer_id).await || self.faults.is_blocked(&peer_id).await {
+                    tracing::debug!("Dropping banned or blocked peer {}", peer_id);
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                    return;
+                }
+                // `connected_peers` already includes this peer
+                if num_established.get() == 1
+                    && self.swarm.connected_peers().count() > self.max_peers
+                {
+                    tracing::debug!("Peer limit reached, dropping {}", peer_id);
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                    return;
+                }
+                self.peers_dirty = true;
+                let address = endpoint.get_remote_address().to_string();
+                let mut peers = self.known_peers.write().await;
+                let peer = peers.entry(peer_id).or_insert_with(|| new_peer(&peer_id));
+                peer.connected = true;
+                peer.last_seen = now();
+                peer.address = address.clone();
+                if !peer.addresses.contains(&address) {
+                    peer.addresses.push(address);
+                }
+                tracing::info!("Connected to peer {}", peer_id);
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
+                if let Some(peer) = self.known_peers.write().await.get_mut(&peer_id) {
+                    peer.connected = false;
+                    peer.last_seen = now();
+                }
+                self.devices.peer_lost(&peer_id).await;
+                self.peers_dirty = true;
+                tracing::info!("Disconnected from peer {}", peer_id);
+            }
+            SwarmEvent::OutgoingConnectionError {
+                peer_id: Some(peer_id),
+                error,
+                ..
+            } => {
+                tracing::debug!("Connection to {} failed: {}", peer_id, error);
+            }
+            SwarmEvent::Behaviour(OzoneBehaviourEvent::Mdns(event)) => self.on_mdns(event).await,
+            SwarmEvent::Behaviour(OzoneBehaviourEvent::Identify(event)) => {
+                self.on_identify(event).await
+            }
+            SwarmEvent::Behaviour(OzoneBehaviourEvent::Ping(event)) => self.on_ping(event).await,
+            SwarmEvent::Behaviour(OzoneBehaviourEvent::Gossipsub(event)) => {
+                self.on_gossipsub(event).await
+            }
+            SwarmEvent::Behaviour(OzoneBehaviourEvent::Transfer(event)) => {
+                self.on_transfer(event).await
+            }
+            _ => {}
+        }
+    }
+
+    async fn on_mdns(&mut self, event: mdns::Event) {
+        match event {
+            mdns::Event::Discovered(found) => {
+                self.peers_dirty = true;
+                for (peer_id, addr) in found {
+                    {
+                        let mut peers = self.known_peers.write().await;
+                        let peer = peers.entry(peer_id).or_insert_with(|| new_peer(&peer_id));
+                        let addr_str = addr.to_string();
+                        if peer.address.is_empty() {
+                            peer.address = addr_str.clone();
+                        }
+                        if !peer.addresses.contains(&addr_str) {
+                            peer.addresses.push(addr_str);
+                        }
+                    }
+                    tracing::debug!("mDNS discovered {} at {}", peer_id, addr);
+
+                    self.swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .add_explicit_peer(&peer_id);
+                    let connected = self.swarm.connected_peers().count();
+                    if !self.swarm.is_connected(&peer_id)
+                        && connected < self.max_peers
+                        && !self.is_banned(&peer_id).await
+                    {
+                        if let Err(e) = self.swarm.dial(addr) {
+                            tracing::debug!("Failed to dial discovered peer {}: {}", peer_id, e);
+                        }
+                    }
+                }
+            }
+            mdns::Event::Expired(expired) => {
+                let mut peers = self.known_peers.write().await;
+                for (peer_id, addr) in expired {
+                    if let Some(peer) = peers.get_mut(&peer_id) {
+                        let addr = addr.to_string();
+                        peer.addresses.retain(|a| *a != addr);
+                    }
+                    if !self.swarm.is_connected(&peer_id) {
+                        self.swarm
+                            .behaviour_mut()
+                            .gossipsub
+                            .remove_explicit_peer(&peer_id);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn on_identify(&mut self, event: identify::Event) {
+        let identify::Event::Received { peer_id, info } = event else {
+            return;
+        };
+        self.peers_dirty = true;
+        let mut peers = self.known_peers.write().await;
+        let peer = peers.entry(peer_id).or_insert_with(|| new_peer(&peer_id));
+        peer.last_seen = now();
+        peer.capabilities = info.protocols.iter().map(|p| p.to_string()).collect();
+        peer.agent_version = Some(info.agent_version);
+        for addr in info.listen_addrs {
+            let addr = addr.to_string();
+            if !peer.addresses.contains(&addr) {
+                peer.addresses.push(addr);
+            }
+        }
+        if info.protocol_version != super::protocol::PROTOCOL_VERSION {
+            tracing::warn!(
+                "Peer {} speaks {}, expected {}",
+                peer_id,
+                info.protocol_version,
+                super::protocol::PROTOCOL_VERSION
+            );
+        }
+    }
+
+    async fn on_ping(&mut self, event: ping::Event) {
+        let Ok(rtt) = event.result else {
+            return;
+        };
+        if let Some(peer) = self.known_peers.write().await.get_mut(&event.peer) {
+            peer.latency_ms = rtt.as_millis().min(u32::MAX as u128) as u32;
+            peer.last_seen = now();
+        }
+    }
+
+    async fn on_gossipsub(&mut self, event: gossipsub::Event) {
+        let gossipsub::Event::Message {
+            propagation_source,
+            message_id,
+            message,
+        } = event
+        else {
+            return;
+        };
+
+        let inbound = Inbound::Gossip {
+            propagation_source,
+            message_id,
+            message,
+        };
+        if let Some(inbound) = self.admit(inbound).await {
+            self.deliver(inbound).await;
+        }
+    }
+
+    async fn deliver(&mut self, inbound: Inbound) {
+        match inbound {
+            Inbound::Gossip {
+                propagation_source,
+                message_id,
+                message,
+            } => {
+                self.on_message(propagation_source, message_id, message)
+                    .await
+            }
+            Inbound::Request {
+                peer,
+                request,
+                channel,
+            } => {
+                let response = self.respond(peer, request).await;
+                let _ = self
+                    .swarm
+                    .behaviour_mut()
+                    .transfer
+                    .send_response(channel, response);
+            }
+        }
+    }
+
+    async fn on_message(
+        &mut self,
+        propagation_source: PeerId,
+        message_id: MessageId,
+        message: gossipsub::Message,
+    ) {
+        let author = message.source.unwrap_or(propagation_source);
+        let acceptance = if message.topic.as_str() == super::protocol::CONSENSUS_TOPIC {
+            self.on_consensus(&author, &message.data).await
+        } else {
+            match self
+                .apply(&author, Some(message.topic.as_str()), &message.data)
+                .await
+            {
+                InboundOutcome::Stored(_) => MessageAcceptance::Accept,
+                InboundOutcome::Ignored(_) => MessageAcceptance::Ignore,
+                InboundOutcome::Rejected(_) => MessageAcceptance::Reject,
+            }
+        };
+
+        let _ = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(&message_id, &propagation_source, acceptance);
+    }
+
+    async fn on_consensus(&mut self, author: &PeerId, data: &[u8]) -> MessageAcceptance {
+        {
+            let mut stats = self.stats.lock().await;
+            stats.messages_received += 1;
+            stats.bytes_received += data.len() as u64;
+        }
+        if self.flooding(author).await || self.is_banned(author).await {
+            return MessageAcceptance::Ignore;
+        }
+
+        let result = {
+            let peers = self.known_peers.read().await;
+            self.consensus.handle(author, data, &peers, now()).await
+        };
+        match result {
+            Ok(true) => MessageAcceptance::Accept,
+            Ok(false) => MessageAcceptance::Ignore,
+            Err(rejection) => {
+                tracing::warn!("Rejected consensus message from {}: {}", author, rejection);
+                self.stats.lock().await.messages_rejected += 1;
+                self.record(author, Event::Offence(rejection.offence)).await;
+                MessageAcceptance::Reject
+            }
+        }
+    }
+
+    async fn on_transfer(
+        &mut self,
+        event: request_response::Event<TransferRequest, TransferResponse>,
+    ) {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+            } => {
+                let inbound = Inbound::Request {
+                    peer,
+                    request,
+                    channel,
+                };
+                if let Some(inbound) = self.admit(inbound).await {
+                    self.deliver(inbound).await;
+                }
+            }
+            request_response::Event::Message {
+                message:
+                    request_response::Message::Response {
+                        request_id,
+                        response,
+                    },
+                ..
+            } => {
+                if let Some(reply) = self.pending.remove(&request_id) {
+                    let _ = reply.send(Ok(response));
+                }
+            }
+            request_response::Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => {
+                if let Some(reply) = self.pending.remove(&request_id) {
+                    let _ = reply.send(Err(OzoneError::NetworkError(format!(
+                        "Transfer to {} failed: {}",
+                        peer, error
+                    ))));
+                }
+            }
+            request_response::Event::InboundFailure { peer, error, .. } => {
+                tracing::debug!("Inbound transfer from {} failed: {}", peer, error);
+            }
+            request_response::Event::ResponseSent { .. } => {}
+        }
+    }
+
+    async fn respond(&mut self, peer: PeerId, request: TransferRequest) -> TransferResponse {
+        match request {
+            TransferRequest::Envelope(data) => {
+                TransferResponse::Done(self.apply(&peer, None, &data).await)
+            }
+            TransferRequest::Chunk(chunk) => match self.reassembler.accept(peer, chunk) {
+                Ok(Some(data)) => TransferResponse::Done(self.apply(&peer, None, &data).await),
+                Ok(None) => TransferResponse::ChunkReceived,
+                Err(reason) => {
+                    tracing::warn!("Rejected transfer from {}: {}", peer, reason);
+                    self.stats.lock().await.messages_rejected += 1;
+                    TransferResponse::Done(InboundOutcome::Rejected(reason))
+                }
+            },
+            TransferRequest::Digests { after } => match &self.replica {
+                Some(replica) => {
+                    let (digests, more) = replica.digest_page(after.as_ref()).await;
+                    TransferResponse::Digests { digests, more }
+                }
+                None => TransferResponse::Digests {
+                    digests: Vec::new(),
+                    more: false,
+                },
+            },
+            TransferRequest::Fetch { id, index } => match &self.replica {
+                Some(replica) => match replica.serve(peer, id, index).await {
+                    Ok(chunk) => TransferResponse::Chunk(chunk),
+                    Err(e) => TransferResponse::Refused(e.to_string()),
+                },
+                None => TransferResponse::Refused("ZSEI not attached".into()),
+            },
+            TransferRequest::CodeChunk { chunk_id } => match self.chunks.get(&chunk_id) {
+                Ok(Some(data)) => TransferResponse::CodeChunk(data),
+                Ok(None) => TransferResponse::Refused(format!(
+                    "Chunk {} is not stored",
+                    hex::encode(chunk_id)
+                )),
+                Err(e) => TransferResponse::Refused(e.to_string()),
+            },
+            TransferRequest::Device(advert) => {
+                match self.devices.observe(&peer, advert, now()).await {
+                    Ok(()) => TransferResponse::Device(self.devices.local().await),
+                    Err(reason) => TransferResponse::Refused(reason),
+                }
+            }
+            TransferRequest::Offload(request) => self.accept_offload(peer, request).await,
+            TransferRequest::OffloadProgress {
+                offload_id,
+                progress,
+            } => acknowledge(self.devices.progress(&peer, offload_id, progress).await),
+            TransferRequest::OffloadResult {
+                offload_id,
+                output,
+                error,
+            } => {
+                let result = match error {
+                    Some(error) => Err(OzoneError::PipelineError(error)),
+                    None => serde_json::from_slice(&output)
+                        .map_err(|e| OzoneError::SerializationError(e.to_string())),
+                };
+                acknowledge(self.devices.complete(&peer, offload_id, result).await)
+            }
+        }
+    }
+
+    /// Queue a pipeline run from another device of the account
+    async fn accept_offload(&mut self, peer: PeerId, request: OffloadRequest) -> TransferResponse {
+        let Some(offloads) = &self.offloads else {
+            return TransferResponse::Refused(
+                "This device does not run offloaded pipelines".into(),
+            );
+        };
+        if let Err(reason) = self.devices.admit(&peer).await {
+            return TransferResponse::Refused(reason);
+        }
+        match offloads.try_send((peer, request)) {
+            Ok(()) => TransferResponse::Acknowledged,
+            Err(_) => {
+                self.devices.release().await;
+                TransferResponse::Refused("Offload queue is full".into())
+            }
+        }
+    }
+
+    /// Handle an envelope from `author` and record the outcome
+    async fn apply(&mut self, author: &PeerId, topic: Option<&str>, data: &[u8]) -> InboundOutcome {
+        {
+            let mut stats = self.stats.lock().await;
+            stats.messages_received += 1;
+            stats.bytes_received += data.len() as u64;
+        }
+
+        if self.flooding(author).await {
+            return InboundOutcome::Ignored("Too many messages".to_string());
+        }
+        let trusted = {
+            let peers = self.known_peers.read().await;
+            reputation::may_sync(author, peers.get(author), &self.reputation, now())
+        };
+        if !trusted {
+            return InboundOutcome::Ignored("Peer is banned or below the sync reputation".into());
+        }
+
+        let (outcome, offence) = match self.inbound.handle(author, topic, data).await {
+            // The replica rejects containers conflicting with local ones
+            Ok(outcome) => (outcome, Offence::Invalid),
+            Err(rejection) => (
+                InboundOutcome::Rejected(rejection.reason),
+                rejection.offence,
+            ),
+        };
+        match &outcome {
+            InboundOutcome::Stored(id) => {
+                tracing::info!("Stored container {} from peer {}", id, author);
+                if let Some(peer) = self.known_peers.write().await.get_mut(author) {
+                    peer.contribution_count += 1;
+                }
+                self.record(author, Event::Contribution).await;
+            }
+            InboundOutcome::Ignored(reason) => {
+                tracing::debug!("Ignored message from {}: {}", author, reason);
+            }
+            InboundOutcome::Rejected(reason) => {
+                tracing::warn!("Rejected message from {}: {}", author, reason);
+                self.stats.lock().await.messages_rejected += 1;
+                self.record(author, Event::Offence(offence)).await;
+            }
+        }
+        outcome
+    }
+
+    /// Apply injected faults to a message; returns it if it is to be
+    /// handled now. A delayed message is handed back through `delayed` once
+    /// the injected latency has passed. A lost gossip message is not
+    /// forwarded and a lost request gets no response, failing it.
+    async fn admit(&mut self, inbound: Inbound) -> Option<Inbound> {
+        let peer_id = match &inbound {
+            Inbound::Gossip {
+                propagation_source, ..
+            } => propagation_source,
+            Inbound::Request { peer, .. } => peer,
+        };
+        match self.faults.admit(peer_id).await {
+            Some(latency) if latency.is_zero() => Some(inbound),
+            Some(latency) => {
+                let delayed = self.delayed_sender.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(latency).await;
+                    let _ = delayed.send(inbound);
+                });
+                None
+            }
+            None => {
+                if let Inbound::Gossip {
+                    propagation_source,
+                    message_id,
+                    ..
+                } = inbound
+                {
+                    let _ = self
+                        .swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .report_message_validation_result(
+                            &message_id,
+                            &propagation_source,
+                            MessageAcceptance::Ignore,
+                        );
+                }
+                None
+            }
+        }
+    }
+
+    /// Takes `&mut self` so the future stays `Send`; the swarm is not `Sync`
+    async fn is_banned(&mut self, peer_id: &PeerId) -> bool {
+        let peers = self.known_peers.read().await;
+        reputation::is_banned(peer_id, peers.get(peer_id), &self.reputation, now())
+    }
+
+    /// Count a message from `author`; true once it exceeds the per-minute
+    /// limit, which is charged as spam the first time
+    async fn flooding(&mut self, author: &PeerId) -> bool {
+        let limit = self.reputation.max_messages_per_minute;
+        let count = self.rate_limit.count(*author, now());
+        if count == limit.saturating_add(1) {
+            tracing::warn!("Peer {} sent more than {} messages a minute", author, limit);
+            self.record(author, Event::Offence(Offence::Spam)).await;
+        }
+        count > limit
+    }
+
+    /// Apply a reputation event, disconnecting the peer if it gets banned
+    async fn record(&mut self, peer_id: &PeerId, event: Event) {
+        let now = now();
+        let banned = {
+            let mut peers = self.known_peers.write().await;
+            let peer = peers.entry(*peer_id).or_insert_with(|| new_peer(peer_id));
+            reputation::record(peer, event, &self.reputation, now);
+            reputation::is_banned(peer_id, Some(peer), &self.reputation, now)
+        };
+        self.peers_dirty = true;
+        if banned {
+            let _ = self.swarm.disconnect_peer_id(*peer_id);
+        }
+    }
+}
+
+fn new_peer(peer_id: &PeerId) -> PeerInfo {
+    PeerInfo {
+        peer_id: peer_id.to_string(),
+        address: String::new(),
+        addresses: Vec::new(),
+        last_seen: now(),
+        latency_ms: 0,
+        reputation: reputation::NEUTRAL,
+        contribution_count: 0,
+        capabilities: Vec::new(),
+        connected: false,
+        agent_version: None,
+        reputation_updated: 0,
+        banned_until: 0,
+    }
+}
+
+fn acknowledge(result: Result<(), String>) -> TransferResponse {
+    match result {
+        Ok(()) => TransferResponse::Acknowledged,
+        Err(reason) => TransferResponse::Refused(reason),
+    }
+}
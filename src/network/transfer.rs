@@ -0,0 +1,326 @@
+//! Direct transfers between two peers
+//!
+//! A request/response protocol carrying encoded envelopes to a single peer.
+//! Envelopes up to `CHUNK_SIZE` go in one request; larger ones are split
+//! into numbered chunks, sent in order and reassembled by the receiver,
+//! which answers the last chunk with the inbound outcome. Anti-entropy (see
+//! `delta`) pages through a peer's digests and fetches containers chunk by
+//! chunk over the same protocol, peers fetching pipeline code request its
+//! chunks by ID (see `chunks`) and the devices of an account exchange
+//! adverts and offloaded pipeline runs (see `devices`).
+
+use super::delta::{Digest, SyncId};
+use super::devices::{DeviceAdvert, OffloadRequest};
+use super::inbound::InboundOutcome;
+use super::protocol::hex_bytes;
+use crate::pipeline::PipelineProgress;
+use crate::types::pipeline::ChunkID;
+use libp2p::{PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Request/response protocol name
+pub const TRANSFER_PROTOCOL: StreamProtocol = StreamProtocol::new("/ozone/transfer/1.0.0");
+
+/// Largest payload carried by a single request
+pub const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Largest envelope accepted through chunked transfer
+pub const MAX_TRANSFER_SIZE: usize = 64 * 1024 * 1024;
+
+/// Incomplete transfers are dropped after this long without a chunk
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Incomplete transfers one peer may have open at once
+const MAX_TRANSFERS_PER_PEER: usize = 4;
+
+/// Bytes buffered across all incomplete transfers
+const MAX_BUFFERED_BYTES: usize = 256 * 1024 * 1024;
+
+/// Incomplete transfers idle this long are evicted to make room for others
+const IDLE_EVICTION: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TransferRequest {
+    /// A complete encoded envelope
+    Envelope(#[serde(with = "hex_bytes")] Vec<u8>),
+    /// Part of an encoded envelope
+    Chunk(Chunk),
+    /// Digests of the peer's shared containers with `SyncId`s after `after`
+    Digests { after: Option<SyncId> },
+    /// Chunk `index` of the peer's envelope for a container
+    Fetch { id: SyncId, index: u32 },
+    /// A pipeline code chunk from the peer's chunk store
+    CodeChunk { chunk_id: ChunkID },
+    /// The sender's device advert; answered with the peer's
+    Device(DeviceAdvert),
+    /// Run a pipeline for the sender
+    Offload(OffloadRequest),
+    /// Progress of a pipeline run offloaded by the peer
+    OffloadProgress {
+        offload_id: u64,
+        progress: PipelineProgress,
+    },
+    /// Outcome of a pipeline run offloaded by the peer: the JSON-encoded
+    /// output, or the error it failed with
+    OffloadResult {
+        offload_id: u64,
+        #[serde(with = "hex_bytes")]
+        output: Vec<u8>,
+        error: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    /// Chosen by the sender, unique per transfer
+    pub transfer_id: u64,
+    pub index: u32,
+    pub total: u32,
+    #[serde(with = "hex_bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TransferResponse {
+    /// Chunk buffered, send the next one
+    ChunkReceived,
+    /// Envelope complete and handled
+    Done(InboundOutcome),
+    /// A page of digests
+    Digests { digests: Vec<Digest>, more: bool },
+    /// Part of a fetched envelope
+    Chunk(Chunk),
+    /// A requested code chunk
+    CodeChunk(#[serde(with = "hex_bytes")] Vec<u8>),
+    /// The peer's device advert
+    Device(DeviceAdvert),
+    /// The request was accepted
+    Acknowledged,
+    /// The request was refused
+    Refused(String),
+}
+
+/// Split an encoded envelope into requests
+pub fn split(transfer_id: u64, data: &[u8]) -> Vec<TransferRequest> {
+    if data.len() <= CHUNK_SIZE {
+        return vec![TransferRequest::Envelope(data.to_vec())];
+    }
+    (0..chunk_count(data))
+        .filter_map(|index| chunk(transfer_id, data, index))
+        .map(TransferRequest::Chunk)
+        .collect()
+}
+
+fn chunk_count(data: &[u8]) -> u32 {
+    data.len().div_ceil(CHUNK_SIZE).max(1) as u32
+}
+
+/// Chunk `index` of an encoded envelope
+pub fn chunk(transfer_id: u64, data: &[u8], index: u32) -> Option<Chunk> {
+    let total = chunk_count(data);
+    if index >= total {
+        return None;
+    }
+    let start = index as usize * CHUNK_SIZE;
+    let end = (start + CHUNK_SIZE).min(data.len());
+    Some(Chunk {
+        transfer_id,
+        index,
+        total,
+        data: data[start..end].to_vec(),
+    })
+}
+
+struct Partial {
+    total: u32,
+    data: Vec<u8>,
+    next: u32,
+    updated: Instant,
+}
+
+/// Reassembles chunked transfers per peer, within a per-peer limit on open
+/// transfers and a byte budget shared by all of them
+#[derive(Default)]
+pub struct Reassembler {
+    partial: HashMap<(PeerId, u64), Partial>,
+    /// Bytes held by `partial`
+    buffered: usize,
+}
+
+impl Reassembler {
+    /// Add a chunk; returns the envelope bytes once the last chunk arrives
+    pub fn accept(&mut self, peer: PeerId, chunk: Chunk) -> Result<Option<Vec<u8>>, String> {
+        self.expire();
+        let key = (peer, chunk.transfer_id);
+        if chunk.index == 0 {
+            if chunk.total == 0 || chunk.total as usize > MAX_TRANSFER_SIZE.div_ceil(CHUNK_SIZE) {
+                return Err(format!("Transfer of {} chunks refused", chunk.total));
+            }
+            self.remove(&key);
+            if self.open_by(&peer) >= MAX_TRANSFERS_PER_PEER {
+                self.evict_idle(Some(&peer));
+                if self.open_by(&peer) >= MAX_TRANSFERS_PER_PEER {
+                    return Err(format!(
+                        "Too many open transfers from {}, transfer {} refused",
+                        peer, chunk.transfer_id
+                    ));
+                }
+            }
+            self.partial.insert(
+                key,
+                Partial {
+                    total: chunk.total,
+                    data: Vec::new(),
+                    next: 0,
+                    updated: Instant::now(),
+                },
+            );
+        }
+
+        let Some(partial) = self.partial.get(&key) else {
+            return Err(format!("Unknown transfer {}", chunk.transfer_id));
+        };
+        if chunk.index != partial.next || chunk.total != partial.total {
+            self.remove(&key);
+            return Err(format!(
+                "Transfer {} chunk {} out of order",
+                chunk.transfer_id, chunk.index
+            ));
+        }
+        if chunk.data.len() > CHUNK_SIZE
+            || partial.data.len() + chunk.data.len() > MAX_TRANSFER_SIZE
+        {
+            self.remove(&key);
+            return Err(format!("Transfer {} is too large", chunk.transfer_id));
+        }
+        if self.buffered + chunk.data.len() > MAX_BUFFERED_BYTES {
+            self.evict_idle(None);
+            if self.buffered + chunk.data.len() > MAX_BUFFERED_BYTES {
+                self.remove(&key);
+                return Err(format!(
+                    "Transfer buffer full, transfer {} dropped",
+                    chunk.transfer_id
+                ));
+            }
+        }
+
+        // Eviction above only removes idle transfers, never this one
+        let Some(partial) = self.partial.get_mut(&key) else {
+            return Err(format!("Unknown transfer {}", chunk.transfer_id));
+        };
+        partial.data.extend_from_slice(&chunk.data);
+        partial.next += 1;
+        partial.updated = Instant::now();
+        self.buffered += chunk.data.len();
+        if partial.next < partial.total {
+            return Ok(None);
+        }
+        Ok(self.remove(&key))
+    }
+
+    /// Drop transfers that stalled
+    pub fn expire(&mut self) {
+        self.drop_where(|_, partial| partial.updated.elapsed() >= TRANSFER_TIMEOUT);
+    }
+
+    /// Drop transfers idle for `IDLE_EVICTION`, only `peer`'s if given
+    fn evict_idle(&mut self, peer: Option<&PeerId>) {
+        self.drop_where(|(from, _), partial| {
+            peer.is_none_or(|peer| from == peer) && partial.updated.elapsed() >= IDLE_EVICTION
+        });
+    }
+
+    fn drop_where(&mut self, mut condition: impl FnMut(&(PeerId, u64), &Partial) -> bool) {
+        let mut freed = 0;
+        self.partial.retain(|key, partial| {
+            let drop = condition(key, partial);
+            if drop {
+                freed += partial.data.len();
+            }
+            !drop
+        });
+        self.buffered -= freed;
+    }
+
+    fn remove(&mut self, key: &(PeerId, u64)) -> Option<Vec<u8>> {
+        let partial = self.partial.remove(key)?;
+        self.buffered -= partial.data.len();
+        Some(partial.data)
+    }
+
+    fn open_by(&self, peer: &PeerId) -> usize {
+        self.partial.keys().filter(|(from, _)| from == peer).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunked_transfer_round_trip() {
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 10).map(|i| i as u8).collect();
+        let peer = PeerId::random();
+        let mut reassembler = Reassembler::default();
+
+        let requests = split(7, &data);
+        assert_eq!(requests.len(), 3);
+        let mut result = None;
+        for request in requests {
+            let TransferRequest::Chunk(chunk) = request else {
+                panic!("expected a chunk");
+            };
+            result = reassembler.accept(peer, chunk).unwrap();
+        }
+        assert_eq!(result.unwrap(), data);
+
+        // A chunk from another transfer out of sequence is refused
+        let TransferRequest::Chunk(mut chunk) = split(8, &data).remove(1) else {
+            panic!("expected a chunk");
+        };
+        assert!(reassembler.accept(peer, chunk.clone()).is_err());
+        chunk.index = 0;
+        chunk.total = u32::MAX;
+        assert!(reassembler.accept(peer, chunk).is_err());
+
+        assert!(matches!(
+            split(9, b"small").as_slice(),
+            [TransferRequest::Envelope(_)]
+        ));
+    }
+
+    #[test]
+    fn test_open_transfer_limits() {
+        let data = vec![0u8; CHUNK_SIZE * 2];
+        let first_chunk = |transfer_id| match split(transfer_id, &data).remove(0) {
+            TransferRequest::Chunk(chunk) => chunk,
+            _ => panic!("expected a chunk"),
+        };
+        let (peer, other) = (PeerId::random(), PeerId::random());
+        let mut reassembler = Reassembler::default();
+
+        for id in 0..MAX_TRANSFERS_PER_PEER as u64 {
+            assert!(reassembler.accept(peer, first_chunk(id)).is_ok());
+        }
+        assert!(reassembler.accept(peer, first_chunk(99)).is_err());
+        assert!(reassembler.accept(other, first_chunk(99)).is_ok());
+        assert_eq!(reassembler.buffered, CHUNK_SIZE * (MAX_TRANSFERS_PER_PEER + 1));
+
+        // Idle transfers give way to new ones
+        for partial in reassembler.partial.values_mut() {
+            partial.updated -= IDLE_EVICTION;
+        }
+        assert!(reassembler.accept(peer, first_chunk(100)).is_ok());
+        assert_eq!(reassembler.open_by(&peer), 1);
+        assert_eq!(reassembler.buffered, CHUNK_SIZE * 2);
+
+        // Buffered bytes are released when a transfer completes
+        let TransferRequest::Chunk(last) = split(100, &data).remove(1) else {
+            panic!("expected a chunk");
+        };
+        assert_eq!(reassembler.accept(peer, last).unwrap(), Some(data.clone()));
+        assert_eq!(reassembler.buffered, CHUNK_SIZE);
+    }
+}
@@ -0,0 +1,256 @@
+//! Orchestration checkpoints
+//!
+//! The orchestrator saves its whole state (AMT, blueprint steps, step
+//! outputs, graph states) after every stage and every blueprint step, one
+//! JSON file per orchestration under the checkpoint directory. A failed or
+//! interrupted orchestration is resumed from its checkpoint: completed
+//! stages and steps are not run again, so their LLM calls are not repeated.
+//! The orchestrator does the file IO on blocking threads; a running
+//! orchestration serializes its checkpoint first (see `encode`).
+
+use super::OrchestrationState;
+use crate::types::{OzoneError, OzoneResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Where an orchestration stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointStatus {
+    /// Still running, or interrupted by a restart
+    Running,
+    /// Stopped by an error; can be resumed
+    Failed,
+    /// Stopped on questions for the user; continued by
+    /// `PromptOrchestrator::answer_clarification`
+    AwaitingClarification,
+    Complete,
+}
+
+/// A saved orchestration
+#[derive(Deserialize)]
+pub(super) struct Checkpoint {
+    pub status: CheckpointStatus,
+    pub error: Option<String>,
+    pub updated_at: u64,
+    pub state: OrchestrationState,
+}
+
+/// A checkpoint as written, borrowing the running state
+#[derive(Serialize)]
+struct SavedCheckpoint<'a> {
+    status: CheckpointStatus,
+    error: Option<&'a str>,
+    updated_at: u64,
+    state: &'a OrchestrationState,
+}
+
+/// What a checkpoint records, for listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointSummary {
+    pub orchestration_id: u64,
+    pub task_id: Option<u64>,
+    /// User who made the request
+    #[serde(default)]
+    pub user_id: u64,
+    pub status: CheckpointStatus,
+    pub error: Option<String>,
+    /// Last stage that completed
+    pub stage: u8,
+    pub steps_completed: u32,
+    pub total_steps: u32,
+    pub updated_at: u64,
+}
+
+impl Checkpoint {
+    pub fn summary(&self) -> CheckpointSummary {
+        CheckpointSummary {
+            orchestration_id: self.state.orchestration_id,
+            task_id: self.state.task_id,
+            user_id: self.state.request.user_id,
+            status: self.status,
+            error: self.error.clone(),
+            stage: self.state.last_stage,
+            steps_completed: self.state.step_results.len() as u32,
+            total_steps: self.state.blueprint_steps.len() as u32,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Checkpoint files, named by orchestration ID
+pub struct CheckpointStore {
+    dir: PathBuf,
+}
+
+impl CheckpointStore {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn save(
+        &self,
+        state: &OrchestrationState,
+        status: CheckpointStatus,
+        error: Option<&str>,
+    ) -> OzoneResult<()> {
+        let content = encode(state, status, error)?;
+        self.write(state.orchestration_id, &content)
+    }
+
+    /// Write a checkpoint serialized by `encode`
+    pub(super) fn write(&self, orchestration_id: u64, content: &[u8]) -> OzoneResult<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(orchestration_id);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    pub(super) fn load(&self, orchestration_id: u64) -> OzoneResult<Option<Checkpoint>> {
+        let path = self.path(orchestration_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read(&path)?;
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))
+    }
+
+    /// Summaries of every readable checkpoint, most recent first
+    pub fn list(&self) -> Vec<CheckpointSummary> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut summaries: Vec<CheckpointSummary> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let id = name.to_str()?.strip_suffix(".json")?.parse().ok()?;
+                match self.load(id) {
+                    Ok(checkpoint) => checkpoint.map(|c| c.summary()),
+                    Err(e) => {
+                        tracing::warn!("Ignoring unreadable checkpoint {}: {}", id, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| {
+            (b.updated_at, b.orchestration_id).cmp(&(a.updated_at, a.orchestration_id))
+        });
+        summaries
+    }
+
+    /// The most recent checkpoint of a task
+    pub fn find_task(&self, task_id: u64) -> Option<CheckpointSummary> {
+        self.list()
+            .into_iter()
+            .find(|summary| summary.task_id == Some(task_id))
+    }
+
+    pub fn remove(&self, orchestration_id: u64) -> OzoneResult<()> {
+        let path = self.path(orchestration_id);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn path(&self, orchestration_id: u64) -> PathBuf {
+        self.dir.join(format!("{}.json", orchestration_id))
+    }
+}
+
+/// Serialize a checkpoint of the running state
+pub(super) fn encode(
+    state: &OrchestrationState,
+    status: CheckpointStatus,
+    error: Option<&str>,
+) -> OzoneResult<Vec<u8>> {
+    let checkpoint = SavedCheckpoint {
+        status,
+        error,
+        updated_at: now(),
+        state,
+    };
+    serde_json::to_vec(&checkpoint).map_err(|e| OzoneError::SerializationError(e.to_string()))
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::OrchestrationRequest;
+
+    fn state(orchestration_id: u64, user_id: u64, task_id: Option<u64>) -> OrchestrationState {
+        let request = OrchestrationRequest {
+            prompt: "Summarize the notes".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let mut state = OrchestrationState::new(request, "model".to_string(), 1000, 4, Vec::new());
+        state.orchestration_id = orchestration_id;
+        state.task_id = task_id;
+        state
+    }
+
+    #[test]
+    fn test_save_load_and_list() {
+        let dir = std::env::temp_dir().join(format!("ozone-checkpoints-{}", uuid::Uuid::new_v4()));
+        let store = CheckpointStore::new(&dir);
+        assert!(store.list().is_empty());
+        assert!(store.load(1).unwrap().is_none());
+
+        let mut first = state(1, 10, None);
+        first.last_stage = 3;
+        store.save(&first, CheckpointStatus::Running, None).unwrap();
+        store
+            .save(&state(2, 20, Some(7)), CheckpointStatus::Failed, Some("Model unavailable"))
+            .unwrap();
+
+        let loaded = store.load(1).unwrap().unwrap();
+        assert_eq!(loaded.status, CheckpointStatus::Running);
+        assert_eq!(loaded.state.request.prompt, "Summarize the notes");
+        let summary = loaded.summary();
+        assert_eq!((summary.user_id, summary.stage, summary.task_id), (10, 3, None));
+
+        assert_eq!(store.list().len(), 2);
+        let failed = store.find_task(7).unwrap();
+        assert_eq!((failed.orchestration_id, failed.user_id), (2, 20));
+        assert_eq!(failed.error.as_deref(), Some("Model unavailable"));
+        assert!(store.find_task(8).is_none());
+
+        // A later save replaces the checkpoint; other files are skipped
+        store.save(&first, CheckpointStatus::Complete, None).unwrap();
+        assert_eq!(store.load(1).unwrap().unwrap().status, CheckpointStatus::Complete);
+        std::fs::write(dir.join("3.json"), "not json").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        assert!(store.load(3).is_err());
+        assert_eq!(store.list().len(), 2);
+
+        store.remove(1).unwrap();
+        store.remove(1).unwrap();
+        let remaining: Vec<u64> = store.list().iter().map(|s| s.orchestration_id).collect();
+        assert_eq!(remaining, vec![2]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,276 @@
+//! Context packing for blueprint steps
+//!
+//! A step's prompt draws on outputs of earlier steps, AMT nodes,
+//! methodologies, containers found by context aggregation and the prompt's
+//! chunks. `pack` ranks these candidates by how many of the step's terms
+//! they mention, condenses those that do not fit whole to their most
+//! relevant sentences, and stops at the token budget. Each `PackedItem`
+//! names the step, node, container or chunk it came from, so a step's
+//! context can be rebuilt from its `StepResult`.
+
+use crate::llm::Tokenizer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Least room worth condensing a candidate into
+const MIN_CONDENSED_TOKENS: u32 = 32;
+
+/// Where a piece of step context came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ContextSource {
+    /// Output of an earlier blueprint step
+    StepOutput { step_index: u32 },
+    /// A node of the orchestration's AMT
+    AmtNode { node_id: u64 },
+    /// A methodology container
+    Methodology { container_id: u64 },
+    /// A container returned by context aggregation
+    Container { container_id: u64 },
+    /// A chunk of the cleaned prompt
+    Chunk { chunk_index: u32 },
+    /// Aggregated context that named no containers
+    Aggregation,
+}
+
+impl ContextSource {
+    fn label(&self) -> String {
+        match self {
+            Self::StepOutput { step_index } => format!("[Step {} output]", step_index + 1),
+            Self::AmtNode { node_id } => format!("[AMT node {}]", node_id),
+            Self::Methodology { container_id } => format!("[Methodology {}]", container_id),
+            Self::Container { container_id } => format!("[Container {}]", container_id),
+            Self::Chunk { chunk_index } => format!("[Prompt chunk {}]", chunk_index),
+            Self::Aggregation => "[Aggregated context]".to_string(),
+        }
+    }
+}
+
+/// Material that may go into a step's context
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub source: ContextSource,
+    pub text: String,
+    /// Added to the relevance, for material the step is known to need
+    pub boost: f32,
+}
+
+impl Candidate {
+    pub fn new(source: ContextSource, text: impl Into<String>) -> Self {
+        Self {
+            source,
+            text: text.into(),
+            boost: 0.0,
+        }
+    }
+
+    pub fn with_boost(mut self, boost: f32) -> Self {
+        self.boost = boost;
+        self
+    }
+}
+
+/// A candidate included in a step's context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackedItem {
+    pub source: ContextSource,
+    pub relevance: f32,
+    pub tokens: u32,
+    /// Whether only part of the text fit
+    pub condensed: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PackedContext {
+    pub text: String,
+    pub tokens: u32,
+    /// Included candidates, most relevant first
+    pub items: Vec<PackedItem>,
+    /// Candidates left out for lack of room
+    pub dropped: Vec<ContextSource>,
+}
+
+/// Pack the candidates most relevant to `query` into `budget` tokens
+pub fn pack(
+    query: &str,
+    candidates: Vec<Candidate>,
+    budget: u32,
+    tokenizer: &dyn Tokenizer,
+) -> PackedContext {
+    let query_terms = terms(query);
+    let mut ranked: Vec<(f32, Candidate)> = candidates
+        .into_iter()
+        .filter(|c| !c.text.trim().is_empty())
+        .map(|c| (c.boost + overlap(&query_terms, &c.text), c))
+        .collect();
+    // Stable, so equally relevant candidates keep their order
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut packed = PackedContext::default();
+    let mut sections = Vec::new();
+    for (relevance, candidate) in ranked {
+        let remaining = budget.saturating_sub(packed.tokens);
+        let label = candidate.source.label();
+        // Label, newline and the blank line before the next section
+        let overhead = tokenizer.count(&label) + 2;
+        let whole = tokenizer.count(&candidate.text) + overhead;
+
+        let (text, tokens, condensed) = if whole <= remaining {
+            (candidate.text, whole, false)
+        } else if remaining >= MIN_CONDENSED_TOKENS + overhead {
+            match condense(
+                &candidate.text,
+                &query_terms,
+                remaining - overhead,
+                tokenizer,
+            ) {
+                Some(text) => {
+                    let tokens = tokenizer.count(&text) + overhead;
+                    (text, tokens, true)
+                }
+                None => {
+                    packed.dropped.push(candidate.source);
+                    continue;
+                }
+            }
+        } else {
+            packed.dropped.push(candidate.source);
+            continue;
+        };
+
+        packed.tokens += tokens;
+        sections.push(format!("{}\n{}", label, text));
+        packed.items.push(PackedItem {
+            source: candidate.source,
+            relevance,
+            tokens,
+            condensed,
+        });
+    }
+    packed.text = sections.join("\n\n");
+    packed
+}
+
+/// Lowercased words of three or more characters
+fn terms(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Fraction of the query's terms that `text` mentions
+fn overlap(query_terms: &HashSet<String>, text: &str) -> f32 {
+    if query_terms.is_empty() {
+        return 0.0;
+    }
+    let found = terms(text).intersection(query_terms).count();
+    found as f32 / query_terms.len() as f32
+}
+
+/// The sentences of `text` that mention most query terms, in their
+/// original order, within `budget` tokens; a leading cut of the most
+/// relevant sentence when none fits whole
+fn condense(
+    text: &str,
+    query_terms: &HashSet<String>,
+    budget: u32,
+    tokenizer: &dyn Tokenizer,
+) -> Option<String> {
+    let sentences: Vec<&str> = text
+        .split_inclusive(['.', '!', '?', '\n'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    let mut by_relevance: Vec<usize> = (0..sentences.len()).collect();
+    by_relevance.sort_by(|&a, &b| {
+        overlap(query_terms, sentences[b]).total_cmp(&overlap(query_terms, sentences[a]))
+    });
+
+    let join = |chosen: &[usize]| {
+        let mut chosen = chosen.to_vec();
+        chosen.sort_unstable();
+        chosen
+            .iter()
+            .map(|&i| sentences[i])
+            .collect::<Vec<_>>()
+            .join(" ")
+            + " ..."
+    };
+    let mut chosen = Vec::new();
+    let mut used = tokenizer.count(" ...");
+    for &index in &by_relevance {
+        let tokens = tokenizer.count(sentences[index]) + 1;
+        if used + tokens <= budget {
+            chosen.push(index);
+            used += tokens;
+        }
+    }
+    // The joined text can count differently from its sentences
+    while !chosen.is_empty() && tokenizer.count(&join(&chosen)) > budget {
+        chosen.pop();
+    }
+    if !chosen.is_empty() {
+        return Some(join(&chosen));
+    }
+
+    // Longest prefix of the best sentence that fits
+    let best = sentences.get(*by_relevance.first()?)?;
+    let cuts: Vec<usize> = best.char_indices().map(|(i, _)| i).skip(1).collect();
+    let fits = |end: usize| tokenizer.count(&format!("{} ...", &best[..end])) <= budget;
+    let count = cuts.partition_point(|&end| fits(end));
+    (count > 0).then(|| format!("{} ...", &best[..cuts[count - 1]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::tokenizer::HeuristicTokenizer;
+
+    #[test]
+    fn test_pack_ranks_condenses_and_records_sources() {
+        let filler = "Unrelated background material about other things. ".repeat(40);
+        let candidates = vec![
+            Candidate::new(ContextSource::Chunk { chunk_index: 0 }, filler.clone()),
+            Candidate::new(
+                ContextSource::AmtNode { node_id: 7 },
+                format!("{}The parser must report errors with line numbers.", filler),
+            ),
+            Candidate::new(
+                ContextSource::StepOutput { step_index: 0 },
+                "Wrote the tokenizer.",
+            )
+            .with_boost(1.0),
+            Candidate::new(ContextSource::Methodology { container_id: 42 }, "   "),
+        ];
+
+        let packed = pack(
+            "Report parser errors with line numbers and columns",
+            candidates,
+            120,
+            &HeuristicTokenizer,
+        );
+        let sources: Vec<&ContextSource> = packed.items.iter().map(|i| &i.source).collect();
+        assert_eq!(
+            sources,
+            vec![
+                &ContextSource::StepOutput { step_index: 0 },
+                &ContextSource::AmtNode { node_id: 7 },
+            ]
+        );
+        assert!(!packed.items[0].condensed);
+        assert!(packed.items[1].condensed);
+        assert!(packed
+            .text
+            .contains("The parser must report errors with line numbers. ..."));
+        assert_eq!(
+            packed.dropped,
+            vec![ContextSource::Chunk { chunk_index: 0 }]
+        );
+        assert!(packed.tokens <= 120);
+        assert_eq!(
+            packed.tokens,
+            packed.items.iter().map(|i| i.tokens).sum::<u32>()
+        );
+    }
+}
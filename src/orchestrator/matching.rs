@@ -0,0 +1,357 @@
+//! Blueprint matching
+//!
+//! Stage 3 scores each stored blueprint against the request on four
+//! signals: an exact `TaskSignature` hash match, compatibility of input and
+//! output types, embedding similarity of keywords and topics, and the
+//! blueprint's success over past runs. A high score reuses the blueprint as it is, a moderate
+//! one has the model adapt it, and only requests nothing resembles get a
+//! blueprint written from scratch. `BlueprintStats` is kept in the
+//! blueprint's storage and updated after every task it runs.
+
+use crate::types::TaskSignature;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Least score at which a blueprint is reused unchanged
+pub const REUSE_SCORE: f32 = 0.7;
+
+/// Least score at which a blueprint is adapted instead of replaced
+pub const ADAPT_SCORE: f32 = 0.4;
+
+/// Runs after which a blueprint's success rate is trusted
+const MIN_RUNS_FOR_VERDICT: u32 = 3;
+
+/// Runs a blueprint needs, at `VALIDATED_SUCCESS_RATE`, to be validated
+const VALIDATION_RUNS_REQUIRED: u32 = 5;
+
+const VALIDATED_SUCCESS_RATE: f32 = 0.8;
+
+/// Output types and the keywords that ask for them
+const OUTPUT_TYPES: &[(&str, &[&str])] = &[
+    (
+        "code",
+        &[
+            "code",
+            "implement",
+            "function",
+            "refactor",
+            "compile",
+            "script",
+            "program",
+            "bug",
+            "fix",
+        ],
+    ),
+    (
+        "documentation",
+        &[
+            "document",
+            "documentation",
+            "readme",
+            "docs",
+            "tutorial",
+            "guide",
+        ],
+    ),
+    (
+        "analysis",
+        &[
+            "analyze",
+            "analyse",
+            "analysis",
+            "statistics",
+            "review",
+            "compare",
+            "evaluate",
+        ],
+    ),
+    (
+        "verification",
+        &["prove", "proof", "verify", "theorem", "lemma"],
+    ),
+];
+
+/// How a blueprint has done in the tasks it ran
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlueprintStats {
+    pub usage_count: u64,
+    pub validation_runs: u32,
+    pub success_rate: f32,
+    pub validated: bool,
+}
+
+impl BlueprintStats {
+    /// Count one more task run with the blueprint
+    pub fn record(&mut self, success: bool) {
+        let successes = self.success_rate * self.validation_runs as f32;
+        self.usage_count += 1;
+        self.validation_runs += 1;
+        self.success_rate =
+            (successes + if success { 1.0 } else { 0.0 }) / self.validation_runs as f32;
+        self.validated = self.validation_runs >= VALIDATION_RUNS_REQUIRED
+            && self.success_rate >= VALIDATED_SUCCESS_RATE;
+    }
+
+    /// Expected success of the next run; untried blueprints count as even
+    pub fn expected_success(&self) -> f32 {
+        (self.success_rate * self.validation_runs as f32 + 1.0)
+            / (self.validation_runs as f32 + 2.0)
+    }
+
+    /// False once enough runs show the blueprint mostly fails
+    pub fn reliable(&self) -> bool {
+        self.validation_runs < MIN_RUNS_FOR_VERDICT || self.success_rate >= 0.5
+    }
+}
+
+/// Signature with its fields normalized and hashed, so equal tasks hash
+/// equal whatever the order their types were found in
+pub fn task_signature(
+    input_types: &[String],
+    output_type: &str,
+    constraints: &[String],
+) -> TaskSignature {
+    let normalize = |values: &[String]| -> Vec<String> {
+        let mut values: Vec<String> = values
+            .iter()
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect();
+        values.sort();
+        values.dedup();
+        values
+    };
+    let input_types = normalize(input_types);
+    let output_type = output_type.trim().to_lowercase();
+    let constraints = normalize(constraints);
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(input_types.join(",").as_bytes());
+    hasher.update(b"|");
+    hasher.update(output_type.as_bytes());
+    hasher.update(b"|");
+    hasher.update(constraints.join(",").as_bytes());
+    TaskSignature {
+        input_types,
+        output_type,
+        constraints,
+        hash: *hasher.finalize().as_bytes(),
+    }
+}
+
+/// The output type most of the keywords ask for, or "text"
+pub fn infer_output_type(keywords: &[String]) -> &'static str {
+    let keywords: HashSet<String> = keywords.iter().map(|k| k.to_lowercase()).collect();
+    let mut best = ("text", 0);
+    for (output_type, words) in OUTPUT_TYPES {
+        let hits = words.iter().filter(|w| keywords.contains(**w)).count();
+        if hits > best.1 {
+            best = (output_type, hits);
+        }
+    }
+    best.0
+}
+
+/// A blueprint container as stage 3 reads it
+#[derive(Debug, Clone)]
+pub struct StoredBlueprint {
+    pub blueprint_id: u64,
+    pub name: String,
+    pub keywords: Vec<String>,
+    pub topics: Vec<String>,
+    /// Absent on blueprints stored before signatures were recorded
+    pub signature: Option<TaskSignature>,
+    /// Term embedding of the keywords and topics, when one was stored
+    pub embedding: Option<Vec<f32>>,
+    pub stats: BlueprintStats,
+    pub steps: Value,
+}
+
+impl StoredBlueprint {
+    pub fn from_container(blueprint_id: u64, container: &Value) -> Option<Self> {
+        let local_state = container.get("local_state")?;
+        let context = &local_state["context"];
+        let storage = &local_state["storage"];
+        let strings = |value: &Value| -> Vec<String> {
+            serde_json::from_value(value.clone()).unwrap_or_default()
+        };
+        Some(Self {
+            blueprint_id,
+            name: local_state["metadata"]["name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            keywords: strings(&context["keywords"]),
+            topics: strings(&context["topics"]),
+            signature: serde_json::from_value(context["task_signature"].clone()).ok(),
+            embedding: serde_json::from_value(context["embedding"].clone()).ok(),
+            stats: serde_json::from_value(storage.clone()).unwrap_or_default(),
+            steps: storage["steps"].clone(),
+        })
+    }
+
+    /// Score against a request; `embedding` is the request's term embedding
+    pub fn score(
+        &self,
+        signature: &TaskSignature,
+        embedding: &[f32],
+        keywords: &[String],
+        topics: &[String],
+    ) -> MatchScore {
+        let (signature_match, types) = match &self.signature {
+            Some(stored) => {
+                let inputs = if signature.input_types.is_empty() {
+                    1.0
+                } else {
+                    signature
+                        .input_types
+                        .iter()
+                        .filter(|t| stored.input_types.contains(t))
+                        .count() as f32
+                        / signature.input_types.len() as f32
+                };
+                let output = if stored.output_type == signature.output_type {
+                    1.0
+                } else {
+                    0.0
+                };
+                let exact = if stored.hash == signature.hash {
+                    1.0
+                } else {
+                    0.0
+                };
+                (exact, (inputs + output) / 2.0)
+            }
+            None => (0.0, 0.0),
+        };
+
+        // Blueprints stored without an embedding fall back to term overlap
+        let similarity = self
+            .embedding
+            .as_deref()
+            .and_then(|stored| crate::zsei::cosine_similarity(stored, embedding));
+        let semantic = match similarity {
+            Some(similarity) => similarity.clamp(0.0, 1.0),
+            None => {
+                let terms = |keywords: &[String], topics: &[String]| -> HashSet<String> {
+                    keywords
+                        .iter()
+                        .chain(topics)
+                        .map(|w| w.to_lowercase())
+                        .collect()
+                };
+                let requested = terms(keywords, topics);
+                let stored = terms(&self.keywords, &self.topics);
+                let union = requested.union(&stored).count();
+                if union > 0 {
+                    requested.intersection(&stored).count() as f32 / union as f32
+                } else {
+                    0.0
+                }
+            }
+        };
+
+        MatchScore {
+            signature: signature_match,
+            types,
+            semantic,
+            history: self.stats.expected_success(),
+        }
+    }
+}
+
+/// How well a stored blueprint fits a request, each signal from 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchScore {
+    pub signature: f32,
+    pub types: f32,
+    pub semantic: f32,
+    pub history: f32,
+}
+
+impl MatchScore {
+    pub fn total(&self) -> f32 {
+        0.3 * self.signature + 0.2 * self.types + 0.3 * self.semantic + 0.2 * self.history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_signature_and_history_decide_reuse() {
+        let keywords = strings(&["implement", "parser", "errors"]);
+        let signature = task_signature(&strings(&["Text", "code"]), "code", &strings(&["rust"]));
+        assert_eq!(infer_output_type(&keywords), "code");
+        assert_eq!(infer_output_type(&strings(&["hello"])), "text");
+        assert_eq!(
+            task_signature(
+                &strings(&["code", "text", "text"]),
+                "Code",
+                &strings(&["Rust"])
+            )
+            .hash,
+            signature.hash
+        );
+
+        let embed = |terms: &[&str]| {
+            crate::zsei::term_embedding(&strings(terms), crate::zsei::TERM_EMBEDDING_DIMENSION)
+        };
+        let requested = embed(&["implement", "parser", "errors", "rust"]);
+        let container = serde_json::json!({
+            "local_state": {
+                "metadata": {"name": "Parser"},
+                "context": {
+                    "keywords": ["implement", "parser", "tokens"],
+                    "topics": ["rust"],
+                    "task_signature": signature,
+                    "embedding": embed(&["implement", "parser", "tokens", "rust"])
+                },
+                "storage": {"steps": [], "usage_count": 0, "validation_runs": 0}
+            }
+        });
+        let mut stored = StoredBlueprint::from_container(3, &container).unwrap();
+        let score = stored.score(&signature, &requested, &keywords, &strings(&["rust"]));
+        assert_eq!(
+            (score.signature, score.types, score.history),
+            (1.0, 1.0, 0.5)
+        );
+        assert!(score.semantic > 0.6 && score.semantic < 1.0);
+        assert!(score.total() >= REUSE_SCORE);
+
+        let untried = score.total();
+
+        // Without a stored embedding the terms' overlap is used
+        let mut plain = stored.clone();
+        plain.embedding = None;
+        let score = plain.score(&signature, &requested, &keywords, &strings(&["rust"]));
+        assert_eq!(score.semantic, 0.6);
+
+        // Failures lower the score and, past a verdict, rule reuse out
+        for success in [true, false, false, false] {
+            stored.stats.record(success);
+        }
+        assert_eq!(stored.stats.usage_count, 4);
+        assert!((stored.stats.success_rate - 0.25).abs() < 1e-6);
+        assert!(!stored.stats.reliable());
+        let score = stored.score(&signature, &requested, &keywords, &strings(&["rust"]));
+        assert!(score.total() < untried && score.total() >= ADAPT_SCORE);
+
+        let other = task_signature(&strings(&["image"]), "analysis", &[]);
+        let score = stored.score(&other, &embed(&["photo"]), &strings(&["photo"]), &[]);
+        assert!(score.total() < ADAPT_SCORE);
+
+        let mut stats = BlueprintStats::default();
+        for _ in 0..5 {
+            stats.record(true);
+        }
+        assert!(stats.validated);
+    }
+}
@@ -21,19 +21,51 @@
 //! KEY FEATURES:
 //! - Layer-by-layer AMT building from chunks (processes each chunk individually)
 //! - 5 consecutive Valid validations required
-//! - Blueprint step execution with loop/sub-step/dependency support
+//! - Blueprint step execution with loop/sub-step/dependency support;
+//!   independent steps run in parallel, each with a failure policy
 //! - Direct ZSEI access (no deprecated pipeline wrappers)
 //! - Pipeline awareness for blueprint creation
 //! - Coverage aspects derived from methodologies (not hardcoded)
 //! - Queue-based task execution via TaskManager
-
+//! - Checkpoints after every stage and step (`checkpoint`), so a failed or
+//!   interrupted orchestration resumes where it stopped
+//! - Clarification round-trip: an orchestration that stops on questions
+//!   (stage 2 or 4) continues from that stage once they are answered
+//! - Record/replay (`replay`): a real run's pipeline, ZSEI and model calls
+//!   saved as a fixture and served back for regression tests
+//! - Structured model output checked against per-stage schemas (`schema`);
+//!   violations go back to the model for repair, and those that remain are
+//!   recorded in the stage's result instead of replaced by defaults
+//! - Token usage as reported by the providers, per stage and step, counted
+//!   and budgeted with each model's tokenizer and limits (`ModelCatalog`)
+//! - Step context packed to the model's budget (`context`): earlier outputs,
+//!   AMT nodes, methodologies, aggregated containers and chunks ranked by
+//!   relevance, with the sources included recorded per step
+//! - Blueprint matching (`matching`) on task signature, type compatibility,
+//!   keyword similarity and past success; close matches are reused, partial
+//!   ones adapted, and each blueprint's statistics updated after its task
+
+pub mod checkpoint;
+pub mod context;
+pub mod matching;
+pub mod replay;
+pub mod schema;
+
+use futures_util::stream::{FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 // Import task module
+use crate::events::{EventBus, EventTopic};
+use crate::llm::{LlmRequest, LlmRouter, ModelCatalog, TokenUsage};
 use crate::task::{RefinementConfig, TaskData, TaskManager, TaskPriority, TaskQueueConfig};
+use crate::types::LogLevel;
+use checkpoint::{CheckpointStatus, CheckpointStore, CheckpointSummary};
+use context::{Candidate, ContextSource, PackedItem};
+use matching::{BlueprintStats, MatchScore, StoredBlueprint};
+use schema::ResponseSchema;
 
 // ============================================================================
 // Types
@@ -54,7 +86,9 @@ pub struct OrchestrationRequest {
     pub device_id: u64,
     /// Whether consciousness is enabled
     pub consciousness_enabled: bool,
-    /// Token budget for context (overrides model default if set)
+    /// Most tokens the orchestration may fill of the model's context. It
+    /// narrows the model's context length and never raises it; `None` uses
+    /// the whole context
     pub token_budget: Option<u32>,
     /// Model configuration override
     pub model_config: Option<ModelConfigOverride>,
@@ -83,6 +117,18 @@ pub struct OrchestrationResponse {
     pub consciousness_gate: Option<GateResult>,
     pub error: Option<String>,
     pub total_tokens_used: Option<u32>,
+    /// `total_tokens_used` split into input and output
+    #[serde(default)]
+    pub token_usage: TokenUsage,
+    /// Tokens used by each blueprint step, by step index
+    #[serde(default)]
+    pub step_tokens_used: HashMap<u32, u32>,
+    /// Sources packed into each blueprint step's context, by step index
+    #[serde(default)]
+    pub step_context: HashMap<u32, Vec<PackedItem>>,
+    /// Cost of the model calls made, for models with prices configured
+    #[serde(default)]
+    pub estimated_cost_usd: Option<f64>,
     pub execution_time_ms: u64,
     /// Methodologies used during this request
     pub methodologies_used: Vec<u64>,
@@ -96,6 +142,22 @@ pub struct OrchestrationResponse {
     pub needs_clarification: bool,
     /// AMT structure (for debugging/visualization)
     pub amt_summary: Option<AMTSummary>,
+    /// Pass to `PromptOrchestrator::resume` to continue after a failure,
+    /// or to `PromptOrchestrator::answer_clarification`
+    #[serde(default)]
+    pub orchestration_id: u64,
+    /// Questions asked so far in this orchestration and their answers
+    #[serde(default)]
+    pub clarifications: Vec<Clarification>,
+}
+
+/// A question an orchestration asked the user, and the answer once given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Clarification {
+    pub question: String,
+    pub answer: Option<String>,
+    /// Stage that asked; it runs again once answered
+    pub stage: u8,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,6 +167,12 @@ pub struct StageResult {
     pub success: bool,
     pub duration_ms: u64,
     pub output_summary: Option<String>,
+    /// Tokens used by the model calls the stage made
+    #[serde(default)]
+    pub tokens_used: u32,
+    /// Schema violations in model output that were left after repair
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub validation_failures: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -440,6 +508,15 @@ impl AMTNode {
         }
     }
 
+    fn max_id(&self) -> u64 {
+        self.children
+            .iter()
+            .map(|c| c.max_id())
+            .max()
+            .unwrap_or(0)
+            .max(self.id)
+    }
+
     fn count_nodes(&self) -> usize {
         1 + self.children.iter().map(|c| c.count_nodes()).sum::<usize>()
     }
@@ -526,10 +603,27 @@ pub struct BlueprintStep {
     pub depends_on: Vec<u32>,
     /// Whether to wait for graph update before proceeding
     pub wait_for_graph_update: bool,
-    /// Maximum retries on failure
+    /// Times the step is run again after failing, with backoff
     pub max_retries: u32,
     /// Timeout in milliseconds
     pub timeout_ms: Option<u64>,
+    /// What a failure of this step does to the rest of the blueprint
+    #[serde(default)]
+    pub failure_policy: FailurePolicy,
+}
+
+/// How the orchestrator handles a blueprint step that fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Fail the whole blueprint
+    #[default]
+    FailFast,
+    /// Skip the steps that depend on it and run the other branches
+    Continue,
+    /// Run the step again up to `attempts` more times (or `max_retries`,
+    /// if larger), then fail the blueprint
+    Retry { attempts: u32 },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -569,6 +663,8 @@ pub struct BlueprintSubStep {
 // Model Context Limits
 // ============================================================================
 
+/// Context length of well-known models, for models not in the
+/// `ModelCatalog`
 fn get_model_context_limit(model_identifier: &str) -> u32 {
     match model_identifier {
         // Claude models
@@ -595,12 +691,22 @@ fn get_model_context_limit(model_identifier: &str) -> u32 {
     }
 }
 
+/// At most the first `max_bytes` of `text`, cut back to a character
+/// boundary
+fn truncate_str(text: &str, max_bytes: usize) -> &str {
+    let mut end = text.len().min(max_bytes);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
 // ============================================================================
 // Internal State
 // ============================================================================
 
 /// Tracks a discovered intent with provenance
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct IntentCapture {
     intent: String,
     is_parallel: bool, // true if this is an unrelated parallel intent
@@ -610,7 +716,7 @@ struct IntentCapture {
 }
 
 /// Tracks a discovered branch with methodology provenance
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct BranchCapture {
     branch: String,
     parent_intent: String,
@@ -621,7 +727,7 @@ struct BranchCapture {
 }
 
 /// Tracks a discovered detail/sub-task
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct DetailCapture {
     content: String,
     detail_type: String, // "detail", "requirement", "constraint"
@@ -633,7 +739,7 @@ struct DetailCapture {
 }
 
 /// Tracks cross-references between branches
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct CrossRef {
     from_branch: String,
     to_branch: String,
@@ -643,14 +749,34 @@ struct CrossRef {
     description: String,
 }
 
+/// Everything an orchestration has produced so far; checkpointed after each
+/// stage and step (see `checkpoint`). Running steps read a clone taken when
+/// they start
+#[derive(Clone, Serialize, Deserialize)]
 struct OrchestrationState {
+    orchestration_id: u64,
     request: OrchestrationRequest,
+    #[serde(skip, default = "std::time::Instant::now")]
     start_time: std::time::Instant,
     stages: Vec<StageResult>,
+    /// Last stage that completed; a resumed orchestration continues after it
+    last_stage: u8,
 
     // Model context management
+    /// Model the orchestration budgets for
+    #[serde(default)]
+    model_identifier: String,
     model_context_limit: u32,
+    /// Total of the stages' `tokens_used`
     tokens_used_so_far: u32,
+    /// Usage of the recorded stages by model; pipelines that report usage
+    /// themselves count as `pipeline:<id>`
+    #[serde(default)]
+    model_usage: HashMap<String, TokenUsage>,
+    /// Usage by model since the last recorded stage; shared with the clones
+    /// concurrent steps run on
+    #[serde(skip)]
+    pending_usage: Arc<std::sync::Mutex<HashMap<String, TokenUsage>>>,
 
     // Stage 2 outputs
     raw_chunks: Vec<RawChunk>,
@@ -681,6 +807,12 @@ struct OrchestrationState {
     validation_streak: u32, // Need 5 consecutive Valid for completion
     needs_clarification: bool,
     clarification_points: Vec<String>,
+    /// Questions asked so far, answered or pending
+    #[serde(default)]
+    clarifications: Vec<Clarification>,
+    /// Schema violations of the current stage, moved to its `StageResult`
+    #[serde(default)]
+    validation_failures: Vec<String>,
     intent_captures: Vec<IntentCapture>,
     branch_captures: Vec<BranchCapture>,
     detail_captures: Vec<DetailCapture>,
@@ -709,7 +841,7 @@ struct OrchestrationState {
     available_pipelines: Vec<PipelineInfo>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct StepResult {
     step_index: u32,
     pipeline_id: u64,
@@ -717,9 +849,12 @@ struct StepResult {
     tokens_used: u32,
     iterations: u32,
     sub_step_results: Vec<SubStepResult>,
+    /// Sources packed into the context of the step's last iteration
+    #[serde(default)]
+    context: Vec<PackedItem>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct SubStepResult {
     sub_index: u32,
     output: serde_json::Value,
@@ -737,6 +872,72 @@ pub struct VoiceIdentity {
     pub vocabulary_style: String,
 }
 
+impl OrchestrationState {
+    fn new(
+        request: OrchestrationRequest,
+        model_identifier: String,
+        model_context_limit: u32,
+        prompt_tokens: u32,
+        available_pipelines: Vec<PipelineInfo>,
+    ) -> Self {
+        Self {
+            orchestration_id: PromptOrchestrator::generate_id_static(),
+            request,
+            start_time: std::time::Instant::now(),
+            stages: Vec::new(),
+            last_stage: 0,
+            model_identifier,
+            model_context_limit,
+            tokens_used_so_far: 0,
+            model_usage: HashMap::new(),
+            pending_usage: Default::default(),
+            raw_chunks: Vec::new(),
+            file_graphs: HashMap::new(),
+            classified_file_graphs: Vec::new(),
+            chunk_graph_ids: Vec::new(),
+            modality_graphs: HashMap::new(),
+            graph_states: HashMap::new(),
+            root_modality_list: RootModalityList::default(),
+            initial_graphs_created: false,
+            cross_modal_index_id: None,
+            processed_chunks: Vec::new(),
+            cleaned_prompt: String::new(),
+            prompt_tokens,
+            keywords: Vec::new(),
+            entities: Vec::new(),
+            topics: Vec::new(),
+            methodologies: Vec::new(),
+            categories: Vec::new(),
+            categories_created: 0,
+            amt: None,
+            amt_validated: false,
+            validation_streak: 0,
+            needs_clarification: false,
+            clarification_points: Vec::new(),
+            clarifications: Vec::new(),
+            validation_failures: Vec::new(),
+            intent_captures: Vec::new(),
+            branch_captures: Vec::new(),
+            detail_captures: Vec::new(),
+            cross_refs: Vec::new(),
+            amt_pass_count: 0,
+            coverage_aspects: Vec::new(),
+            blueprint_id: None,
+            blueprint_steps: Vec::new(),
+            orch_step_states: HashMap::new(),
+            blueprints_created: 0,
+            task_id: None,
+            step_results: Vec::new(),
+            final_response: None,
+            step_contexts: HashMap::new(),
+            step_outputs: HashMap::new(),
+            gate_result: None,
+            voice_identity: None,
+            available_pipelines,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct ValidationResult {
     is_valid: bool,
@@ -776,7 +977,7 @@ pub trait ZSEIAccess: Send + Sync {
         container: serde_json::Value,
     ) -> Result<u64, String>;
 
-    /// Update a container
+    /// Update a container; objects in `updates` merge into its local state
     async fn update_container(
         &self,
         container_id: u64,
@@ -801,11 +1002,60 @@ pub trait ZSEIAccess: Send + Sync {
 // Orchestrator Implementation
 // ============================================================================
 
+/// Independent blueprint steps run at once unless configured otherwise
+const DEFAULT_MAX_PARALLEL_STEPS: usize = 4;
+
+/// Pause before a step's first retry; each further retry waits this much
+/// longer
+const STEP_RETRY_BACKOFF_MS: u64 = 100;
+
+/// The prompt pipeline, which calls the model when no `LlmRouter` is set
+const PROMPT_PIPELINE_ID: u64 = 9;
+
+/// Times a response that violates its schema is sent back for repair
+const MAX_REPAIR_ATTEMPTS: u32 = 2;
+
+/// Why a structured model call produced nothing usable
+enum StructuredCallError {
+    /// The model could not be called
+    Call(String),
+    /// Schema violations left after the last repair attempt
+    Invalid(Vec<String>),
+}
+
 pub struct PromptOrchestrator {
     executor: Arc<dyn PipelineExecutor>,
     zsei: Arc<dyn ZSEIAccess>,
     task_manager: Arc<TaskManager>,
     pipeline_index: Arc<RwLock<Option<PipelineIndex>>>,
+    events: Option<Arc<EventBus>>,
+    checkpoints: Option<Arc<CheckpointStore>>,
+    /// Most blueprint steps run at once
+    max_parallel_steps: usize,
+    /// Model providers; without them model calls go to the prompt pipeline
+    llm: Option<Arc<LlmRouter>>,
+    /// Context lengths, output limits, prices and tokenizers of the models
+    models: Arc<ModelCatalog>,
+    /// Held while a blueprint's statistics are read and written back, so
+    /// tasks finishing together all count
+    blueprint_locks: std::sync::Mutex<HashMap<u64, Arc<tokio::sync::Mutex<()>>>>,
+    /// Orchestrations running in this process, which cannot be resumed
+    active: std::sync::Mutex<HashSet<u64>>,
+}
+
+/// Marks an orchestration as running until dropped
+struct ActiveOrchestration<'a> {
+    active: &'a std::sync::Mutex<HashSet<u64>>,
+    orchestration_id: u64,
+}
+
+impl Drop for ActiveOrchestration<'_> {
+    fn drop(&mut self) {
+        self.active
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.orchestration_id);
+    }
 }
 
 impl PromptOrchestrator {
@@ -819,19 +1069,285 @@ impl PromptOrchestrator {
             zsei,
             task_manager,
             pipeline_index: Arc::new(RwLock::new(None)),
+            events: None,
+            checkpoints: None,
+            max_parallel_steps: DEFAULT_MAX_PARALLEL_STEPS,
+            llm: None,
+            models: Arc::new(ModelCatalog::default()),
+            blueprint_locks: Default::default(),
+            active: Default::default(),
         }
     }
 
-    fn extract_json_from_response<'a>(s: &'a str, open: char, close: char) -> &'a str {
-        let trimmed = s.trim();
-        if let Some(start) = trimmed.find(open) {
-            if let Some(end) = trimmed.rfind(close) {
-                if end >= start {
-                    return &trimmed[start..=end];
-                }
+    /// Publish stage results to the event bus
+    pub fn with_event_bus(mut self, events: Arc<EventBus>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Checkpoint every stage and step so orchestrations can be resumed
+    pub fn with_checkpoints(mut self, store: CheckpointStore) -> Self {
+        self.checkpoints = Some(Arc::new(store));
+        self
+    }
+
+    /// Run at most `limit` independent blueprint steps at once
+    pub fn with_max_parallel_steps(mut self, limit: usize) -> Self {
+        self.max_parallel_steps = limit.max(1);
+        self
+    }
+
+    /// Call models through `router`, picking the model per stage, instead
+    /// of through the prompt pipeline
+    pub fn with_llm(mut self, router: LlmRouter) -> Self {
+        self.llm = Some(Arc::new(router));
+        self
+    }
+
+    /// Budget and count tokens with the models' metadata instead of
+    /// estimates
+    pub fn with_models(mut self, catalog: ModelCatalog) -> Self {
+        self.models = Arc::new(catalog);
+        self
+    }
+
+    /// Run a prompt pipeline input through the stage's model, or through
+    /// the prompt pipeline itself when no router is set; either way the
+    /// result has the pipeline's `{"response", "tokens_used"}` shape
+    async fn call_llm(
+        &self,
+        state: &OrchestrationState,
+        stage: &str,
+        input: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let Some(router) = &self.llm else {
+            let mut result = self
+                .executor
+                .execute(PROMPT_PIPELINE_ID, input.clone())
+                .await?;
+            let usage = self.prompt_pipeline_usage(state, &input, &result);
+            Self::add_usage(state, &state.model_identifier, usage);
+            if let Some(result) = result.as_object_mut() {
+                result.insert("tokens_used".to_string(), usage.total().into());
+            }
+            return Ok(result);
+        };
+        let model_override = state
+            .request
+            .model_config
+            .as_ref()
+            .and_then(|c| c.model_identifier.as_deref());
+        let (provider, model) = router.route(stage, model_override);
+        let request = LlmRequest {
+            model,
+            system: input
+                .get("system_context")
+                .and_then(|s| s.as_str())
+                .map(String::from),
+            prompt: input
+                .get("prompt")
+                .and_then(|p| p.as_str())
+                .unwrap_or("")
+                .to_string(),
+            max_tokens: input
+                .get("max_tokens")
+                .and_then(|m| m.as_u64())
+                .unwrap_or(1024) as u32,
+            temperature: input
+                .get("temperature")
+                .and_then(|t| t.as_f64())
+                .unwrap_or(0.7) as f32,
+        };
+        let response = provider
+            .complete(&request)
+            .await
+            .map_err(|e| e.to_string())?;
+        Self::add_usage(state, &request.model, response.usage());
+        Ok(serde_json::json!({
+            "response": response.text,
+            "tokens_used": response.tokens_used(),
+            "model": response.model
+        }))
+    }
+
+    /// Usage of a prompt pipeline call: input counted with the model's
+    /// tokenizer, output the rest of the total the pipeline reports, or
+    /// counted too when it reports none
+    fn prompt_pipeline_usage(
+        &self,
+        state: &OrchestrationState,
+        input: &serde_json::Value,
+        result: &serde_json::Value,
+    ) -> TokenUsage {
+        let tokenizer = self.models.tokenizer(&state.model_identifier);
+        let text = |value: &serde_json::Value, key: &str| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        let input_tokens = tokenizer.count(&text(input, "system_context"))
+            + tokenizer.count(&text(input, "prompt"));
+        let output_tokens = match result.get("tokens_used").and_then(|t| t.as_u64()) {
+            Some(total) => (total as u32).saturating_sub(input_tokens),
+            None => tokenizer.count(&text(result, "response")),
+        };
+        TokenUsage {
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    /// Run a pipeline other than the prompt pipeline, counting the tokens
+    /// it reports
+    async fn execute_pipeline(
+        &self,
+        state: &OrchestrationState,
+        pipeline_id: u64,
+        input: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let result = self.executor.execute(pipeline_id, input).await?;
+        let output_tokens = Self::reported_tokens(&result);
+        if output_tokens > 0 {
+            let usage = TokenUsage {
+                input_tokens: 0,
+                output_tokens,
+            };
+            Self::add_usage(state, &format!("pipeline:{}", pipeline_id), usage);
+        }
+        Ok(result)
+    }
+
+    fn reported_tokens(result: &serde_json::Value) -> u32 {
+        result
+            .get("tokens_used")
+            .and_then(|t| t.as_u64())
+            .unwrap_or(0) as u32
+    }
+
+    /// Add a call's usage to the stage in progress
+    fn add_usage(state: &OrchestrationState, model: &str, usage: TokenUsage) {
+        if let Ok(mut pending) = state.pending_usage.lock() {
+            pending.entry(model.to_string()).or_default().add(usage);
+        }
+    }
+
+    /// Call the stage's model and parse its answer against `schema`. An
+    /// answer that violates the schema is sent back with the violations, up
+    /// to `MAX_REPAIR_ATTEMPTS` times; violations left after that are
+    /// returned, prefixed with the schema name.
+    async fn call_llm_structured(
+        &self,
+        state: &OrchestrationState,
+        stage: &str,
+        input: serde_json::Value,
+        schema: ResponseSchema,
+    ) -> Result<serde_json::Value, StructuredCallError> {
+        let prompt = input
+            .get("prompt")
+            .and_then(|p| p.as_str())
+            .unwrap_or("")
+            .to_string();
+        let mut request = input;
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .call_llm(state, stage, request.clone())
+                .await
+                .map_err(StructuredCallError::Call)?;
+            let response = result
+                .get("response")
+                .and_then(|r| r.as_str())
+                .unwrap_or("");
+            let violations = match schema.parse(response) {
+                Ok(value) => return Ok(value),
+                Err(violations) => violations,
+            };
+            if attempt == MAX_REPAIR_ATTEMPTS {
+                return Err(StructuredCallError::Invalid(
+                    violations
+                        .into_iter()
+                        .map(|v| format!("{}: {}", schema.name(), v))
+                        .collect(),
+                ));
             }
+            attempt += 1;
+            tracing::debug!(
+                "Repairing {} response (attempt {}): {:?}",
+                schema.name(),
+                attempt,
+                violations
+            );
+            request["prompt"] = serde_json::Value::String(format!(
+                r#"{}
+
+Your previous response did not match the required JSON schema.
+
+PROBLEMS:
+{}
+
+REQUIRED SCHEMA:
+{}
+
+PREVIOUS RESPONSE:
+{}
+
+Return ONLY the corrected JSON, with no explanation."#,
+                prompt,
+                violations
+                    .iter()
+                    .map(|v| format!("- {}", v))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                schema.schema(),
+                truncate_str(response, 2000)
+            ));
+        }
+    }
+
+    /// The value of a structured call that stage 2 can do without: schema
+    /// violations are added to `failures` for the stage's result and a
+    /// failed call is skipped
+    fn valid_or_record(
+        failures: &mut Vec<String>,
+        outcome: Result<serde_json::Value, StructuredCallError>,
+    ) -> Option<serde_json::Value> {
+        match outcome {
+            Ok(value) => Some(value),
+            Err(StructuredCallError::Invalid(violations)) => {
+                failures.extend(violations);
+                None
+            }
+            Err(StructuredCallError::Call(_)) => None,
         }
-        trimmed
+    }
+
+    /// Record `stage` as failed on structured output that stage cannot do
+    /// without, and return the error to fail the orchestration with
+    fn fail_stage(
+        &self,
+        state: &mut OrchestrationState,
+        stage: u8,
+        name: &str,
+        error: StructuredCallError,
+        duration_ms: u64,
+    ) -> String {
+        let violations = match error {
+            StructuredCallError::Call(e) => return e,
+            StructuredCallError::Invalid(violations) => violations,
+        };
+        let error = format!("{}: {}", name, violations.join("; "));
+        state.validation_failures.extend(violations);
+        self.record_stage_timed(
+            state,
+            stage,
+            name,
+            false,
+            "Output failed schema validation",
+            duration_ms,
+        );
+        error
     }
 
     /// Load pipeline index from ZSEI
@@ -869,94 +1385,453 @@ impl PromptOrchestrator {
         let model_identifier = request
             .model_config
             .as_ref()
-            .and_then(|c| c.model_identifier.as_ref())
-            .map(|s| s.as_str())
-            .unwrap_or("claude-sonnet-4");
+            .and_then(|c| c.model_identifier.as_deref())
+            .or(self.models.default_model())
+            .unwrap_or("claude-sonnet-4")
+            .to_string();
 
+        // The request's budget can only narrow the model's context (see
+        // `OrchestrationRequest::token_budget`)
         let model_context_limit = request
             .model_config
             .as_ref()
             .and_then(|c| c.context_length)
-            .unwrap_or_else(|| get_model_context_limit(model_identifier));
+            .or_else(|| {
+                self.models
+                    .get(&model_identifier)
+                    .map(|info| info.context_length)
+            })
+            .unwrap_or_else(|| get_model_context_limit(&model_identifier))
+            .min(request.token_budget.unwrap_or(u32::MAX));
 
-        let prompt_tokens = Self::estimate_tokens(&request.prompt);
+        let prompt_tokens = self
+            .models
+            .tokenizer(&model_identifier)
+            .count(&request.prompt);
 
         // Load pipeline index if not already loaded
         let _ = self.load_pipeline_index().await;
         let available_pipelines = self.get_available_pipelines().await;
 
-        let mut state = OrchestrationState {
-            request: request.clone(),
-            start_time: std::time::Instant::now(),
-            stages: Vec::new(),
+        let mut state = OrchestrationState::new(
+            request,
+            model_identifier,
             model_context_limit,
-            tokens_used_so_far: prompt_tokens,
-            raw_chunks: Vec::new(),
-            file_graphs: HashMap::new(),
-            classified_file_graphs: Vec::new(),
-            chunk_graph_ids: Vec::new(),
-            modality_graphs: HashMap::new(),
-            graph_states: HashMap::new(),
-            root_modality_list: RootModalityList::default(),
-            initial_graphs_created: false,
-            cross_modal_index_id: None,
-            processed_chunks: Vec::new(),
-            cleaned_prompt: String::new(),
             prompt_tokens,
-            keywords: Vec::new(),
-            entities: Vec::new(),
-            topics: Vec::new(),
-            methodologies: Vec::new(),
-            categories: Vec::new(),
-            categories_created: 0,
-            amt: None,
-            amt_validated: false,
-            validation_streak: 0,
-            needs_clarification: false,
-            clarification_points: Vec::new(),
-            intent_captures: Vec::new(),
-            branch_captures: Vec::new(),
-            detail_captures: Vec::new(),
-            cross_refs: Vec::new(),
-            amt_pass_count: 0,
-            coverage_aspects: Vec::new(),
-            blueprint_id: None,
-            blueprint_steps: Vec::new(),
-            orch_step_states: HashMap::new(),
-            blueprints_created: 0,
-            task_id: None,
-            step_results: Vec::new(),
-            final_response: None,
-            step_contexts: HashMap::new(),
-            step_outputs: HashMap::new(),
-            gate_result: None,
-            voice_identity: None,
             available_pipelines,
+        );
+
+        let _active = match self.claim(state.orchestration_id) {
+            Ok(active) => active,
+            Err(e) => return self.build_error_response(&mut state, e),
         };
+        self.run(&mut state).await
+    }
 
-        // Check I-Loop before starting (if consciousness enabled)
-        if request.consciousness_enabled {
-            if let Err(e) = self.wait_for_i_loop().await {
-                return self.build_error_response(&mut state, format!("I-Loop wait failed: {}", e));
-            }
+    /// Mark an orchestration as running, refusing one that already is
+    fn claim(&self, orchestration_id: u64) -> Result<ActiveOrchestration<'_>, String> {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        if !active.insert(orchestration_id) {
+            return Err(format!(
+                "Orchestration {} is already running",
+                orchestration_id
+            ));
         }
+        Ok(ActiveOrchestration {
+            active: &self.active,
+            orchestration_id,
+        })
+    }
+
+    /// Run checkpoint file IO on a blocking thread
+    async fn checkpoint_io<T, F>(&self, work: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&CheckpointStore) -> T + Send + 'static,
+    {
+        let store = self
+            .checkpoints
+            .clone()
+            .ok_or("Checkpoints are not enabled")?;
+        tokio::task::spawn_blocking(move || work(&store))
+            .await
+            .map_err(|e| format!("Checkpoint IO failed: {}", e))
+    }
 
-        let result = self.execute_stages(&mut state).await;
+    /// Continue a checkpointed orchestration after its last completed stage
+    /// and step
+    pub async fn resume(&self, orchestration_id: u64) -> Result<OrchestrationResponse, String> {
+        // A `Running` checkpoint is only resumed once its run is gone
+        let _active = self.claim(orchestration_id)?;
+        let checkpoint = self
+            .checkpoint_io(move |store| store.load(orchestration_id))
+            .await?
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No checkpoint for orchestration {}", orchestration_id))?;
+        let mut state = checkpoint.state;
+        match checkpoint.status {
+            CheckpointStatus::Complete => return Ok(self.build_success_response(&state)),
+            // Running it again would only ask the same questions
+            CheckpointStatus::AwaitingClarification => {
+                return Err(format!(
+                    "Orchestration {} is awaiting clarification; answer its questions instead",
+                    orchestration_id
+                ))
+            }
+            CheckpointStatus::Running | CheckpointStatus::Failed => {}
+        }
 
-        match result {
-            Ok(_) => self.build_success_response(&state),
-            Err(e) => self.build_error_response(&mut state, e),
+        tracing::info!(
+            "Resuming orchestration {} after stage {} ({} steps done)",
+            orchestration_id,
+            state.last_stage,
+            state.step_results.len()
+        );
+        let _ = self.load_pipeline_index().await;
+        let available_pipelines = self.get_available_pipelines().await;
+        if !available_pipelines.is_empty() {
+            state.available_pipelines = available_pipelines;
         }
+        Ok(self.run(&mut state).await)
     }
 
-    async fn execute_stages(&self, state: &mut OrchestrationState) -> Result<(), String> {
-        // STAGE 1: Input Capture (already done - prompt is in request)
-        self.record_stage(state, 1, "Input Capture", true, "Prompt received");
-
-        // STAGE 2: Text/Prompt Normalization + AMT Building
-        self.stage_2_text_normalization_and_amt(state).await?;
+    /// Resume the most recent orchestration of a task
+    pub async fn resume_task(&self, task_id: u64) -> Result<OrchestrationResponse, String> {
+        let summary = self
+            .checkpoint_io(move |store| store.find_task(task_id))
+            .await?
+            .ok_or_else(|| format!("No checkpoint for task {}", task_id))?;
+        self.resume(summary.orchestration_id).await
+    }
 
-        // PHASE 2: Initial graph creation — BEFORE AMT
+    /// Saved orchestrations, most recent first
+    pub async fn checkpoints(&self) -> Vec<CheckpointSummary> {
+        self.checkpoint_io(|store| store.list())
+            .await
+            .unwrap_or_default()
+    }
+
+    /// What the checkpoint of an orchestration records, if it has a
+    /// readable one
+    pub async fn find_checkpoint(&self, orchestration_id: u64) -> Option<CheckpointSummary> {
+        match self
+            .checkpoint_io(move |store| store.load(orchestration_id))
+            .await
+        {
+            Ok(Ok(checkpoint)) => checkpoint.map(|c| c.summary()),
+            Ok(Err(e)) => {
+                tracing::warn!("Ignoring unreadable checkpoint {}: {}", orchestration_id, e);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Answer the questions an orchestration of `user_id` stopped on, in
+    /// the order of its `clarification_points`, and continue it from the
+    /// stage that asked
+    pub async fn answer_clarification(
+        &self,
+        orchestration_id: u64,
+        user_id: u64,
+        answers: Vec<String>,
+    ) -> Result<OrchestrationResponse, String> {
+        // Held from the status check on, so a second answer given meanwhile
+        // is refused rather than run alongside
+        let _active = self.claim(orchestration_id)?;
+        let checkpoint = self
+            .checkpoint_io(move |store| store.load(orchestration_id))
+            .await?
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No checkpoint for orchestration {}", orchestration_id))?;
+        if checkpoint.state.request.user_id != user_id {
+            return Err(format!(
+                "Orchestration {} belongs to another user",
+                orchestration_id
+            ));
+        }
+        if checkpoint.status != CheckpointStatus::AwaitingClarification {
+            return Err(format!(
+                "Orchestration {} is not awaiting clarification",
+                orchestration_id
+            ));
+        }
+        let mut state = checkpoint.state;
+
+        let pending: Vec<usize> = (0..state.clarifications.len())
+            .filter(|&i| state.clarifications[i].answer.is_none())
+            .collect();
+        if answers.len() != pending.len() {
+            return Err(format!(
+                "Expected {} answers, got {}",
+                pending.len(),
+                answers.len()
+            ));
+        }
+        for (&i, answer) in pending.iter().zip(answers) {
+            state.clarifications[i].answer = Some(answer);
+        }
+        let answered: Vec<Clarification> = pending
+            .iter()
+            .map(|&i| state.clarifications[i].clone())
+            .collect();
+
+        // Run the asking stage again; stage 2 rebuilds the prompt and AMT
+        // itself and merges every answer then
+        let stage = answered.iter().map(|c| c.stage).min().unwrap_or(2);
+        if stage > 2 {
+            self.merge_clarifications(&mut state, &answered);
+        }
+        state.last_stage = state.last_stage.min(stage.saturating_sub(1));
+        state.needs_clarification = false;
+        state.clarification_points.clear();
+        // Answered for good once the checkpoint says so
+        let content = checkpoint::encode(&state, CheckpointStatus::Running, None)
+            .map_err(|e| e.to_string())?;
+        self.checkpoint_io(move |store| store.write(orchestration_id, &content))
+            .await?
+            .map_err(|e| e.to_string())?;
+        if let Some(task_id) = state.task_id {
+            self.log_clarifications(task_id, &answered).await;
+        }
+
+        tracing::info!(
+            "Orchestration {} answered {} clarifications, continuing at stage {}",
+            orchestration_id,
+            answered.len(),
+            stage
+        );
+        let _ = self.load_pipeline_index().await;
+        let available_pipelines = self.get_available_pipelines().await;
+        if !available_pipelines.is_empty() {
+            state.available_pipelines = available_pipelines;
+        }
+        Ok(self.run(&mut state).await)
+    }
+
+    /// Record the open clarification points as questions asked by `stage`
+    fn ask_clarification(&self, state: &mut OrchestrationState, stage: u8) {
+        let questions: Vec<Clarification> = state
+            .clarification_points
+            .iter()
+            .map(|question| Clarification {
+                question: question.clone(),
+                answer: None,
+                stage,
+            })
+            .collect();
+        state.clarifications.extend(questions);
+    }
+
+    /// Add answered questions to the cleaned prompt and as details under
+    /// the AMT root
+    fn merge_clarifications(&self, state: &mut OrchestrationState, answered: &[Clarification]) {
+        for clarification in answered {
+            let Some(answer) = &clarification.answer else {
+                continue;
+            };
+            let qa = format!("Q: {}\nA: {}", clarification.question, answer);
+            if !state.cleaned_prompt.contains("\n\nClarifications:") {
+                state.cleaned_prompt.push_str("\n\nClarifications:");
+            }
+            state.cleaned_prompt.push('\n');
+            state.cleaned_prompt.push_str(&qa);
+
+            if let Some(amt) = state.amt.as_mut() {
+                let id = amt.max_id() + 1;
+                let mut node = AMTNode::new(id, AMTNodeType::Leaf, qa, 1);
+                node.metadata
+                    .insert("source".to_string(), "clarification".to_string());
+                amt.children.push(node);
+            }
+        }
+        state.prompt_tokens = self.count_tokens(state, &state.cleaned_prompt);
+    }
+
+    /// Keep the questions and answers in the task's history
+    async fn log_clarifications(&self, task_id: u64, clarifications: &[Clarification]) {
+        for clarification in clarifications {
+            let Some(answer) = &clarification.answer else {
+                continue;
+            };
+            let message = format!(
+                "Clarification (stage {}): {} -> {}",
+                clarification.stage, clarification.question, answer
+            );
+            if let Err(e) = self
+                .task_manager
+                .add_log(task_id, LogLevel::Info, message)
+                .await
+            {
+                tracing::warn!("Failed to log clarification for task {}: {}", task_id, e);
+            }
+        }
+    }
+
+    /// Run the stages not yet completed and checkpoint the outcome
+    async fn run(&self, state: &mut OrchestrationState) -> OrchestrationResponse {
+        // Check I-Loop before starting (if consciousness enabled)
+        let mut result = Ok(());
+        if state.request.consciousness_enabled {
+            if let Err(e) = self.wait_for_i_loop().await {
+                result = Err(format!("I-Loop wait failed: {}", e));
+            }
+        }
+        if result.is_ok() {
+            result = self.execute_stages(state).await;
+        }
+
+        match result {
+            Ok(_) => {
+                let status = if state.needs_clarification {
+                    CheckpointStatus::AwaitingClarification
+                } else {
+                    CheckpointStatus::Complete
+                };
+                self.checkpoint(state, status, None).await;
+                self.build_success_response(state)
+            }
+            Err(e) => {
+                // Execution started but never reached result collection
+                if (5..9).contains(&state.last_stage) {
+                    self.record_blueprint_outcome(state, false).await;
+                }
+                self.checkpoint(state, CheckpointStatus::Failed, Some(&e)).await;
+                self.build_error_response(state, e)
+            }
+        }
+    }
+
+    /// Save the state if checkpoints are enabled; a failed save is logged
+    /// and the orchestration goes on
+    async fn checkpoint(
+        &self,
+        state: &OrchestrationState,
+        status: CheckpointStatus,
+        error: Option<&str>,
+    ) {
+        if self.checkpoints.is_none() {
+            return;
+        }
+        let orchestration_id = state.orchestration_id;
+        let result = match checkpoint::encode(state, status, error) {
+            Ok(content) => self
+                .checkpoint_io(move |store| store.write(orchestration_id, &content))
+                .await
+                .and_then(|result| result.map_err(|e| e.to_string())),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to checkpoint orchestration {}: {}",
+                orchestration_id,
+                e
+            );
+        }
+    }
+
+    /// Mark a stage done and checkpoint
+    async fn complete_stage(&self, state: &mut OrchestrationState, stage: u8) {
+        state.last_stage = stage;
+        self.checkpoint(state, CheckpointStatus::Running, None).await;
+    }
+
+    /// Run each stage after the last completed one; stages 6-8 also
+    /// checkpoint after every blueprint step
+    async fn execute_stages(&self, state: &mut OrchestrationState) -> Result<(), String> {
+        // STAGE 1: Input Capture (already done - prompt is in request)
+        if state.last_stage < 1 {
+            self.record_stage(state, 1, "Input Capture", true, "Prompt received");
+            self.complete_stage(state, 1).await;
+        }
+
+        if state.last_stage < 2 {
+            self.stage_2_and_initial_graphs(state).await?;
+            self.complete_stage(state, 2).await;
+        }
+
+        // If clarification needed, stop here and return to user
+        if state.needs_clarification {
+            self.ask_clarification(state, 2);
+            return Ok(());
+        }
+
+        // STAGE 3: Blueprint Assignment
+        if state.last_stage < 3 {
+            self.stage_3_blueprint_assignment(state).await?;
+            self.complete_stage(state, 3).await;
+        }
+
+        // STAGE 4: Zero-Shot Simulation (with AMT traversal)
+        if state.last_stage < 4 {
+            self.stage_4_zero_shot_simulation(state).await?;
+            self.complete_stage(state, 4).await;
+        }
+
+        if state.needs_clarification {
+            self.ask_clarification(state, 4);
+            return Ok(());
+        }
+
+        // STAGE 5: Consciousness Decision Gate
+        if state.last_stage < 5 {
+            if state.request.consciousness_enabled {
+                self.stage_5_consciousness_gate(state).await?;
+            } else {
+                self.record_stage(state, 5, "Consciousness Gate", true, "Skipped (disabled)");
+            }
+            self.complete_stage(state, 5).await;
+        }
+
+        // STAGE 6-8: Context Aggregation + Task Creation + Execution
+        if state.last_stage < 8 {
+            self.stage_6_to_8_execute_steps(state).await?;
+            self.complete_stage(state, 8).await;
+        }
+
+        // STAGE 9: Result Collection
+        if state.last_stage < 9 {
+            self.stage_9_result_collection(state).await?;
+            self.complete_stage(state, 9).await;
+        }
+
+        // STAGE 10: Post-execution Consciousness
+        if state.last_stage < 10 {
+            if state.request.consciousness_enabled {
+                self.stage_10_post_execution(state).await?;
+            } else {
+                self.record_stage(state, 10, "Post-execution", true, "Skipped (disabled)");
+            }
+            self.complete_stage(state, 10).await;
+        }
+
+        // STAGE 11: Response Delivery
+        if state.last_stage < 11 {
+            self.stage_11_response_delivery(state).await?;
+            self.complete_stage(state, 11).await;
+        }
+
+        Ok(())
+    }
+
+    /// STAGE 2 followed by initial graph creation
+    async fn stage_2_and_initial_graphs(
+        &self,
+        state: &mut OrchestrationState,
+    ) -> Result<(), String> {
+        // STAGE 2: Text/Prompt Normalization + AMT Building
+        self.stage_2_text_normalization_and_amt(state).await?;
+
+        // A rebuilt prompt and AMT need the answers given so far
+        let answered: Vec<Clarification> = state
+            .clarifications
+            .iter()
+            .filter(|c| c.answer.is_some())
+            .cloned()
+            .collect();
+        self.merge_clarifications(state, &answered);
+
+        // PHASE 2: Initial graph creation — BEFORE AMT
         self.aggregate_root_modalities(state).await;
         self.create_initial_modality_graphs(state)
             .await
@@ -982,41 +1857,6 @@ impl PromptOrchestrator {
                     .sum::<usize>()
             ),
         );
-
-        // If clarification needed, stop here and return to user
-        if state.needs_clarification {
-            return Ok(());
-        }
-
-        // STAGE 3: Blueprint Assignment
-        self.stage_3_blueprint_assignment(state).await?;
-
-        // STAGE 4: Zero-Shot Simulation (with AMT traversal)
-        self.stage_4_zero_shot_simulation(state).await?;
-
-        // STAGE 5: Consciousness Decision Gate
-        if state.request.consciousness_enabled {
-            self.stage_5_consciousness_gate(state).await?;
-        } else {
-            self.record_stage(state, 5, "Consciousness Gate", true, "Skipped (disabled)");
-        }
-
-        // STAGE 6-8: Context Aggregation + Task Creation + Execution
-        self.stage_6_to_8_execute_steps(state).await?;
-
-        // STAGE 9: Result Collection
-        self.stage_9_result_collection(state).await?;
-
-        // STAGE 10: Post-execution Consciousness
-        if state.request.consciousness_enabled {
-            self.stage_10_post_execution(state).await?;
-        } else {
-            self.record_stage(state, 10, "Post-execution", true, "Skipped (disabled)");
-        }
-
-        // STAGE 11: Response Delivery
-        self.stage_11_response_delivery(state).await?;
-
         Ok(())
     }
 
@@ -1160,7 +2000,7 @@ impl PromptOrchestrator {
             .unwrap_or(&state.request.prompt)
             .to_string();
 
-        state.prompt_tokens = Self::estimate_tokens(&state.cleaned_prompt);
+        state.prompt_tokens = self.count_tokens(state, &state.cleaned_prompt);
         state.keywords = all_keywords.into_iter().collect();
         state.entities = all_entities;
         state.topics = all_topics.into_iter().collect();
@@ -1374,7 +2214,7 @@ impl PromptOrchestrator {
                     serde_json::to_string(&known_intents_json).unwrap_or_default(),
                     chunk.index + 1,
                     state.processed_chunks.len(),
-                    &chunk.cleaned_text[..chunk.cleaned_text.len().min(1500)],
+                    truncate_str(&chunk.cleaned_text, 1500),
                     detected_modality_names.join(", ")
                 );
 
@@ -1385,15 +2225,11 @@ impl PromptOrchestrator {
                     "system_context": "Extract new intents not already listed. Return only valid JSON. No explanation."
                 });
 
-                if let Ok(result) = self.executor.execute(9, intent_input).await {
-                    let response = result
-                        .get("response")
-                        .and_then(|r| r.as_str())
-                        .unwrap_or("{}");
-                    let json_str = Self::extract_json_from_response(response, '{', '}');
-                    let parsed = serde_json::from_str::<serde_json::Value>(json_str.trim())
-                        .unwrap_or_else(|_| serde_json::json!({"new_intents": []}));
-
+                let outcome = self
+                    .call_llm_structured(state, "amt", intent_input, ResponseSchema::Intents)
+                    .await;
+                if let Some(parsed) = Self::valid_or_record(&mut state.validation_failures, outcome)
+                {
                     if let Some(new_intents) = parsed.get("new_intents").and_then(|n| n.as_array())
                     {
                         for intent_val in new_intents {
@@ -1530,7 +2366,7 @@ impl PromptOrchestrator {
         }}
         If no new branches apply, return: {{"branches": []}}"#,
                         method_name,
-                        &method_description[..method_description.len().min(300)],
+                        truncate_str(&method_description, 300),
                         intents_summary.join("\n"),
                         serde_json::to_string(&known_branches_json).unwrap_or_default()
                     );
@@ -1542,15 +2378,12 @@ impl PromptOrchestrator {
                         "system_context": "Suggest branches per methodology. Return only valid JSON. No explanation."
                     });
 
-                    if let Ok(result) = self.executor.execute(9, branch_input).await {
-                        let response = result
-                            .get("response")
-                            .and_then(|r| r.as_str())
-                            .unwrap_or("{}");
-                        let json_str = Self::extract_json_from_response(response, '{', '}');
-                        let parsed = serde_json::from_str::<serde_json::Value>(json_str.trim())
-                            .unwrap_or_else(|_| serde_json::json!({"branches": []}));
-
+                    let outcome = self
+                        .call_llm_structured(state, "amt", branch_input, ResponseSchema::Branches)
+                        .await;
+                    if let Some(parsed) =
+                        Self::valid_or_record(&mut state.validation_failures, outcome)
+                    {
                         if let Some(branches) = parsed.get("branches").and_then(|b| b.as_array()) {
                             for branch_val in branches {
                                 let branch_str = branch_val
@@ -1706,7 +2539,7 @@ impl PromptOrchestrator {
                     serde_json::to_string(&known_details_json).unwrap_or_default(),
                     chunk.index + 1,
                     state.processed_chunks.len(),
-                    &chunk.cleaned_text[..chunk.cleaned_text.len().min(1500)]
+                    truncate_str(&chunk.cleaned_text, 1500)
                 );
 
                 let detail_input = serde_json::json!({
@@ -1716,15 +2549,11 @@ impl PromptOrchestrator {
                     "system_context": "Extract details per branch. Return only valid JSON. No explanation."
                 });
 
-                if let Ok(result) = self.executor.execute(9, detail_input).await {
-                    let response = result
-                        .get("response")
-                        .and_then(|r| r.as_str())
-                        .unwrap_or("{}");
-                    let json_str = Self::extract_json_from_response(response, '{', '}');
-                    let parsed = serde_json::from_str::<serde_json::Value>(json_str.trim())
-                        .unwrap_or_else(|_| serde_json::json!({"details": [], "new_branches": []}));
-
+                let outcome = self
+                    .call_llm_structured(state, "amt", detail_input, ResponseSchema::Details)
+                    .await;
+                if let Some(parsed) = Self::valid_or_record(&mut state.validation_failures, outcome)
+                {
                     // Process new details
                     if let Some(details) = parsed.get("details").and_then(|d| d.as_array()) {
                         for detail_val in details {
@@ -1986,45 +2815,47 @@ impl PromptOrchestrator {
                     "system_context": "Identify cross-branch relationships. Return only valid JSON."
                 });
 
-                if let Ok(result) = self.executor.execute(9, crossref_input).await {
-                    let response = result
-                        .get("response")
-                        .and_then(|r| r.as_str())
-                        .unwrap_or("{}");
-                    let json_str = Self::extract_json_from_response(response, '{', '}');
-                    if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.trim()) {
-                        if parsed
-                            .get("related")
-                            .and_then(|r| r.as_bool())
-                            .unwrap_or(false)
-                        {
-                            let rel_type_str = parsed
-                                .get("relationship_type")
-                                .and_then(|rt| rt.as_str())
-                                .unwrap_or("relates_to");
-                            let description = parsed
-                                .get("description")
-                                .and_then(|d| d.as_str())
-                                .unwrap_or("")
-                                .to_string();
+                let outcome = self
+                    .call_llm_structured(
+                        state,
+                        "amt",
+                        crossref_input,
+                        ResponseSchema::CrossReference,
+                    )
+                    .await;
+                if let Some(parsed) = Self::valid_or_record(&mut state.validation_failures, outcome)
+                {
+                    if parsed
+                        .get("related")
+                        .and_then(|r| r.as_bool())
+                        .unwrap_or(false)
+                    {
+                        let rel_type_str = parsed
+                            .get("relationship_type")
+                            .and_then(|rt| rt.as_str())
+                            .unwrap_or("relates_to");
+                        let description = parsed
+                            .get("description")
+                            .and_then(|d| d.as_str())
+                            .unwrap_or("")
+                            .to_string();
+
+                        let relation_type = match rel_type_str {
+                            "depends_on" => AMTRelationType::DependsOn,
+                            "requires" => AMTRelationType::Requires,
+                            "contradicts" => AMTRelationType::Contradicts,
+                            "shared_context" => AMTRelationType::SharedContext,
+                            _ => AMTRelationType::RelatesTo,
+                        };
 
-                            let relation_type = match rel_type_str {
-                                "depends_on" => AMTRelationType::DependsOn,
-                                "requires" => AMTRelationType::Requires,
-                                "contradicts" => AMTRelationType::Contradicts,
-                                "shared_context" => AMTRelationType::SharedContext,
-                                _ => AMTRelationType::RelatesTo,
-                            };
-
-                            state.cross_refs.push(CrossRef {
-                                from_branch: branch_a.clone(),
-                                to_branch: branch_b.clone(),
-                                from_intent: intent_a.clone(),
-                                to_intent: intent_b.clone(),
-                                relation_type,
-                                description,
-                            });
-                        }
+                        state.cross_refs.push(CrossRef {
+                            from_branch: branch_a.clone(),
+                            to_branch: branch_b.clone(),
+                            from_intent: intent_a.clone(),
+                            to_intent: intent_b.clone(),
+                            relation_type,
+                            description,
+                        });
                     }
                 }
             }
@@ -2240,14 +3071,90 @@ impl PromptOrchestrator {
     // STAGE 3: Blueprint Assignment
     // ========================================================================
 
+    /// What the request takes and produces: the prompt's and attached
+    /// files' modalities, the output type its keywords ask for, and its
+    /// topics as constraints
+    fn request_signature(state: &OrchestrationState) -> crate::types::TaskSignature {
+        let mut input_types = vec!["text".to_string()];
+        input_types.extend(
+            state
+                .root_modality_list
+                .verified_modalities
+                .iter()
+                .map(|m| m.modality.clone()),
+        );
+        input_types.extend(
+            state
+                .classified_file_graphs
+                .iter()
+                .map(|f| f.modality.clone()),
+        );
+        matching::task_signature(
+            &input_types,
+            matching::infer_output_type(&state.keywords),
+            &state.topics,
+        )
+    }
+
+    /// Count a finished task in its blueprint's statistics
+    async fn record_blueprint_outcome(&self, state: &OrchestrationState, success: bool) {
+        let Some(blueprint_id) = state.blueprint_id else {
+            return;
+        };
+        let lock = self
+            .blueprint_locks
+            .lock()
+            .map(|mut locks| locks.entry(blueprint_id).or_default().clone())
+            .unwrap_or_default();
+        let _guard = lock.lock().await;
+        let mut stats = match self.zsei.get_container(blueprint_id).await {
+            Ok(Some(container)) => StoredBlueprint::from_container(blueprint_id, &container)
+                .map(|stored| stored.stats)
+                .unwrap_or_default(),
+            _ => return,
+        };
+        stats.record(success);
+        let updates = serde_json::json!({ "storage": stats });
+        if let Err(e) = self.zsei.update_container(blueprint_id, updates).await {
+            tracing::warn!(
+                "Failed to update statistics of blueprint {}: {}",
+                blueprint_id,
+                e
+            );
+        }
+    }
+
     async fn stage_3_blueprint_assignment(
         &self,
         state: &mut OrchestrationState,
     ) -> Result<(), String> {
         let stage_start = std::time::Instant::now();
 
-        // Search for blueprint with 100% match
-        let blueprint_ids = self
+        let signature = Self::request_signature(state);
+        let terms: Vec<String> = state
+            .keywords
+            .iter()
+            .chain(&state.topics)
+            .cloned()
+            .collect();
+        let embedding = crate::zsei::term_embedding(&terms, crate::zsei::TERM_EMBEDDING_DIMENSION);
+
+        // Candidates: blueprints with this signature, then those sharing
+        // keywords
+        let search = crate::types::zsei::ZSEIQuery::SearchBlueprints {
+            task_signature: signature.clone(),
+        };
+        let mut candidate_ids: Vec<u64> = match serde_json::to_value(search) {
+            Ok(query) => self
+                .zsei
+                .query(query)
+                .await
+                .ok()
+                .and_then(|result| serde_json::from_value(result["Containers"].clone()).ok())
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        let keyword_ids = self
             .zsei
             .search_by_keywords(
                 &state.keywords.iter().take(15).cloned().collect::<Vec<_>>(),
@@ -2255,53 +3162,45 @@ impl PromptOrchestrator {
             )
             .await
             .unwrap_or_default();
+        for id in keyword_ids {
+            if !candidate_ids.contains(&id) {
+                candidate_ids.push(id);
+            }
+        }
 
-        let mut best_match: Option<(u64, f32)> = None;
-
-        for bp_id in blueprint_ids {
-            if let Ok(Some(container)) = self.zsei.get_container(bp_id).await {
-                // Calculate match score
-                let bp_keywords: Vec<String> = container
-                    .get("local_state")
-                    .and_then(|ls| ls.get("context"))
-                    .and_then(|ctx| ctx.get("keywords"))
-                    .and_then(|k| serde_json::from_value(k.clone()).ok())
-                    .unwrap_or_default();
-
-                let state_keywords_set: HashSet<_> =
-                    state.keywords.iter().map(|s| s.to_lowercase()).collect();
-                let bp_keywords_set: HashSet<_> =
-                    bp_keywords.iter().map(|s| s.to_lowercase()).collect();
-
-                let intersection = state_keywords_set.intersection(&bp_keywords_set).count();
-                let union = state_keywords_set.union(&bp_keywords_set).count();
-
-                let match_score = if union > 0 {
-                    intersection as f32 / union as f32
-                } else {
-                    0.0
-                };
-
-                if match_score > best_match.map(|(_, s)| s).unwrap_or(0.0) {
-                    best_match = Some((bp_id, match_score));
+        // Best scoring blueprint; more used ones win ties
+        let mut best_match: Option<(StoredBlueprint, MatchScore)> = None;
+        for bp_id in candidate_ids {
+            let Ok(Some(container)) = self.zsei.get_container(bp_id).await else {
+                continue;
+            };
+            let Some(stored) = StoredBlueprint::from_container(bp_id, &container) else {
+                continue;
+            };
+            let score = stored.score(&signature, &embedding, &state.keywords, &state.topics);
+            let better = match &best_match {
+                Some((best, best_score)) => {
+                    score.total() > best_score.total()
+                        || (score.total() == best_score.total()
+                            && stored.stats.usage_count > best.stats.usage_count)
                 }
+                None => true,
+            };
+            if better {
+                best_match = Some((stored, score));
             }
         }
 
-        // Only use if 100% match (or very close - 95%+)
-        if let Some((bp_id, score)) = best_match {
-            if score >= 0.95 {
-                state.blueprint_id = Some(bp_id);
-
-                // Load blueprint steps
-                if let Ok(Some(container)) = self.zsei.get_container(bp_id).await {
-                    state.blueprint_steps = container
-                        .get("local_state")
-                        .and_then(|ls| ls.get("storage"))
-                        .and_then(|s| s.get("steps"))
-                        .and_then(|steps| serde_json::from_value(steps.clone()).ok())
-                        .unwrap_or_default();
-                }
+        // Reuse a close match that has not proven unreliable
+        if let Some((stored, score)) = &best_match {
+            let steps: Vec<BlueprintStep> =
+                serde_json::from_value(stored.steps.clone()).unwrap_or_default();
+            if score.total() >= matching::REUSE_SCORE
+                && stored.stats.reliable()
+                && !steps.is_empty()
+            {
+                state.blueprint_id = Some(stored.blueprint_id);
+                state.blueprint_steps = steps;
 
                 self.record_stage_timed(
                     state,
@@ -2309,17 +3208,23 @@ impl PromptOrchestrator {
                     "Blueprint Assignment",
                     true,
                     &format!(
-                        "Using existing blueprint {} (match: {:.0}%)",
-                        bp_id,
-                        score * 100.0
+                        "Using existing blueprint {} (match: {:.0}%, success: {:.0}% over {} runs)",
+                        stored.blueprint_id,
+                        score.total() * 100.0,
+                        stored.stats.success_rate * 100.0,
+                        stored.stats.validation_runs
                     ),
                     stage_start.elapsed().as_millis() as u64,
                 );
                 return Ok(());
             }
         }
+        // A partial match is adapted rather than written from scratch
+        let adapt_from = best_match
+            .filter(|(_, score)| score.total() >= matching::ADAPT_SCORE)
+            .map(|(stored, _)| stored);
 
-        // No 100% match - create new blueprint
+        // No close match - create a new blueprint
         let amt = state.amt.as_ref().ok_or("No AMT available")?;
 
         // Generate blueprint from AMT with pipeline awareness
@@ -2331,8 +3236,17 @@ impl PromptOrchestrator {
             .collect::<Vec<_>>()
             .join("\n");
 
+        let adaptation = match &adapt_from {
+            Some(stored) => format!(
+                "\n\nEXISTING BLUEPRINT \"{}\" for a similar task. Adapt it: keep the steps that \
+                 still apply and change or add only what this request needs.\nSTEPS: {}",
+                stored.name, stored.steps
+            ),
+            None => String::new(),
+        };
+
         let blueprint_prompt = format!(
-            r#"Create a blueprint (execution plan) from this AMT.
+            r#"Create a blueprint (execution plan) from this AMT.{}
 
 AMT ROOT: {}
 BRANCHES:
@@ -2345,6 +3259,9 @@ METHODOLOGIES: {:?}
 
 For each step, select the most appropriate pipeline from the list.
 If no existing pipeline can handle a requirement, add it to missing_capabilities.
+Steps that do not depend on each other run in parallel. failure_policy is
+"fail_fast", "continue" (skip only the steps depending on it) or
+{{"retry": {{"attempts": 2}}}}.
 
 Return JSON:
 {{
@@ -2357,13 +3274,16 @@ Return JSON:
             "description": "What this step does",
             "pipeline_id": 9,
             "context_requirements": ["full_context"],
+            "sub_steps": [],
             "depends_on": [],
             "wait_for_graph_update": false,
-            "max_retries": 1
+            "max_retries": 1,
+            "failure_policy": "fail_fast"
         }}
     ],
     "missing_capabilities": ["capability1", "capability2"]
 }}"#,
+            adaptation,
             amt.content,
             amt.children
                 .iter()
@@ -2386,17 +3306,21 @@ Return JSON:
             "system_context": "Generate execution blueprints. Respond with JSON only."
         });
 
-        let bp_result = self.executor.execute(9, bp_input).await?;
-        let response = bp_result
-            .get("response")
-            .and_then(|r| r.as_str())
-            .unwrap_or("{}");
-        let bp_json = Self::parse_json_object(response);
+        let bp_json = match self
+            .call_llm_structured(state, "blueprint", bp_input, ResponseSchema::Blueprint)
+            .await
+        {
+            Ok(bp_json) => bp_json,
+            Err(e) => {
+                let duration_ms = stage_start.elapsed().as_millis() as u64;
+                return Err(self.fail_stage(state, 3, "Blueprint Assignment", e, duration_ms));
+            }
+        };
 
         let name = bp_json
             .get("name")
             .and_then(|n| n.as_str())
-            .unwrap_or("Generated Blueprint")
+            .unwrap_or_default()
             .to_string();
         let description = bp_json
             .get("description")
@@ -2423,27 +3347,20 @@ Return JSON:
             );
         }
 
-        state.blueprint_steps = bp_json
-            .get("steps")
-            .and_then(|s| serde_json::from_value(s.clone()).ok())
-            .unwrap_or_else(|| {
-                vec![BlueprintStep {
-                    step_index: 0,
-                    action: "execute_prompt".to_string(),
-                    description: "Process the user prompt".to_string(),
-                    pipeline_id: 9,
-                    context_requirements: vec!["full_context".to_string()],
-                    loop_config: None,
-                    sub_steps: Vec::new(),
-                    depends_on: Vec::new(),
-                    wait_for_graph_update: false,
-                    max_retries: 1,
-                    timeout_ms: None,
-                }]
-            });
+        // The schema covers the step fields, but nested loop configs and
+        // sub-steps are only checked here
+        state.blueprint_steps = match serde_json::from_value(bp_json["steps"].clone()) {
+            Ok(steps) => steps,
+            Err(e) => {
+                let error =
+                    StructuredCallError::Invalid(vec![format!("blueprint: $.steps: {}", e)]);
+                let duration_ms = stage_start.elapsed().as_millis() as u64;
+                return Err(self.fail_stage(state, 3, "Blueprint Assignment", error, duration_ms));
+            }
+        };
 
         // Store blueprint in ZSEI
-        let blueprint_container = serde_json::json!({
+        let mut blueprint_container = serde_json::json!({
             "container_type": "Blueprint",
             "metadata": {
                 "name": name,
@@ -2453,13 +3370,22 @@ Return JSON:
             "context": {
                 "keywords": state.keywords,
                 "topics": state.topics,
-                "methodology_ids": state.methodologies
+                "methodology_ids": state.methodologies,
+                "task_signature": signature,
+                "embedding": embedding,
+                "adapted_from": adapt_from.as_ref().map(|stored| stored.blueprint_id)
             },
             "storage": {
                 "steps": state.blueprint_steps,
                 "missing_capabilities": missing_capabilities
             }
         });
+        if let (Some(storage), Ok(serde_json::Value::Object(stats))) = (
+            blueprint_container["storage"].as_object_mut(),
+            serde_json::to_value(BlueprintStats::default()),
+        ) {
+            storage.extend(stats);
+        }
 
         if let Ok(new_id) = self.zsei.create_container(0, blueprint_container).await {
             state.blueprint_id = Some(new_id);
@@ -2472,7 +3398,11 @@ Return JSON:
             "Blueprint Assignment",
             true,
             &format!(
-                "Created new blueprint with {} steps (missing: {})",
+                "{} with {} steps (missing: {})",
+                match &adapt_from {
+                    Some(stored) => format!("Adapted blueprint {}", stored.blueprint_id),
+                    None => "Created new blueprint".to_string(),
+                },
                 state.blueprint_steps.len(),
                 missing_capabilities.len()
             ),
@@ -2553,22 +3483,21 @@ Return JSON:
             "system_context": "Simulate execution and predict outcomes. Respond with JSON only."
         });
 
-        let sim_result = self.executor.execute(9, sim_input).await?;
-        let response = sim_result
-            .get("response")
-            .and_then(|r| r.as_str())
-            .unwrap_or("{}");
-        let sim_json = Self::parse_json_object(response);
-
-        let confidence = sim_json
-            .get("simulation_confidence")
-            .and_then(|c| c.as_f64())
-            .unwrap_or(0.7);
+        let sim_json = match self
+            .call_llm_structured(state, "simulation", sim_input, ResponseSchema::Simulation)
+            .await
+        {
+            Ok(sim_json) => sim_json,
+            Err(e) => {
+                let duration_ms = stage_start.elapsed().as_millis() as u64;
+                return Err(self.fail_stage(state, 4, "Zero-Shot Simulation", e, duration_ms));
+            }
+        };
 
-        let feasibility = sim_json
-            .get("overall_feasibility")
-            .and_then(|f| f.as_str())
-            .unwrap_or("medium");
+        let confidence = sim_json["simulation_confidence"]
+            .as_f64()
+            .unwrap_or_default();
+        let feasibility = sim_json["overall_feasibility"].as_str().unwrap_or_default();
 
         // Check for clarifications needed
         if let Some(clarifications) = sim_json
@@ -2577,7 +3506,9 @@ Return JSON:
         {
             for c in clarifications {
                 if let Some(c_str) = c.as_str() {
-                    if !c_str.is_empty() {
+                    // Questions the user already answered are not asked again
+                    let answered = state.clarifications.iter().any(|c| c.question == c_str);
+                    if !c_str.is_empty() && !answered {
                         state.clarification_points.push(c_str.to_string());
                     }
                 }
@@ -2618,7 +3549,7 @@ Return JSON:
         let input = serde_json::json!({
             "action": "Evaluate",
             "task_id": 0,
-            "task_summary": &state.cleaned_prompt[..state.cleaned_prompt.len().min(500)],
+            "task_summary": truncate_str(&state.cleaned_prompt, 500),
             "blueprint_id": state.blueprint_id.unwrap_or(0),
             "user_id": state.request.user_id,
             "amt_summary": {
@@ -2628,18 +3559,15 @@ Return JSON:
         });
 
         let result = self.executor.execute(39, input).await?;
+        if let Err(violations) = ResponseSchema::Gate.validate(&result) {
+            let violations = violations.iter().map(|v| format!("gate: {}", v)).collect();
+            let error = StructuredCallError::Invalid(violations);
+            let duration_ms = stage_start.elapsed().as_millis() as u64;
+            return Err(self.fail_stage(state, 5, "Consciousness Gate", error, duration_ms));
+        }
 
-        let decision = result
-            .get("gate")
-            .and_then(|g| g.get("decision"))
-            .and_then(|d| d.as_str())
-            .unwrap_or("Proceed");
-
-        let confidence = result
-            .get("gate")
-            .and_then(|g| g.get("confidence"))
-            .and_then(|c| c.as_f64())
-            .unwrap_or(0.8) as f32;
+        let decision = result["gate"]["decision"].as_str().unwrap_or_default();
+        let confidence = result["gate"]["confidence"].as_f64().unwrap_or_default() as f32;
 
         let reasoning = result
             .get("gate")
@@ -2680,149 +3608,206 @@ Return JSON:
     ) -> Result<(), String> {
         let stage_start = std::time::Instant::now();
 
-        // STAGE 7: Create task via TaskManager
-        let mut inputs = HashMap::new();
-        inputs.insert(
-            "prompt".to_string(),
-            serde_json::json!(state.cleaned_prompt),
-        );
-        inputs.insert(
-            "blueprint_id".to_string(),
-            serde_json::json!(state.blueprint_id),
-        );
-        if let Some(ref amt) = state.amt {
-            inputs.insert("amt_intent".to_string(), serde_json::json!(amt.content));
-        }
-
-        // Enqueue task via TaskManager
-        let task_result = self
-            .task_manager
-            .enqueue_task(
-                state.blueprint_id,
-                inputs,
-                state.request.user_id,
-                state.request.device_id,
-                state.request.workspace_id,
-                state.request.project_id,
-                TaskPriority::Normal,
-            )
-            .await;
-
-        match task_result {
-            Ok(task_id) => {
-                state.task_id = Some(task_id);
+        // STAGE 7: Create task via TaskManager, unless a resumed
+        // orchestration already did
+        if state.task_id.is_none() {
+            let mut inputs = HashMap::new();
+            inputs.insert(
+                "prompt".to_string(),
+                serde_json::json!(state.cleaned_prompt),
+            );
+            inputs.insert(
+                "blueprint_id".to_string(),
+                serde_json::json!(state.blueprint_id),
+            );
+            if let Some(ref amt) = state.amt {
+                inputs.insert("amt_intent".to_string(), serde_json::json!(amt.content));
             }
-            Err(e) => {
-                self.record_stage(state, 7, "Task Creation", false, &format!("Failed: {}", e));
-                return Err(e.to_string());
+            if !state.clarifications.is_empty() {
+                inputs.insert(
+                    "clarifications".to_string(),
+                    serde_json::json!(state.clarifications),
+                );
             }
-        }
-
-        self.record_stage(
-            state,
-            7,
-            "Task Creation",
-            state.task_id.is_some(),
-            &format!("Task: {:?}", state.task_id),
-        );
 
-        // STAGES 6 & 8: Execute steps
-        let steps = state.blueprint_steps.clone();
-        let mut all_outputs: Vec<String> = Vec::new();
-        let mut completed: HashSet<u32> = HashSet::new();
-
-        // Build dependency order
-        let mut step_queue: Vec<&BlueprintStep> = steps.iter().collect();
-        let mut iterations = 0;
-        let max_iterations = steps.len() * 2;
-
-        while !step_queue.is_empty() && iterations < max_iterations {
-            iterations += 1;
+            // Enqueue task via TaskManager
+            let task_result = self
+                .task_manager
+                .enqueue_task(
+                    state.blueprint_id,
+                    inputs,
+                    state.request.user_id,
+                    state.request.device_id,
+                    state.request.workspace_id,
+                    state.request.project_id,
+                    TaskPriority::Normal,
+                )
+                .await;
 
-            // Find steps whose dependencies are satisfied
-            let ready_steps: Vec<_> = step_queue
-                .iter()
-                .filter(|s| s.depends_on.iter().all(|dep| completed.contains(dep)))
-                .cloned()
-                .collect();
+            match task_result {
+                Ok(task_id) => {
+                    state.task_id = Some(task_id);
+                    let clarifications = state.clarifications.clone();
+                    self.log_clarifications(task_id, &clarifications).await;
+                }
+                Err(e) => {
+                    let summary = format!("Failed: {}", e);
+                    self.record_stage(state, 7, "Task Creation", false, &summary);
+                    return Err(e.to_string());
+                }
+            }
 
-            if ready_steps.is_empty() && !step_queue.is_empty() {
-                // Force execute first remaining step (break deadlock)
-                if let Some(step) = step_queue.first().cloned() {
-                    let result = self.execute_step(state, step, &all_outputs).await?;
-                    let output_text = self.extract_output_text(&result.output);
-                    all_outputs.push(output_text.clone());
-                    state.step_results.push(result.clone());
-                    state.step_outputs.insert(
-                        step.step_index,
-                        serde_json::json!({"output": all_outputs.last()}),
-                    );
-                    completed.insert(step.step_index);
-                    step_queue.retain(|s| s.step_index != step.step_index);
+            self.record_stage(
+                state,
+                7,
+                "Task Creation",
+                state.task_id.is_some(),
+                &format!("Task: {:?}", state.task_id),
+            );
+            self.checkpoint(state, CheckpointStatus::Running, None).await;
+        }
 
-                    // Update TaskManager
-                    if let Some(task_id) = state.task_id {
-                        let _ = self
-                            .task_manager
-                            .update_step(
-                                task_id,
-                                step.step_index,
-                                "completed",
-                                result.tokens_used,
-                                Some(output_text[..200.min(output_text.len())].to_string()),
-                                None,
-                            )
-                            .await;
+        // STAGES 6 & 8: Execute steps, skipping those completed before a
+        // resume
+        let steps = state.blueprint_steps.clone();
+        Self::check_step_dependencies(&steps)?;
+        let mut completed: HashSet<u32> = state.step_results.iter().map(|r| r.step_index).collect();
+        // Steps that failed under the `Continue` policy, and those skipped
+        // because they depend on one
+        let mut failed: HashSet<u32> = HashSet::new();
+        let mut skipped = 0;
+        let mut failure = None;
+
+        // Each step starts as soon as the steps it depends on have
+        // completed, up to the parallelism limit
+        let mut step_queue: Vec<&BlueprintStep> = steps
+            .iter()
+            .filter(|s| !completed.contains(&s.step_index))
+            .collect();
+        let mut running = FuturesUnordered::new();
+        loop {
+            // Skip steps downstream of a failed branch
+            let (blocked, waiting): (Vec<&BlueprintStep>, Vec<&BlueprintStep>) = step_queue
+                .into_iter()
+                .partition(|s| s.depends_on.iter().any(|dep| failed.contains(dep)));
+            step_queue = waiting;
+            for step in blocked {
+                failed.insert(step.step_index);
+                skipped += 1;
+                if let Some(task_id) = state.task_id {
+                    let _ = self
+                        .task_manager
+                        .update_step(
+                            task_id,
+                            step.step_index,
+                            "skipped",
+                            0,
+                            None,
+                            Some("A step it depends on failed".to_string()),
+                        )
+                        .await;
+                }
+            }
 
-                        let _ = self
-                            .task_manager
-                            .update_progress(task_id, completed.len() as u32, steps.len() as u32)
-                            .await;
+            // After a failure, only the steps already running finish
+            if failure.is_none() {
+                let free = self.max_parallel_steps.max(1) - running.len();
+                let ready: Vec<&BlueprintStep> = step_queue
+                    .iter()
+                    .filter(|s| s.depends_on.iter().all(|dep| completed.contains(dep)))
+                    .take(free)
+                    .cloned()
+                    .collect();
+                if !ready.is_empty() {
+                    let snapshot = Arc::new(state.clone());
+                    for step in ready {
+                        step_queue.retain(|s| s.step_index != step.step_index);
+                        let snapshot = snapshot.clone();
+                        running.push(async move {
+                            (step, self.execute_step_with_policy(&snapshot, step).await)
+                        });
                     }
                 }
-            } else {
-                for step in ready_steps {
-                    let result = self.execute_step(state, step, &all_outputs).await?;
-                    let output_text = self.extract_output_text(&result.output);
-                    all_outputs.push(output_text.clone());
-                    state.step_results.push(result.clone());
-                    state.step_outputs.insert(
-                        step.step_index,
-                        serde_json::json!({"output": all_outputs.last()}),
-                    );
-                    completed.insert(step.step_index);
-                    step_queue.retain(|s| s.step_index != step.step_index);
+            }
 
-                    // Update TaskManager
+            // Apply every finished step, even after a failure, so a resume
+            // does not repeat them
+            let Some((step, run)) = running.next().await else {
+                break;
+            };
+            let (result, step_context) = match run {
+                Ok(run) => run,
+                Err(e) => {
+                    tracing::warn!("Step {} failed: {}", step.step_index, e);
                     if let Some(task_id) = state.task_id {
                         let _ = self
                             .task_manager
                             .update_step(
                                 task_id,
                                 step.step_index,
-                                "completed",
-                                result.tokens_used,
-                                Some(output_text[..200.min(output_text.len())].to_string()),
+                                "failed",
+                                0,
                                 None,
+                                Some(e.clone()),
                             )
                             .await;
-
-                        let _ = self
-                            .task_manager
-                            .update_progress(task_id, completed.len() as u32, steps.len() as u32)
-                            .await;
                     }
+                    if step.failure_policy == FailurePolicy::Continue {
+                        failed.insert(step.step_index);
+                    } else if failure.is_none() {
+                        failure = Some(format!("Step {} failed: {}", step.step_index, e));
+                    }
+                    continue;
                 }
+            };
+            self.finish_step(state, step, &result, step_context).await;
+
+            let output_text = self.extract_output_text(&result.output);
+            state.step_results.push(result.clone());
+            state
+                .step_outputs
+                .insert(step.step_index, serde_json::json!({"output": output_text}));
+            completed.insert(step.step_index);
+
+            // Update TaskManager
+            if let Some(task_id) = state.task_id {
+                let _ = self
+                    .task_manager
+                    .update_step(
+                        task_id,
+                        step.step_index,
+                        "completed",
+                        result.tokens_used,
+                        Some(truncate_str(&output_text, 200).to_string()),
+                        None,
+                    )
+                    .await;
+
+                let _ = self
+                    .task_manager
+                    .update_progress(task_id, completed.len() as u32, steps.len() as u32)
+                    .await;
             }
+            self.checkpoint(state, CheckpointStatus::Running, None).await;
+        }
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        if !step_queue.is_empty() {
+            return Err("No blueprint step can run; dependencies are unsatisfiable".into());
         }
 
-        // Combine outputs into final response
-        state.final_response = if all_outputs.len() == 1 {
-            Some(all_outputs[0].clone())
-        } else if !all_outputs.is_empty() {
-            Some(all_outputs.join("\n\n"))
-        } else {
+        // Combine outputs into final response, in blueprint order
+        let mut results: Vec<&StepResult> = state.step_results.iter().collect();
+        results.sort_by_key(|r| r.step_index);
+        let all_outputs: Vec<String> = results
+            .iter()
+            .map(|r| self.extract_output_text(&r.output))
+            .collect();
+        state.final_response = if all_outputs.is_empty() {
             None
+        } else {
+            Some(all_outputs.join("\n\n"))
         };
 
         self.record_stage_timed(
@@ -2831,9 +3816,15 @@ Return JSON:
             "Step Execution",
             state.final_response.is_some(),
             &format!(
-                "{} steps executed, tokens: {}",
+                "{} steps executed, {} failed, {} skipped, tokens: {}",
                 state.step_results.len(),
-                state.tokens_used_so_far
+                failed.len() - skipped,
+                skipped,
+                state
+                    .step_results
+                    .iter()
+                    .map(|r| r.tokens_used)
+                    .sum::<u32>()
             ),
             stage_start.elapsed().as_millis() as u64,
         );
@@ -2841,16 +3832,118 @@ Return JSON:
         Ok(())
     }
 
-    /// Execute a single blueprint step (handles loops, sub-steps, retries)
-    async fn execute_step(
+    /// Check that every dependency names a step of the blueprint and that
+    /// they form no cycle
+    fn check_step_dependencies(steps: &[BlueprintStep]) -> Result<(), String> {
+        let by_index: HashMap<u32, &BlueprintStep> =
+            steps.iter().map(|s| (s.step_index, s)).collect();
+        for step in steps {
+            if let Some(dep) = step.depends_on.iter().find(|d| !by_index.contains_key(d)) {
+                return Err(format!(
+                    "Blueprint step {} depends on unknown step {}",
+                    step.step_index, dep
+                ));
+            }
+        }
+
+        // Depth-first search; a step met again while on the path closes a
+        // cycle
+        fn visit(
+            index: u32,
+            by_index: &HashMap<u32, &BlueprintStep>,
+            done: &mut HashSet<u32>,
+            path: &mut Vec<u32>,
+        ) -> Result<(), String> {
+            if done.contains(&index) {
+                return Ok(());
+            }
+            if let Some(pos) = path.iter().position(|&i| i == index) {
+                let cycle: Vec<String> = path[pos..]
+                    .iter()
+                    .chain(std::iter::once(&index))
+                    .map(|i| i.to_string())
+                    .collect();
+                return Err(format!(
+                    "Blueprint steps have a dependency cycle: {}",
+                    cycle.join(" -> ")
+                ));
+            }
+            path.push(index);
+            for &dep in &by_index[&index].depends_on {
+                visit(dep, by_index, done, path)?;
+            }
+            path.pop();
+            done.insert(index);
+            Ok(())
+        }
+
+        let mut done = HashSet::new();
+        for step in steps {
+            visit(step.step_index, &by_index, &mut done, &mut Vec::new())?;
+        }
+        Ok(())
+    }
+
+    /// Execute a step, running it again after a growing pause as its
+    /// `max_retries` and failure policy allow
+    async fn execute_step_with_policy(
+        &self,
+        state: &OrchestrationState,
+        step: &BlueprintStep,
+    ) -> Result<(StepResult, String), String> {
+        let retries = match step.failure_policy {
+            FailurePolicy::Retry { attempts } => attempts.max(step.max_retries),
+            _ => step.max_retries,
+        };
+        let mut result = self.execute_step(state, step).await;
+        for attempt in 1..=retries {
+            let Err(e) = &result else {
+                break;
+            };
+            tracing::info!(
+                "Retrying step {} ({}/{}): {}",
+                step.step_index,
+                attempt,
+                retries,
+                e
+            );
+            tokio::time::sleep(tokio::time::Duration::from_millis(
+                STEP_RETRY_BACKOFF_MS * attempt as u64,
+            ))
+            .await;
+            result = self.execute_step(state, step).await;
+        }
+        result
+    }
+
+    /// Record a finished step's context and fire its hook
+    async fn finish_step(
         &self,
         state: &mut OrchestrationState,
         step: &BlueprintStep,
-        previous_outputs: &[String],
-    ) -> Result<StepResult, String> {
+        result: &StepResult,
+        step_context: String,
+    ) {
+        state.step_contexts.insert(step.step_index, step_context);
+
+        // Fire OnStepComplete hook — living system integration
+        self.on_step_complete(state, step, result).await;
+    }
+
+    /// Execute a single blueprint step (handles loops and sub-steps);
+    /// returns its result and the context aggregated for it. Only reads the
+    /// state, so independent steps run concurrently
+    async fn execute_step(
+        &self,
+        state: &OrchestrationState,
+        step: &BlueprintStep,
+    ) -> Result<(StepResult, String), String> {
         let mut total_iterations = 0;
         let mut sub_step_results = Vec::new();
         let mut final_output = serde_json::json!({});
+        let mut last_context = String::new();
+        let mut context_items = Vec::new();
+        let mut tokens_used = 0;
 
         // Handle loop configuration
         let (iterations, should_loop) = if let Some(loop_config) = &step.loop_config {
@@ -2870,11 +3963,24 @@ Return JSON:
         for iteration in 0..iterations {
             total_iterations = iteration + 1;
 
+            // The prompt without its context, and the room left for it
+            let request_excerpt = truncate_str(&state.cleaned_prompt, 500);
+            let prompt_frame = format!(
+                "Step {}: {}\n\nContext:\n\n\nOriginal request: {}",
+                step.step_index + 1,
+                step.description,
+                request_excerpt
+            );
+            let context_budget = state.model_context_limit.saturating_sub(
+                self.max_output_tokens(state) + self.count_tokens(state, &prompt_frame),
+            );
+
             // STAGE 6: Context aggregation for this step
+            let query = format!("{} - {}", state.cleaned_prompt, step.description);
             let context_input = serde_json::json!({
                 "action": "ForQuery",
-                "query": format!("{} - {}", state.cleaned_prompt, step.description),
-                "token_budget": state.model_context_limit / 4,
+                "query": query,
+                "token_budget": context_budget,
                 "project_id": state.request.project_id,
                 "workspace_id": state.request.workspace_id,
                 "priority_order": step.context_requirements,
@@ -2883,37 +3989,33 @@ Return JSON:
             });
 
             let context_result = self.executor.execute(21, context_input).await?;
-            let step_context = context_result
-                .get("context")
-                .and_then(|c| c.get("context_text"))
-                .and_then(|t| t.as_str())
-                .unwrap_or("")
-                .to_string();
-
-            state
-                .step_contexts
-                .insert(step.step_index, step_context.clone());
-
-            // Build full context with previous outputs
-            let full_context = if !previous_outputs.is_empty() {
-                format!(
-                    "{}\n\nPrevious step outputs:\n{}",
-                    step_context,
-                    previous_outputs
-                        .iter()
-                        .enumerate()
-                        .map(|(i, o)| format!("Step {}: {}", i + 1, &o[..o.len().min(300)]))
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                )
-            } else {
-                step_context
-            };
+            let candidates = self
+                .step_context_candidates(state, step, &context_result)
+                .await;
+            let packed = context::pack(
+                &query,
+                candidates,
+                context_budget,
+                self.models.tokenizer(&state.model_identifier).as_ref(),
+            );
+            if !packed.dropped.is_empty() {
+                tracing::debug!(
+                    "Step {} context left out {} source(s) over its {} token budget",
+                    step.step_index,
+                    packed.dropped.len(),
+                    context_budget
+                );
+            }
+            context_items = packed.items;
+            last_context = packed.text;
 
             // Execute sub-steps first if any
             for sub_step in &step.sub_steps {
-                let sub_input = self.build_sub_step_input(state, sub_step, &full_context)?;
-                let sub_result = self.executor.execute(sub_step.pipeline_id, sub_input).await;
+                let sub_input = self.build_sub_step_input(state, sub_step, &last_context)?;
+                let sub_result = self
+                    .execute_pipeline(state, sub_step.pipeline_id, sub_input)
+                    .await;
+                tokens_used += sub_result.as_ref().map(Self::reported_tokens).unwrap_or(0);
 
                 sub_step_results.push(SubStepResult {
                     sub_index: sub_step.sub_index,
@@ -2927,33 +4029,21 @@ Return JSON:
                 "Step {}: {}\n\nContext:\n{}\n\nOriginal request: {}",
                 step.step_index + 1,
                 step.description,
-                full_context,
-                &state.cleaned_prompt[..state.cleaned_prompt.len().min(500)]
+                last_context,
+                request_excerpt
             );
 
             let exec_input = serde_json::json!({
                 "prompt": step_prompt,
-                "max_tokens": state.model_context_limit / 4,
+                "max_tokens": self.max_output_tokens(state),
                 "temperature": 0.7,
                 "action": step.action
             });
 
-            let mut retries = 0;
-            let mut exec_result = self
-                .executor
-                .execute(step.pipeline_id, exec_input.clone())
-                .await;
-
-            while exec_result.is_err() && retries < step.max_retries {
-                retries += 1;
-                tokio::time::sleep(tokio::time::Duration::from_millis(100 * retries as u64)).await;
-                exec_result = self
-                    .executor
-                    .execute(step.pipeline_id, exec_input.clone())
-                    .await;
-            }
-
-            final_output = exec_result?;
+            final_output = self
+                .execute_step_pipeline(state, step, &exec_input)
+                .await?;
+            tokens_used += Self::reported_tokens(&final_output);
 
             // Wait for graph update if configured
             if step.wait_for_graph_update {
@@ -2986,36 +4076,34 @@ Return JSON:
             }
         }
 
-        let tokens_used = final_output
-            .get("tokens_used")
-            .and_then(|t| t.as_u64())
-            .unwrap_or(0) as u32;
-
-        state.tokens_used_so_far += tokens_used;
-
-        // Fire OnStepComplete hook — living system integration
-        self.on_step_complete(
-            state,
-            step,
-            &StepResult {
+        Ok((
+            StepResult {
                 step_index: step.step_index,
                 pipeline_id: step.pipeline_id,
-                output: final_output.clone(),
+                output: final_output,
                 tokens_used,
                 iterations: total_iterations,
-                sub_step_results: sub_step_results.clone(),
+                sub_step_results,
+                context: context_items,
             },
-        )
-        .await;
+            last_context,
+        ))
+    }
 
-        Ok(StepResult {
-            step_index: step.step_index,
-            pipeline_id: step.pipeline_id,
-            output: final_output,
-            tokens_used,
-            iterations: total_iterations,
-            sub_step_results,
-        })
+    /// Run a step's pipeline; prompt pipeline steps go to the execution
+    /// model
+    async fn execute_step_pipeline(
+        &self,
+        state: &OrchestrationState,
+        step: &BlueprintStep,
+        input: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        if step.pipeline_id == PROMPT_PIPELINE_ID {
+            self.call_llm(state, "execution", input.clone()).await
+        } else {
+            self.execute_pipeline(state, step.pipeline_id, input.clone())
+                .await
+        }
     }
 
     /// Hook fires on step completion. Detects graph changes, reviews AMT,
@@ -3080,7 +4168,7 @@ Return JSON:
             "system_context": "AMT alignment review. Return only valid JSON."
         });
 
-        if let Ok(result) = self.executor.execute(9, input).await {
+        if let Ok(result) = self.call_llm(state, "execution", input).await {
             let raw = result
                 .get("response")
                 .and_then(|r| r.as_str())
@@ -3151,6 +4239,111 @@ Return JSON:
         Ok(input)
     }
 
+    /// Everything a step's context may include: outputs of the steps run
+    /// so far, those it depends on first, the AMT's nodes, the
+    /// methodologies, the containers context aggregation found and the
+    /// prompt's chunks
+    async fn step_context_candidates(
+        &self,
+        state: &OrchestrationState,
+        step: &BlueprintStep,
+        aggregated: &serde_json::Value,
+    ) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+
+        for result in &state.step_results {
+            let boost = if step.depends_on.contains(&result.step_index) {
+                1.0
+            } else {
+                0.0
+            };
+            candidates.push(
+                Candidate::new(
+                    ContextSource::StepOutput {
+                        step_index: result.step_index,
+                    },
+                    self.extract_output_text(&result.output),
+                )
+                .with_boost(boost),
+            );
+        }
+
+        let context = aggregated.get("context");
+        let sources = context
+            .and_then(|c| c.get("sources"))
+            .and_then(|s| s.as_array())
+            .filter(|s| !s.is_empty());
+        if let Some(sources) = sources {
+            for source in sources {
+                let Some(container_id) = source.get("container_id").and_then(|id| id.as_u64())
+                else {
+                    continue;
+                };
+                let text = source
+                    .get("snippet")
+                    .and_then(|s| s.as_str())
+                    .filter(|s| !s.is_empty())
+                    .or_else(|| source.get("name").and_then(|n| n.as_str()))
+                    .unwrap_or("");
+                let relevance = source
+                    .get("relevance")
+                    .and_then(|r| r.as_f64())
+                    .unwrap_or(0.0);
+                candidates.push(
+                    Candidate::new(ContextSource::Container { container_id }, text)
+                        .with_boost(relevance as f32 / 2.0),
+                );
+            }
+        } else if let Some(text) = context
+            .and_then(|c| c.get("context_text"))
+            .and_then(|t| t.as_str())
+        {
+            candidates.push(Candidate::new(ContextSource::Aggregation, text));
+        }
+
+        let mut nodes: Vec<&AMTNode> = state.amt.iter().collect();
+        while let Some(node) = nodes.pop() {
+            candidates.push(Candidate::new(
+                ContextSource::AmtNode { node_id: node.id },
+                node.content.clone(),
+            ));
+            nodes.extend(node.children.iter().rev());
+        }
+
+        for &container_id in &state.methodologies {
+            let Ok(Some(container)) = self.zsei.get_container(container_id).await else {
+                continue;
+            };
+            let local_state = container.get("local_state");
+            let name = local_state
+                .and_then(|ls| ls.get("metadata"))
+                .and_then(|m| m.get("name"))
+                .and_then(|n| n.as_str())
+                .unwrap_or("Methodology");
+            let principles: Vec<&str> = local_state
+                .and_then(|ls| ls.get("storage"))
+                .and_then(|s| s.get("principles"))
+                .and_then(|p| p.as_array())
+                .map(|p| p.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default();
+            candidates.push(Candidate::new(
+                ContextSource::Methodology { container_id },
+                format!("{}: {}", name, principles.join("; ")),
+            ));
+        }
+
+        for chunk in &state.processed_chunks {
+            candidates.push(Candidate::new(
+                ContextSource::Chunk {
+                    chunk_index: chunk.index,
+                },
+                chunk.cleaned_text.clone(),
+            ));
+        }
+
+        candidates
+    }
+
     fn extract_output_text(&self, output: &serde_json::Value) -> String {
         output
             .get("response")
@@ -3204,6 +4397,8 @@ Return JSON:
                     .await;
             }
         }
+        self.record_blueprint_outcome(state, state.final_response.is_some())
+            .await;
 
         self.record_stage_timed(
             state,
@@ -3245,7 +4440,7 @@ Return JSON:
         let experience_input = serde_json::json!({
             "action": "StoreExperience",
             "experience_type": if state.final_response.is_some() { "task_success" } else { "task_failure" },
-            "summary": &state.cleaned_prompt[..state.cleaned_prompt.len().min(200)],
+            "summary": truncate_str(&state.cleaned_prompt, 200),
             "task_id": state.task_id,
             "user_id": state.request.user_id,
             "tags": state.topics.clone(),
@@ -3277,7 +4472,7 @@ Return JSON:
             "trigger_type": if state.final_response.is_some() { "task_success" } else { "task_failure" },
             "source": "orchestrator",
             "intensity": 0.5,
-            "context": &state.cleaned_prompt[..state.cleaned_prompt.len().min(100)]
+            "context": truncate_str(&state.cleaned_prompt, 100)
         });
 
         // Pipeline 43 = EmotionalBaselineUpdate
@@ -3345,7 +4540,8 @@ Return JSON:
                                 "system_context": "Apply voice identity while maintaining content accuracy."
                             });
 
-                            if let Ok(styled) = self.executor.execute(9, style_input).await {
+                            if let Ok(styled) = self.call_llm(state, "response", style_input).await
+                            {
                                 if let Some(new_response) =
                                     styled.get("response").and_then(|r| r.as_str())
                                 {
@@ -3373,7 +4569,7 @@ Return JSON:
         // Generate task recommendations for next steps (Pipeline 23 = TaskRecommendation)
         let recommend_input = serde_json::json!({
             "action": "Suggest",
-            "context": &state.cleaned_prompt[..state.cleaned_prompt.len().min(200)],
+            "context": truncate_str(&state.cleaned_prompt, 200),
             "completed_task_id": state.task_id,
             "topics": state.topics.clone(),
             "keywords": state.keywords.iter().take(5).cloned().collect::<Vec<_>>()
@@ -3672,36 +4868,34 @@ Return JSON:
             "system_context": "File role classification. Return only valid JSON array."
         });
 
-        let result = self.executor.execute(9, input).await.unwrap_or_default();
-        let raw = result
-            .get("response")
-            .and_then(|r| r.as_str())
-            .unwrap_or("[]");
-        let json_str = Self::extract_json_from_response(raw, '[', ']');
+        let outcome = self
+            .call_llm_structured(state, "amt", input, ResponseSchema::FileRoles)
+            .await;
+        let roles = Self::valid_or_record(&mut state.validation_failures, outcome);
 
-        let classifications: Vec<ClassifiedFileGraph> =
-            serde_json::from_str::<Vec<serde_json::Value>>(&json_str)
-                .unwrap_or_default()
-                .into_iter()
-                .filter_map(|v| {
-                    Some(ClassifiedFileGraph {
-                        file_path: v["file_path"].as_str()?.to_string(),
-                        graph_id: v["graph_id"].as_u64().or_else(|| {
-                            state
-                                .file_graphs
-                                .get(v["file_path"].as_str().unwrap_or(""))
-                                .copied()
-                        })?,
-                        modality: self.detect_file_modality(v["file_path"].as_str().unwrap_or("")),
-                        role: match v["role"].as_str().unwrap_or("raw_data") {
-                            "primary" => FileGraphRole::Primary,
-                            "supplementary" => FileGraphRole::Supplementary,
-                            _ => FileGraphRole::RawData,
-                        },
-                        reasoning: v["reasoning"].as_str().unwrap_or("").to_string(),
-                    })
+        let classifications: Vec<ClassifiedFileGraph> = roles
+            .and_then(|roles| roles.as_array().cloned())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|v| {
+                Some(ClassifiedFileGraph {
+                    file_path: v["file_path"].as_str()?.to_string(),
+                    graph_id: v["graph_id"].as_u64().or_else(|| {
+                        state
+                            .file_graphs
+                            .get(v["file_path"].as_str().unwrap_or(""))
+                            .copied()
+                    })?,
+                    modality: self.detect_file_modality(v["file_path"].as_str().unwrap_or("")),
+                    role: match v["role"].as_str().unwrap_or("raw_data") {
+                        "primary" => FileGraphRole::Primary,
+                        "supplementary" => FileGraphRole::Supplementary,
+                        _ => FileGraphRole::RawData,
+                    },
+                    reasoning: v["reasoning"].as_str().unwrap_or("").to_string(),
                 })
-                .collect();
+            })
+            .collect();
 
         state.classified_file_graphs = classifications;
         Ok(())
@@ -4096,18 +5290,14 @@ Return JSON:
             "system_context": "Methodology domain identification. Return only valid JSON array."
         });
 
-        let required_domains: Vec<String> = match self.executor.execute(9, input).await {
-            Ok(result) => {
-                let raw = result
-                    .get("response")
-                    .and_then(|r| r.as_str())
-                    .unwrap_or("[]");
-                let start = raw.find('[').unwrap_or(0);
-                let end = raw.rfind(']').map(|i| i + 1).unwrap_or(raw.len());
-                serde_json::from_str::<Vec<String>>(&raw[start..end]).unwrap_or_default()
-            }
-            Err(_) => return findings,
-        };
+        let outcome = self
+            .call_llm_structured(state, "amt", input, ResponseSchema::MethodologyDomains)
+            .await;
+        let required_domains: Vec<String> =
+            match Self::valid_or_record(&mut state.validation_failures, outcome) {
+                Some(domains) => serde_json::from_value(domains).unwrap_or_default(),
+                None => return findings,
+            };
 
         for domain in &required_domains {
             // Search ZSEI for existing methodology matching this domain
@@ -4145,14 +5335,12 @@ Return JSON:
                     "system_context": "Methodology synthesis. Return only valid JSON."
                 });
 
-                if let Ok(synth_result) = self.executor.execute(9, synth_input).await {
-                    let raw = synth_result
-                        .get("response")
-                        .and_then(|r| r.as_str())
-                        .unwrap_or("{}");
-                    let start = raw.find('{').unwrap_or(0);
-                    let end = raw.rfind('}').map(|i| i + 1).unwrap_or(raw.len());
-
+                let outcome = self
+                    .call_llm_structured(state, "amt", synth_input, ResponseSchema::Methodology)
+                    .await;
+                if let Some(methodology) =
+                    Self::valid_or_record(&mut state.validation_failures, outcome)
+                {
                     let methodology_container = serde_json::json!({
                         "container_type": "Methodology",
                         "metadata": {
@@ -4164,8 +5352,7 @@ Return JSON:
                             "keywords": [domain.to_lowercase()],
                             "topics": [domain.to_lowercase()]
                         },
-                        "storage": serde_json::from_str::<serde_json::Value>(&raw[start..end])
-                            .unwrap_or_default()
+                        "storage": methodology
                     });
 
                     if let Ok(new_id) = self.zsei.create_container(0, methodology_container).await {
@@ -4187,13 +5374,7 @@ Return JSON:
         success: bool,
         summary: &str,
     ) {
-        state.stages.push(StageResult {
-            stage,
-            name: name.to_string(),
-            success,
-            duration_ms: 0,
-            output_summary: Some(summary.to_string()),
-        });
+        self.record_stage_timed(state, stage, name, success, summary, 0);
     }
 
     fn record_stage_timed(
@@ -4205,16 +5386,75 @@ Return JSON:
         summary: &str,
         duration_ms: u64,
     ) {
-        state.stages.push(StageResult {
+        let tokens_used = Self::flush_usage(state);
+        let mut result = StageResult {
             stage,
             name: name.to_string(),
             success,
             duration_ms,
             output_summary: Some(summary.to_string()),
-        });
+            tokens_used,
+            validation_failures: std::mem::take(&mut state.validation_failures),
+        };
+        if let Some(events) = &self.events {
+            events.publish(
+                EventTopic::Orchestration,
+                "stage_completed",
+                Some(state.request.user_id),
+                state.task_id,
+                serde_json::to_value(&result).unwrap_or_default(),
+            );
+        }
+        // A stage run again after a resume replaces its earlier result,
+        // keeping the tokens that run used
+        match state
+            .stages
+            .iter_mut()
+            .find(|s| s.stage == stage && s.name == name)
+        {
+            Some(earlier) => {
+                result.tokens_used += earlier.tokens_used;
+                *earlier = result;
+            }
+            None => state.stages.push(result),
+        }
+    }
+
+    /// Move the usage since the last recorded stage into the totals and
+    /// return how many tokens it was
+    fn flush_usage(state: &mut OrchestrationState) -> u32 {
+        let pending = state
+            .pending_usage
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default();
+        let mut tokens_used = 0;
+        for (model, usage) in pending {
+            tokens_used += usage.total();
+            state.model_usage.entry(model).or_default().add(usage);
+        }
+        state.tokens_used_so_far += tokens_used;
+        tokens_used
+    }
+
+    fn step_tokens_used(state: &OrchestrationState) -> HashMap<u32, u32> {
+        state
+            .step_results
+            .iter()
+            .map(|r| (r.step_index, r.tokens_used))
+            .collect()
+    }
+
+    fn step_context(state: &OrchestrationState) -> HashMap<u32, Vec<PackedItem>> {
+        state
+            .step_results
+            .iter()
+            .map(|r| (r.step_index, r.context.clone()))
+            .collect()
     }
 
     fn build_success_response(&self, state: &OrchestrationState) -> OrchestrationResponse {
+        let (token_usage, estimated_cost_usd) = self.usage_summary(state);
         OrchestrationResponse {
             success: !state.needs_clarification,
             response: state.final_response.clone(),
@@ -4224,12 +5464,17 @@ Return JSON:
             consciousness_gate: state.gate_result.clone(),
             error: None,
             total_tokens_used: Some(state.tokens_used_so_far),
+            token_usage,
+            step_tokens_used: Self::step_tokens_used(state),
+            step_context: Self::step_context(state),
+            estimated_cost_usd,
             execution_time_ms: state.start_time.elapsed().as_millis() as u64,
             methodologies_used: state.methodologies.clone(),
             categories_created: state.categories_created,
             blueprints_created: state.blueprints_created,
             clarification_points: state.clarification_points.clone(),
             needs_clarification: state.needs_clarification,
+            clarifications: state.clarifications.clone(),
             amt_summary: state.amt.as_ref().map(|amt| AMTSummary {
                 total_nodes: amt.count_nodes(),
                 branch_count: amt.branch_count(),
@@ -4240,6 +5485,7 @@ Return JSON:
                     format!("Streak: {}/5", state.validation_streak)
                 },
             }),
+            orchestration_id: state.orchestration_id,
         }
     }
 
@@ -4248,6 +5494,9 @@ Return JSON:
         state: &mut OrchestrationState,
         error: String,
     ) -> OrchestrationResponse {
+        // Calls of the stage that failed count too
+        Self::flush_usage(state);
+        let (token_usage, estimated_cost_usd) = self.usage_summary(state);
         OrchestrationResponse {
             success: false,
             response: None,
@@ -4257,33 +5506,50 @@ Return JSON:
             consciousness_gate: state.gate_result.clone(),
             error: Some(error),
             total_tokens_used: Some(state.tokens_used_so_far),
+            token_usage,
+            step_tokens_used: Self::step_tokens_used(state),
+            step_context: Self::step_context(state),
+            estimated_cost_usd,
             execution_time_ms: state.start_time.elapsed().as_millis() as u64,
             methodologies_used: state.methodologies.clone(),
             categories_created: state.categories_created,
             blueprints_created: state.blueprints_created,
             clarification_points: state.clarification_points.clone(),
             needs_clarification: state.needs_clarification,
+            clarifications: state.clarifications.clone(),
             amt_summary: None,
+            orchestration_id: state.orchestration_id,
         }
     }
 
-    fn estimate_tokens(text: &str) -> u32 {
-        ((text.len() + 3) / 4) as u32
+    /// Tokens `text` takes in the orchestration's model
+    fn count_tokens(&self, state: &OrchestrationState, text: &str) -> u32 {
+        self.models.tokenizer(&state.model_identifier).count(text)
     }
 
-    fn parse_json_object(s: &str) -> serde_json::Value {
-        let trimmed = s.trim();
-        let json_str = if let Some(start) = trimmed.find('{') {
-            if let Some(end) = trimmed.rfind('}') {
-                &trimmed[start..=end]
-            } else {
-                trimmed
-            }
-        } else {
-            trimmed
-        };
+    /// Response length to ask a step's model for: a quarter of the context,
+    /// within the model's output limit
+    fn max_output_tokens(&self, state: &OrchestrationState) -> u32 {
+        let limit = self
+            .models
+            .get(&state.model_identifier)
+            .and_then(|info| info.max_output_tokens)
+            .unwrap_or(u32::MAX);
+        (state.model_context_limit / 4).min(limit)
+    }
 
-        serde_json::from_str(json_str).unwrap_or_else(|_| serde_json::json!({}))
+    /// Usage of all recorded stages, and its cost where every model used
+    /// has prices
+    fn usage_summary(&self, state: &OrchestrationState) -> (TokenUsage, Option<f64>) {
+        let mut total = TokenUsage::default();
+        let mut cost = Some(0.0);
+        for (model, usage) in &state.model_usage {
+            total.add(*usage);
+            cost = cost
+                .zip(self.models.get(model).and_then(|info| info.cost(usage)))
+                .map(|(sum, c)| sum + c);
+        }
+        (total, cost.filter(|_| !state.model_usage.is_empty()))
     }
 }
 
@@ -4297,6 +5563,24 @@ mod tests {
 
     struct MockExecutor;
 
+    /// A one-step blueprint on the prompt pipeline
+    fn single_step_blueprint() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Answer",
+            "steps": [{
+                "step_index": 0,
+                "action": "execute_prompt",
+                "description": "Process the user prompt",
+                "pipeline_id": 9,
+                "context_requirements": ["full_context"],
+                "sub_steps": [],
+                "depends_on": [],
+                "wait_for_graph_update": false,
+                "max_retries": 1
+            }]
+        })
+    }
+
     #[async_trait::async_trait]
     impl PipelineExecutor for MockExecutor {
         async fn execute(
@@ -4304,7 +5588,16 @@ mod tests {
             pipeline_id: u64,
             input: serde_json::Value,
         ) -> Result<serde_json::Value, String> {
+            let prompt = input.get("prompt").and_then(|p| p.as_str()).unwrap_or("");
             match pipeline_id {
+                9 if prompt.starts_with("Create a blueprint") => Ok(serde_json::json!({
+                    "response": single_step_blueprint().to_string(),
+                    "tokens_used": 100
+                })),
+                9 if prompt.starts_with("Simulate executing") => Ok(serde_json::json!({
+                    "response": r#"{"simulation_confidence": 0.9, "overall_feasibility": "high"}"#,
+                    "tokens_used": 100
+                })),
                 9 => Ok(serde_json::json!({
                     "response": "Test response from LLM",
                     "tokens_used": 100
@@ -4493,6 +5786,662 @@ mod tests {
         assert!(response.stages_completed[0].success);
     }
 
+    /// Plans three chained steps on pipeline 50 and fails the second one once
+    #[derive(Default)]
+    struct FlakyExecutor {
+        blueprint_calls: std::sync::atomic::AtomicU32,
+        step_calls: std::sync::atomic::AtomicU32,
+        failed: std::sync::atomic::AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl PipelineExecutor for FlakyExecutor {
+        async fn execute(
+            &self,
+            pipeline_id: u64,
+            input: serde_json::Value,
+        ) -> Result<serde_json::Value, String> {
+            use std::sync::atomic::Ordering;
+            let prompt = input.get("prompt").and_then(|p| p.as_str()).unwrap_or("");
+            if pipeline_id == 9 && prompt.starts_with("Create a blueprint") {
+                self.blueprint_calls.fetch_add(1, Ordering::SeqCst);
+                let steps: Vec<serde_json::Value> = (0..3)
+                    .map(|i| {
+                        serde_json::json!({
+                            "step_index": i,
+                            "action": "execute_prompt",
+                            "description": format!("Part {}", i),
+                            "pipeline_id": 50,
+                            "context_requirements": [],
+                            "loop_config": null,
+                            "sub_steps": [],
+                            "depends_on": if i == 0 { vec![] } else { vec![i - 1] },
+                            "wait_for_graph_update": false,
+                            "max_retries": 0,
+                            "timeout_ms": null
+                        })
+                    })
+                    .collect();
+                let blueprint = serde_json::json!({"name": "Three parts", "steps": steps});
+                return Ok(serde_json::json!({"response": blueprint.to_string()}));
+            }
+            if pipeline_id == 50 {
+                self.step_calls.fetch_add(1, Ordering::SeqCst);
+                if prompt.starts_with("Step 2:") && !self.failed.swap(true, Ordering::SeqCst) {
+                    return Err("Model unavailable".into());
+                }
+                return Ok(serde_json::json!({"response": "done", "tokens_used": 10}));
+            }
+            MockExecutor.execute(pipeline_id, input).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_continues_after_failed_step() {
+        use std::sync::atomic::Ordering;
+        let dir = "/tmp/test_orchestration_checkpoints";
+        let _ = std::fs::remove_dir_all(dir);
+        let executor = Arc::new(FlakyExecutor::default());
+        let task_config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: format!("{}/tasks", dir),
+            ..Default::default()
+        };
+        let refinement_config = RefinementConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let task_manager = Arc::new(TaskManager::new(task_config, refinement_config).unwrap());
+        let orchestrator =
+            PromptOrchestrator::new(executor.clone(), Arc::new(MockZSEI), task_manager)
+                .with_checkpoints(CheckpointStore::new(format!("{}/checkpoints", dir)));
+
+        let request = OrchestrationRequest {
+            prompt: "Do three things".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let failed = orchestrator.orchestrate(request).await;
+        assert!(!failed.success);
+        let checkpoint = &orchestrator.checkpoints().await[0];
+        assert_eq!(checkpoint.orchestration_id, failed.orchestration_id);
+        assert_eq!(checkpoint.status, CheckpointStatus::Failed);
+        assert_eq!((checkpoint.steps_completed, checkpoint.total_steps), (1, 3));
+        assert_eq!(executor.step_calls.load(Ordering::SeqCst), 2);
+
+        // A run still going in this process is not resumed alongside it
+        {
+            let _running = orchestrator.claim(failed.orchestration_id).unwrap();
+            let err = orchestrator.resume(failed.orchestration_id).await.unwrap_err();
+            assert!(err.contains("already running"), "{}", err);
+        }
+        assert_eq!(executor.step_calls.load(Ordering::SeqCst), 2);
+
+        // Only the failed step and those after it run again
+        let task_id = failed.task_id.unwrap();
+        let resumed = orchestrator.resume_task(task_id).await.unwrap();
+        assert!(resumed.success, "{:?}", resumed.error);
+        assert_eq!(resumed.task_id, Some(task_id));
+        assert_eq!(executor.step_calls.load(Ordering::SeqCst), 4);
+        assert_eq!(executor.blueprint_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(resumed.response.as_deref(), Some("done\n\ndone\n\ndone"));
+        // Stages that ran before the failure are reported once
+        let mut stages: Vec<(u8, &str)> = resumed
+            .stages_completed
+            .iter()
+            .map(|s| (s.stage, s.name.as_str()))
+            .collect();
+        let reported = stages.len();
+        stages.sort();
+        stages.dedup();
+        assert_eq!(stages.len(), reported);
+        assert_eq!(
+            orchestrator.checkpoints().await[0].status,
+            CheckpointStatus::Complete
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// Asks which database to use until the AMT carries the answer
+    #[derive(Default)]
+    struct ClarifyingExecutor {
+        simulations: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl PipelineExecutor for ClarifyingExecutor {
+        async fn execute(
+            &self,
+            pipeline_id: u64,
+            input: serde_json::Value,
+        ) -> Result<serde_json::Value, String> {
+            let prompt = input.get("prompt").and_then(|p| p.as_str()).unwrap_or("");
+            if pipeline_id == 9 && prompt.starts_with("Simulate executing") {
+                self.simulations
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let simulation = if prompt.contains("Postgres") {
+                    serde_json::json!({"simulation_confidence": 0.9, "overall_feasibility": "high"})
+                } else {
+                    serde_json::json!({
+                        "simulation_confidence": 0.2,
+                        "overall_feasibility": "low",
+                        "clarifications_needed": ["Which database?"]
+                    })
+                };
+                return Ok(serde_json::json!({"response": simulation.to_string()}));
+            }
+            MockExecutor.execute(pipeline_id, input).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_answer_clarification_continues_orchestration() {
+        let dir = "/tmp/test_orchestration_clarification";
+        let _ = std::fs::remove_dir_all(dir);
+        let executor = Arc::new(ClarifyingExecutor::default());
+        let task_config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: format!("{}/tasks", dir),
+            ..Default::default()
+        };
+        let refinement_config = RefinementConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let task_manager = Arc::new(TaskManager::new(task_config, refinement_config).unwrap());
+        let orchestrator =
+            PromptOrchestrator::new(executor.clone(), Arc::new(MockZSEI), task_manager.clone())
+                .with_checkpoints(CheckpointStore::new(format!("{}/checkpoints", dir)));
+
+        let request = OrchestrationRequest {
+            prompt: "Store the orders".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let asked = orchestrator.orchestrate(request).await;
+        assert!(asked.needs_clarification);
+        assert_eq!(asked.clarification_points, vec!["Which database?"]);
+        assert_eq!(asked.task_id, None);
+        assert_eq!(
+            orchestrator.checkpoints().await[0].status,
+            CheckpointStatus::AwaitingClarification
+        );
+        assert!(orchestrator
+            .answer_clarification(asked.orchestration_id, 1, Vec::new())
+            .await
+            .is_err());
+        // Only the user who asked can answer, and resuming would ask again
+        assert!(orchestrator
+            .answer_clarification(asked.orchestration_id, 2, vec!["MySQL".to_string()])
+            .await
+            .is_err());
+        assert!(orchestrator.resume(asked.orchestration_id).await.is_err());
+        assert_eq!(
+            executor
+                .simulations
+                .load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+
+        // Of two answers given together only one is taken
+        let (first, second) = tokio::join!(
+            orchestrator.answer_clarification(
+                asked.orchestration_id,
+                1,
+                vec!["Postgres".to_string()]
+            ),
+            orchestrator.answer_clarification(asked.orchestration_id, 1, vec!["MySQL".to_string()]),
+        );
+        let answered = first.unwrap();
+        assert!(second.unwrap_err().contains("already running"));
+        assert!(answered.success, "{:?}", answered.error);
+        assert!(!answered.needs_clarification);
+        assert_eq!(answered.clarifications.len(), 1);
+        assert_eq!(answered.clarifications[0].stage, 4);
+        assert_eq!(
+            executor
+                .simulations
+                .load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+        let logs = task_manager.get_logs(answered.task_id.unwrap(), None).await;
+        assert!(logs
+            .iter()
+            .any(|log| log.message.contains("Which database? -> Postgres")));
+        assert!(orchestrator
+            .answer_clarification(asked.orchestration_id, 1, vec!["MySQL".to_string()])
+            .await
+            .unwrap_err()
+            .contains("not awaiting clarification"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// Plans five steps on pipeline 50: 0, 1 and 2 are independent, 3
+    /// depends on 0 and 4 on 1, which always fails under `Continue`
+    #[derive(Default)]
+    struct BranchingExecutor {
+        running: std::sync::atomic::AtomicU32,
+        max_running: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl PipelineExecutor for BranchingExecutor {
+        async fn execute(
+            &self,
+            pipeline_id: u64,
+            input: serde_json::Value,
+        ) -> Result<serde_json::Value, String> {
+            use std::sync::atomic::Ordering;
+            let prompt = input.get("prompt").and_then(|p| p.as_str()).unwrap_or("");
+            if pipeline_id == 9 && prompt.starts_with("Create a blueprint") {
+                let steps: Vec<serde_json::Value> = [vec![], vec![], vec![], vec![0], vec![1]]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, depends_on)| {
+                        serde_json::json!({
+                            "step_index": i,
+                            "action": "execute_prompt",
+                            "description": format!("Part {}", i),
+                            "pipeline_id": 50,
+                            "context_requirements": [],
+                            "loop_config": null,
+                            "sub_steps": [],
+                            "depends_on": depends_on,
+                            "wait_for_graph_update": false,
+                            "max_retries": 0,
+                            "timeout_ms": null,
+                            "failure_policy": if i == 1 { "continue" } else { "fail_fast" }
+                        })
+                    })
+                    .collect();
+                let blueprint = serde_json::json!({"name": "Branches", "steps": steps});
+                return Ok(serde_json::json!({"response": blueprint.to_string()}));
+            }
+            if pipeline_id == 50 {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_running.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                if prompt.starts_with("Step 2:") {
+                    return Err("Model unavailable".into());
+                }
+                let part = prompt.split(':').next().unwrap_or_default();
+                return Ok(serde_json::json!({"response": part, "tokens_used": 10}));
+            }
+            MockExecutor.execute(pipeline_id, input).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_independent_steps_run_in_parallel() {
+        let dir =
+            std::env::temp_dir().join(format!("ozone-parallel-steps-{}", uuid::Uuid::new_v4()));
+        let executor = Arc::new(BranchingExecutor::default());
+        let task_config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: dir.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let refinement_config = RefinementConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let task_manager = Arc::new(TaskManager::new(task_config, refinement_config).unwrap());
+        let orchestrator =
+            PromptOrchestrator::new(executor.clone(), Arc::new(MockZSEI), task_manager)
+                .with_max_parallel_steps(2);
+
+        let request = OrchestrationRequest {
+            prompt: "Do five things".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let response = orchestrator.orchestrate(request).await;
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(
+            executor
+                .max_running
+                .load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+        // Step 1 failed and step 4, which depends on it, was skipped
+        assert_eq!(
+            response.response.as_deref(),
+            Some("Step 1\n\nStep 3\n\nStep 4")
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_step_dependency_cycles_are_rejected() {
+        let step = |step_index: u32, depends_on: Vec<u32>| BlueprintStep {
+            step_index,
+            action: "execute_prompt".to_string(),
+            description: String::new(),
+            pipeline_id: 9,
+            context_requirements: Vec::new(),
+            loop_config: None,
+            sub_steps: Vec::new(),
+            depends_on,
+            wait_for_graph_update: false,
+            max_retries: 0,
+            timeout_ms: None,
+            failure_policy: FailurePolicy::FailFast,
+        };
+
+        let acyclic = [step(0, vec![]), step(1, vec![0]), step(2, vec![0, 1])];
+        assert!(PromptOrchestrator::check_step_dependencies(&acyclic).is_ok());
+
+        let cyclic = [step(0, vec![2]), step(1, vec![0]), step(2, vec![1])];
+        assert_eq!(
+            PromptOrchestrator::check_step_dependencies(&cyclic).unwrap_err(),
+            "Blueprint steps have a dependency cycle: 0 -> 2 -> 1 -> 0"
+        );
+
+        let dangling = [step(0, vec![]), step(1, vec![7])];
+        assert!(PromptOrchestrator::check_step_dependencies(&dangling)
+            .unwrap_err()
+            .contains("unknown step 7"));
+    }
+
+    /// Runs every pipeline but the prompt pipeline
+    struct NoPromptPipeline;
+
+    #[async_trait::async_trait]
+    impl PipelineExecutor for NoPromptPipeline {
+        async fn execute(
+            &self,
+            pipeline_id: u64,
+            input: serde_json::Value,
+        ) -> Result<serde_json::Value, String> {
+            if pipeline_id == PROMPT_PIPELINE_ID {
+                return Err("Model calls must go through the LLM router".into());
+            }
+            MockExecutor.execute(pipeline_id, input).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_flow_through_llm_provider() {
+        use crate::llm::mock_server::MockServer;
+        use crate::llm::{ApiFormat, HttpProvider, MockProvider};
+
+        let mock = Arc::new(
+            MockProvider::new()
+                .reply("Step 1:", "Steps done")
+                .reply("Create a blueprint", single_step_blueprint().to_string())
+                .reply(
+                    "Simulate executing",
+                    r#"{"simulation_confidence": 0.9, "overall_feasibility": "high"}"#,
+                ),
+        );
+        let server = MockServer::start(mock.clone()).await.unwrap();
+        let provider = Arc::new(HttpProvider::new(
+            ApiFormat::Anthropic,
+            server.url(ApiFormat::Anthropic),
+        ));
+        let router = LlmRouter::new(provider, "planner").with_stage_model("execution", "executor");
+
+        let task_config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: "/tmp/test_llm_provider_flow".to_string(),
+            ..Default::default()
+        };
+        let refinement_config = RefinementConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let task_manager = Arc::new(TaskManager::new(task_config, refinement_config).unwrap());
+        let orchestrator =
+            PromptOrchestrator::new(Arc::new(NoPromptPipeline), Arc::new(MockZSEI), task_manager)
+                .with_llm(router);
+
+        let request = OrchestrationRequest {
+            prompt: "Summarise the notes".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let response = orchestrator.orchestrate(request).await;
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.stages_completed.last().map(|s| s.stage), Some(11));
+        assert_eq!(response.response.as_deref(), Some("Steps done"));
+
+        // Planning stages use the default model, blueprint steps their own
+        let requests = mock.requests();
+        assert_eq!(requests.len() as u32, server.requests());
+        assert!(requests
+            .iter()
+            .any(|r| r.prompt.starts_with("Create a blueprint") && r.model == "planner"));
+        assert!(requests
+            .iter()
+            .any(|r| r.prompt.starts_with("Step 1:") && r.model == "executor"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_model_output_is_repaired_or_fails_the_stage() {
+        use crate::llm::MockProvider;
+
+        let mut broken = single_step_blueprint();
+        broken["steps"][0]["pipeline_id"] = serde_json::json!("nine");
+        // Repair prompts repeat the original prompt, so the simulation
+        // never conforms while the blueprint is fixed on its first repair
+        let mock = Arc::new(
+            MockProvider::new()
+                .reply("Simulate executing", "It should work fine")
+                .reply(
+                    "did not match the required JSON schema",
+                    single_step_blueprint().to_string(),
+                )
+                .reply("Create a blueprint", broken.to_string()),
+        );
+        let task_config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: "/tmp/test_structured_output".to_string(),
+            ..Default::default()
+        };
+        let refinement_config = RefinementConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let task_manager = Arc::new(TaskManager::new(task_config, refinement_config).unwrap());
+        let orchestrator =
+            PromptOrchestrator::new(Arc::new(NoPromptPipeline), Arc::new(MockZSEI), task_manager)
+                .with_llm(LlmRouter::new(mock.clone(), "planner"));
+
+        let request = OrchestrationRequest {
+            prompt: "Plan the release".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let response = orchestrator.orchestrate(request).await;
+        assert!(!response.success);
+        assert!(response
+            .error
+            .unwrap()
+            .starts_with("Zero-Shot Simulation: simulation: $: not valid JSON"));
+
+        let stage = |n: u8| {
+            response
+                .stages_completed
+                .iter()
+                .find(|s| s.stage == n)
+                .unwrap()
+        };
+        // Stage 2 gets by without the intents the model failed to give
+        assert!(stage(2).success);
+        assert!(stage(2)
+            .validation_failures
+            .iter()
+            .any(|v| v.starts_with("intents: ")));
+        assert!(stage(3).success);
+        assert!(stage(3).validation_failures.is_empty());
+        assert!(!stage(4).success);
+        assert_eq!(stage(4).validation_failures.len(), 1);
+
+        let requests = mock.requests();
+        let blueprint_calls: Vec<_> = requests
+            .iter()
+            .filter(|r| r.prompt.starts_with("Create a blueprint"))
+            .collect();
+        assert_eq!(blueprint_calls.len(), 2);
+        assert!(blueprint_calls[1]
+            .prompt
+            .contains("- $.steps[0].pipeline_id: expected integer, found string"));
+        let simulation_calls = requests
+            .iter()
+            .filter(|r| r.prompt.starts_with("Simulate executing"))
+            .count();
+        assert_eq!(simulation_calls, 1 + MAX_REPAIR_ATTEMPTS as usize);
+    }
+
+    /// `BranchingExecutor` with a fixed graph ID, so that no request of a
+    /// run depends on a generated one and the run can be replayed
+    #[derive(Default)]
+    struct ReplayableExecutor(BranchingExecutor);
+
+    #[async_trait::async_trait]
+    impl PipelineExecutor for ReplayableExecutor {
+        async fn execute(
+            &self,
+            pipeline_id: u64,
+            input: serde_json::Value,
+        ) -> Result<serde_json::Value, String> {
+            if input["action"]["type"] == "CreateGraph" {
+                return Ok(serde_json::json!({"graph_id": 500}));
+            }
+            self.0.execute(pipeline_id, input).await
+        }
+    }
+
+    /// Replays `tests/fixtures/orchestration_replay.json`; set
+    /// `OZONE_RECORD_FIXTURES` to record it again after a deliberate change
+    /// to the orchestrator's prompts or pipeline inputs
+    #[tokio::test]
+    async fn test_replay_reproduces_recorded_orchestration() {
+        use replay::{Recorder, Replayer};
+
+        let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/orchestration_replay.json");
+        let dir = std::env::temp_dir().join(format!("ozone-replay-{}", uuid::Uuid::new_v4()));
+        // Each run gets its own task store, so task IDs match the recording
+        let orchestrator = |run: &str, executor: Arc<dyn PipelineExecutor>, zsei| {
+            let task_config = TaskQueueConfig {
+                consciousness_enabled: false,
+                storage_path: dir.join(run).to_string_lossy().to_string(),
+                ..Default::default()
+            };
+            let refinement_config = RefinementConfig {
+                enabled: false,
+                ..Default::default()
+            };
+            let task_manager = Arc::new(TaskManager::new(task_config, refinement_config).unwrap());
+            PromptOrchestrator::new(executor, zsei, task_manager)
+        };
+        let request = |prompt: &str| OrchestrationRequest {
+            prompt: prompt.to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+
+        if std::env::var_os("OZONE_RECORD_FIXTURES").is_some() {
+            let recorder = Arc::new(Recorder::new(
+                Arc::new(ReplayableExecutor::default()),
+                Arc::new(MockZSEI),
+            ));
+            let recorded = orchestrator("record", recorder.clone(), recorder.clone())
+                .orchestrate(request("Do five things"))
+                .await;
+            assert!(recorded.success, "{:?}", recorded.error);
+            recorder.save(&fixture).unwrap();
+        }
+
+        let replayer = Arc::new(Replayer::load(&fixture).unwrap());
+        let replayed = orchestrator("replay", replayer.clone(), replayer.clone())
+            .orchestrate(request("Do five things"))
+            .await;
+        assert!(replayed.success, "{:?}", replayed.error);
+        assert_eq!(
+            replayed.response.as_deref(),
+            Some("Step 1\n\nStep 3\n\nStep 4")
+        );
+        let step_execution = replayed
+            .stages_completed
+            .iter()
+            .find(|s| s.stage == 8)
+            .unwrap();
+        assert!(step_execution
+            .output_summary
+            .as_deref()
+            .unwrap_or_default()
+            .starts_with("3 steps executed, 1 failed, 1 skipped"));
+        assert_eq!(replayer.remaining(), 0);
+
+        // A prompt the fixture does not cover fails instead of reaching a model
+        let replayer = Arc::new(Replayer::load(&fixture).unwrap());
+        let changed = orchestrator("changed", replayer.clone(), replayer)
+            .orchestrate(request("Do six things"))
+            .await;
+        assert!(!changed.success);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        // Models without a vocabulary are estimated
+        let tokenizer = ModelCatalog::default().tokenizer("claude-sonnet-4");
+        assert_eq!(tokenizer.count("test"), 1);
+        assert_eq!(tokenizer.count("test test test test"), 5);
+        assert_eq!(tokenizer.count(""), 0);
+    }
+
+    #[test]
+    fn test_truncate_str_keeps_characters_whole() {
+        assert_eq!(truncate_str("héllo", 2), "h");
+        assert_eq!(truncate_str("héllo", 3), "hé");
+        assert_eq!(truncate_str("héllo", 100), "héllo");
+        assert_eq!(truncate_str("日本", 1), "");
+    }
+
     #[test]
     fn test_amt_node_counting() {
         let mut root = AMTNode::new(1, AMTNodeType::Root, "Root".to_string(), 0);
@@ -4507,27 +6456,288 @@ mod tests {
         assert_eq!(root.max_depth(), 2);
     }
 
-    #[test]
-    fn test_parse_json_object() {
-        let result = PromptOrchestrator::parse_json_object(
-            r#"Some text before {"key": "value"} some text after"#,
+    #[tokio::test]
+    async fn test_token_usage_rolls_up_by_stage_and_step() {
+        use crate::llm::{MockProvider, ModelInfo};
+
+        let mock = Arc::new(
+            MockProvider::new()
+                .reply("Step 1:", "Steps done")
+                .reply("Create a blueprint", single_step_blueprint().to_string())
+                .reply(
+                    "Simulate executing",
+                    r#"{"simulation_confidence": 0.9, "overall_feasibility": "high"}"#,
+                ),
         );
-        assert_eq!(result.get("key").and_then(|v| v.as_str()), Some("value"));
+        let task_config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: "/tmp/test_token_usage".to_string(),
+            ..Default::default()
+        };
+        let refinement_config = RefinementConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let task_manager = Arc::new(TaskManager::new(task_config, refinement_config).unwrap());
+        let models = ModelCatalog::new()
+            .with_model(
+                ModelInfo::new("planner", 100_000)
+                    .with_max_output_tokens(100)
+                    .with_prices(1.0, 2.0),
+            )
+            .with_default_model("planner");
+        let orchestrator =
+            PromptOrchestrator::new(Arc::new(NoPromptPipeline), Arc::new(MockZSEI), task_manager)
+                .with_llm(LlmRouter::new(mock.clone(), "planner"))
+                .with_models(models);
 
-        let empty_result = PromptOrchestrator::parse_json_object("no json here");
-        assert!(empty_result
-            .as_object()
-            .map(|o| o.is_empty())
-            .unwrap_or(true));
-    }
+        let request = OrchestrationRequest {
+            prompt: "Summarise the notes".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: Some(200),
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let response = orchestrator.orchestrate(request).await;
+        assert!(response.success, "{:?}", response.error);
 
-    #[test]
-    fn test_estimate_tokens() {
-        assert_eq!(PromptOrchestrator::estimate_tokens("test"), 1);
+        // Every provider-reported token is counted once, in some stage
+        let requests = mock.requests();
+        let mut expected = TokenUsage::default();
+        for request in &requests {
+            expected.add(mock.answer(request).usage());
+        }
+        assert_eq!(response.token_usage, expected);
+        assert_eq!(response.total_tokens_used, Some(expected.total()));
+        let by_stage: u32 = response
+            .stages_completed
+            .iter()
+            .map(|s| s.tokens_used)
+            .sum();
+        assert_eq!(by_stage, expected.total());
+
+        let step = requests
+            .iter()
+            .find(|r| r.prompt.starts_with("Step 1:"))
+            .unwrap();
+        assert_eq!(
+            response.step_tokens_used.get(&0),
+            Some(&mock.answer(step).tokens_used())
+        );
+        let stage_8 = response.stages_completed.iter().find(|s| s.stage == 8);
         assert_eq!(
-            PromptOrchestrator::estimate_tokens("test test test test"),
-            5
+            stage_8.map(|s| s.tokens_used),
+            Some(mock.answer(step).tokens_used())
         );
-        assert_eq!(PromptOrchestrator::estimate_tokens(""), 0);
+        // A quarter of the 200-token budget, under the 100-token output limit
+        assert_eq!(step.max_tokens, 50);
+        // The step's context fits in what the output leaves of the budget,
+        // and names what it was packed from
+        assert!(step.prompt.len().div_ceil(4) as u32 <= 200 - 50);
+        assert!(step.prompt.contains("[Prompt chunk 0]\nTest cleaned"));
+        assert!(response.step_context[&0]
+            .iter()
+            .any(|item| item.source == ContextSource::Chunk { chunk_index: 0 }));
+
+        let cost = (expected.input_tokens as f64 + 2.0 * expected.output_tokens as f64) / 1e6;
+        assert!((response.estimated_cost_usd.unwrap() - cost).abs() < 1e-12);
+    }
+
+    /// Keeps the containers orchestrations create, so a later run finds
+    /// them
+    #[derive(Default)]
+    struct StoringZSEI {
+        containers: std::sync::Mutex<HashMap<u64, serde_json::Value>>,
+    }
+
+    impl StoringZSEI {
+        fn container(&self, id: u64) -> serde_json::Value {
+            self.containers.lock().unwrap()[&id].clone()
+        }
+    }
+
+    fn merge(target: &mut serde_json::Value, updates: serde_json::Value) {
+        match (target, updates) {
+            (serde_json::Value::Object(target), serde_json::Value::Object(updates)) => {
+                for (key, value) in updates {
+                    merge(target.entry(key).or_insert(serde_json::Value::Null), value);
+                }
+            }
+            (target, updates) => *target = updates,
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ZSEIAccess for StoringZSEI {
+        async fn query(&self, query: serde_json::Value) -> Result<serde_json::Value, String> {
+            let hash = &query["SearchBlueprints"]["task_signature"]["hash"];
+            let containers = self.containers.lock().unwrap();
+            let mut ids: Vec<u64> = containers
+                .iter()
+                .filter(|(_, c)| !hash.is_null() && c["context"]["task_signature"]["hash"] == *hash)
+                .map(|(id, _)| *id)
+                .collect();
+            ids.sort();
+            Ok(serde_json::json!({ "Containers": ids }))
+        }
+
+        async fn traverse(&self, _request: serde_json::Value) -> Result<serde_json::Value, String> {
+            Ok(serde_json::json!({"results": []}))
+        }
+
+        async fn create_container(
+            &self,
+            _parent_id: u64,
+            container: serde_json::Value,
+        ) -> Result<u64, String> {
+            let mut containers = self.containers.lock().unwrap();
+            let id = 2000 + containers.len() as u64;
+            containers.insert(id, container);
+            Ok(id)
+        }
+
+        async fn update_container(
+            &self,
+            container_id: u64,
+            updates: serde_json::Value,
+        ) -> Result<(), String> {
+            let mut containers = self.containers.lock().unwrap();
+            let container = containers
+                .get_mut(&container_id)
+                .ok_or("No such container")?;
+            merge(container, updates);
+            Ok(())
+        }
+
+        async fn get_container(
+            &self,
+            container_id: u64,
+        ) -> Result<Option<serde_json::Value>, String> {
+            // Let concurrent writers interleave between read and write
+            tokio::task::yield_now().await;
+            let containers = self.containers.lock().unwrap();
+            Ok(containers
+                .get(&container_id)
+                .map(|c| serde_json::json!({"local_state": c})))
+        }
+
+        async fn search_by_keywords(
+            &self,
+            _keywords: &[String],
+            _container_type: Option<&str>,
+        ) -> Result<Vec<u64>, String> {
+            // Blueprints are only found through their signature
+            Ok(vec![])
+        }
+
+        async fn get_categories(&self, _modality: &str) -> Result<Vec<u64>, String> {
+            Ok(vec![])
+        }
+    }
+
+    fn blueprint_orchestrator(
+        storage_path: &std::path::Path,
+    ) -> (PromptOrchestrator, Arc<StoringZSEI>) {
+        let task_config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: storage_path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let refinement_config = RefinementConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let task_manager = Arc::new(TaskManager::new(task_config, refinement_config).unwrap());
+        let zsei = Arc::new(StoringZSEI::default());
+        let orchestrator =
+            PromptOrchestrator::new(Arc::new(MockExecutor), zsei.clone(), task_manager);
+        (orchestrator, zsei)
+    }
+
+    #[tokio::test]
+    async fn test_matching_blueprint_is_reused_and_learns_from_runs() {
+        let dir =
+            std::env::temp_dir().join(format!("ozone-blueprint-reuse-{}", uuid::Uuid::new_v4()));
+        let (orchestrator, zsei) = blueprint_orchestrator(&dir);
+        let request = OrchestrationRequest {
+            prompt: "Summarise the quarterly report".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let stats = |id: u64| -> BlueprintStats {
+            serde_json::from_value(zsei.container(id)["storage"].clone()).unwrap()
+        };
+
+        let first = orchestrator.orchestrate(request.clone()).await;
+        assert!(first.success, "{:?}", first.error);
+        assert_eq!(first.blueprints_created, 1);
+        let blueprint_id = first.blueprint_id.unwrap();
+        let signature = &zsei.container(blueprint_id)["context"]["task_signature"];
+        assert_eq!(signature["output_type"], "text");
+        assert_eq!(stats(blueprint_id).usage_count, 1);
+        assert_eq!(stats(blueprint_id).success_rate, 1.0);
+
+        let second = orchestrator.orchestrate(request).await;
+        assert!(second.success, "{:?}", second.error);
+        assert_eq!(second.blueprints_created, 0);
+        assert_eq!(second.blueprint_id, Some(blueprint_id));
+        let stage_3 = second
+            .stages_completed
+            .iter()
+            .find(|s| s.stage == 3)
+            .unwrap();
+        assert!(stage_3
+            .output_summary
+            .as_deref()
+            .unwrap()
+            .starts_with(&format!("Using existing blueprint {}", blueprint_id)));
+        assert_eq!(stats(blueprint_id).usage_count, 2);
+        assert_eq!(stats(blueprint_id).validation_runs, 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_outcomes_are_all_counted() {
+        let dir =
+            std::env::temp_dir().join(format!("ozone-blueprint-stats-{}", uuid::Uuid::new_v4()));
+        let (orchestrator, zsei) = blueprint_orchestrator(&dir);
+        let blueprint_id = zsei
+            .create_container(0, serde_json::json!({"storage": BlueprintStats::default()}))
+            .await
+            .unwrap();
+        let request = OrchestrationRequest {
+            prompt: "Summarise the quarterly report".to_string(),
+            project_id: None,
+            workspace_id: None,
+            user_id: 1,
+            device_id: 1,
+            consciousness_enabled: false,
+            token_budget: None,
+            model_config: None,
+            attached_files: Vec::new(),
+        };
+        let mut state = OrchestrationState::new(request, "model".into(), 1000, 4, Vec::new());
+        state.blueprint_id = Some(blueprint_id);
+
+        futures_util::future::join_all(
+            (0..5).map(|i| orchestrator.record_blueprint_outcome(&state, i % 2 == 0)),
+        )
+        .await;
+        let stats: BlueprintStats =
+            serde_json::from_value(zsei.container(blueprint_id)["storage"].clone()).unwrap();
+        assert_eq!(stats.usage_count, 5);
+        assert_eq!(stats.validation_runs, 5);
+        assert!((stats.success_rate - 0.6).abs() < 1e-6);
+        let _ = std::fs::remove_dir_all(&dir);
     }
 }
@@ -0,0 +1,352 @@
+//! Record and replay of orchestration interactions
+//!
+//! `Recorder` wraps the pipeline executor, ZSEI and (optionally) the LLM
+//! providers of a real orchestration and logs every call with its request and
+//! response; `save` writes them to a fixture file. `Replayer` loads a fixture
+//! and stands in for all three, answering each call with the response
+//! recorded for the identical request. A request that was not recorded is an
+//! error, so a change to any prompt or pipeline input shows up as a failed
+//! replay rather than as a silently different run. Requests are compared
+//! whole, so a fixture only replays if they carry nothing that changes
+//! between runs; the orchestrator generates IDs of its own only when a
+//! pipeline returns none.
+
+use super::{truncate_str, PipelineExecutor, ZSEIAccess};
+use crate::llm::{LlmProvider, LlmRequest, LlmResponse};
+use crate::types::{OzoneError, OzoneResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// One call and its outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// `pipeline:<id>`, `zsei:<method>` or `llm`
+    pub call: String,
+    pub request: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Interaction {
+    fn new(call: String, request: Value, outcome: &Result<Value, String>) -> Self {
+        let (response, error) = match outcome {
+            Ok(value) => (Some(value.clone()), None),
+            Err(e) => (None, Some(e.clone())),
+        };
+        Self {
+            call,
+            request,
+            response,
+            error,
+        }
+    }
+
+    fn outcome(&self) -> Result<Value, String> {
+        match &self.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(self.response.clone().unwrap_or(Value::Null)),
+        }
+    }
+}
+
+/// A recorded orchestration, as stored on disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fixture {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Fixture {
+    pub fn load(path: impl AsRef<Path>) -> OzoneResult<Self> {
+        let content = std::fs::read(path)?;
+        serde_json::from_slice(&content).map_err(|e| OzoneError::SerializationError(e.to_string()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> OzoneResult<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_vec_pretty(self)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn from_value<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, String> {
+    serde_json::from_value(value).map_err(|e| format!("Invalid recorded response: {}", e))
+}
+
+// ============================================================================
+// Recording
+// ============================================================================
+
+/// Passes calls through to the real executor and ZSEI and logs them
+pub struct Recorder {
+    executor: Arc<dyn PipelineExecutor>,
+    zsei: Arc<dyn ZSEIAccess>,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl Recorder {
+    pub fn new(executor: Arc<dyn PipelineExecutor>, zsei: Arc<dyn ZSEIAccess>) -> Self {
+        Self {
+            executor,
+            zsei,
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Wrap an LLM provider so its calls are recorded too
+    pub fn provider(self: &Arc<Self>, inner: Arc<dyn LlmProvider>) -> Arc<dyn LlmProvider> {
+        Arc::new(RecordingProvider {
+            recorder: self.clone(),
+            inner,
+        })
+    }
+
+    /// Everything recorded so far, in call order
+    pub fn fixture(&self) -> Fixture {
+        Fixture {
+            interactions: self
+                .interactions
+                .lock()
+                .map(|interactions| interactions.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> OzoneResult<()> {
+        self.fixture().save(path)
+    }
+
+    fn record(&self, call: String, request: Value, outcome: &Result<Value, String>) {
+        if let Ok(mut interactions) = self.interactions.lock() {
+            interactions.push(Interaction::new(call, request, outcome));
+        }
+    }
+
+    /// Record a ZSEI call whose result is `T`
+    fn record_zsei<T: Serialize>(&self, method: &str, request: Value, result: &Result<T, String>) {
+        let outcome = result.as_ref().map(to_value).map_err(|e| e.clone());
+        self.record(format!("zsei:{}", method), request, &outcome);
+    }
+}
+
+#[async_trait::async_trait]
+impl PipelineExecutor for Recorder {
+    async fn execute(&self, pipeline_id: u64, input: Value) -> Result<Value, String> {
+        let result = self.executor.execute(pipeline_id, input.clone()).await;
+        self.record(format!("pipeline:{}", pipeline_id), input, &result);
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl ZSEIAccess for Recorder {
+    async fn query(&self, query: Value) -> Result<Value, String> {
+        let result = self.zsei.query(query.clone()).await;
+        self.record_zsei("query", query, &result);
+        result
+    }
+
+    async fn traverse(&self, request: Value) -> Result<Value, String> {
+        let result = self.zsei.traverse(request.clone()).await;
+        self.record_zsei("traverse", request, &result);
+        result
+    }
+
+    async fn create_container(&self, parent_id: u64, container: Value) -> Result<u64, String> {
+        let request = json!({"parent_id": parent_id, "container": container});
+        let result = self.zsei.create_container(parent_id, container).await;
+        self.record_zsei("create_container", request, &result);
+        result
+    }
+
+    async fn update_container(&self, container_id: u64, updates: Value) -> Result<(), String> {
+        let request = json!({"container_id": container_id, "updates": updates});
+        let result = self.zsei.update_container(container_id, updates).await;
+        self.record_zsei("update_container", request, &result);
+        result
+    }
+
+    async fn get_container(&self, container_id: u64) -> Result<Option<Value>, String> {
+        let result = self.zsei.get_container(container_id).await;
+        self.record_zsei(
+            "get_container",
+            json!({"container_id": container_id}),
+            &result,
+        );
+        result
+    }
+
+    async fn search_by_keywords(
+        &self,
+        keywords: &[String],
+        container_type: Option<&str>,
+    ) -> Result<Vec<u64>, String> {
+        let result = self.zsei.search_by_keywords(keywords, container_type).await;
+        let request = json!({"keywords": keywords, "container_type": container_type});
+        self.record_zsei("search_by_keywords", request, &result);
+        result
+    }
+
+    async fn get_categories(&self, modality: &str) -> Result<Vec<u64>, String> {
+        let result = self.zsei.get_categories(modality).await;
+        self.record_zsei("get_categories", json!({"modality": modality}), &result);
+        result
+    }
+}
+
+struct RecordingProvider {
+    recorder: Arc<Recorder>,
+    inner: Arc<dyn LlmProvider>,
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for RecordingProvider {
+    async fn complete(&self, request: &LlmRequest) -> OzoneResult<LlmResponse> {
+        let result = self.inner.complete(request).await;
+        let outcome = match &result {
+            Ok(response) => Ok(to_value(response)),
+            Err(e) => Err(e.to_string()),
+        };
+        self.recorder
+            .record("llm".to_string(), to_value(request), &outcome);
+        result
+    }
+}
+
+// ============================================================================
+// Replay
+// ============================================================================
+
+/// Recorded outcomes keyed by call and serialized request
+type Outcomes = HashMap<(String, String), VecDeque<Result<Value, String>>>;
+
+/// Answers calls from a fixture instead of running anything
+pub struct Replayer {
+    /// Recorded outcomes by call and request, in recorded order; identical
+    /// requests get their responses in turn
+    outcomes: Mutex<Outcomes>,
+}
+
+impl Replayer {
+    pub fn new(fixture: Fixture) -> Self {
+        let mut outcomes = Outcomes::new();
+        for interaction in fixture.interactions {
+            outcomes
+                .entry(Self::key(&interaction.call, &interaction.request))
+                .or_default()
+                .push_back(interaction.outcome());
+        }
+        Self {
+            outcomes: Mutex::new(outcomes),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> OzoneResult<Self> {
+        Fixture::load(path).map(Self::new)
+    }
+
+    /// Recorded calls not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.outcomes
+            .lock()
+            .map(|outcomes| outcomes.values().map(VecDeque::len).sum())
+            .unwrap_or(0)
+    }
+
+    fn key(call: &str, request: &Value) -> (String, String) {
+        (call.to_string(), request.to_string())
+    }
+
+    fn replay(&self, call: String, request: Value) -> Result<Value, String> {
+        let key = Self::key(&call, &request);
+        let outcome = self
+            .outcomes
+            .lock()
+            .map_err(|_| "Replay fixture lock poisoned".to_string())?
+            .get_mut(&key)
+            .and_then(VecDeque::pop_front);
+        outcome.unwrap_or_else(|| {
+            let request = key.1;
+            Err(format!(
+                "No recorded response for {} with request {}",
+                call,
+                truncate_str(&request, 200)
+            ))
+        })
+    }
+
+    fn replay_zsei<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        request: Value,
+    ) -> Result<T, String> {
+        self.replay(format!("zsei:{}", method), request)
+            .and_then(from_value)
+    }
+}
+
+#[async_trait::async_trait]
+impl PipelineExecutor for Replayer {
+    async fn execute(&self, pipeline_id: u64, input: Value) -> Result<Value, String> {
+        self.replay(format!("pipeline:{}", pipeline_id), input)
+    }
+}
+
+#[async_trait::async_trait]
+impl ZSEIAccess for Replayer {
+    async fn query(&self, query: Value) -> Result<Value, String> {
+        self.replay_zsei("query", query)
+    }
+
+    async fn traverse(&self, request: Value) -> Result<Value, String> {
+        self.replay_zsei("traverse", request)
+    }
+
+    async fn create_container(&self, parent_id: u64, container: Value) -> Result<u64, String> {
+        let request = json!({"parent_id": parent_id, "container": container});
+        self.replay_zsei("create_container", request)
+    }
+
+    async fn update_container(&self, container_id: u64, updates: Value) -> Result<(), String> {
+        let request = json!({"container_id": container_id, "updates": updates});
+        self.replay_zsei("update_container", request)
+    }
+
+    async fn get_container(&self, container_id: u64) -> Result<Option<Value>, String> {
+        self.replay_zsei("get_container", json!({"container_id": container_id}))
+    }
+
+    async fn search_by_keywords(
+        &self,
+        keywords: &[String],
+        container_type: Option<&str>,
+    ) -> Result<Vec<u64>, String> {
+        let request = json!({"keywords": keywords, "container_type": container_type});
+        self.replay_zsei("search_by_keywords", request)
+    }
+
+    async fn get_categories(&self, modality: &str) -> Result<Vec<u64>, String> {
+        self.replay_zsei("get_categories", json!({"modality": modality}))
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for Replayer {
+    async fn complete(&self, request: &LlmRequest) -> OzoneResult<LlmResponse> {
+        self.replay("llm".to_string(), to_value(request))
+            .and_then(from_value)
+            .map_err(OzoneError::ModelError)
+    }
+}
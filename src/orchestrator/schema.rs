@@ -0,0 +1,407 @@
+//! Response schemas for structured model output
+//!
+//! Every stage that asks a model for JSON declares the shape it expects as a
+//! `ResponseSchema`. `ResponseSchema::parse` extracts the JSON from a raw
+//! response and validates it, returning each violation with the path it was
+//! found at, e.g. `$.steps[0].pipeline_id: expected integer, found string`.
+//! The orchestrator sends violations back to the model as a repair prompt and
+//! records the ones that remain in the stage's `StageResult`.
+//!
+//! The schemas are a subset of JSON Schema: `type` (a name or a list of
+//! names), `properties`, `required`, `items`, `enum`, `minimum`, `maximum`
+//! and `minItems`. Properties a schema does not list are allowed.
+
+use serde_json::{json, Value};
+
+/// Expected response of one kind of structured call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseSchema {
+    /// Stage 2: intents found in a chunk
+    Intents,
+    /// Stage 2: branches a methodology adds to the intents
+    Branches,
+    /// Stage 2: details and new branches found in a chunk
+    Details,
+    /// Stage 2: relationship between two branches
+    CrossReference,
+    /// Stage 2: roles of attached files
+    FileRoles,
+    /// Stage 2: methodology domains an AMT layer needs
+    MethodologyDomains,
+    /// Stage 2: a synthesized methodology
+    Methodology,
+    /// Stage 3: a new blueprint
+    Blueprint,
+    /// Stage 4: simulated execution of the blueprint
+    Simulation,
+    /// Stage 5: decision gate pipeline output
+    Gate,
+}
+
+impl ResponseSchema {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Intents => "intents",
+            Self::Branches => "branches",
+            Self::Details => "details",
+            Self::CrossReference => "cross_reference",
+            Self::FileRoles => "file_roles",
+            Self::MethodologyDomains => "methodology_domains",
+            Self::Methodology => "methodology",
+            Self::Blueprint => "blueprint",
+            Self::Simulation => "simulation",
+            Self::Gate => "gate",
+        }
+    }
+
+    /// The schema as a JSON Schema document
+    pub fn schema(&self) -> Value {
+        let string = json!({"type": "string"});
+        let strings = json!({"type": "array", "items": {"type": "string"}});
+        match self {
+            Self::Intents => json!({
+                "type": "object",
+                "required": ["new_intents"],
+                "properties": {
+                    "new_intents": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["intent"],
+                            "properties": {
+                                "intent": string,
+                                "is_parallel": {"type": "boolean"},
+                                "source_sentence": string
+                            }
+                        }
+                    }
+                }
+            }),
+            Self::Branches => json!({
+                "type": "object",
+                "required": ["branches"],
+                "properties": {
+                    "branches": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["branch", "parent_intent"],
+                            "properties": {
+                                "branch": string,
+                                "parent_intent": string,
+                                "rationale": string
+                            }
+                        }
+                    }
+                }
+            }),
+            Self::Details => json!({
+                "type": "object",
+                "required": ["details"],
+                "properties": {
+                    "details": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["content", "parent_branch"],
+                            "properties": {
+                                "content": string,
+                                "type": {"enum": ["detail", "requirement", "constraint"]},
+                                "parent_branch": string,
+                                "source_sentence": string
+                            }
+                        }
+                    },
+                    "new_branches": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["branch"],
+                            "properties": {
+                                "branch": string,
+                                "parent_intent": string,
+                                "source_sentence": string
+                            }
+                        }
+                    }
+                }
+            }),
+            Self::CrossReference => json!({
+                "type": "object",
+                "required": ["related"],
+                "properties": {
+                    "related": {"type": "boolean"},
+                    "relationship_type": {
+                        "enum": ["depends_on", "requires", "relates_to", "contradicts", "shared_context"]
+                    },
+                    "description": string
+                }
+            }),
+            Self::FileRoles => json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["file_path", "role"],
+                    "properties": {
+                        "file_path": string,
+                        "graph_id": {"type": "integer", "minimum": 0},
+                        "role": {"enum": ["primary", "supplementary", "raw_data"]},
+                        "reasoning": string
+                    }
+                }
+            }),
+            Self::MethodologyDomains => strings,
+            Self::Methodology => json!({
+                "type": "object",
+                "required": ["name", "description", "principles"],
+                "properties": {
+                    "name": string,
+                    "description": string,
+                    "category": string,
+                    "principles": strings,
+                    "keywords": strings
+                }
+            }),
+            Self::Blueprint => json!({
+                "type": "object",
+                "required": ["name", "steps"],
+                "properties": {
+                    "name": string,
+                    "description": string,
+                    "steps": {
+                        "type": "array",
+                        "minItems": 1,
+                        "items": {
+                            "type": "object",
+                            // Everything `BlueprintStep` cannot default
+                            "required": [
+                                "step_index", "action", "description", "pipeline_id",
+                                "context_requirements", "sub_steps", "depends_on",
+                                "wait_for_graph_update", "max_retries"
+                            ],
+                            "properties": {
+                                "step_index": {"type": "integer", "minimum": 0},
+                                "action": string,
+                                "description": string,
+                                "pipeline_id": {"type": "integer", "minimum": 0},
+                                "context_requirements": strings,
+                                "loop_config": {"type": ["object", "null"]},
+                                "sub_steps": {"type": "array", "items": {"type": "object"}},
+                                "depends_on": {
+                                    "type": "array",
+                                    "items": {"type": "integer", "minimum": 0}
+                                },
+                                "wait_for_graph_update": {"type": "boolean"},
+                                "max_retries": {"type": "integer", "minimum": 0},
+                                "timeout_ms": {"type": ["integer", "null"], "minimum": 0},
+                                "failure_policy": {"type": ["string", "object"]}
+                            }
+                        }
+                    },
+                    "missing_capabilities": strings
+                }
+            }),
+            Self::Simulation => json!({
+                "type": "object",
+                "required": ["simulation_confidence", "overall_feasibility"],
+                "properties": {
+                    "simulation_confidence": {"type": "number", "minimum": 0, "maximum": 1},
+                    "step_predictions": {"type": "array", "items": {"type": "object"}},
+                    "overall_feasibility": {"enum": ["high", "medium", "low"]},
+                    "clarifications_needed": strings
+                }
+            }),
+            Self::Gate => json!({
+                "type": "object",
+                "required": ["gate"],
+                "properties": {
+                    "gate": {
+                        "type": "object",
+                        "required": ["decision", "confidence"],
+                        "properties": {
+                            "decision": string,
+                            "confidence": {"type": "number", "minimum": 0, "maximum": 1},
+                            "reasoning": string
+                        }
+                    }
+                }
+            }),
+        }
+    }
+
+    /// Extract the JSON value from a model response and validate it
+    pub fn parse(&self, response: &str) -> Result<Value, Vec<String>> {
+        let schema = self.schema();
+        let (open, close) = if schema["type"] == "array" {
+            ('[', ']')
+        } else {
+            ('{', '}')
+        };
+        let trimmed = response.trim();
+        let json_str = match (trimmed.find(open), trimmed.rfind(close)) {
+            (Some(start), Some(end)) if end >= start => &trimmed[start..=end],
+            _ => trimmed,
+        };
+        let value: Value = serde_json::from_str(json_str)
+            .map_err(|e| vec![format!("$: not valid JSON ({})", e)])?;
+        self.validate(&value).map(|_| value)
+    }
+
+    /// Check an already parsed value
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<String>> {
+        let mut violations = Vec::new();
+        check(&self.schema(), value, "$", &mut violations);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    let actual = type_name(value);
+    actual == expected || (expected == "number" && actual == "integer")
+}
+
+fn check(schema: &Value, value: &Value, path: &str, violations: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type") {
+        let names: Vec<&str> = match expected {
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            other => other.as_str().into_iter().collect(),
+        };
+        if !names.iter().any(|name| has_type(value, name)) {
+            violations.push(format!(
+                "{}: expected {}, found {}",
+                path,
+                names.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            violations.push(format!(
+                "{}: {} is not one of {}",
+                path,
+                value,
+                allowed.join(", ")
+            ));
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < minimum {
+                violations.push(format!("{}: {} is below {}", path, number, minimum));
+            }
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > maximum {
+                violations.push(format!("{}: {} is above {}", path, number, maximum));
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        for field in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(field) {
+                violations.push(format!("{}: missing required field \"{}\"", path, field));
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (field, field_schema) in properties {
+                if let Some(field_value) = object.get(field) {
+                    check(
+                        field_schema,
+                        field_value,
+                        &format!("{}.{}", path, field),
+                        violations,
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        if let Some(min_items) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < min_items {
+                violations.push(format!(
+                    "{}: expected at least {} item(s), found {}",
+                    path,
+                    min_items,
+                    items.len()
+                ));
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                check(
+                    item_schema,
+                    item,
+                    &format!("{}[{}]", path, index),
+                    violations,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violations_carry_their_path() {
+        let response = r#"Here is the plan:
+{"name": "Plan", "steps": [{"step_index": 0, "action": "run", "description": "",
+"pipeline_id": "nine", "context_requirements": [], "sub_steps": [],
+"depends_on": [-1], "wait_for_graph_update": false}]}"#;
+        let violations = ResponseSchema::Blueprint.parse(response).unwrap_err();
+        assert_eq!(
+            violations,
+            vec![
+                "$.steps[0]: missing required field \"max_retries\"",
+                "$.steps[0].depends_on[0]: -1 is below 0",
+                "$.steps[0].pipeline_id: expected integer, found string",
+            ]
+        );
+
+        let simulation = ResponseSchema::Simulation
+            .parse(r#"{"simulation_confidence": 1, "overall_feasibility": "high"}"#)
+            .unwrap();
+        assert_eq!(simulation["overall_feasibility"], "high");
+        assert!(ResponseSchema::Simulation
+            .parse(r#"{"simulation_confidence": 1.5, "overall_feasibility": "sure"}"#)
+            .unwrap_err()
+            .iter()
+            .any(|v| v.contains("\"sure\" is not one of")));
+        assert_eq!(
+            ResponseSchema::MethodologyDomains.parse("Test response"),
+            Err(vec![
+                "$: not valid JSON (expected value at line 1 column 1)".to_string()
+            ])
+        );
+    }
+}
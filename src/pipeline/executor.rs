@@ -4,13 +4,14 @@
 //! This maintains separation between core (here) and pipeline logic (pipelines/).
 
 use crate::config::PipelineConfig;
-use crate::types::pipeline::ExecutionID;
+use crate::events::{EventBus, EventTopic};
+use crate::types::pipeline::{ExecutionID, Language};
 use crate::types::{
     BuiltinPipeline, OzoneError, OzoneResult, PipelineBlueprint, PipelineID, PipelineInput,
     PipelineOutput, TaskID, Value,
 };
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
 
@@ -54,6 +55,9 @@ pub struct PipelineExecutor {
 
     progress_map: Arc<tokio::sync::RwLock<std::collections::HashMap<String, PipelineProgress>>>,
     cancel_set: Arc<tokio::sync::RwLock<std::collections::HashSet<String>>>,
+
+    /// Event bus for pipeline progress events
+    events: Option<Arc<EventBus>>,
 }
 
 impl PipelineExecutor {
@@ -66,9 +70,30 @@ impl PipelineExecutor {
             running_count: std::sync::atomic::AtomicUsize::new(0),
             progress_map: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
             cancel_set: Arc::new(tokio::sync::RwLock::new(std::collections::HashSet::new())),
+            events: None,
         })
     }
 
+    /// Publish progress changes to the event bus
+    pub fn set_event_bus(&mut self, events: Arc<EventBus>) {
+        self.events = Some(events);
+    }
+
+    /// Publish the current progress of an execution, scoped to its user
+    async fn publish_progress(&self, execution_id: &str, kind: &str, user_id: u64) {
+        if let Some(events) = &self.events {
+            if let Some(progress) = self.progress_map.read().await.get(execution_id) {
+                events.publish(
+                    EventTopic::Pipeline,
+                    kind,
+                    Some(user_id),
+                    progress.task_id,
+                    serde_json::to_value(progress).unwrap_or_default(),
+                );
+            }
+        }
+    }
+
     /// Execute a pipeline
     pub async fn execute(
         &self,
@@ -76,8 +101,21 @@ impl PipelineExecutor {
         input: PipelineInput,
         task_id: Option<TaskID>,
     ) -> OzoneResult<PipelineOutput> {
-        let execution_id = ExecutionID::new();
+        self.execute_as(ExecutionID::new(), blueprint, input, task_id)
+            .await
+    }
+
+    /// Execute a pipeline under a given execution ID, so its progress can
+    /// be followed while it runs
+    pub async fn execute_as(
+        &self,
+        execution_id: ExecutionID,
+        blueprint: &PipelineBlueprint,
+        input: PipelineInput,
+        task_id: Option<TaskID>,
+    ) -> OzoneResult<PipelineOutput> {
         let execution_id_str = execution_id.as_str().to_string();
+        let user_id = input.context.user_id;
 
         tracing::info!(
             pipeline = %blueprint.name,
@@ -129,6 +167,9 @@ impl PipelineExecutor {
             );
         }
 
+        self.publish_progress(&execution_id_str, "started", user_id)
+            .await;
+
         {
             let cancelled = self.cancel_set.read().await;
             if cancelled.contains(execution_id_str) {
@@ -139,6 +180,9 @@ impl PipelineExecutor {
                     p.status = ProgressStatus::Cancelled;
                     p.completed_at = Some(now_secs());
                 }
+                drop(map);
+                self.publish_progress(&execution_id_str, "cancelled", user_id)
+                    .await;
                 return Err(OzoneError::PipelineError(format!(
                     "Execution {} cancelled",
                     execution_id
@@ -172,6 +216,10 @@ impl PipelineExecutor {
             }
         }
 
+        let kind = if result.is_ok() { "completed" } else { "failed" };
+        self.publish_progress(&execution_id_str, kind, user_id)
+            .await;
+
         // Wrap result
         match result {
             Ok(mut output) => {
@@ -300,7 +348,7 @@ impl PipelineExecutor {
         execution_id: ExecutionID,
         task_id: Option<TaskID>,
     ) -> OzoneResult<PipelineOutput> {
-        let pipeline_path = self.custom_path.join(&blueprint.name);
+        let pipeline_path = self.custom_path.join(custom_file_name(blueprint));
 
         if !pipeline_path.exists() {
             tracing::error!(
@@ -439,6 +487,22 @@ impl PipelineExecutor {
         map.get(execution_id).cloned()
     }
 
+    /// Record progress of an execution running on another device
+    pub async fn record_progress(&self, progress: PipelineProgress, user_id: u64) {
+        let kind = match progress.status {
+            ProgressStatus::Completed => "completed",
+            ProgressStatus::Failed => "failed",
+            ProgressStatus::Cancelled => "cancelled",
+            ProgressStatus::Queued | ProgressStatus::Running => "progress",
+        };
+        let execution_id = progress.execution_id.clone();
+        self.progress_map
+            .write()
+            .await
+            .insert(execution_id.clone(), progress);
+        self.publish_progress(&execution_id, kind, user_id).await;
+    }
+
     /// Request cancellation of an execution
     pub async fn cancel(&self, execution_id: &str) -> bool {
         let map = self.progress_map.read().await;
@@ -456,6 +520,25 @@ impl PipelineExecutor {
         }
     }
 
+    /// Number of executions currently running
+    pub fn running_count(&self) -> usize {
+        self.running_count.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Request cancellation of every running or queued execution; returns how many
+    pub async fn cancel_all(&self) -> usize {
+        let ids: Vec<String> = self
+            .progress_map
+            .read()
+            .await
+            .values()
+            .filter(|p| matches!(p.status, ProgressStatus::Running | ProgressStatus::Queued))
+            .map(|p| p.execution_id.clone())
+            .collect();
+        self.cancel_set.write().await.extend(ids.iter().cloned());
+        ids.len()
+    }
+
     /// Clean up completed executions older than TTL
     pub async fn cleanup_old_progress(&self, ttl_secs: u64) {
         let now = now_secs();
@@ -477,6 +560,28 @@ impl PipelineExecutor {
     }
 }
 
+/// File a custom pipeline is installed as: its name, plus the extension
+/// `invoke_pipeline` picks the interpreter by when its executable
+/// implementation is a script
+pub fn custom_file_name(blueprint: &PipelineBlueprint) -> String {
+    let language = blueprint
+        .implementations
+        .iter()
+        .find(|implementation| implementation.executable)
+        .map(|implementation| &implementation.language);
+    let extension = match language {
+        Some(Language::Python) => "py",
+        Some(Language::JavaScript) => "js",
+        Some(Language::TypeScript) => "ts",
+        _ => return blueprint.name.clone(),
+    };
+    if Path::new(&blueprint.name).extension().is_some() {
+        blueprint.name.clone()
+    } else {
+        format!("{}.{}", blueprint.name, extension)
+    }
+}
+
 // Helper function - you should define this in a common utils module if not already present
 fn now_secs() -> u64 {
     std::time::SystemTime::now()
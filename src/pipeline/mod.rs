@@ -21,13 +21,41 @@ pub use store::*;
 
 use crate::config::PipelineConfig;
 use crate::types::pipeline::{
-    BuiltinPipeline, ExecutionContext, PipelineBlueprint, PipelineInput, PipelineOutput, Schema,
+    BuiltinPipeline, CodePointer, ExecutionContext, ExecutionID, PipelineBlueprint, PipelineInput,
+    PipelineOutput, Schema,
 };
 use crate::types::{OzoneError, OzoneResult, PipelineID, TaskID};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
+
+/// Where the code of pipelines received from peers comes from
+/// (implemented by the network)
+#[async_trait::async_trait]
+pub trait CodeSource: Send + Sync {
+    /// Assemble the code a pointer describes, verified against its hash
+    async fn fetch_code(&self, pointer: &CodePointer) -> OzoneResult<Vec<u8>>;
+
+    /// Whether network consensus has accepted this code
+    async fn code_accepted(&self, pointer: &CodePointer) -> bool;
+}
+
+/// Runs pipelines on this account's other devices (implemented by the
+/// network)
+#[async_trait::async_trait]
+pub trait Offloader: Send + Sync {
+    /// Run a pipeline on another device if one suits it better than this
+    /// one, sending the progress it reports to `progress`; `None` to run
+    /// it here
+    async fn offload(
+        &self,
+        blueprint: &PipelineBlueprint,
+        input: &PipelineInput,
+        task_id: Option<TaskID>,
+        progress: mpsc::UnboundedSender<PipelineProgress>,
+    ) -> Option<OzoneResult<PipelineOutput>>;
+}
 
 /// Pipeline registry - manages available pipelines
 pub struct PipelineRegistry {
@@ -45,6 +73,12 @@ pub struct PipelineRegistry {
 
     /// Custom pipeline path
     custom_path: PathBuf,
+
+    /// Fetches code for custom pipelines that are not installed
+    code_source: Option<Arc<dyn CodeSource>>,
+
+    /// Picks other devices to run pipelines on
+    offloader: Option<Arc<dyn Offloader>>,
 }
 
 impl PipelineRegistry {
@@ -90,6 +124,8 @@ impl PipelineRegistry {
             executor,
             builtin_path,
             custom_path,
+            code_source: None,
+            offloader: None,
         })
     }
 
@@ -162,6 +198,9 @@ impl PipelineRegistry {
     }
 
     /// Execute a pipeline
+    ///
+    /// Runs on another device when the offloader picks one, and locally if
+    /// that device fails or does not finish in time.
     pub async fn execute(
         &self,
         pipeline_id: PipelineID,
@@ -173,9 +212,91 @@ impl PipelineRegistry {
             .get(&pipeline_id)
             .ok_or_else(|| OzoneError::NotFound(format!("Pipeline {} not found", pipeline_id)))?;
 
+        if let Some(offloader) = &self.offloader {
+            match self
+                .offload(offloader.as_ref(), blueprint, &input, task_id)
+                .await
+            {
+                Some(Ok(output)) => return Ok(output),
+                Some(Err(e)) => tracing::warn!(
+                    "Offloaded pipeline {} failed, running it locally: {}",
+                    pipeline_id,
+                    e
+                ),
+                None => {}
+            }
+        }
+
+        self.check_accepted(blueprint).await?;
         self.executor.execute(blueprint, input, task_id).await
     }
 
+    /// Offload a run, recording the progress the other device reports
+    async fn offload(
+        &self,
+        offloader: &dyn Offloader,
+        blueprint: &PipelineBlueprint,
+        input: &PipelineInput,
+        task_id: Option<TaskID>,
+    ) -> Option<OzoneResult<PipelineOutput>> {
+        let (progress, mut updates) = mpsc::unbounded_channel();
+        let offload = offloader.offload(blueprint, input, task_id, progress);
+        tokio::pin!(offload);
+        loop {
+            tokio::select! {
+                result = &mut offload => return result,
+                Some(update) = updates.recv() => {
+                    self.executor
+                        .record_progress(update, input.context.user_id)
+                        .await
+                }
+            }
+        }
+    }
+
+    /// Run a pipeline for another device of this account, registering its
+    /// blueprint first if this device does not know the pipeline. The run
+    /// is never offloaded again.
+    pub async fn execute_offloaded(
+        &self,
+        execution_id: ExecutionID,
+        blueprint: PipelineBlueprint,
+        input: PipelineInput,
+        task_id: Option<TaskID>,
+    ) -> OzoneResult<PipelineOutput> {
+        let blueprint = match self.get_blueprint(blueprint.pipeline_id).await {
+            Some(known) => known,
+            None => {
+                self.register_custom(blueprint.clone()).await?;
+                blueprint
+            }
+        };
+        self.check_accepted(&blueprint).await?;
+        self.executor
+            .execute_as(execution_id, &blueprint, input, task_id)
+            .await
+    }
+
+    /// Publish pipeline progress events to the event bus
+    pub fn set_event_bus(&mut self, events: Arc<crate::events::EventBus>) {
+        self.executor.set_event_bus(events);
+    }
+
+    /// Install custom pipeline code from the network
+    pub fn set_code_source(&mut self, source: Arc<dyn CodeSource>) {
+        self.code_source = Some(source);
+    }
+
+    /// Run pipelines on other devices of this account
+    pub fn set_offloader(&mut self, offloader: Arc<dyn Offloader>) {
+        self.offloader = Some(offloader);
+    }
+
+    /// Get the pipeline executor (progress tracking and cancellation)
+    pub fn executor(&self) -> &PipelineExecutor {
+        &self.executor
+    }
+
     /// Get pipeline blueprint
     pub async fn get_blueprint(&self, pipeline_id: PipelineID) -> Option<PipelineBlueprint> {
         self.blueprints.read().await.get(&pipeline_id).cloned()
@@ -192,9 +313,131 @@ impl PipelineRegistry {
     }
 
     /// Register a custom pipeline
+    ///
+    /// A blueprint with an executable implementation, such as one received
+    /// from a peer, has its code installed in the custom path unless the
+    /// installed file already matches the code's hash. The code is not run
+    /// until network consensus accepts it.
     pub async fn register_custom(&self, blueprint: PipelineBlueprint) -> OzoneResult<PipelineID> {
+        if let Some(implementation) = blueprint.implementations.iter().find(|i| i.executable) {
+            let file_name = custom_file_name(&blueprint);
+            let mut components = Path::new(&file_name).components();
+            if !matches!(
+                (components.next(), components.next()),
+                (Some(Component::Normal(_)), None)
+            ) {
+                return Err(OzoneError::ValidationError(format!(
+                    "Invalid pipeline name: {}",
+                    blueprint.name
+                )));
+            }
+            let path = self.custom_path.join(file_name);
+            if !self.install(&path, &implementation.code_location).await? {
+                tracing::info!(
+                    "Pipeline {} installed, awaiting consensus before it runs",
+                    blueprint.name
+                );
+            }
+        }
+
         let id = blueprint.pipeline_id;
         self.blueprints.write().await.insert(id, blueprint);
         Ok(id)
     }
+
+    /// Refuse to run a custom pipeline's code until network consensus has
+    /// accepted it
+    async fn check_accepted(&self, blueprint: &PipelineBlueprint) -> OzoneResult<()> {
+        let Some(implementation) = blueprint.implementations.iter().find(|i| i.executable) else {
+            return Ok(());
+        };
+        let path = self.custom_path.join(custom_file_name(blueprint));
+        if !self.install(&path, &implementation.code_location).await? {
+            return Err(OzoneError::PermissionDenied(format!(
+                "Code of pipeline {} has not been accepted by network consensus",
+                blueprint.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Write pipeline code to `path`, fetching it if the file there differs.
+    /// The file is only made executable once consensus has accepted the
+    /// code; returns whether it has.
+    async fn install(&self, path: &Path, pointer: &CodePointer) -> OzoneResult<bool> {
+        let source = self.code_source.as_ref().ok_or_else(|| {
+            OzoneError::PipelineError(format!("No code source to install {}", path.display()))
+        })?;
+        let accepted = source.code_accepted(pointer).await;
+        let installed = std::fs::read(path).ok();
+        if installed.is_some_and(|code| *blake3::hash(&code).as_bytes() == pointer.hash) {
+            set_executable(path, accepted)?;
+            return Ok(accepted);
+        }
+
+        let code = source.fetch_code(pointer).await?;
+        if *blake3::hash(&code).as_bytes() != pointer.hash {
+            return Err(OzoneError::IntegrityError(format!(
+                "Code for {} does not match its hash",
+                path.display()
+            )));
+        }
+
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, &code)?;
+        set_executable(&tmp, accepted)?;
+        std::fs::rename(&tmp, path)?;
+        tracing::info!("Installed pipeline code at {}", path.display());
+        Ok(accepted)
+    }
+}
+
+/// Lets the orchestrator run pipelines from the registry; its inputs are
+/// JSON objects and run with the system's context
+#[async_trait::async_trait]
+impl crate::orchestrator::PipelineExecutor for RwLock<PipelineRegistry> {
+    async fn execute(
+        &self,
+        pipeline_id: u64,
+        input: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let data = serde_json::from_value(input)
+            .map_err(|e| format!("Input of pipeline {} is not an object: {}", pipeline_id, e))?;
+        let input = PipelineInput {
+            data,
+            context: ExecutionContext {
+                user_id: 0,
+                device_id: 0,
+                workspace_id: None,
+                project_id: None,
+                task_context_id: None,
+                metadata: HashMap::new(),
+            },
+        };
+        let output = self
+            .read()
+            .await
+            .execute(pipeline_id, input, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        if !output.success {
+            return Err(output
+                .error
+                .unwrap_or_else(|| format!("Pipeline {} failed", pipeline_id)));
+        }
+        serde_json::to_value(output.data).map_err(|e| e.to_string())
+    }
+}
+
+/// Make installed code executable, or only readable
+fn set_executable(path: &Path, executable: bool) -> OzoneResult<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = if executable { 0o755 } else { 0o644 };
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, executable);
+    Ok(())
 }
@@ -29,6 +29,7 @@
 //! - Cross-references and deduplicates
 
 use crate::config::TaskConfig;
+use crate::events::{EventBus, EventTopic};
 use crate::types::{
     ContainerID, DeviceID, LogEntry, LogLevel, OzoneError, OzoneResult, PipelineID, ResourceUsage,
     Task, TaskExecutionState, TaskID, TaskInput, TaskOutput, TaskStatus, UserID,
@@ -558,6 +559,9 @@ struct TaskStoreData {
 // ============================================================================
 
 /// Task manager - tracks all task executions with queue support
+///
+/// Clones share the same tasks, logs and queue.
+#[derive(Clone)]
 pub struct TaskManager {
     /// Configuration
     config: TaskQueueConfig,
@@ -571,6 +575,9 @@ pub struct TaskManager {
     /// Task logs
     logs: Arc<RwLock<HashMap<TaskID, Vec<LogEntry>>>>,
 
+    /// Entries dropped from the front of each task's logs by the size cap
+    logs_dropped: Arc<RwLock<HashMap<TaskID, usize>>>,
+
     /// Task execution states
     execution_states: Arc<RwLock<HashMap<TaskID, TaskExecutionState>>>,
 
@@ -594,6 +601,9 @@ pub struct TaskManager {
 
     /// Last refinement run timestamp
     last_refinement: Arc<RwLock<u64>>,
+
+    /// Event bus for task lifecycle events
+    events: Option<Arc<EventBus>>,
 }
 
 impl TaskManager {
@@ -606,6 +616,7 @@ impl TaskManager {
             refinement_config,
             tasks: Arc::new(RwLock::new(HashMap::new())),
             logs: Arc::new(RwLock::new(HashMap::new())),
+            logs_dropped: Arc::new(RwLock::new(HashMap::new())),
             execution_states: Arc::new(RwLock::new(HashMap::new())),
             queue: Arc::new(RwLock::new(VecDeque::new())),
             running: Arc::new(RwLock::new(Vec::new())),
@@ -614,6 +625,7 @@ impl TaskManager {
             queue_running: Arc::new(RwLock::new(false)),
             refinement_running: Arc::new(RwLock::new(false)),
             last_refinement: Arc::new(RwLock::new(0)),
+            events: None,
         };
 
         // Load from disk
@@ -622,16 +634,57 @@ impl TaskManager {
         Ok(manager)
     }
 
+    /// Publish task lifecycle events to the event bus
+    pub fn set_event_bus(&mut self, events: Arc<EventBus>) {
+        self.events = Some(events);
+    }
+
+    /// Publish a task event scoped to the task's owner
+    async fn publish_event(&self, task_id: TaskID, kind: &str, data: serde_json::Value) {
+        if let Some(events) = &self.events {
+            let user_id = self.tasks.read().await.get(&task_id).map(|t| t.user_id);
+            events.publish(EventTopic::Task, kind, user_id, Some(task_id), data);
+        }
+    }
+
     pub async fn active_count(&self) -> usize {
         self.running.read().await.len()
     }
 
+    /// IDs of tasks that are queued or running
+    pub async fn unfinished_tasks(&self) -> Vec<TaskID> {
+        let mut ids: Vec<TaskID> = self
+            .tasks
+            .read()
+            .await
+            .values()
+            .filter(|t| is_unfinished(&t.status))
+            .map(|t| t.task_id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Fail every queued or running task with `reason` so it can be retried
+    /// after a restart; returns the interrupted task IDs
+    pub async fn interrupt_unfinished(&self, reason: &str) -> Vec<TaskID> {
+        self.queue.write().await.clear();
+        let ids = self.unfinished_tasks().await;
+        for &task_id in &ids {
+            if let Err(e) = self.fail_task(task_id, reason.to_string()).await {
+                tracing::warn!("Failed to interrupt task {}: {}", task_id, e);
+            }
+        }
+        ids
+    }
+
     /// Load tasks from disk (sync version for initialization)
     fn load_from_disk_sync(&self) {
         let path = Path::new(&self.storage_path);
         if path.exists() {
             if let Ok(content) = std::fs::read_to_string(path.join("tasks.json")) {
-                if let Ok(data) = serde_json::from_str::<TaskStoreData>(&content) {
+                if let Ok(mut data) = serde_json::from_str::<TaskStoreData>(&content) {
+                    recover_unfinished(&mut data);
                     if let Ok(mut tasks) = self.tasks.try_write() {
                         *tasks = data.tasks;
                     }
@@ -646,6 +699,11 @@ impl TaskManager {
         }
     }
 
+    /// Write task state to disk
+    pub async fn persist(&self) -> OzoneResult<()> {
+        self.save_to_disk().await
+    }
+
     /// Save tasks to disk
     async fn save_to_disk(&self) -> OzoneResult<()> {
         let path = Path::new(&self.storage_path);
@@ -796,6 +854,16 @@ impl TaskManager {
         // Save to disk
         let _ = self.save_to_disk().await;
 
+        if let Some(events) = &self.events {
+            events.publish(
+                EventTopic::Task,
+                "queued",
+                Some(user_id),
+                Some(task_id),
+                serde_json::json!({ "priority": priority, "blueprint_id": blueprint_id }),
+            );
+        }
+
         tracing::info!("Enqueued task {} with priority {:?}", task_id, priority);
 
         Ok(task_id)
@@ -816,6 +884,7 @@ impl TaskManager {
         let running_tasks = Arc::clone(&self.running);
         let queue_running = Arc::clone(&self.queue_running);
         let config = self.config.clone();
+        let events = self.events.clone();
 
         tokio::spawn(async move {
             loop {
@@ -859,6 +928,16 @@ impl TaskManager {
                         });
                     }
 
+                    if let Some(events) = &events {
+                        events.publish(
+                            EventTopic::Task,
+                            "started",
+                            Some(queued_task.user_id),
+                            Some(task_id),
+                            serde_json::json!({}),
+                        );
+                    }
+
                     tracing::info!("Started task {}", task_id);
 
                     // Note: Actual task execution is handled by the orchestrator
@@ -875,6 +954,38 @@ impl TaskManager {
         *self.queue_running.write().await = false;
     }
 
+    /// Mark a queued task as running when it is executed directly rather
+    /// than picked up by the queue processor
+    pub async fn mark_running(&self, task_id: TaskID) -> OzoneResult<()> {
+        self.queue.write().await.retain(|t| t.task_id != task_id);
+
+        {
+            let mut tasks = self.tasks.write().await;
+            let task = tasks
+                .get_mut(&task_id)
+                .ok_or_else(|| OzoneError::NotFound(format!("Task {} not found", task_id)))?;
+            task.status = "running".to_string();
+            task.started_at = Some(now());
+        }
+
+        {
+            let mut running = self.running.write().await;
+            if !running.contains(&task_id) {
+                running.push(task_id);
+            }
+        }
+
+        self.add_log(task_id, LogLevel::Info, "Task started".to_string())
+            .await?;
+
+        self.publish_event(task_id, "started", serde_json::json!({}))
+            .await;
+
+        tracing::info!("Started task {}", task_id);
+
+        Ok(())
+    }
+
     /// Mark a task as completed
     pub async fn complete_task(
         &self,
@@ -939,6 +1050,13 @@ impl TaskManager {
         // Save to disk
         let _ = self.save_to_disk().await;
 
+        self.publish_event(
+            task_id,
+            "completed",
+            serde_json::json!({ "total_tokens": total_tokens }),
+        )
+        .await;
+
         tracing::info!("Completed task {}", task_id);
 
         Ok(())
@@ -1001,6 +1119,9 @@ impl TaskManager {
         // Save to disk
         let _ = self.save_to_disk().await;
 
+        self.publish_event(task_id, "failed", serde_json::json!({ "error": error }))
+            .await;
+
         tracing::error!("Task {} failed: {}", task_id, error);
 
         Ok(())
@@ -1029,19 +1150,34 @@ impl TaskManager {
             0.0
         };
 
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
-            task.progress = progress;
+        {
+            let mut tasks = self.tasks.write().await;
+            if let Some(task) = tasks.get_mut(&task_id) {
+                task.progress = progress;
+            }
         }
 
         // Also update execution state
-        let mut states = self.execution_states.write().await;
-        if let Some(state) = states.get_mut(&task_id) {
-            state.current_step = current_step;
-            state.total_steps = total_steps;
-            state.last_updated = now();
+        {
+            let mut states = self.execution_states.write().await;
+            if let Some(state) = states.get_mut(&task_id) {
+                state.current_step = current_step;
+                state.total_steps = total_steps;
+                state.last_updated = now();
+            }
         }
 
+        self.publish_event(
+            task_id,
+            "progress",
+            serde_json::json!({
+                "progress": progress,
+                "current_step": current_step,
+                "total_steps": total_steps,
+            }),
+        )
+        .await;
+
         Ok(())
     }
 
@@ -1166,8 +1302,10 @@ impl TaskManager {
         });
 
         // Keep last 1000 logs per task
-        while task_logs.len() > 1000 {
-            task_logs.remove(0);
+        let excess = task_logs.len().saturating_sub(1000);
+        if excess > 0 {
+            task_logs.drain(..excess);
+            *self.logs_dropped.write().await.entry(task_id).or_insert(0) += excess;
         }
 
         Ok(())
@@ -1208,6 +1346,20 @@ impl TaskManager {
         results.into_iter().skip(offset).take(limit).collect()
     }
 
+    /// Count tasks matching the same filters as `list_tasks`
+    pub async fn count_tasks(
+        &self,
+        status_filter: Option<&str>,
+        user_filter: Option<UserID>,
+    ) -> usize {
+        let tasks = self.tasks.read().await;
+        tasks
+            .values()
+            .filter(|t| status_filter.map(|s| t.status == s).unwrap_or(true))
+            .filter(|t| user_filter.map(|u| t.user_id == u).unwrap_or(true))
+            .count()
+    }
+
     /// Get task logs
     pub async fn get_logs(&self, task_id: TaskID, limit: Option<usize>) -> Vec<LogEntry> {
         let logs = self.logs.read().await;
@@ -1219,6 +1371,30 @@ impl TaskManager {
             .unwrap_or_default()
     }
 
+    /// Get task logs from position `offset` on, oldest first, with the
+    /// position to continue from. Positions count every entry ever logged
+    /// for the task, so they stay valid after the cap drops old entries.
+    pub async fn get_logs_since(&self, task_id: TaskID, offset: usize) -> (Vec<LogEntry>, usize) {
+        let logs = self.logs.read().await;
+        let dropped = self
+            .logs_dropped
+            .read()
+            .await
+            .get(&task_id)
+            .copied()
+            .unwrap_or(0);
+        match logs.get(&task_id) {
+            Some(l) => (
+                l.iter()
+                    .skip(offset.saturating_sub(dropped))
+                    .cloned()
+                    .collect(),
+                dropped + l.len(),
+            ),
+            None => (Vec::new(), offset),
+        }
+    }
+
     /// Get task timeline
     pub async fn get_timeline(&self, task_id: TaskID) -> Vec<TimelineEvent> {
         let tasks = self.tasks.read().await;
@@ -1298,7 +1474,7 @@ impl TaskManager {
             let mut tasks = self.tasks.write().await;
             if let Some(task) = tasks.get_mut(&task_id) {
                 if task.status == "completed" || task.status == "failed" {
-                    return Err(OzoneError::TaskError(format!(
+                    return Err(OzoneError::Conflict(format!(
                         "Task {} is already finished",
                         task_id
                     )));
@@ -1321,6 +1497,9 @@ impl TaskManager {
         // Save to disk
         let _ = self.save_to_disk().await;
 
+        self.publish_event(task_id, "cancelled", serde_json::json!({}))
+            .await;
+
         tracing::info!("Cancelled task {}", task_id);
 
         Ok(())
@@ -1393,6 +1572,7 @@ impl TaskManager {
 
         let mut tasks = self.tasks.write().await;
         let mut logs = self.logs.write().await;
+        let mut logs_dropped = self.logs_dropped.write().await;
 
         let to_remove: Vec<TaskID> = tasks
             .iter()
@@ -1408,10 +1588,12 @@ impl TaskManager {
         for id in &to_remove {
             tasks.remove(id);
             logs.remove(id);
+            logs_dropped.remove(id);
         }
 
         drop(tasks);
         drop(logs);
+        drop(logs_dropped);
 
         let _ = self.save_to_disk();
 
@@ -1656,6 +1838,34 @@ fn now() -> u64 {
         .as_secs()
 }
 
+fn is_unfinished(status: &str) -> bool {
+    status == "queued" || status == "running"
+}
+
+/// Tasks left queued or running by a previous process can never finish
+/// (the queue and executions lived in memory); fail them so they can be retried
+fn recover_unfinished(data: &mut TaskStoreData) {
+    const REASON: &str = "Interrupted: the runtime stopped before the task finished";
+    for task in data.tasks.values_mut() {
+        if !is_unfinished(&task.status) {
+            continue;
+        }
+        task.status = "failed".to_string();
+        task.completed_at = Some(now());
+        task.error = Some(REASON.to_string());
+        data.logs.entry(task.task_id).or_default().push(LogEntry {
+            timestamp: now(),
+            level: LogLevel::Warn,
+            message: REASON.to_string(),
+            metadata: std::collections::HashMap::new(),
+        });
+        tracing::warn!(
+            "Task {} was interrupted by a previous shutdown",
+            task.task_id
+        );
+    }
+}
+
 // ============================================================================
 // TESTS
 // ============================================================================
@@ -1712,6 +1922,78 @@ mod tests {
         assert_eq!(task.total_tokens, 100);
     }
 
+    #[tokio::test]
+    async fn test_unfinished_tasks_fail_on_reload() {
+        let storage_path = "/tmp/test_tasks_reload".to_string();
+        let _ = std::fs::remove_dir_all(&storage_path);
+        let config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: storage_path.clone(),
+            ..Default::default()
+        };
+
+        let manager = TaskManager::new(config.clone(), RefinementConfig::default()).unwrap();
+        let task_id = manager
+            .enqueue_task(None, HashMap::new(), 1, 1, None, None, TaskPriority::Normal)
+            .await
+            .unwrap();
+        manager.persist().await.unwrap();
+        assert_eq!(manager.unfinished_tasks().await, vec![task_id]);
+
+        let reloaded = TaskManager::new(config, RefinementConfig::default()).unwrap();
+        let task = reloaded.get_task(task_id).await.unwrap();
+        assert_eq!(task.status, "failed");
+        assert!(reloaded.unfinished_tasks().await.is_empty());
+        assert!(reloaded.retry_task(task_id).await.is_ok());
+
+        let _ = std::fs::remove_dir_all(&storage_path);
+    }
+
+    #[tokio::test]
+    async fn test_logs_since_survive_cap() {
+        let storage_path = std::env::temp_dir()
+            .join(format!("ozone-test-logs-{}", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        let config = TaskQueueConfig {
+            consciousness_enabled: false,
+            storage_path: storage_path.clone(),
+            ..Default::default()
+        };
+        let manager = TaskManager::new(config, RefinementConfig::default()).unwrap();
+        let task_id = manager
+            .enqueue_task(None, HashMap::new(), 1, 1, None, None, TaskPriority::Normal)
+            .await
+            .unwrap();
+
+        let (_, mut offset) = manager.get_logs_since(task_id, 0).await;
+        let mut seen = Vec::new();
+        for i in 0..1500 {
+            manager
+                .add_log(task_id, LogLevel::Info, format!("line {}", i))
+                .await
+                .unwrap();
+            if i % 400 == 0 {
+                let (logs, next) = manager.get_logs_since(task_id, offset).await;
+                seen.extend(logs.into_iter().map(|l| l.message));
+                offset = next;
+            }
+        }
+        let (logs, _) = manager.get_logs_since(task_id, offset).await;
+        seen.extend(logs.into_iter().map(|l| l.message));
+
+        // Lines that were dropped before being read are skipped, the rest
+        // arrive once each and in order
+        assert_eq!(seen.first().map(String::as_str), Some("line 0"));
+        assert_eq!(seen.last().map(String::as_str), Some("line 1499"));
+        assert!(seen.windows(2).all(|w| {
+            let n = |s: &str| s[5..].parse::<u32>().unwrap();
+            n(&w[0]) < n(&w[1])
+        }));
+
+        let _ = std::fs::remove_dir_all(&storage_path);
+    }
+
     #[test]
     fn test_task_priority_ordering() {
         assert!(TaskPriority::Critical > TaskPriority::High);
@@ -99,6 +99,7 @@ pub enum DeviceStatus {
 
 /// Resource allocation for a device
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct ResourceAllocation {
     pub cpu_cores: u8,
     pub memory_gb: f32,
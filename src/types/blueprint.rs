@@ -54,7 +54,7 @@ pub struct TaskSignature {
 }
 
 /// Blueprint step
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BlueprintStep {
     pub step_id: u64,
     pub order: u32,
@@ -68,7 +68,7 @@ pub struct BlueprintStep {
 }
 
 /// Step input
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StepInput {
     pub name: String,
     pub source: StepInputSource,
@@ -76,7 +76,7 @@ pub struct StepInput {
 }
 
 /// Step input source
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StepInputSource {
     TaskInput(String),
     PreviousStep(u64, String),
@@ -85,7 +85,7 @@ pub enum StepInputSource {
 }
 
 /// Step output
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StepOutput {
     pub name: String,
     pub description: String,
@@ -93,14 +93,14 @@ pub struct StepOutput {
 }
 
 /// Step condition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StepCondition {
     pub condition: String,
     pub skip_if_false: bool,
 }
 
 /// Blueprint dependency
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BlueprintDependency {
     pub step_id: u64,
     pub depends_on: Vec<u64>,
@@ -117,7 +117,7 @@ pub enum BlueprintDependencyType {
 }
 
 /// Blueprint modification (§14.4)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BlueprintModification {
     AddStep(BlueprintStep),
     RemoveStep(u64),
@@ -129,7 +129,7 @@ pub enum BlueprintModification {
 }
 
 /// Step update for partial modifications
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct StepUpdate {
     pub action: Option<String>,
     pub description: Option<String>,
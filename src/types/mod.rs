@@ -108,7 +108,7 @@ pub enum Priority {
 }
 
 /// Semantic version
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct SemVer {
     pub major: u16,
     pub minor: u16,
@@ -131,6 +131,28 @@ impl std::fmt::Display for SemVer {
     }
 }
 
+impl std::str::FromStr for SemVer {
+    type Err = OzoneError;
+
+    /// Parse "major.minor.patch", with or without a leading "v"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || OzoneError::ValidationError(format!("Invalid version: {}", s));
+        let mut parts = s.trim().trim_start_matches('v').split('.');
+        let mut next = || -> Result<u16, OzoneError> {
+            parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)
+        };
+        let version = Self {
+            major: next()?,
+            minor: next()?,
+            patch: next()?,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(version)
+    }
+}
+
 /// Blake3 hash type alias
 pub type Blake3Hash = [u8; 32];
 
@@ -197,6 +219,9 @@ pub enum OzoneError {
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -214,4 +239,7 @@ pub enum OzoneError {
 
     #[error("Consciousness error: {0}")]
     ConsciousnessError(String),
+
+    #[error("Model error: {0}")]
+    ModelError(String),
 }
@@ -0,0 +1,214 @@
+//! ZSEI as the orchestrator sees it
+//!
+//! `PromptOrchestrator` reads and writes containers as JSON. Fields the
+//! typed local state has a place for (type, modality, name, keywords,
+//! topics, categories, methodologies) go into the container; the rest of
+//! its `context` and `storage` — a blueprint's steps and statistics, a
+//! methodology's principles — is kept in the container's object file and
+//! merged back into `local_state` when the container is read.
+
+use super::ZSEI;
+use crate::orchestrator::ZSEIAccess;
+use crate::types::container::{Container, ContainerType, GlobalState, LocalState, Modality};
+use crate::types::zsei::{TraversalRequest, ZSEIQuery};
+use crate::types::OzoneResult;
+use serde_json::{Map, Value};
+use tokio::sync::RwLock;
+
+/// Parts of the local state that are partly kept in the object file
+const OBJECT_PARTS: [&str; 2] = ["context", "storage"];
+
+#[async_trait::async_trait]
+impl ZSEIAccess for RwLock<ZSEI> {
+    async fn query(&self, query: Value) -> Result<Value, String> {
+        let query: ZSEIQuery = serde_json::from_value(query).map_err(|e| e.to_string())?;
+        let result = self.read().await.query(query).await.map_err(|e| e.to_string())?;
+        serde_json::to_value(result).map_err(|e| e.to_string())
+    }
+
+    async fn traverse(&self, request: Value) -> Result<Value, String> {
+        let request: TraversalRequest =
+            serde_json::from_value(request).map_err(|e| e.to_string())?;
+        let result = self
+            .read()
+            .await
+            .traverse(request)
+            .await
+            .map_err(|e| e.to_string())?;
+        serde_json::to_value(result).map_err(|e| e.to_string())
+    }
+
+    async fn create_container(&self, parent_id: u64, container: Value) -> Result<u64, String> {
+        let container_type: ContainerType =
+            serde_json::from_value(container["container_type"].clone())
+                .map_err(|e| format!("Invalid container type: {}", e))?;
+        let zsei = self.write().await;
+        let id = zsei.allocate_id().await;
+
+        let mut local_state = LocalState::default();
+        local_state.metadata.container_type = container_type;
+        local_state.metadata.modality =
+            serde_json::from_value(container["modality"].clone()).unwrap_or(Modality::Unknown);
+        local_state.metadata.created_at = now();
+        local_state.metadata.updated_at = now();
+        if let Some(created_by) = container["metadata"]["created_by"].as_str() {
+            local_state.metadata.provenance = created_by.to_string();
+        }
+        let mut created = Container {
+            global_state: GlobalState {
+                container_id: id,
+                parent_id,
+                ..Default::default()
+            },
+            local_state,
+        };
+        let mut object = Map::new();
+        absorb(&mut created.local_state, &container, &mut object);
+        persist(&zsei, created, object).await.map_err(|e| e.to_string())?;
+
+        if let Some(mut parent) = zsei.get_container(parent_id).await.map_err(|e| e.to_string())? {
+            parent.global_state.child_ids.push(id);
+            parent.global_state.child_count = parent.global_state.child_ids.len() as u32;
+            zsei.store_container(parent).await.map_err(|e| e.to_string())?;
+        }
+        Ok(id)
+    }
+
+    async fn update_container(&self, container_id: u64, updates: Value) -> Result<(), String> {
+        // The write lock keeps the object file and container in step
+        let zsei = self.write().await;
+        let mut container = zsei
+            .get_container(container_id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Container {} not found", container_id))?;
+        let mut object = match zsei.load_object(&container).map_err(|e| e.to_string())? {
+            Some(Value::Object(object)) => object,
+            _ => Map::new(),
+        };
+        absorb(&mut container.local_state, &updates, &mut object);
+        container.local_state.metadata.updated_at = now();
+        container.global_state.version += 1;
+        persist(&zsei, container, object).await.map_err(|e| e.to_string())
+    }
+
+    async fn get_container(&self, container_id: u64) -> Result<Option<Value>, String> {
+        let zsei = self.read().await;
+        let Some(container) = zsei
+            .get_container(container_id)
+            .await
+            .map_err(|e| e.to_string())?
+        else {
+            return Ok(None);
+        };
+        let object = zsei.load_object(&container).map_err(|e| e.to_string())?;
+        let mut view = serde_json::to_value(&container).map_err(|e| e.to_string())?;
+        if let Some(Value::Object(object)) = object {
+            for part in OBJECT_PARTS {
+                let (Some(Value::Object(kept)), Some(Value::Object(typed))) =
+                    (object.get(part), view["local_state"].get_mut(part))
+                else {
+                    continue;
+                };
+                for (key, value) in kept {
+                    typed.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+        Ok(Some(view))
+    }
+
+    async fn search_by_keywords(
+        &self,
+        keywords: &[String],
+        container_type: Option<&str>,
+    ) -> Result<Vec<u64>, String> {
+        let container_type = container_type
+            .map(|t| serde_json::from_value(Value::String(t.to_string())))
+            .transpose()
+            .map_err(|e| format!("Invalid container type: {}", e))?;
+        self.read()
+            .await
+            .search_keywords(keywords, container_type)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn get_categories(&self, modality: &str) -> Result<Vec<u64>, String> {
+        let modality = serde_json::from_value(Value::String(modality.to_string()))
+            .unwrap_or(Modality::Unknown);
+        let result = self
+            .read()
+            .await
+            .query(ZSEIQuery::GetCategories {
+                modality,
+                parent_category: None,
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        match result {
+            crate::types::zsei::ZSEIQueryResult::Containers(ids) => Ok(ids),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Apply `fields` (`metadata`, `context` and `storage` objects) to a local
+/// state: typed fields are set on it, everything else of `context` and
+/// `storage` merges into `object`
+fn absorb(local_state: &mut LocalState, fields: &Value, object: &mut Map<String, Value>) {
+    let strings = |value: &Value| -> Option<Vec<String>> { serde_json::from_value(value.clone()).ok() };
+    let ids = |value: &Value| -> Option<Vec<u64>> { serde_json::from_value(value.clone()).ok() };
+
+    if let Some(name) = fields["metadata"]["name"].as_str() {
+        local_state.metadata.name = Some(name.to_string());
+    }
+    let context = &fields["context"];
+    let typed = &mut local_state.context;
+    if let Some(keywords) = strings(&context["keywords"]) {
+        typed.keywords = keywords;
+    }
+    if let Some(topics) = strings(&context["topics"]) {
+        typed.topics = topics;
+    }
+    if let Some(categories) = ids(&context["categories"]) {
+        typed.categories = categories;
+    }
+    if let Some(methodologies) = ids(&context["methodology_ids"]) {
+        typed.methodologies = methodologies;
+    }
+    if let Ok(embedding) = serde_json::from_value(context["embedding"].clone()) {
+        typed.embedding = Some(embedding);
+    }
+
+    for part in OBJECT_PARTS {
+        let Some(Value::Object(updates)) = fields.get(part) else {
+            continue;
+        };
+        let kept = object
+            .entry(part)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !kept.is_object() {
+            *kept = Value::Object(Map::new());
+        }
+        if let Value::Object(kept) = kept {
+            kept.extend(updates.clone());
+        }
+    }
+}
+
+/// Store a container with its object file, if it has one
+async fn persist(zsei: &ZSEI, mut container: Container, object: Map<String, Value>) -> OzoneResult<()> {
+    if !object.is_empty() {
+        zsei.store_object(&mut container, &Value::Object(object))?;
+    }
+    zsei.store_container(container).await?;
+    Ok(())
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -217,6 +217,26 @@ pub struct LLMRequestConfig {
     pub response_format: Option<String>,
 }
 
+/// Hooks run on the same providers as the orchestrator
+#[async_trait]
+impl<P: crate::llm::LlmProvider> LLMClient for P {
+    async fn complete(&self, prompt: &str, config: &LLMRequestConfig) -> OzoneResult<String> {
+        let request = crate::llm::LlmRequest {
+            model: config.model.clone(),
+            system: config
+                .response_format
+                .as_ref()
+                .map(|format| format!("Respond in {} only.", format)),
+            prompt: prompt.to_string(),
+            max_tokens: config.max_tokens as u32,
+            temperature: config.temperature,
+        };
+        crate::llm::LlmProvider::complete(self, &request)
+            .await
+            .map(|response| response.text)
+    }
+}
+
 impl ZSEIHookProcessor {
     /// Create a new hook processor
     pub fn new(config: HookConfig, llm_client: Arc<dyn LLMClient>) -> Self {
@@ -17,6 +17,7 @@
 //! - Context not copies (link files, store semantic meaning)
 //! - Zero-shot discovery (no task-specific training)
 
+mod access;
 mod storage;
 mod traversal;
 mod query;
@@ -26,10 +27,12 @@ pub use traversal::*;
 pub use query::*;
 
 use crate::config::ZSEIConfig;
-use crate::types::{ContainerID, OzoneResult};
-use crate::types::container::Container;
+use crate::events::{EventBus, EventTopic};
+use crate::types::{ContainerID, OzoneError, OzoneResult};
+use crate::types::container::{Container, ContainerType};
 use crate::types::zsei::{ZSEIQuery, ZSEIQueryResult, TraversalRequest, TraversalResult};
 use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -49,6 +52,9 @@ pub struct ZSEI {
     
     /// Query processor
     query_processor: Arc<RwLock<QueryProcessor>>,
+
+    /// Event bus for container change events
+    events: Option<Arc<EventBus>>,
 }
 
 impl ZSEI {
@@ -71,8 +77,14 @@ impl ZSEI {
             cache: Arc::new(RwLock::new(HashMap::new())),
             traversal,
             query_processor: Arc::new(RwLock::new(query_processor)),
+            events: None,
         })
     }
+
+    /// Publish container changes to the event bus
+    pub fn set_event_bus(&mut self, events: Arc<EventBus>) {
+        self.events = Some(events);
+    }
     
     /// Query ZSEI
     pub async fn query(&self, query: ZSEIQuery) -> OzoneResult<ZSEIQueryResult> {
@@ -109,6 +121,12 @@ impl ZSEI {
     /// Store a container
     pub async fn store_container(&self, container: Container) -> OzoneResult<ContainerID> {
         let id = container.global_state.container_id;
+        let owner = Some(container.local_state.metadata.owner_id).filter(|o| *o != 0);
+        let change = serde_json::json!({
+            "container_id": id,
+            "parent_id": container.global_state.parent_id,
+            "version": container.global_state.version,
+        });
         
         // Store to disk
         {
@@ -121,10 +139,115 @@ impl ZSEI {
             let mut cache = self.cache.write().await;
             cache.insert(id, container);
         }
+
+        if let Some(events) = &self.events {
+            events.publish(EventTopic::Zsei, "container_stored", owner, None, change);
+        }
         
         Ok(id)
     }
-    
+
+    /// Allocate a fresh container ID
+    pub async fn allocate_id(&self) -> ContainerID {
+        self.storage.write().await.allocate_id()
+    }
+
+    /// Delete a container and evict it from the cache
+    pub async fn delete_container(&self, id: ContainerID) -> OzoneResult<()> {
+        let owner = self
+            .get_container(id)
+            .await?
+            .map(|c| c.local_state.metadata.owner_id)
+            .filter(|o| *o != 0);
+        self.storage.write().await.delete(id)?;
+        self.cache.write().await.remove(&id);
+        if let Some(events) = &self.events {
+            events.publish(
+                EventTopic::Zsei,
+                "container_deleted",
+                owner,
+                None,
+                serde_json::json!({ "container_id": id }),
+            );
+        }
+        Ok(())
+    }
+
+    /// Containers of `container_type` whose name, keywords or topics
+    /// contain any of `keywords`, most matches first
+    pub async fn search_keywords(
+        &self,
+        keywords: &[String],
+        container_type: Option<ContainerType>,
+    ) -> OzoneResult<Vec<ContainerID>> {
+        let keywords: Vec<String> = keywords.iter().map(|k| k.to_lowercase()).collect();
+        let storage = self.storage.read().await;
+        let mut matches: Vec<(ContainerID, usize)> = Vec::new();
+        for id in storage.all_ids() {
+            let Some(container) = storage.load(id)? else {
+                continue;
+            };
+            let local = &container.local_state;
+            if container_type.is_some_and(|t| t != local.metadata.container_type) {
+                continue;
+            }
+            let terms: Vec<String> = local
+                .context
+                .keywords
+                .iter()
+                .chain(&local.context.topics)
+                .chain(&local.metadata.name)
+                .map(|t| t.to_lowercase())
+                .collect();
+            let hits = keywords
+                .iter()
+                .filter(|k| terms.iter().any(|t| t == *k))
+                .count();
+            if hits > 0 {
+                matches.push((id, hits));
+            }
+        }
+        matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Ok(matches.into_iter().map(|(id, _)| id).collect())
+    }
+
+    /// Read the JSON object a container's `object_store_path` names
+    pub fn load_object(&self, container: &Container) -> OzoneResult<Option<serde_json::Value>> {
+        read_object(Path::new(&self.config.local_path), container)
+    }
+
+    /// Write a container's object, pointing its `object_store_path` at
+    /// `objects/<id>.json` if it has none yet
+    pub fn store_object(
+        &self,
+        container: &mut Container,
+        object: &serde_json::Value,
+    ) -> OzoneResult<()> {
+        let relative = container
+            .local_state
+            .storage
+            .object_store_path
+            .get_or_insert_with(|| {
+                format!("objects/{}.json", container.global_state.container_id)
+            })
+            .clone();
+        let path = object_path(Path::new(&self.config.local_path), &relative)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_vec(object)
+            .map_err(|e| OzoneError::SerializationError(e.to_string()))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Flush container storage to disk
+    pub async fn sync(&self) -> OzoneResult<()> {
+        self.storage.write().await.sync()
+    }
+
     /// Traverse from a starting container
     pub async fn traverse(&self, request: TraversalRequest) -> OzoneResult<TraversalResult> {
         let storage = self.storage.read().await;
@@ -136,3 +259,34 @@ impl ZSEI {
         0 // Root is always ID 0
     }
 }
+
+/// The object of a container in the store at `local_path`
+fn read_object(local_path: &Path, container: &Container) -> OzoneResult<Option<serde_json::Value>> {
+    let Some(path) = &container.local_state.storage.object_store_path else {
+        return Ok(None);
+    };
+    let path = object_path(local_path, path)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read(&path)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| OzoneError::SerializationError(e.to_string()))
+}
+
+/// Object paths are relative to the directory holding the local store,
+/// where bootstrap puts the blueprint and methodology files
+fn object_path(local_path: &Path, relative: &str) -> OzoneResult<PathBuf> {
+    let relative = Path::new(relative);
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(OzoneError::ValidationError(format!(
+            "Object path {} leaves the data directory",
+            relative.display()
+        )));
+    }
+    Ok(local_path.parent().unwrap_or(local_path).join(relative))
+}
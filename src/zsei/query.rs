@@ -357,58 +357,44 @@ impl QueryProcessor {
         storage: &ContainerStorage,
         task_signature: TaskSignature,
     ) -> OzoneResult<Vec<ContainerID>> {
-        let mut results: Vec<(ContainerID, f32)> = Vec::new();
+        let mut results: Vec<(ContainerID, u64)> = Vec::new();
         
         for id in storage.all_ids() {
-            if let Some(container) = storage.load(id)? {
-                if container.local_state.metadata.container_type == ContainerType::Blueprint {
-                    // Calculate match score based on TaskSignature fields
-                    let mut score = 0.0f32;
-                    let mut factors = 0;
-                    
-                    // Match output type against keywords (output_type might match a keyword)
-                    if container.local_state.context.keywords.iter()
-                        .any(|kw| kw.to_lowercase() == task_signature.output_type.to_lowercase()) {
-                        score += 1.0;
-                        factors += 1;
-                    }
-                    
-                    // Match input types against container keywords/topics
-                    let input_match = task_signature.input_types.iter()
-                        .filter(|input| {
-                            container.local_state.context.keywords.iter()
-                                .any(|kw| kw.to_lowercase().contains(&input.to_lowercase()))
-                        })
-                        .count();
-                    if !task_signature.input_types.is_empty() {
-                        score += input_match as f32 / task_signature.input_types.len() as f32;
-                        factors += 1;
-                    }
-                    
-                    // Match constraints against container metadata
-                    let constraint_match = task_signature.constraints.iter()
-                        .filter(|c| {
-                            container.local_state.context.topics.iter()
-                                .any(|t| t.to_lowercase().contains(&c.to_lowercase()))
-                        })
-                        .count();
-                    if !task_signature.constraints.is_empty() {
-                        score += constraint_match as f32 / task_signature.constraints.len() as f32;
-                        factors += 1;
-                    }
-                    
-                    let final_score = if factors > 0 { score / factors as f32 } else { 0.0 };
-                    
-                    // Only include blueprints with score > 0.3
-                    if final_score > 0.3 {
-                        results.push((id, final_score));
-                    }
+            let Some(container) = storage.load(id)? else {
+                continue;
+            };
+            if container.local_state.metadata.container_type != ContainerType::Blueprint {
+                continue;
+            }
+            // The signature lives in the blueprint's object file, in its
+            // context when stored by the orchestrator, at the top level
+            // when written by bootstrap
+            let object = match super::read_object(storage.local_path(), &container) {
+                Ok(Some(object)) => object,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!("Skipping blueprint {}: {}", id, e);
+                    continue;
                 }
+            };
+            let signature = if object["context"]["task_signature"].is_object() {
+                &object["context"]["task_signature"]
+            } else {
+                &object["task_signature"]
+            };
+            let hash: Option<Blake3Hash> = serde_json::from_value(signature["hash"].clone()).ok();
+            if hash != Some(task_signature.hash) {
+                continue;
             }
+            let usage_count = object["storage"]["usage_count"]
+                .as_u64()
+                .or_else(|| object["usage_count"].as_u64())
+                .unwrap_or(0);
+            results.push((id, usage_count));
         }
         
-        // Sort by score descending
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        // Most used first
+        results.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
         
         Ok(results.into_iter().map(|(id, _)| id).collect())
     }
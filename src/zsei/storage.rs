@@ -362,6 +362,11 @@ impl ContainerStorage {
         Ok(())
     }
     
+    /// Directory holding the local store
+    pub fn local_path(&self) -> &std::path::Path {
+        &self.local_path
+    }
+    
     /// Get all container IDs
     pub fn all_ids(&self) -> Vec<ContainerID> {
         let mut ids: Vec<_> = self.index.keys().copied().collect();
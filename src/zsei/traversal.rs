@@ -163,8 +163,9 @@ impl TraversalEngine {
             .iter().map(|k| k.to_lowercase()).collect();
         let start_topics: HashSet<String> = start_container.local_state.context.topics
             .iter().map(|t| t.to_lowercase()).collect();
+        let start_embedding = start_container.local_state.context.embedding.as_deref();
         
-        // Search all containers for keyword/topic similarity
+        // Search all containers for embedding or keyword/topic similarity
         for id in storage.all_ids() {
             if containers.len() >= request.max_results as usize {
                 break;
@@ -185,15 +186,24 @@ impl TraversalEngine {
                 let keyword_overlap = start_keywords.intersection(&container_keywords).count();
                 let topic_overlap = start_topics.intersection(&container_topics).count();
                 
-                // Consider semantically similar if any keyword or topic overlap
-                if keyword_overlap > 0 || topic_overlap > 0 {
+                // Embeddings decide when both containers have one; otherwise
+                // any keyword or topic overlap counts as similar
+                let embedded = start_embedding
+                    .zip(container.local_state.context.embedding.as_deref())
+                    .and_then(|(a, b)| cosine_similarity(a, b));
+                let distance = match embedded {
+                    Some(similarity) => (similarity >= MIN_EMBEDDING_SIMILARITY).then_some(1.0 - similarity),
+                    None => (keyword_overlap > 0 || topic_overlap > 0)
+                        .then(|| 1.0 / (1.0 + (keyword_overlap + topic_overlap) as f32)),
+                };
+                
+                if let Some(distance) = distance {
                     // Check filters
                     if self.matches_filters(storage, id, &request.filters)? {
-                        let similarity = (keyword_overlap + topic_overlap) as f32;
                         containers.push(id);
                         paths.push(Path {
                             hops: vec![request.start_container, id],
-                            total_distance: 1.0 / (1.0 + similarity), // Lower distance for more similar
+                            total_distance: distance, // Lower distance for more similar
                         });
                     }
                 }
@@ -545,3 +555,59 @@ impl TraversalEngine {
         }
     }
 }
+
+
+/// Dimension of `term_embedding` vectors
+pub const TERM_EMBEDDING_DIMENSION: usize = 384;
+
+/// Least cosine similarity at which semantic traversal follows an embedding
+const MIN_EMBEDDING_SIMILARITY: f32 = 0.3;
+
+/// Cosine similarity of two embeddings; `None` when their dimensions differ
+/// or either is all zeros
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a * norm_b))
+}
+
+/// Embedding of keywords and topics for containers without a model
+/// embedding: every word and its character trigrams are hashed into one of
+/// `dimension` buckets, so different forms of a word ("parser", "parsing")
+/// still come out similar. The vector has unit length.
+pub fn term_embedding(terms: &[String], dimension: usize) -> Vec<f32> {
+    let mut embedding = vec![0.0f32; dimension];
+    if dimension == 0 {
+        return embedding;
+    }
+    let mut add = |feature: &str, weight: f32| {
+        let hash = blake3::hash(feature.as_bytes());
+        let bytes: [u8; 8] = hash.as_bytes()[..8].try_into().unwrap_or_default();
+        embedding[(u64::from_le_bytes(bytes) % dimension as u64) as usize] += weight;
+    };
+    for term in terms {
+        for word in term
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+        {
+            add(word, 1.0);
+            let padded: Vec<char> = format!(" {} ", word).chars().collect();
+            for trigram in padded.windows(3) {
+                add(&trigram.iter().collect::<String>(), 0.5);
+            }
+        }
+    }
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+    embedding
+}